@@ -0,0 +1,92 @@
+// Block classification used by heightmaps (and anything else that needs to know how a block behaves).
+// Vanilla computes this from collision shapes, we don't have those so these are id based lists.
+// TODO: Generate these from the version data once we have the block reports
+
+pub const AIR_BLOCKS: &[&str] = &["minecraft:air", "minecraft:cave_air", "minecraft:void_air"];
+
+// blocks that always hold a fluid, even without the waterlogged state
+pub const FLUID_BLOCKS: &[&str] = &[
+    "minecraft:water", "minecraft:lava", "minecraft:bubble_column",
+    "minecraft:kelp", "minecraft:kelp_plant", "minecraft:seagrass", "minecraft:tall_seagrass",
+];
+
+pub const NON_MOTION_BLOCKING_BLOCKS: &[&str] = &[
+    "minecraft:water", "minecraft:lava", "minecraft:bubble_column",
+    "minecraft:kelp", "minecraft:kelp_plant", "minecraft:seagrass", "minecraft:tall_seagrass",
+    "minecraft:short_grass", "minecraft:grass", "minecraft:tall_grass", "minecraft:fern", "minecraft:large_fern",
+    "minecraft:dead_bush", "minecraft:sugar_cane", "minecraft:cobweb", "minecraft:bamboo_sapling",
+    "minecraft:dandelion", "minecraft:poppy", "minecraft:blue_orchid", "minecraft:allium", "minecraft:azure_bluet",
+    "minecraft:red_tulip", "minecraft:orange_tulip", "minecraft:white_tulip", "minecraft:pink_tulip",
+    "minecraft:oxeye_daisy", "minecraft:cornflower", "minecraft:lily_of_the_valley", "minecraft:wither_rose",
+    "minecraft:sunflower", "minecraft:lilac", "minecraft:rose_bush", "minecraft:peony", "minecraft:torchflower",
+    "minecraft:torchflower_crop", "minecraft:pitcher_plant", "minecraft:pitcher_crop", "minecraft:pink_petals",
+    "minecraft:brown_mushroom", "minecraft:red_mushroom", "minecraft:crimson_fungus", "minecraft:warped_fungus",
+    "minecraft:crimson_roots", "minecraft:warped_roots", "minecraft:nether_sprouts", "minecraft:hanging_roots",
+    "minecraft:wheat", "minecraft:carrots", "minecraft:potatoes", "minecraft:beetroots", "minecraft:nether_wart",
+    "minecraft:sweet_berry_bush", "minecraft:melon_stem", "minecraft:pumpkin_stem", "minecraft:attached_melon_stem",
+    "minecraft:attached_pumpkin_stem", "minecraft:torch", "minecraft:wall_torch", "minecraft:soul_torch",
+    "minecraft:soul_wall_torch", "minecraft:redstone_torch", "minecraft:redstone_wall_torch", "minecraft:redstone_wire",
+    "minecraft:repeater", "minecraft:comparator", "minecraft:lever", "minecraft:tripwire", "minecraft:tripwire_hook",
+    "minecraft:vine", "minecraft:glow_lichen", "minecraft:sculk_vein", "minecraft:cave_vines", "minecraft:cave_vines_plant",
+    "minecraft:weeping_vines", "minecraft:weeping_vines_plant", "minecraft:twisting_vines", "minecraft:twisting_vines_plant",
+    "minecraft:spore_blossom", "minecraft:small_dripleaf", "minecraft:big_dripleaf_stem", "minecraft:fire",
+    "minecraft:soul_fire", "minecraft:structure_void", "minecraft:light", "minecraft:frogspawn",
+    "minecraft:lily_pad", "minecraft:candle", "minecraft:flower_pot", "minecraft:player_head",
+    "minecraft:player_wall_head", "minecraft:zombie_head", "minecraft:zombie_wall_head",
+    "minecraft:creeper_head", "minecraft:creeper_wall_head", "minecraft:dragon_head",
+    "minecraft:dragon_wall_head", "minecraft:piglin_head", "minecraft:piglin_wall_head",
+];
+
+// suffixes that cover whole block families (all wood types, colors, etc.)
+pub const NON_MOTION_BLOCKING_SUFFIXES: &[&str] = &[
+    "_sapling", "_sign", "_banner", "_button", "_pressure_plate", "_carpet", "rail",
+    "_coral", "_coral_fan", "_coral_wall_fan", "_candle", "_skull",
+];
+
+// blocks whose block entity id is the block id itself
+pub const SAME_ID_BLOCK_ENTITY_BLOCKS: &[&str] = &[
+    "minecraft:chest", "minecraft:trapped_chest", "minecraft:ender_chest", "minecraft:barrel", "minecraft:furnace",
+    "minecraft:smoker", "minecraft:blast_furnace", "minecraft:brewing_stand", "minecraft:hopper", "minecraft:dropper",
+    "minecraft:dispenser", "minecraft:lectern", "minecraft:spawner", "minecraft:command_block", "minecraft:beacon",
+    "minecraft:bell", "minecraft:campfire", "minecraft:chiseled_bookshelf", "minecraft:comparator", "minecraft:conduit",
+    "minecraft:daylight_detector", "minecraft:enchanting_table", "minecraft:end_gateway", "minecraft:end_portal",
+    "minecraft:jigsaw", "minecraft:jukebox", "minecraft:structure_block", "minecraft:sculk_sensor",
+    "minecraft:calibrated_sculk_sensor", "minecraft:sculk_catalyst", "minecraft:sculk_shrieker",
+    "minecraft:decorated_pot", "minecraft:beehive",
+];
+
+// blocks the game keeps point of interest records for (beds, workstations, bells, ...), in the POI regions
+pub const POI_BLOCKS: &[&str] = &[
+    "minecraft:blast_furnace", "minecraft:smoker", "minecraft:cartography_table", "minecraft:brewing_stand",
+    "minecraft:composter", "minecraft:barrel", "minecraft:fletching_table", "minecraft:cauldron",
+    "minecraft:water_cauldron", "minecraft:lava_cauldron", "minecraft:powder_snow_cauldron", "minecraft:lectern",
+    "minecraft:stonecutter", "minecraft:loom", "minecraft:smithing_table", "minecraft:grindstone", "minecraft:bell",
+    "minecraft:beehive", "minecraft:bee_nest", "minecraft:nether_portal", "minecraft:lodestone", "minecraft:lightning_rod",
+];
+
+pub fn is_poi_block(block: &str) -> bool {
+    POI_BLOCKS.contains(&block) || (block.starts_with("minecraft:") && block.ends_with("_bed"))
+}
+
+// The id of the block entity a block has, None if it has none
+// TODO: Generate this from the version data once we have the block reports
+pub fn block_entity_id(block: &str) -> Option<&'static str> {
+    if let Some(id) = SAME_ID_BLOCK_ENTITY_BLOCKS.iter().find(|id| **id == block) { return Some(id); }
+    let id = match block {
+        "minecraft:repeating_command_block" | "minecraft:chain_command_block" => "minecraft:command_block",
+        "minecraft:soul_campfire" => "minecraft:campfire",
+        "minecraft:bee_nest" => "minecraft:beehive",
+        "minecraft:moving_piston" => "minecraft:piston",
+        "minecraft:suspicious_sand" | "minecraft:suspicious_gravel" => "minecraft:brushable_block",
+        "minecraft:piston_head" => return None,
+        _ if !block.starts_with("minecraft:") => return None,
+        _ if block.ends_with("shulker_box") => "minecraft:shulker_box",
+        _ if block.ends_with("_hanging_sign") => "minecraft:hanging_sign",
+        _ if block.ends_with("_sign") => "minecraft:sign",
+        _ if block.ends_with("_banner") => "minecraft:banner",
+        _ if block.ends_with("_bed") => "minecraft:bed",
+        _ if block.ends_with("_skull") || block.ends_with("_head") => "minecraft:skull",
+        _ => return None,
+    };
+    Some(id)
+}
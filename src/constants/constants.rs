@@ -3,10 +3,14 @@ pub const MCA_REGION_LOCATION_SECTOR_ENTRY_SIZE: usize = 4;
 pub const ZLIB_COMPRESSION_TYPE: u8 = 2;
 pub const BIOME_CELL_SIZE: i32 = 4;
 pub const REGION_CHUNK_LINE: usize = 32;
+pub const BLOCK_INDEX_FILE_NAME: &str = "cubicle_block_index.json";
+pub const LEVEL_FILE_NAME: &str = "level.dat";
+pub const VANILLA_DIMENSIONS: [&str; 3] = ["overworld", "the_nether", "the_end"];
 
 pub const CAMPFIRE_ITEM_COUNT: usize = 4;
 pub const BREWING_STAND_RESULT_COUNT: usize = 3;
 pub const MAX_SIGN_LINE_COUNT: usize = 8;
 pub const PLAYER_INVENTORY_SLOT_COUNT: usize = 41;
+pub const PLAYER_ENTITY_ID: &str = "minecraft:player";
 
 pub const MOJANG_VERSION_MANIFEST: &str = "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
\ No newline at end of file
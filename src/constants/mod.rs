@@ -1,3 +1,5 @@
 pub mod versions;
 mod config;
-pub mod constants;
\ No newline at end of file
+pub mod constants;
+pub mod blocks;
+pub mod structure_sets;
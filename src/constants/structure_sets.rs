@@ -0,0 +1,78 @@
+// Structure sets from the 1.20.1 worldgen data (data/minecraft/worldgen/structure_set)
+// TODO: Move these into the version data once other versions are supported
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpreadType {
+    Linear,
+    Triangular,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrequencyReduction {
+    Default,
+    LegacyType1,
+}
+
+// Placement is skipped if the other set has a start candidate within chunk_count chunks
+#[derive(Debug, Clone, Copy)]
+pub struct ExclusionZone {
+    pub other_set: &'static StructureSet,
+    pub chunk_count: i32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StructureSet {
+    pub name: &'static str,
+    pub structures: &'static [&'static str],
+    pub spacing: i32,
+    pub separation: i32,
+    pub salt: i32,
+    pub spread_type: SpreadType,
+    pub frequency: f32,
+    pub frequency_reduction: FrequencyReduction,
+    pub exclusion_zone: Option<ExclusionZone>,
+}
+
+const fn random_spread(name: &'static str, structures: &'static [&'static str], spacing: i32, separation: i32, salt: i32, spread_type: SpreadType) -> StructureSet {
+    StructureSet {
+        name, structures, spacing, separation, salt, spread_type,
+        frequency: 1.0,
+        frequency_reduction: FrequencyReduction::Default,
+        exclusion_zone: None,
+    }
+}
+
+pub const VILLAGES: StructureSet = random_spread("minecraft:villages", &[
+    "minecraft:village_plains", "minecraft:village_desert", "minecraft:village_savanna",
+    "minecraft:village_snowy", "minecraft:village_taiga",
+], 34, 8, 10387312, SpreadType::Linear);
+
+pub const PILLAGER_OUTPOSTS: StructureSet = StructureSet {
+    frequency: 0.2,
+    frequency_reduction: FrequencyReduction::LegacyType1,
+    exclusion_zone: Some(ExclusionZone { other_set: &VILLAGES, chunk_count: 10 }),
+    ..random_spread("minecraft:pillager_outposts", &["minecraft:pillager_outpost"], 32, 8, 165745296, SpreadType::Linear)
+};
+
+pub const DESERT_PYRAMIDS: StructureSet = random_spread("minecraft:desert_pyramids", &["minecraft:desert_pyramid"], 32, 8, 14357617, SpreadType::Linear);
+pub const IGLOOS: StructureSet = random_spread("minecraft:igloos", &["minecraft:igloo"], 32, 8, 14357618, SpreadType::Linear);
+pub const JUNGLE_TEMPLES: StructureSet = random_spread("minecraft:jungle_temples", &["minecraft:jungle_pyramid"], 32, 8, 14357619, SpreadType::Linear);
+pub const SWAMP_HUTS: StructureSet = random_spread("minecraft:swamp_huts", &["minecraft:swamp_hut"], 32, 8, 14357620, SpreadType::Linear);
+pub const OCEAN_MONUMENTS: StructureSet = random_spread("minecraft:ocean_monuments", &["minecraft:monument"], 32, 5, 10387313, SpreadType::Triangular);
+pub const WOODLAND_MANSIONS: StructureSet = random_spread("minecraft:woodland_mansions", &["minecraft:mansion"], 80, 20, 10387319, SpreadType::Triangular);
+pub const ANCIENT_CITIES: StructureSet = random_spread("minecraft:ancient_cities", &["minecraft:ancient_city"], 24, 8, 20083232, SpreadType::Linear);
+pub const TRAIL_RUINS: StructureSet = random_spread("minecraft:trail_ruins", &["minecraft:trail_ruins"], 34, 8, 83469867, SpreadType::Linear);
+
+pub const RANDOM_SPREAD_SETS: &[StructureSet] = &[
+    VILLAGES, PILLAGER_OUTPOSTS, DESERT_PYRAMIDS, IGLOOS, JUNGLE_TEMPLES, SWAMP_HUTS,
+    OCEAN_MONUMENTS, WOODLAND_MANSIONS, ANCIENT_CITIES, TRAIL_RUINS,
+];
+
+pub const STRONGHOLD_ID: &str = "minecraft:stronghold";
+pub const STRONGHOLD_RING_DISTANCE: i32 = 32;
+pub const STRONGHOLD_RING_SPREAD: i32 = 3;
+pub const STRONGHOLD_COUNT: i32 = 128;
+// strongholds get moved to a matching biome within this many blocks of their ring position
+pub const STRONGHOLD_BIOME_SEARCH_RADIUS: i32 = 112;
+
+pub const SLIME_CHUNK_SALT: i64 = 987234911;
@@ -1 +1,2 @@
-pub mod test;
\ No newline at end of file
+pub mod test;
+pub mod structures;
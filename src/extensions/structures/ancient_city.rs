@@ -0,0 +1,45 @@
+use std::cell::OnceCell;
+use std::sync::Arc;
+use crate::extensions::structures::{block_entities_in_pieces, pieces_where, TypedStructure, CHEST_BLOCK_ENTITIES};
+use crate::models::block_entity::block_entity::BlockEntity;
+use crate::models::world::dimension::Dimension;
+use crate::models::world_structures::generic_structure::{BoundingBox, GenericChildStructure, GenericParentStructure};
+
+pub struct AncientCity<'s> {
+    structure: &'s GenericParentStructure,
+    buildings: OnceCell<Vec<&'s GenericChildStructure>>,
+    bounding_box: OnceCell<Option<BoundingBox>>,
+}
+
+impl<'s> TypedStructure<'s> for AncientCity<'s> {
+    const IDS: &'static [&'static str] = &["minecraft:ancient_city"];
+
+    fn wrap(structure: &'s GenericParentStructure) -> Self {
+        AncientCity { structure, buildings: OnceCell::new(), bounding_box: OnceCell::new() }
+    }
+
+    fn structure(&self) -> &'s GenericParentStructure { self.structure }
+}
+
+impl<'s> AncientCity<'s> {
+    // Pool elements are like "minecraft:ancient_city/city_center/city_center_1"
+    pub fn city_center(&self) -> Vec<&'s GenericChildStructure> {
+        pieces_where(self.structure, |element| element.contains("ancient_city/city_center/"))
+    }
+
+    pub fn buildings(&self) -> &[&'s GenericChildStructure] {
+        self.buildings.get_or_init(|| pieces_where(self.structure, |element| element.contains("ancient_city/structures/")))
+    }
+
+    pub fn walls(&self) -> Vec<&'s GenericChildStructure> {
+        pieces_where(self.structure, |element| element.contains("ancient_city/walls/"))
+    }
+
+    pub fn bounding_box(&self) -> Option<&BoundingBox> {
+        self.bounding_box.get_or_init(|| self.structure.bounding_box()).as_ref()
+    }
+
+    pub fn chests(&self, dimension: &Dimension) -> Vec<Arc<BlockEntity>> {
+        block_entities_in_pieces(&self.structure.children().iter().collect::<Vec<_>>(), dimension, CHEST_BLOCK_ENTITIES)
+    }
+}
@@ -0,0 +1,54 @@
+use std::cell::OnceCell;
+use std::sync::Arc;
+use crate::extensions::structures::{block_entities_in_pieces, pieces_with_ids, TypedStructure, CHEST_BLOCK_ENTITIES, SPAWNER_BLOCK_ENTITY};
+use crate::models::block_entity::block_entity::BlockEntity;
+use crate::models::world::dimension::Dimension;
+use crate::models::world_structures::generic_structure::{GenericChildStructure, GenericParentStructure};
+
+const MONSTER_THRONE: &str = "minecraft:nemt";
+const NETHER_WART_ROOM: &str = "minecraft:necsr";
+// the small corridor turns are the only corridors that can generate a chest
+const CHEST_CORRIDORS: &[&str] = &["minecraft:nesclt", "minecraft:nescrt"];
+const BRIDGE_PIECES: &[&str] = &["minecraft:nebcr", "minecraft:nebef", "minecraft:nebs", "minecraft:nerc", "minecraft:nesr", "minecraft:nestart"];
+
+pub struct Fortress<'s> {
+    structure: &'s GenericParentStructure,
+    blaze_spawner_rooms: OnceCell<Vec<&'s GenericChildStructure>>,
+    chest_corridors: OnceCell<Vec<&'s GenericChildStructure>>,
+}
+
+impl<'s> TypedStructure<'s> for Fortress<'s> {
+    const IDS: &'static [&'static str] = &["minecraft:fortress"];
+
+    fn wrap(structure: &'s GenericParentStructure) -> Self {
+        Fortress { structure, blaze_spawner_rooms: OnceCell::new(), chest_corridors: OnceCell::new() }
+    }
+
+    fn structure(&self) -> &'s GenericParentStructure { self.structure }
+}
+
+impl<'s> Fortress<'s> {
+    pub fn blaze_spawner_rooms(&self) -> &[&'s GenericChildStructure] {
+        self.blaze_spawner_rooms.get_or_init(|| pieces_with_ids(self.structure, &[MONSTER_THRONE]))
+    }
+
+    pub fn chest_corridors(&self) -> &[&'s GenericChildStructure] {
+        self.chest_corridors.get_or_init(|| pieces_with_ids(self.structure, CHEST_CORRIDORS))
+    }
+
+    pub fn nether_wart_rooms(&self) -> Vec<&'s GenericChildStructure> {
+        pieces_with_ids(self.structure, &[NETHER_WART_ROOM])
+    }
+
+    pub fn bridges(&self) -> Vec<&'s GenericChildStructure> {
+        pieces_with_ids(self.structure, BRIDGE_PIECES)
+    }
+
+    pub fn chests(&self, dimension: &Dimension) -> Vec<Arc<BlockEntity>> {
+        block_entities_in_pieces(self.chest_corridors(), dimension, CHEST_BLOCK_ENTITIES)
+    }
+
+    pub fn spawners(&self, dimension: &Dimension) -> Vec<Arc<BlockEntity>> {
+        block_entities_in_pieces(self.blaze_spawner_rooms(), dimension, &[SPAWNER_BLOCK_ENTITY])
+    }
+}
@@ -0,0 +1,269 @@
+use std::sync::Arc;
+use fastnbt::Value;
+use crate::models::block_entity::block_entity::BlockEntity;
+use crate::models::world::dimension::Dimension;
+use crate::models::world_structures::generic_structure::{GenericChildStructure, GenericParentStructure};
+use crate::traits::block_entity::BlockEntityTrait;
+
+pub mod village;
+pub mod stronghold;
+pub mod fortress;
+pub mod monument;
+pub mod ancient_city;
+
+pub const CHEST_BLOCK_ENTITIES: &[&str] = &["minecraft:chest", "minecraft:trapped_chest", "minecraft:barrel"];
+pub const SPAWNER_BLOCK_ENTITY: &str = "minecraft:spawner";
+
+/*
+Typed views over GenericParentStructure, usage:
+    let village = structure.as_typed::<Village>()?;
+    village.houses()
+Views only borrow the structure, anything derived from the pieces is calculated on first use and cached in the view.
+*/
+pub trait TypedStructure<'s>: Sized {
+    const IDS: &'static [&'static str];
+
+    fn wrap(structure: &'s GenericParentStructure) -> Self;
+    fn structure(&self) -> &'s GenericParentStructure;
+
+    fn from_generic(structure: &'s GenericParentStructure) -> Option<Self> {
+        if Self::IDS.contains(&structure.id().as_str()) { Some(Self::wrap(structure)) } else { None }
+    }
+}
+
+pub trait StructureExt {
+    fn as_typed<'s, T: TypedStructure<'s>>(&'s self) -> Option<T>;
+}
+
+impl StructureExt for GenericParentStructure {
+    fn as_typed<'s, T: TypedStructure<'s>>(&'s self) -> Option<T> { T::from_generic(self) }
+}
+
+// Jigsaw pieces are all "minecraft:jigsaw", what they actually are is the pool element they were placed from.
+// For other pieces this is just the piece id
+pub fn piece_element(piece: &GenericChildStructure) -> String {
+    let props = piece.properties();
+    for path in ["pool_element.location", "pool_element.feature"] {
+        if let Some(Value::String(location)) = props.get(path) {
+            return location;
+        }
+    }
+    piece.id().clone()
+}
+
+pub fn pieces_where<F>(structure: &GenericParentStructure, predicate: F) -> Vec<&GenericChildStructure>
+where F: Fn(&str) -> bool {
+    structure.children().iter().filter(|piece| predicate(&piece_element(piece))).collect()
+}
+
+pub fn pieces_with_ids<'s>(structure: &'s GenericParentStructure, ids: &[&str]) -> Vec<&'s GenericChildStructure> {
+    pieces_where(structure, |element| ids.contains(&element))
+}
+
+// Only looks at loaded chunks of the dimension
+pub fn block_entities_in_piece(piece: &GenericChildStructure, dimension: &Dimension) -> Vec<Arc<BlockEntity>> {
+    let chunk_size = dimension.version().data.chunk_size;
+    let bounding_box = piece.bounding_box();
+    let mut block_entities = Vec::new();
+
+    for chunk_x in bounding_box.min().x().div_euclid(chunk_size)..=bounding_box.max().x().div_euclid(chunk_size) {
+        for chunk_z in bounding_box.min().z().div_euclid(chunk_size)..=bounding_box.max().z().div_euclid(chunk_size) {
+            let Some(chunk) = dimension.chunk((chunk_x, chunk_z)) else { continue };
+            let locked = chunk.lock().unwrap();
+            block_entities.extend(
+                locked.block_entities_store().get_all().iter()
+                    .filter(|block_entity| bounding_box.contains(block_entity.base().position()))
+                    .cloned()
+            );
+        }
+    }
+    block_entities
+}
+
+pub fn block_entities_in_pieces(pieces: &[&GenericChildStructure], dimension: &Dimension, ids: &[&str]) -> Vec<Arc<BlockEntity>> {
+    let mut block_entities: Vec<Arc<BlockEntity>> = Vec::new();
+    for piece in pieces {
+        for block_entity in block_entities_in_piece(piece, dimension) {
+            // pieces can overlap, dont return the same block entity twice
+            if ids.contains(&block_entity.base().id())
+                && !block_entities.iter().any(|b| b.base().position() == block_entity.base().position()) {
+                block_entities.push(block_entity);
+            }
+        }
+    }
+    block_entities
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use fastnbt::IntArray;
+    use crate::constants::constants::ZLIB_COMPRESSION_TYPE;
+    use crate::constants::versions::VersionManager;
+    use crate::extensions::structures::ancient_city::AncientCity;
+    use crate::extensions::structures::fortress::Fortress;
+    use crate::extensions::structures::monument::Monument;
+    use crate::extensions::structures::stronghold::Stronghold;
+    use crate::extensions::structures::village::{Village, VillageKind};
+    use crate::loaders::loader_utils::compress_zlib;
+    use crate::loaders::templates::block_loader::get_block_loader;
+    use crate::models::other::tick::Tick;
+    use crate::models::positions::chunk_position::ChunkPosition;
+    use crate::models::world::block::PaletteBlock;
+    use crate::models::world::chunk::Chunk;
+    use crate::types::WorldKind;
+
+    fn compound(entries: Vec<(&str, Value)>) -> Value { Value::Compound(entries.into_iter().map(|(k, v)| (k.to_string(), v)).collect()) }
+
+    // A structure piece as the game saves it, jigsaw pieces carry the pool element they were placed from
+    fn piece(id: &str, bb: [i32; 6], element: Option<&str>) -> Value {
+        let mut entries = vec![("id", Value::String(id.to_string())), ("BB", Value::IntArray(IntArray::new(bb.to_vec()))), ("GD", Value::Int(0))];
+        if let Some(element) = element {
+            entries.push(("pool_element", compound(vec![
+                ("element_type", Value::String("minecraft:single_pool_element".to_string())),
+                ("location", Value::String(element.to_string())),
+            ])));
+        }
+        compound(entries)
+    }
+
+    // Chunk (-1, -1) with the blocks placed (their block entities come with them) and the structure start in its NBT,
+    // read back through the block loader like a chunk from a region file
+    fn fixture(dimension_id: &str, id: &str, pieces: Vec<Value>, blocks: &[(&str, (i32, i32, i32))]) -> (Dimension, GenericParentStructure) {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let position = ChunkPosition::new(-1, -1, dimension_id.into());
+        let mut chunk = Chunk::new(position.clone(), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version);
+        for (block, (x, y, z)) in blocks {
+            chunk.set_block_at_index(((y + 64) as usize * 16 + z.rem_euclid(16) as usize) * 16 + x.rem_euclid(16) as usize, PaletteBlock::from(*block));
+        }
+
+        let loader = get_block_loader(version.clone());
+        let mut nbt: HashMap<String, Value> = fastnbt::from_bytes(&loader.write_chunk(&chunk, None).unwrap()).unwrap();
+        let start = compound(vec![
+            ("id", Value::String(id.to_string())), ("ChunkX", Value::Int(-1)), ("ChunkZ", Value::Int(-1)),
+            ("references", Value::Int(0)), ("Children", Value::List(pieces)),
+        ]);
+        nbt.insert("structures".to_string(), compound(vec![("References", compound(vec![])), ("starts", compound(vec![(id, start)]))]));
+        let data = compress_zlib(&fastnbt::to_bytes(&Value::Compound(nbt)).unwrap()).unwrap();
+        let (chunk, mut structures) = loader.parse_chunk(data, ZLIB_COMPRESSION_TYPE, position.dimension()).unwrap();
+
+        let mut dimension = Dimension::new(dimension_id.into(), version);
+        dimension.set_chunk(chunk);
+        (dimension, structures.remove(0))
+    }
+
+    fn positions(block_entities: &[Arc<BlockEntity>]) -> Vec<(i32, i32, i32)> {
+        let mut positions = block_entities.iter().map(|b| { let p = b.base().position(); (p.x(), p.y(), p.z()) }).collect::<Vec<_>>();
+        positions.sort();
+        positions
+    }
+
+    #[test]
+    fn fortress_fixture() {
+        let (dimension, structure) = fixture("the_nether", "minecraft:fortress", vec![
+            piece("minecraft:nestart", [-16, 64, -16, -12, 70, -12], None),
+            piece("minecraft:nemt", [-11, 64, -16, -6, 70, -10], None),
+            piece("minecraft:nesclt", [-5, 64, -16, -1, 70, -12], None),
+            piece("minecraft:necsr", [-16, 64, -8, -8, 70, -1], None),
+        ], &[("minecraft:spawner", (-8, 66, -13)), ("minecraft:chest", (-3, 65, -14)), ("minecraft:chest", (-12, 65, -4))]);
+
+        assert!(structure.as_typed::<Stronghold>().is_none());
+        let fortress = structure.as_typed::<Fortress>().unwrap();
+        assert_eq!(fortress.blaze_spawner_rooms().len(), 1);
+        assert_eq!(fortress.chest_corridors().len(), 1);
+        assert_eq!(fortress.nether_wart_rooms().len(), 1);
+        assert_eq!(fortress.bridges().len(), 1);
+        // the chest in the nether wart room isn't one a fortress generates
+        assert_eq!(positions(&fortress.chests(&dimension)), vec![(-3, 65, -14)]);
+        assert_eq!(positions(&fortress.spawners(&dimension)), vec![(-8, 66, -13)]);
+    }
+
+    #[test]
+    fn stronghold_fixture() {
+        let (dimension, structure) = fixture("overworld", "minecraft:stronghold", vec![
+            piece("minecraft:shs", [-16, 20, -16, -12, 30, -12], None),
+            piece("minecraft:shpr", [-11, 10, -16, -1, 18, -1], None),
+            piece("minecraft:shcc", [-16, 20, -11, -12, 25, -5], None),
+            piece("minecraft:shli", [-16, 30, -4, -2, 40, -1], None),
+            piece("minecraft:shph", [-16, 10, -4, -12, 15, -1], None),
+            piece("minecraft:shrc", [-16, -10, -16, -6, -3, -6], None),
+        ], &[
+            ("minecraft:spawner", (-6, 13, -8)), ("minecraft:chest", (-14, 22, -8)), ("minecraft:chest", (-3, 35, -2)),
+            ("minecraft:chest", (-10, -8, -10)), ("minecraft:chest", (-14, 12, -2)),
+        ]);
+
+        let stronghold = structure.as_typed::<Stronghold>().unwrap();
+        assert_eq!(stronghold.portal_room().unwrap().bounding_box().min().y(), 10);
+        assert_eq!((stronghold.chest_corridors().len(), stronghold.libraries().len(), stronghold.jail_cells().len()), (1, 1, 1));
+        // the chest in the prison hall doesn't count
+        assert_eq!(positions(&stronghold.chests(&dimension)), vec![(-14, 22, -8), (-10, -8, -10), (-3, 35, -2)]);
+        let spawner = stronghold.portal_room_spawner(&dimension).unwrap();
+        assert_eq!(spawner.base().id(), "minecraft:spawner");
+        assert_eq!(spawner.base().position().y(), 13);
+    }
+
+    #[test]
+    fn village_fixture() {
+        let (dimension, structure) = fixture("overworld", "minecraft:village_plains", vec![
+            piece("minecraft:jigsaw", [-16, 64, -16, -8, 70, -8], Some("minecraft:village/plains/town_centers/plains_meeting_point_1")),
+            piece("minecraft:jigsaw", [-7, 64, -16, -1, 70, -10], Some("minecraft:village/plains/houses/plains_small_house_1")),
+            piece("minecraft:jigsaw", [-7, 64, -9, -1, 70, -1], Some("minecraft:village/plains/zombie/houses/plains_weaponsmith_1")),
+            piece("minecraft:jigsaw", [-16, 64, -7, -8, 64, -1], Some("minecraft:village/plains/streets/straight_01")),
+        ], &[("minecraft:chest", (-4, 65, -12)), ("minecraft:barrel", (-2, 66, -3)), ("minecraft:chest", (-12, 65, -12))]);
+
+        let village = structure.as_typed::<Village>().unwrap();
+        assert_eq!(village.kind(), VillageKind::Plains);
+        assert_eq!(village.houses().len(), 2);
+        assert_eq!(village.streets().len(), 1);
+        assert!(village.town_center().is_some());
+        assert!(village.is_zombie_village());
+        assert_eq!(village.bounding_box().unwrap().size(), (16, 7, 16));
+        // only house chests and barrels, the one at the meeting point isn't in a house
+        assert_eq!(positions(&village.chests(&dimension)), vec![(-4, 65, -12), (-2, 66, -3)]);
+    }
+
+    #[test]
+    fn monument_fixture() {
+        let (_, structure) = fixture("overworld", "minecraft:monument", vec![
+            piece("minecraft:omb", [-45, 39, -45, 12, 61, 12], None),
+            piece("minecraft:omentry", [-24, 39, -45, -9, 47, -38], None),
+            piece("minecraft:omcr", [-24, 47, -24, -9, 55, -9], None),
+            piece("minecraft:omwr", [-45, 39, -24, -24, 61, -9], None),
+            piece("minecraft:omwr", [-9, 39, -24, 12, 61, -9], None),
+            piece("minecraft:ompenthouse", [-22, 61, -9, -11, 65, -1], None),
+            piece("minecraft:omsimple", [-24, 39, -9, -17, 47, -1], None),
+        ], &[]);
+
+        assert!(structure.as_typed::<Village>().is_none());
+        let monument = structure.as_typed::<Monument>().unwrap();
+        assert_eq!(monument.building().unwrap().id(), "minecraft:omb");
+        assert_eq!(monument.bounding_box().unwrap().size(), (58, 23, 58));
+        // every piece but the shell is a room
+        assert_eq!(monument.rooms().len(), 6);
+        assert_eq!(monument.wing_rooms().len(), 2);
+        assert_eq!(monument.core_room().unwrap().bounding_box().min().y(), 47);
+        assert_eq!(monument.penthouse().unwrap().bounding_box().max().y(), 65);
+        assert_eq!(monument.entry_room().unwrap().bounding_box().min().z(), -45);
+    }
+
+    #[test]
+    fn ancient_city_fixture() {
+        let (dimension, structure) = fixture("overworld", "minecraft:ancient_city", vec![
+            piece("minecraft:jigsaw", [-16, -51, -16, -8, -40, -8], Some("minecraft:ancient_city/city_center/city_center_1")),
+            piece("minecraft:jigsaw", [-7, -51, -16, -1, -44, -8], Some("minecraft:ancient_city/structures/small_statue")),
+            piece("minecraft:jigsaw", [-7, -51, -7, -1, -44, -1], Some("minecraft:ancient_city/structures/barracks")),
+            piece("minecraft:jigsaw", [-16, -51, -7, -8, -30, -1], Some("minecraft:ancient_city/walls/intact_corner_wall_1")),
+        ], &[("minecraft:chest", (-12, -50, -12)), ("minecraft:chest", (-3, -49, -3)), ("minecraft:chest", (-3, -20, -3))]);
+
+        let city = structure.as_typed::<AncientCity>().unwrap();
+        assert_eq!(city.city_center().len(), 1);
+        assert_eq!(city.buildings().len(), 2);
+        assert_eq!(city.walls().len(), 1);
+        assert_eq!(city.bounding_box().unwrap().max().y(), -30);
+        // the chest above the city isn't in any piece
+        assert_eq!(positions(&city.chests(&dimension)), vec![(-12, -50, -12), (-3, -49, -3)]);
+    }
+}
@@ -0,0 +1,57 @@
+use std::cell::OnceCell;
+use crate::extensions::structures::{pieces_where, pieces_with_ids, TypedStructure};
+use crate::models::world_structures::generic_structure::{BoundingBox, GenericChildStructure, GenericParentStructure};
+
+const BUILDING: &str = "minecraft:omb";
+const CORE_ROOM: &str = "minecraft:omcr";
+const WING_ROOM: &str = "minecraft:omwr";
+const PENTHOUSE: &str = "minecraft:ompenthouse";
+const ENTRY_ROOM: &str = "minecraft:omentry";
+
+pub struct Monument<'s> {
+    structure: &'s GenericParentStructure,
+    rooms: OnceCell<Vec<&'s GenericChildStructure>>,
+}
+
+impl<'s> TypedStructure<'s> for Monument<'s> {
+    const IDS: &'static [&'static str] = &["minecraft:monument"];
+
+    fn wrap(structure: &'s GenericParentStructure) -> Self {
+        Monument { structure, rooms: OnceCell::new() }
+    }
+
+    fn structure(&self) -> &'s GenericParentStructure { self.structure }
+}
+
+impl<'s> Monument<'s> {
+    // The building piece is the whole monument shell, every other om piece is a room inside of it
+    pub fn building(&self) -> Option<&'s GenericChildStructure> {
+        self.structure.children().iter().find(|piece| piece.id() == BUILDING)
+    }
+
+    pub fn rooms(&self) -> &[&'s GenericChildStructure] {
+        self.rooms.get_or_init(|| pieces_where(self.structure, |id| id.starts_with("minecraft:om") && id != BUILDING))
+    }
+
+    // Holds the gold blocks
+    pub fn core_room(&self) -> Option<&'s GenericChildStructure> {
+        self.structure.children().iter().find(|piece| piece.id() == CORE_ROOM)
+    }
+
+    // Each wing room has an elder guardian
+    pub fn wing_rooms(&self) -> Vec<&'s GenericChildStructure> {
+        pieces_with_ids(self.structure, &[WING_ROOM])
+    }
+
+    pub fn penthouse(&self) -> Option<&'s GenericChildStructure> {
+        self.structure.children().iter().find(|piece| piece.id() == PENTHOUSE)
+    }
+
+    pub fn entry_room(&self) -> Option<&'s GenericChildStructure> {
+        self.structure.children().iter().find(|piece| piece.id() == ENTRY_ROOM)
+    }
+
+    pub fn bounding_box(&self) -> Option<&'s BoundingBox> {
+        self.building().map(|building| building.bounding_box())
+    }
+}
@@ -0,0 +1,60 @@
+use std::cell::OnceCell;
+use std::sync::Arc;
+use crate::extensions::structures::{block_entities_in_pieces, pieces_with_ids, TypedStructure, CHEST_BLOCK_ENTITIES, SPAWNER_BLOCK_ENTITY};
+use crate::models::block_entity::block_entity::BlockEntity;
+use crate::models::world::dimension::Dimension;
+use crate::models::world_structures::generic_structure::{GenericChildStructure, GenericParentStructure};
+
+const PORTAL_ROOM: &str = "minecraft:shpr";
+const CHEST_CORRIDOR: &str = "minecraft:shcc";
+const LIBRARY: &str = "minecraft:shli";
+const PRISON_HALL: &str = "minecraft:shph";
+const ROOM_CROSSING: &str = "minecraft:shrc";
+
+pub struct Stronghold<'s> {
+    structure: &'s GenericParentStructure,
+    chest_corridors: OnceCell<Vec<&'s GenericChildStructure>>,
+    libraries: OnceCell<Vec<&'s GenericChildStructure>>,
+    jail_cells: OnceCell<Vec<&'s GenericChildStructure>>,
+}
+
+impl<'s> TypedStructure<'s> for Stronghold<'s> {
+    const IDS: &'static [&'static str] = &["minecraft:stronghold"];
+
+    fn wrap(structure: &'s GenericParentStructure) -> Self {
+        Stronghold { structure, chest_corridors: OnceCell::new(), libraries: OnceCell::new(), jail_cells: OnceCell::new() }
+    }
+
+    fn structure(&self) -> &'s GenericParentStructure { self.structure }
+}
+
+impl<'s> Stronghold<'s> {
+    pub fn portal_room(&self) -> Option<&'s GenericChildStructure> {
+        self.structure.children().iter().find(|piece| piece.id() == PORTAL_ROOM)
+    }
+
+    pub fn chest_corridors(&self) -> &[&'s GenericChildStructure] {
+        self.chest_corridors.get_or_init(|| pieces_with_ids(self.structure, &[CHEST_CORRIDOR]))
+    }
+
+    pub fn libraries(&self) -> &[&'s GenericChildStructure] {
+        self.libraries.get_or_init(|| pieces_with_ids(self.structure, &[LIBRARY]))
+    }
+
+    pub fn jail_cells(&self) -> &[&'s GenericChildStructure] {
+        self.jail_cells.get_or_init(|| pieces_with_ids(self.structure, &[PRISON_HALL]))
+    }
+
+    // Chests are in the chest corridors, libraries and the storeroom kind of room crossings
+    pub fn chests(&self, dimension: &Dimension) -> Vec<Arc<BlockEntity>> {
+        let mut pieces = self.chest_corridors().to_vec();
+        pieces.extend_from_slice(self.libraries());
+        pieces.extend(pieces_with_ids(self.structure, &[ROOM_CROSSING]));
+        block_entities_in_pieces(&pieces, dimension, CHEST_BLOCK_ENTITIES)
+    }
+
+    pub fn portal_room_spawner(&self, dimension: &Dimension) -> Option<Arc<BlockEntity>> {
+        let portal_room = self.portal_room()?;
+        block_entities_in_pieces(&[portal_room], dimension, &[SPAWNER_BLOCK_ENTITY]).into_iter().next()
+    }
+}
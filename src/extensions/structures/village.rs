@@ -0,0 +1,72 @@
+use std::cell::OnceCell;
+use std::sync::Arc;
+use crate::extensions::structures::{block_entities_in_pieces, piece_element, pieces_where, TypedStructure, CHEST_BLOCK_ENTITIES};
+use crate::models::block_entity::block_entity::BlockEntity;
+use crate::models::world::dimension::Dimension;
+use crate::models::world_structures::generic_structure::{BoundingBox, GenericChildStructure, GenericParentStructure};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VillageKind {
+    Plains,
+    Desert,
+    Savanna,
+    Snowy,
+    Taiga,
+}
+
+pub struct Village<'s> {
+    structure: &'s GenericParentStructure,
+    houses: OnceCell<Vec<&'s GenericChildStructure>>,
+    streets: OnceCell<Vec<&'s GenericChildStructure>>,
+    bounding_box: OnceCell<Option<BoundingBox>>,
+}
+
+impl<'s> TypedStructure<'s> for Village<'s> {
+    const IDS: &'static [&'static str] = &[
+        "minecraft:village_plains", "minecraft:village_desert", "minecraft:village_savanna",
+        "minecraft:village_snowy", "minecraft:village_taiga",
+    ];
+
+    fn wrap(structure: &'s GenericParentStructure) -> Self {
+        Village { structure, houses: OnceCell::new(), streets: OnceCell::new(), bounding_box: OnceCell::new() }
+    }
+
+    fn structure(&self) -> &'s GenericParentStructure { self.structure }
+}
+
+impl<'s> Village<'s> {
+    pub fn kind(&self) -> VillageKind {
+        match self.structure.id().as_str() {
+            "minecraft:village_desert" => VillageKind::Desert,
+            "minecraft:village_savanna" => VillageKind::Savanna,
+            "minecraft:village_snowy" => VillageKind::Snowy,
+            "minecraft:village_taiga" => VillageKind::Taiga,
+            _ => VillageKind::Plains,
+        }
+    }
+
+    // Pool elements are like "minecraft:village/plains/houses/plains_small_house_1", zombie villages use "houses" too
+    pub fn houses(&self) -> &[&'s GenericChildStructure] {
+        self.houses.get_or_init(|| pieces_where(self.structure, |element| element.contains("/houses/")))
+    }
+
+    pub fn town_center(&self) -> Option<&'s GenericChildStructure> {
+        self.structure.children().iter().find(|piece| piece_element(piece).contains("/town_centers/"))
+    }
+
+    pub fn streets(&self) -> &[&'s GenericChildStructure] {
+        self.streets.get_or_init(|| pieces_where(self.structure, |element| element.contains("/streets/")))
+    }
+
+    pub fn is_zombie_village(&self) -> bool {
+        self.houses().iter().any(|house| piece_element(house).contains("/zombie/"))
+    }
+
+    pub fn bounding_box(&self) -> Option<&BoundingBox> {
+        self.bounding_box.get_or_init(|| self.structure.bounding_box()).as_ref()
+    }
+
+    pub fn chests(&self, dimension: &Dimension) -> Vec<Arc<BlockEntity>> {
+        block_entities_in_pieces(self.houses(), dimension, CHEST_BLOCK_ENTITIES)
+    }
+}
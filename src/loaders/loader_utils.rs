@@ -1,7 +1,14 @@
+use std::collections::HashSet;
+use std::fs;
 use std::fs::File;
-use std::io::{Read, Seek, SeekFrom};
-use std::path::PathBuf;
-use flate2::read::ZlibDecoder;
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use flate2::Compression;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
 use crate::constants::constants::{MCA_REGION_LOCATION_SECTOR_ENTRY_SIZE, MCA_REGION_SECTOR_SIZE, ZLIB_COMPRESSION_TYPE};
 use crate::models::other::lasso_string::LassoString;
 use crate::models::other::region::{Region, RegionType};
@@ -14,6 +21,14 @@ pub struct ParsedRegionChunk {
     pub compression_type: u8
 }
 
+// A chunk as it's stored in a region file, index is its slot in the location table
+pub struct RawRegionChunk {
+    pub index: usize,
+    pub timestamp: u32,
+    pub compression_type: u8,
+    pub data: Vec<u8>,
+}
+
 pub fn get_region_files_in_folder(folder: &PathBuf, dimension_name: LassoString, region_type: RegionType) -> Vec<Region> {
     if !folder.exists() {
         println!("{} does not exist, skipping...", folder.display());
@@ -92,6 +107,191 @@ pub fn parse_region_file(region: &Region) -> Vec<ParsedRegionChunk> {
     parsed_chunks
 }
 
+// Same as parse_region_file but errors instead of panicking and keeps the timestamps, for rewriting the file
+pub fn read_region_chunks(path: &Path) -> io::Result<Vec<RawRegionChunk>> {
+    let bytes = fs::read(path)?;
+    if bytes.len() < MCA_REGION_SECTOR_SIZE * 2 { return Err(io::Error::new(io::ErrorKind::InvalidData, "region file is missing its header")); }
+
+    let entry_count = MCA_REGION_SECTOR_SIZE / MCA_REGION_LOCATION_SECTOR_ENTRY_SIZE;
+    let mut chunks = Vec::new();
+    for index in 0..entry_count {
+        let entry = u32::from_be_bytes(bytes[index * 4..index * 4 + 4].try_into().unwrap());
+        let (sector_offset, sector_count) = ((entry >> 8) as usize, entry & 0xFF);
+        if sector_offset == 0 || sector_count == 0 { continue; }
+
+        let start = sector_offset * MCA_REGION_SECTOR_SIZE;
+        let Some(header) = bytes.get(start..start + 5) else { return Err(io::Error::new(io::ErrorKind::InvalidData, "chunk outside of the region file")) };
+        let length = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+        let Some(data) = bytes.get(start + 5..start + 4 + length) else { return Err(io::Error::new(io::ErrorKind::InvalidData, "chunk outside of the region file")) };
+        let timestamp_offset = MCA_REGION_SECTOR_SIZE + index * 4;
+        chunks.push(RawRegionChunk {
+            index,
+            timestamp: u32::from_be_bytes(bytes[timestamp_offset..timestamp_offset + 4].try_into().unwrap()),
+            compression_type: header[4],
+            data: data.to_vec(),
+        });
+    }
+    Ok(chunks)
+}
+
+// One chunk of a region file, without reading the others. None when the region doesn't have it
+pub fn read_region_chunk(path: &Path, index: usize) -> io::Result<Option<RawRegionChunk>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "chunk outside of the region file");
+    let mut file = File::open(path)?;
+    let mut entry = [0u8; 4];
+    file.seek(SeekFrom::Start((index * MCA_REGION_LOCATION_SECTOR_ENTRY_SIZE) as u64))?;
+    file.read_exact(&mut entry)?;
+    let entry = u32::from_be_bytes(entry);
+    let (sector_offset, sector_count) = ((entry >> 8) as usize, entry & 0xFF);
+    if sector_offset == 0 || sector_count == 0 { return Ok(None); }
+
+    let mut timestamp = [0u8; 4];
+    file.seek(SeekFrom::Start((MCA_REGION_SECTOR_SIZE + index * 4) as u64))?;
+    file.read_exact(&mut timestamp)?;
+    let mut header = [0u8; 5];
+    file.seek(SeekFrom::Start((sector_offset * MCA_REGION_SECTOR_SIZE) as u64))?;
+    file.read_exact(&mut header).map_err(|_| invalid())?;
+    let length = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+    let mut data = vec![0u8; length.checked_sub(1).ok_or_else(invalid)?];
+    file.read_exact(&mut data).map_err(|_| invalid())?;
+    Ok(Some(RawRegionChunk { index, timestamp: u32::from_be_bytes(timestamp), compression_type: header[4], data }))
+}
+
+// Writes a single region, see RegionWrites
+pub fn write_region_file(path: &Path, chunks: &[RawRegionChunk], backup: bool) -> io::Result<()> {
+    let mut writes = RegionWrites::new();
+    writes.stage(path, chunks)?;
+    writes.commit(backup)
+}
+
+fn region_bytes(chunks: &[RawRegionChunk]) -> io::Result<Vec<u8>> {
+    let mut header = vec![0u8; MCA_REGION_SECTOR_SIZE * 2];
+    let mut body = Vec::new();
+    for chunk in chunks {
+        let start_sector = 2 + body.len() / MCA_REGION_SECTOR_SIZE;
+        body.extend_from_slice(&(chunk.data.len() as u32 + 1).to_be_bytes());
+        body.push(chunk.compression_type);
+        body.extend_from_slice(&chunk.data);
+        body.resize(body.len().next_multiple_of(MCA_REGION_SECTOR_SIZE), 0);
+
+        let sector_count = 2 + body.len() / MCA_REGION_SECTOR_SIZE - start_sector;
+        if sector_count > 0xFF { return Err(io::Error::new(io::ErrorKind::InvalidData, "chunk is too big for a region file")); }
+        let location = ((start_sector as u32) << 8) | sector_count as u32;
+        header[chunk.index * 4..chunk.index * 4 + 4].copy_from_slice(&location.to_be_bytes());
+        let timestamp_offset = MCA_REGION_SECTOR_SIZE + chunk.index * 4;
+        header[timestamp_offset..timestamp_offset + 4].copy_from_slice(&chunk.timestamp.to_be_bytes());
+    }
+    header.extend_from_slice(&body);
+    Ok(header)
+}
+
+// A name next to path that no other write of this or another process uses
+fn unique_path(path: &Path, suffix: &str) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    path.with_extension(format!("{}.{}.{}.{}", extension, process::id(), COUNTER.fetch_add(1, Ordering::Relaxed), suffix))
+}
+
+// Region files that are replaced together. Each one is written (and synced) to a temporary file next to it first,
+// commit then renames them over the old files. The old files are kept until every rename went through, if one fails
+// the regions that were renamed already are put back, so the files on disk are either all old or all new. Temporary
+// files that weren't committed are removed when this is dropped
+#[derive(Default)]
+pub struct RegionWrites {
+    staged: Vec<(PathBuf, PathBuf)>, // temporary file, region file
+}
+
+impl RegionWrites {
+    pub fn new() -> Self { Self::default() }
+    pub fn is_empty(&self) -> bool { self.staged.is_empty() }
+
+    pub fn stage(&mut self, path: &Path, chunks: &[RawRegionChunk]) -> io::Result<()> {
+        let bytes = region_bytes(chunks)?;
+        if let Some(folder) = path.parent() { fs::create_dir_all(folder)?; }
+        let temp_path = unique_path(path, "tmp");
+        self.staged.push((temp_path.clone(), path.to_path_buf()));
+        let mut file = File::create(&temp_path)?;
+        file.write_all(&bytes)?;
+        file.sync_all()
+    }
+
+    // With backup the old files stay as <name>.mca.bak
+    pub fn commit(mut self, backup: bool) -> io::Result<()> {
+        let staged = std::mem::take(&mut self.staged);
+        let mut old_files = Vec::new(); // region file, where its old version is kept (None if it's new)
+        let mut result = Ok(());
+        for (_, path) in &staged {
+            if !path.exists() { old_files.push((path, None)); continue; }
+            let old_path = if backup { path.with_extension("mca.bak") } else { unique_path(path, "old") };
+            let _ = fs::remove_file(&old_path);
+            // a hard link is free, not every file system has them though
+            if let Err(e) = fs::hard_link(path, &old_path).or_else(|_| fs::copy(path, &old_path).map(|_| ())) { result = Err(e); break; }
+            old_files.push((path, Some(old_path)));
+        }
+
+        let mut renamed = 0;
+        if result.is_ok() {
+            for (temp_path, path) in &staged {
+                if let Err(e) = fs::rename(temp_path, path) { result = Err(e); break; }
+                renamed += 1;
+            }
+        }
+        if result.is_ok() { result = sync_folders(staged.iter().map(|(_, path)| path.as_path())); }
+
+        if result.is_err() {
+            for (path, old_path) in &old_files[..renamed.min(old_files.len())] {
+                let _ = match old_path {
+                    Some(old_path) if backup => fs::copy(old_path, path).map(|_| ()),
+                    Some(old_path) => fs::rename(old_path, path),
+                    None => fs::remove_file(path),
+                };
+            }
+            let _ = sync_folders(staged.iter().map(|(_, path)| path.as_path()));
+        }
+        for (temp_path, _) in &staged[renamed..] { let _ = fs::remove_file(temp_path); }
+        if !backup {
+            for (_, old_path) in &old_files { if let Some(old_path) = old_path { let _ = fs::remove_file(old_path); } }
+        }
+        result
+    }
+}
+
+impl Drop for RegionWrites {
+    fn drop(&mut self) {
+        for (temp_path, _) in &self.staged { let _ = fs::remove_file(temp_path); }
+    }
+}
+
+// Writes the file through a synced temporary file next to it, so it's either the old or the new one
+pub fn replace_file(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Some(folder) = path.parent() { fs::create_dir_all(folder)?; }
+    let temp_path = unique_path(path, "tmp");
+    let written = File::create(&temp_path)
+        .and_then(|mut file| { file.write_all(bytes)?; file.sync_all() })
+        .and_then(|_| fs::rename(&temp_path, path));
+    if written.is_err() { let _ = fs::remove_file(&temp_path); }
+    written?;
+    sync_folders(std::iter::once(path))
+}
+
+// A rename is only durable once the folder it happened in is synced
+fn sync_folders<'p>(paths: impl Iterator<Item = &'p Path>) -> io::Result<()> {
+    let folders = paths.filter_map(|p| p.parent()).collect::<HashSet<_>>();
+    for folder in folders {
+        #[cfg(unix)]
+        File::open(folder)?.sync_all()?;
+        #[cfg(not(unix))]
+        let _ = folder;
+    }
+    Ok(())
+}
+
+pub fn compress_zlib(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
 pub fn uncompress_zlib(data: Vec<u8>) -> Option<Vec<u8>> {
     fn guess_output_capacity(compressed_len: usize) -> usize {
         const MIN_START: usize = 256 * 1024;
@@ -112,6 +312,19 @@ pub fn uncompress_zlib(data: Vec<u8>) -> Option<Vec<u8>> {
     Some(decompressed)
 }
 
+// Player and level files are gzipped
+pub fn compress_gzip(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+pub fn uncompress_gzip(data: Vec<u8>) -> Option<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(&data[..]).read_to_end(&mut decompressed).ok()?;
+    Some(decompressed)
+}
+
 pub fn handle_chunk_compression(compression_type: u8, chunk_data: Vec<u8>) -> Option<Vec<u8>> {
     match compression_type {
         ZLIB_COMPRESSION_TYPE => {
@@ -124,10 +337,50 @@ pub fn handle_chunk_compression(compression_type: u8, chunk_data: Vec<u8>) -> Op
     }
 }
 
+pub fn u128_to_nbt_uuid(uuid: u128) -> [i32; 4] {
+    [(uuid >> 96) as u32 as i32, (uuid >> 64) as u32 as i32, (uuid >> 32) as u32 as i32, uuid as u32 as i32]
+}
+
 #[inline(always)]
 pub fn nbt_uuid_to_u128(data: [i32; 4]) -> u128 {
     ((data[0] as u32 as u128) << 96) |
         ((data[1] as u32 as u128) << 64) |
         ((data[2] as u32 as u128) << 32) |
         (data[3] as u32 as u128)
-}
\ No newline at end of file
+}
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_region_writes_leave_the_old_files() {
+        let folder = std::env::temp_dir().join(format!("cubicle_region_writes_test_{}", process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let chunk = |data: u8| RawRegionChunk { index: 0, timestamp: 0, compression_type: ZLIB_COMPRESSION_TYPE, data: vec![data] };
+        let (first, second) = (folder.join("r.0.0.mca"), folder.join("r.1.0.mca"));
+        write_region_file(&first, &[chunk(1)], false).unwrap();
+
+        // a folder where the second region goes can't be replaced
+        fs::create_dir_all(second.join("blocked")).unwrap();
+        let mut writes = RegionWrites::new();
+        writes.stage(&first, &[chunk(2)]).unwrap();
+        writes.stage(&second, &[chunk(2)]).unwrap();
+        assert!(writes.commit(false).is_err());
+
+        assert_eq!(read_region_chunks(&first).unwrap()[0].data, vec![1]);
+        let mut files = fs::read_dir(&folder).unwrap().map(|f| f.unwrap().file_name().into_string().unwrap()).collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, vec!["r.0.0.mca", "r.1.0.mca"]);
+
+        fs::remove_dir_all(&second).unwrap();
+        let mut writes = RegionWrites::new();
+        writes.stage(&first, &[chunk(3)]).unwrap();
+        writes.stage(&second, &[chunk(3)]).unwrap();
+        writes.commit(false).unwrap();
+        assert_eq!(read_region_chunks(&first).unwrap()[0].data, vec![3]);
+        assert_eq!(read_region_chunks(&second).unwrap()[0].data, vec![3]);
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
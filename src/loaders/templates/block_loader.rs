@@ -2,22 +2,37 @@ use std::collections::HashMap;
 use crate::constants::versions::Version;
 use crate::models::other::region::Region;
 use crate::models::world::chunk::Chunk;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use crate::loaders::v3465::block_loader::BlockLoaderV3465;
 use crate::models::other::lasso_string::LassoString;
+use crate::models::positions::whole_position::Position;
 use crate::models::world_structures::generic_structure::GenericParentStructure;
+use crate::types::RegionPosition;
 // TODO: Add more funcs and lazy loading like empty_load all regions so we get metadata of all chunks and we can count them, etc. - Think of a system later
 
-pub trait BlockLoader<'a> {
+pub trait BlockLoader<'a>: Send + Sync {
     fn get_region_files(&self, world_path: PathBuf) -> Vec<Region>;
     fn parse_region(&self, region: &Region) -> (Vec<Chunk>, HashMap<i64, Vec<GenericParentStructure>>);
+    // Same as parse_region, the chunks are parsed by up to threads workers (0 is one per core)
+    fn parse_region_parallel(&self, region: &Region, threads: usize) -> (Vec<Chunk>, HashMap<i64, Vec<GenericParentStructure>>);
     fn parse_chunk(
         &self,
         data: Vec<u8>,
         compression_type: u8,
         dimension: &LassoString,
     ) -> Option<(Chunk, Vec<GenericParentStructure>)>;
+
+    fn region_path(&self, world_path: &Path, position: &RegionPosition) -> PathBuf;
+    // Uncompressed chunk NBT. What the chunk doesn't model (lighting aside) is kept from original, the chunk as it is
+    // on disk, if there is one
+    fn write_chunk(&self, chunk: &Chunk, original: Option<Vec<u8>>) -> Option<Vec<u8>>;
+
+    // Where the points of interest (beds, workstations, ...) of the region's chunks are
+    fn poi_region_path(&self, world_path: &Path, position: &RegionPosition) -> PathBuf;
+    // Uncompressed POI chunk NBT without the records at positions, their sections are marked for the game to check
+    // again. None if it can't be read or there's nothing at positions
+    fn remove_poi_records(&self, poi_chunk: Vec<u8>, positions: &[Position]) -> Option<Vec<u8>>;
 }
 
 pub fn get_block_loader<'a>(version: Arc<Version>) -> Box<dyn BlockLoader<'a>> {
@@ -1,18 +1,24 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use crate::constants::versions::Version;
 use crate::loaders::v3465::entity_loader::EntityLoaderV3465;
 use crate::models::entity::entity::Entity;
 use crate::models::other::lasso_string::LassoString;
 use crate::models::other::region::Region;
+use crate::models::positions::chunk_position::ChunkPosition;
+use crate::types::RegionPosition;
 
 // TODO: Add more funcs and lazy loading like empty_load all regions so we get metadata of all chunks and we can count them, etc. - Think of a system later
 
-pub trait EntityLoader<'a> {
+pub trait EntityLoader<'a>: Send + Sync {
     fn get_region_files(&self, world_path: PathBuf) -> Vec<Region>;
     fn parse_region(&self, region: &Region) -> HashMap<(i32, i32), Vec<Entity>>;
     fn parse_entity_chunk(&self, data: Vec<u8>, compression_type: u8, dimension: &LassoString) -> Option<Vec<Entity>>;
+
+    fn region_path(&self, world_path: &Path, position: &RegionPosition) -> PathBuf;
+    // Uncompressed entity chunk NBT with exactly these entities
+    fn write_chunk(&self, chunk_position: &ChunkPosition, entities: &[&Entity]) -> Option<Vec<u8>>;
 }
 
 pub fn get_entity_loader<'a>(version: Arc<Version>) -> Box<dyn EntityLoader<'a>> {
@@ -4,9 +4,9 @@ use crate::constants::versions::Version;
 use crate::loaders::v3465::player_loader::PlayerLoaderV3465;
 use crate::models::entity::entity::PlayerEntity;
 
-pub trait PlayerLoader<'a> {
+pub trait PlayerLoader<'a>: Send + Sync {
     fn get_player_files(&self, world_path: PathBuf) -> Vec<PathBuf>;
-    fn parse_player(&self, player_path: &PathBuf) -> PlayerEntity;
+    fn parse_player(&self, player_path: &PathBuf) -> Option<PlayerEntity>;
 }
 
 pub fn get_player_loader<'a>(version: Arc<Version>) -> Box<dyn PlayerLoader<'a>> {
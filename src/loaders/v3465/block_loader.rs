@@ -6,14 +6,14 @@ use crate::models::other::region::{Region, RegionType};
 use crate::models::other::tick::Tick;
 use crate::models::world::block::PaletteBlock;
 use crate::models::world::chunk::Chunk;
-use fastnbt::Value;
+use fastnbt::{IntArray, LongArray, Value};
 use std::cmp::{max, min};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use crate::{transmute_map, I32};
 use crate::loaders::templates::block_loader::BlockLoader;
-use crate::loaders::v3465::utils::nbt_utils::{parse_nbt_item, parse_nbt_storage_container, parse_sign_text_block, parse_spawner_spawn_data, parse_spawner_spawn_potentials, take_bool, take_i16, take_i32, take_i32_vec, take_i8, take_inventory, take_list, take_long, take_map, take_string, take_text_component};
+use crate::loaders::v3465::utils::nbt_utils::{parse_nbt_item, parse_nbt_storage_container, parse_sign_text_block, parse_spawner_spawn_data, parse_spawner_spawn_potentials, take_bool, take_i16, take_i32, take_i32_vec, take_i8, take_inventory, take_list, take_long, take_map, take_string, take_text_component, write_inventory, write_nbt_item, write_sign_text_block, write_spawner_spawn_data, write_spawner_spawn_potentials};
 use crate::models::block_entity::block_entity::BlockEntity;
 use crate::models::block_entity::prelude::*;
 use crate::models::block_entity::types::cooker::cooker::CookerBlockEntity;
@@ -30,8 +30,11 @@ use crate::models::stores::heightmap_store::HeightmapStore;
 use crate::models::stores::structure_store::StructureStoreReference;
 use crate::models::world::tile_tick::{TileTick, TileTickType};
 use crate::models::world_structures::generic_structure::{BoundingBox, GenericChildStructure, GenericParentStructure};
-use crate::types::{HeightmapKind, WorldKind};
+use crate::types::{HeightmapKind, RegionPosition, WorldKind};
+use crate::traits::block_entity::BlockEntityTrait;
+use crate::utils::position_utils::block_position_to_chunk_pos_and_block_index;
 use crate::utils::generic_utils::bit_length;
+use crate::utils::parallel::parallel_map;
 // TODO: Support other dimensions (custom paths)
 
 pub struct BlockLoaderV3465 {
@@ -232,8 +235,9 @@ impl BlockLoaderV3465 {
                         take_i16(props, "RequiredPlayerRange").unwrap_or(0),
                         take_i16(props, "SpawnCount").unwrap_or(0),
                         take_i16(props, "SpawnRange").unwrap_or(0),
-                        parse_spawner_spawn_data(take_map(props, "SpawnData"), dimension),
-                        parse_spawner_spawn_potentials(take_list(props, "SpawnPotentials"), dimension).unwrap_or_default(),
+                        // read from copies, the raw spawn data stays in the properties (see SpawnerBlockEntity::drop_raw)
+                        parse_spawner_spawn_data(take_map(&mut props.clone(), "SpawnData"), dimension),
+                        parse_spawner_spawn_potentials(take_list(&mut props.clone(), "SpawnPotentials"), dimension).unwrap_or_default(),
                     )
                 )
             },
@@ -364,41 +368,237 @@ impl BlockLoaderV3465 {
     }
 }
 
-impl<'a> BlockLoader<'a> for BlockLoaderV3465 {
-    fn get_region_files(&self, world_path: PathBuf) -> Vec<Region> {
-        let overworld_region_folder = world_path.join((if self.version.world_type() == &WorldKind::Multiplayer { "world/" } else { "" }).to_owned() + "region");
-        let nether_region_folder = world_path.join((if self.version.world_type() == &WorldKind::Multiplayer { "world/" } else { "" }).to_owned() + "DIM-1/region");
-        let end_region_folder = world_path.join((if self.version.world_type() == &WorldKind::Multiplayer { "world/" } else { "" }).to_owned() + "DIM1/region");
+// Writing
+impl BlockLoaderV3465 {
+    fn region_folder(&self, world_path: &Path, dimension: &LassoString) -> PathBuf {
+        let prefix = if self.version.world_type() == &WorldKind::Multiplayer { "world/" } else { "" };
+        let folder = match dimension.get() {
+            "the_nether" => "DIM-1/region",
+            "the_end" => "DIM1/region",
+            _ => "region",
+        };
+        world_path.join(prefix.to_owned() + folder)
+    }
 
+    // A section palette (as indices into the store palette) and its packed data. Entries don't span longs and a
+    // single entry palette has no data, like the game writes them
+    fn pack_section(indices: &[usize], min_bits: u32) -> (Vec<usize>, Option<Vec<i64>>) {
+        let mut palette = Vec::new();
+        let mut local = HashMap::new();
+        let entries = indices.iter().map(|index| *local.entry(*index).or_insert_with(|| { palette.push(*index); palette.len() - 1 })).collect::<Vec<_>>();
+        if palette.len() == 1 { return (palette, None); }
+
+        let bits = max(bit_length(palette.len() as i32 - 1), min_bits) as usize;
+        let entries_per_long = 64 / bits;
+        let mut longs = vec![0u64; entries.len().div_ceil(entries_per_long)];
+        for (i, entry) in entries.into_iter().enumerate() {
+            longs[i / entries_per_long] |= (entry as u64) << ((i % entries_per_long) * bits);
+        }
+        (palette, Some(longs.into_iter().map(|l| l as i64).collect()))
+    }
 
-        let mut regions = Vec::<Region>::new();
+    fn write_palette_block(block: &PaletteBlock) -> Value {
+        let mut compound = HashMap::from([("Name".to_string(), Value::String(block.full_name().to_string()))]);
+        let properties = block.properties().raw().iter().map(|(key, value)| {
+            let value = match value {
+                Value::Byte(b) => (*b != 0).to_string(),
+                Value::Int(i) => i.to_string(),
+                Value::String(s) => s.clone(),
+                other => format!("{:?}", other),
+            };
+            (key.clone(), Value::String(value))
+        }).collect::<HashMap<_, _>>();
+        if !properties.is_empty() { compound.insert("Properties".to_string(), Value::Compound(properties)); }
+        Value::Compound(compound)
+    }
+
+    fn write_sections(&self, chunk: &Chunk, old_sections: Vec<Value>) -> Vec<Value> {
+        let section_y = |section: &HashMap<String, Value>| match section.get("Y") {
+            Some(Value::Byte(y)) => Some(*y as i32),
+            Some(Value::Int(y)) => Some(*y),
+            _ => None,
+        };
+        let mut old_sections = old_sections.into_iter().filter_map(|section| match section {
+            Value::Compound(section) => section_y(&section).map(|y| (y, section)),
+            _ => None,
+        }).collect::<HashMap<_, _>>();
+
+        let (block_store, biome_store) = (chunk.block_store(), chunk.biome_store());
+        let section_block_count = (self.version.data.section_height * self.version.data.chunk_size * self.version.data.chunk_size) as usize;
+        let section_biome_count = section_block_count / BIOME_CELL_SIZE.pow(3) as usize;
+        let first_section = self.version.data.lowest_y / self.version.data.section_height;
+        let section_count = (self.version.data.highest_y - self.version.data.lowest_y) / self.version.data.section_height;
+
+        // null entries are written as air and plains
+        let air = block_store.get_palette_index_of_block(&PaletteBlock::new("minecraft:air", None)).unwrap_or(0);
+        let plains = biome_store.get_palette_index_of_biome(&"minecraft:plains".to_string()).unwrap_or(0);
+        let block_nbt = |index: usize| if index == 0 && air == 0 { Self::write_palette_block(&PaletteBlock::new("minecraft:air", None)) } else { Self::write_palette_block(&block_store.palette()[index]) };
+        let biome_nbt = |index: usize| if index == 0 && plains == 0 { Value::String("minecraft:plains".to_string()) } else { Value::String(biome_store.palette()[index].clone()) };
+
+        let mut sections = Vec::with_capacity(section_count as usize);
+        for i in 0..section_count as usize {
+            let y = first_section + i as i32;
+            let mut section = old_sections.remove(&y).unwrap_or_default();
+            section.remove("BlockLight");
+            section.remove("SkyLight");
+            section.insert("Y".to_string(), Value::Byte(y as i8));
+
+            let blocks = block_store.indices_slice()[i * section_block_count..(i + 1) * section_block_count].iter().map(|b| if *b == 0 { air } else { *b }).collect::<Vec<_>>();
+            let (palette, data) = Self::pack_section(&blocks, 4);
+            let mut block_states = HashMap::from([("palette".to_string(), Value::List(palette.into_iter().map(block_nbt).collect()))]);
+            if let Some(data) = data { block_states.insert("data".to_string(), Value::LongArray(LongArray::new(data))); }
+            section.insert("block_states".to_string(), Value::Compound(block_states));
+
+            let biomes = biome_store.indices_slice()[i * section_biome_count..(i + 1) * section_biome_count].iter().map(|b| if *b == 0 { plains } else { *b }).collect::<Vec<_>>();
+            let (palette, data) = Self::pack_section(&biomes, 0);
+            let mut biome_states = HashMap::from([("palette".to_string(), Value::List(palette.into_iter().map(biome_nbt).collect()))]);
+            if let Some(data) = data { biome_states.insert("data".to_string(), Value::LongArray(LongArray::new(data))); }
+            section.insert("biomes".to_string(), Value::Compound(biome_states));
+
+            sections.push(Value::Compound(section));
+        }
+        sections
+    }
+
+    // Everything comes from the block entities, what a typed one doesn't model was left in its properties by
+    // parse_block_entities
+    fn write_block_entities(&self, chunk: &Chunk) -> Vec<Value> {
+        chunk.block_entities_store().get_all().iter().map(|block_entity| {
+            let base = block_entity.base();
+            let mut compound = self.write_block_entity_fields(block_entity);
+            compound.extend(base.properties().raw().iter().map(|(k, v)| (k.clone(), v.clone())));
+            compound.insert("id".to_string(), Value::String(base.id().to_string()));
+            compound.insert("x".to_string(), Value::Int(base.position().x()));
+            compound.insert("y".to_string(), Value::Int(base.position().y()));
+            compound.insert("z".to_string(), Value::Int(base.position().z()));
+            compound.insert("keepPacked".to_string(), Value::Byte(0));
+            Value::Compound(compound)
+        }).collect()
+    }
+
+    // The fields parse_block_entities takes out of the NBT, under the same keys
+    fn write_block_entity_fields(&self, block_entity: &BlockEntity) -> HashMap<String, Value> {
+        let mut map = HashMap::new();
+        let tick = |t: &Tick| Value::Short(t.get_tick() as i16);
+        if let Some(items) = block_entity.items() { map.insert("Items".to_string(), write_inventory(items)); }
+        if let Some(lock) = block_entity.lock() { map.insert("Lock".to_string(), Value::String(lock.to_string())); }
+        if let Some(name) = block_entity.custom_name() { map.insert("CustomName".to_string(), Value::String(name.to_json_string())); }
+
+        match block_entity {
+            BlockEntity::StorageContainer(StorageContainerBlockEntity::Hopper(h)) => {
+                map.insert("TransferCooldown".to_string(), Value::Int(h.transfer_cooldown()));
+            },
+            BlockEntity::StorageContainer(StorageContainerBlockEntity::ChiseledBookshelf(b)) => {
+                map.insert("last_interacted_slot".to_string(), Value::Int(b.last_interacted_slot()));
+            },
+            BlockEntity::StorageContainer(_) => {},
+            BlockEntity::Cooker(CookerBlockEntity::Furnace(f)) => {
+                map.insert("RecipesUsed".to_string(), Value::Compound(f.cook_history().iter().map(|(k, v)| (k.clone(), Value::Int(*v))).collect()));
+                map.insert("BurnTime".to_string(), tick(f.current_fuel_ticks_left()));
+                map.insert("CookTime".to_string(), tick(f.current_item_cooking_ticks_spent()));
+                map.insert("CookTimeTotal".to_string(), tick(f.total_cooking_ticks_for_current_item()));
+            },
+            BlockEntity::Cooker(CookerBlockEntity::Campfire(c)) => {
+                let ticks = |t: &Vec<Tick>| Value::IntArray(IntArray::new(t.iter().map(|t| t.get_tick() as i32).collect()));
+                map.insert("CookingTimes".to_string(), ticks(c.ticks_spent_cooking_all()));
+                map.insert("CookingTotalTimes".to_string(), ticks(c.total_ticks_to_cook_all()));
+            },
+            BlockEntity::Cooker(CookerBlockEntity::BrewingStand(b)) => {
+                map.insert("BrewTime".to_string(), tick(b.ticks_left_to_brew()));
+                map.insert("Fuel".to_string(), Value::Byte(b.applied_fuel_left()));
+            },
+            BlockEntity::Lectern(l) => {
+                if let Some(book) = l.book() { map.insert("Book".to_string(), write_nbt_item(None, book)); }
+                map.insert("Page".to_string(), Value::Int(l.page()));
+            },
+            BlockEntity::Spawner(s) => {
+                map.insert("Delay".to_string(), tick(s.ticks_until_next_spawn()));
+                map.insert("MaxNearbyEntities".to_string(), Value::Short(s.max_nearby_entities()));
+                map.insert("MaxSpawnDelay".to_string(), tick(&s.max_spawn_delay()));
+                map.insert("MinSpawnDelay".to_string(), tick(&s.min_spawn_delay()));
+                map.insert("RequiredPlayerRange".to_string(), Value::Short(s.required_player_range()));
+                map.insert("SpawnCount".to_string(), Value::Short(s.spawn_batch_size()));
+                map.insert("SpawnRange".to_string(), Value::Short(s.spawn_range()));
+                // the raw spawn data in the properties replaces these unless the spawner's spawn data was changed
+                map.insert("SpawnData".to_string(), write_spawner_spawn_data(s.next_spawn_data()));
+                map.insert("SpawnPotentials".to_string(), write_spawner_spawn_potentials(s.spawn_potentials()));
+            },
+            BlockEntity::CommandBlock(c) => {
+                map.insert("auto".to_string(), Value::Byte(c.is_auto() as i8));
+                map.insert("Command".to_string(), Value::String(c.command().clone()));
+                map.insert("conditionMet".to_string(), Value::Byte(c.is_condition_met() as i8));
+                map.insert("LastExecution".to_string(), Value::Long(c.last_executed().get_tick() as i64));
+                if !c.last_output().is_empty() { map.insert("LastOutput".to_string(), Value::String(c.last_output().clone())); }
+                map.insert("powered".to_string(), Value::Byte(c.is_powered() as i8));
+                map.insert("SuccessCount".to_string(), Value::Int(c.success_count()));
+                map.insert("TrackOutput".to_string(), Value::Byte(c.is_keep_last_output() as i8));
+                map.insert("UpdateLastExecution".to_string(), Value::Byte(c.is_update_last_executed() as i8));
+            },
+            BlockEntity::Sign(s) => {
+                map.insert("is_waxed".to_string(), Value::Byte(*s.is_waxed() as i8));
+                map.insert("front_text".to_string(), write_sign_text_block(s.front_text()));
+                map.insert("back_text".to_string(), write_sign_text_block(s.back_text()));
+            },
+            BlockEntity::Other(_) => {},
+        }
+        map
+    }
 
-        regions.extend(get_region_files_in_folder(&overworld_region_folder, "overworld".into(), RegionType::Block));
-        regions.extend(get_region_files_in_folder(&nether_region_folder, "the_nether".into(), RegionType::Block));
-        regions.extend(get_region_files_in_folder(&end_region_folder, "the_end".into(), RegionType::Block));
+    // Tile ticks don't keep their block id, it's the block that's there now
+    fn write_tile_ticks(&self, chunk: &Chunk) -> (Vec<Value>, Vec<Value>) {
+        let store = chunk.block_store();
+        let (mut block_ticks, mut fluid_ticks) = (Vec::new(), Vec::new());
+        for tile_tick in chunk.tile_ticks() {
+            let position = tile_tick.position();
+            let (_, index) = block_position_to_chunk_pos_and_block_index(position, self.version.data.chunk_size, self.version.data.lowest_y);
+            let block = store.get_block_at_index(index).map(|b| b.full_name().to_string()).unwrap_or_else(|| "minecraft:air".to_string());
+            let id = match tile_tick.tile_tick_type() {
+                TileTickType::BLOCK => block,
+                TileTickType::FLUID => if block == "minecraft:lava" { block } else { "minecraft:water".to_string() },
+            };
+            let compound = Value::Compound(HashMap::from([
+                ("i".to_string(), Value::String(id)),
+                ("p".to_string(), Value::Int(tile_tick.priority())),
+                ("t".to_string(), Value::Int(tile_tick.ticks_till_processed())),
+                ("x".to_string(), Value::Int(position.x())),
+                ("y".to_string(), Value::Int(position.y())),
+                ("z".to_string(), Value::Int(position.z())),
+            ]));
+            match tile_tick.tile_tick_type() {
+                TileTickType::BLOCK => block_ticks.push(compound),
+                TileTickType::FLUID => fluid_ticks.push(compound),
+            }
+        }
+        (block_ticks, fluid_ticks)
+    }
+}
 
+impl<'a> BlockLoader<'a> for BlockLoaderV3465 {
+    fn get_region_files(&self, world_path: PathBuf) -> Vec<Region> {
+        let mut regions = Vec::<Region>::new();
+        for dimension in ["overworld", "the_nether", "the_end"] {
+            regions.extend(get_region_files_in_folder(&self.region_folder(&world_path, &dimension.into()), dimension.into(), RegionType::Block));
+        }
         regions
     }
 
     fn parse_region(&self, region: &Region) -> (Vec<Chunk>, HashMap<i64, Vec<GenericParentStructure>>) {
+        self.parse_region_parallel(region, 1)
+    }
+
+    fn parse_region_parallel(&self, region: &Region, threads: usize) -> (Vec<Chunk>, HashMap<i64, Vec<GenericParentStructure>>) {
         let parsed_chunks = parse_region_file(region);
+        let dimension = region.position.dimension();
+        let chunks_data = parallel_map(parsed_chunks, threads, |parsed_chunk| {
+            self.parse_chunk(parsed_chunk.raw_bytes, parsed_chunk.compression_type, dimension)
+        });
 
-        let mut chunks = Vec::with_capacity(parsed_chunks.len());
+        let mut chunks = Vec::with_capacity(chunks_data.len());
         let mut new_structures = HashMap::new();
-
-        unsafe {
-            for parsed_chunk in parsed_chunks {
-                let chunk_data = self.parse_chunk(
-                    parsed_chunk.raw_bytes,
-                    parsed_chunk.compression_type,
-                    region.position.dimension(),
-                );
-                if let Some(chunk_data) = chunk_data {
-                    let chunk_ref = chunk_data.0.position().reference();
-                    chunks.push(chunk_data.0);
-                    new_structures.entry(chunk_ref).or_insert_with(Vec::new).extend(chunk_data.1);
-                }
-            }
+        for (chunk, structures) in chunks_data.into_iter().flatten() {
+            let chunk_ref = chunk.position().reference();
+            chunks.push(chunk);
+            new_structures.entry(chunk_ref).or_insert_with(Vec::new).extend(structures);
         }
         (chunks, new_structures)
     }
@@ -434,6 +634,68 @@ impl<'a> BlockLoader<'a> for BlockLoaderV3465 {
         let dim_id = chunk.position().dimension().clone();
         let structures = self.populate_chunk_with_structures(&mut chunk, &mut chunk_nbt);
         unsafe { self.populate_chunk_with_blocks(&mut chunk, chunk_nbt, &dim_id); }
+        chunk.mark_clean();
         Some((chunk, structures))
     }
+
+    fn region_path(&self, world_path: &Path, position: &RegionPosition) -> PathBuf {
+        self.region_folder(world_path, position.dimension()).join(format!("r.{}.{}.mca", position.x(), position.z()))
+    }
+
+    fn poi_region_path(&self, world_path: &Path, position: &RegionPosition) -> PathBuf {
+        self.region_folder(world_path, position.dimension()).with_file_name("poi").join(format!("r.{}.{}.mca", position.x(), position.z()))
+    }
+
+    fn remove_poi_records(&self, poi_chunk: Vec<u8>, positions: &[Position]) -> Option<Vec<u8>> {
+        let mut nbt: HashMap<String, Value> = fastnbt::from_bytes(&poi_chunk).ok()?;
+        let Some(Value::Compound(sections)) = nbt.get_mut("Sections") else { return None };
+        let mut changed = false;
+        for position in positions {
+            let section_y = position.y().div_euclid(self.version.data.section_height);
+            let Some(Value::Compound(section)) = sections.get_mut(&section_y.to_string()) else { continue };
+            // an invalid section is checked against the blocks when the chunk loads, so new POI blocks get records too
+            section.insert("Valid".to_string(), Value::Byte(0));
+            if let Some(Value::List(records)) = section.get_mut("Records") {
+                let at = [position.x(), position.y(), position.z()];
+                records.retain(|record| !matches!(record, Value::Compound(r) if matches!(r.get("pos"), Some(Value::IntArray(pos)) if **pos == at)));
+            }
+            changed = true;
+        }
+        if !changed { return None; }
+        fastnbt::to_bytes(&Value::Compound(nbt)).ok()
+    }
+
+    fn write_chunk(&self, chunk: &Chunk, original: Option<Vec<u8>>) -> Option<Vec<u8>> {
+        let mut nbt: HashMap<String, Value> = match original {
+            Some(bytes) => fastnbt::from_bytes(&bytes).ok()?,
+            None => HashMap::from([
+                ("structures".to_string(), Value::Compound(HashMap::from([
+                    ("References".to_string(), Value::Compound(HashMap::new())),
+                    ("starts".to_string(), Value::Compound(HashMap::new())),
+                ]))),
+            ]),
+        };
+
+        nbt.insert("DataVersion".to_string(), Value::Int(self.version.data.version_data));
+        nbt.insert("xPos".to_string(), Value::Int(chunk.position().x()));
+        nbt.insert("zPos".to_string(), Value::Int(chunk.position().z()));
+        nbt.insert("yPos".to_string(), Value::Int(self.version.data.lowest_y / self.version.data.section_height));
+        nbt.insert("Status".to_string(), Value::String(chunk.status().clone()));
+        nbt.insert("LastUpdate".to_string(), Value::Long(chunk.last_update().get_tick() as i64));
+        nbt.insert("InhabitedTime".to_string(), Value::Long(chunk.inhabited_time().get_tick() as i64));
+        // lighting isn't modelled, the game relights the chunk when it loads it
+        nbt.insert("isLightOn".to_string(), Value::Byte(0));
+        nbt.insert("Heightmaps".to_string(), Value::Compound(chunk.heightmap_store().to_nbt()));
+
+        let old_sections = take_list(&mut nbt, "sections").unwrap_or_default();
+        nbt.insert("sections".to_string(), Value::List(self.write_sections(chunk, old_sections)));
+
+        nbt.insert("block_entities".to_string(), Value::List(self.write_block_entities(chunk)));
+
+        let (block_ticks, fluid_ticks) = self.write_tile_ticks(chunk);
+        nbt.insert("block_ticks".to_string(), Value::List(block_ticks));
+        nbt.insert("fluid_ticks".to_string(), Value::List(fluid_ticks));
+
+        fastnbt::to_bytes(&Value::Compound(nbt)).ok()
+    }
 }
@@ -1,16 +1,18 @@
 use std::collections::HashMap;
 use std::hash::Hash;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use fastnbt::{IntArray, Value};
 use std::sync::Arc;
 use crate::models::other::region::{Region, RegionType};
 use crate::models::nbt_structures::v3465::entities::{NBTChunk};
 use crate::constants::versions::Version;
 use crate::loaders::loader_utils::{get_region_files_in_folder, handle_chunk_compression, nbt_uuid_to_u128, parse_region_file, uncompress_zlib};
 use crate::loaders::templates::entity_loader::EntityLoader;
-use crate::loaders::v3465::utils::entity_utils::parse_nbt_entity;
+use crate::loaders::v3465::utils::entity_utils::{parse_nbt_entity, write_nbt_entity};
+use crate::models::positions::chunk_position::ChunkPosition;
 use crate::models::entity::entity::{Entity, EntityType, MobEntity};
 use crate::models::other::lasso_string::LassoString;
-use crate::types::WorldKind;
+use crate::types::{RegionPosition, WorldKind};
 use crate::utils::position_utils::chunk_offset_to_position;
 // TODO: Support other dimensions (custom paths)
 
@@ -67,6 +69,25 @@ impl<'a> EntityLoader<'a> for EntityLoaderV3465 {
         self.populate_entity_list(&mut entities, chunk_nbt, dimension);
         Some(entities)
     }
+
+    fn region_path(&self, world_path: &Path, position: &RegionPosition) -> PathBuf {
+        let prefix = if self.version.world_type() == &WorldKind::Multiplayer { "world/" } else { "" };
+        let folder = match position.dimension().get() {
+            "the_nether" => "DIM-1/entities",
+            "the_end" => "DIM1/entities",
+            _ => "entities",
+        };
+        world_path.join(prefix.to_owned() + folder).join(format!("r.{}.{}.mca", position.x(), position.z()))
+    }
+
+    fn write_chunk(&self, chunk_position: &ChunkPosition, entities: &[&Entity]) -> Option<Vec<u8>> {
+        let nbt = HashMap::from([
+            ("DataVersion".to_string(), Value::Int(self.version.data.version_data)),
+            ("Position".to_string(), Value::IntArray(IntArray::new(vec![chunk_position.x(), chunk_position.z()]))),
+            ("Entities".to_string(), Value::List(entities.iter().map(|e| Value::Compound(write_nbt_entity(e))).collect())),
+        ]);
+        fastnbt::to_bytes(&Value::Compound(nbt)).ok()
+    }
 }
 
 
@@ -1,23 +1,50 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use fastnbt::Value;
 use crate::constants::versions::Version;
-use crate::loaders::templates::player_loader;
+use crate::loaders::loader_utils::uncompress_gzip;
 use crate::loaders::templates::player_loader::PlayerLoader;
-use crate::loaders::v3465::utils::entity_utils::parse_nbt_entity;
+use crate::loaders::v3465::utils::entity_utils::parse_nbt_player;
 use crate::models::entity::entity::PlayerEntity;
-use crate::models::nbt_structures::v3465::entities::NBTChunk;
-use crate::models::other::lasso_string::LassoString;
+use crate::types::WorldKind;
 
 pub struct PlayerLoaderV3465 {
     pub version: Arc<Version>
 }
 
+// usercache.json is next to the world folder on servers, maps uuids to the last known names
+fn cached_player_name(player_path: &Path) -> Option<String> {
+    let uuid = player_path.file_stem()?.to_str()?;
+    let world_folder = player_path.parent()?.parent()?;
+    let cache_path = [Some(world_folder), world_folder.parent()].into_iter().flatten()
+        .map(|folder| folder.join("usercache.json"))
+        .find(|path| path.exists())?;
+
+    let cache: Vec<serde_json::Value> = serde_json::from_slice(&fs::read(cache_path).ok()?).ok()?;
+    cache.iter()
+        .find(|entry| entry.get("uuid").and_then(|u| u.as_str()).is_some_and(|u| u.eq_ignore_ascii_case(uuid)))
+        .and_then(|entry| entry.get("name")?.as_str().map(str::to_owned))
+}
+
 impl<'a> PlayerLoader<'a> for PlayerLoaderV3465 {
     fn get_player_files(&self, world_path: PathBuf) -> Vec<PathBuf> {
-        todo!()
+        let folder = world_path.join((if self.version.world_type() == &WorldKind::Multiplayer {"world/"} else {""}).to_owned() + "playerdata");
+        // worlds nobody has joined yet have no playerdata folder
+        let Ok(entries) = folder.read_dir() else { return Vec::new() };
+
+        let mut paths = entries.filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "dat"))
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths
     }
 
-    fn parse_player(&self, player_path: &PathBuf) -> PlayerEntity {
-        todo!()
+    fn parse_player(&self, player_path: &PathBuf) -> Option<PlayerEntity> {
+        let data = uncompress_gzip(fs::read(player_path).ok()?)?;
+        let map: HashMap<String, Value> = fastnbt::from_bytes(&data).ok()?;
+        parse_nbt_player(map, cached_player_name(player_path))
     }
-}
\ No newline at end of file
+}
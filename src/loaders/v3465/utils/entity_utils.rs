@@ -1,7 +1,9 @@
 use std::collections::HashMap;
-use fastnbt::Value;
-use crate::loaders::loader_utils::nbt_uuid_to_u128;
-use crate::models::entity::entity::{Entity, MobEntity};
+use fastnbt::{IntArray, Value};
+use crate::loaders::loader_utils::{nbt_uuid_to_u128, u128_to_nbt_uuid};
+use crate::constants::constants::{PLAYER_ENTITY_ID, PLAYER_INVENTORY_SLOT_COUNT};
+use crate::loaders::v3465::utils::nbt_utils::{take_i32, take_inventory};
+use crate::models::entity::entity::{Entity, GameMode, GenericEntity, MobEntity, PlayerEntity};
 use crate::models::nbt_structures::v3465::entities::NBTEntity;
 use crate::models::other::lasso_string::LassoString;
 use crate::models::other::properties::Properties;
@@ -36,4 +38,73 @@ pub fn parse_raw_nbt_entity(raw: Option<HashMap<String, Value>>, dimension: &Las
         }
     }
     None
-}
\ No newline at end of file
+}
+// The other way around, what the entity doesn't model comes from its properties
+pub fn write_nbt_entity(entity: &Entity) -> HashMap<String, Value> {
+    let base = entity.base();
+    let position = base.position();
+    let (mx, my, mz) = *base.velocity_vector();
+    let mut map = entity.properties().raw().clone();
+    map.extend([
+        ("Air".to_string(), Value::Short(base.air_remaining().get_tick() as i16)),
+        ("FallDistance".to_string(), Value::Float(*base.distance_fallen())),
+        ("Fire".to_string(), Value::Short(base.fire_ticks_left().get_tick() as i16)),
+        ("Invulnerable".to_string(), Value::Byte(*base.is_invulnerable() as i8)),
+        ("Motion".to_string(), Value::List(vec![Value::Double(mx), Value::Double(my), Value::Double(mz)])),
+        ("OnGround".to_string(), Value::Byte(*base.is_on_ground() as i8)),
+        ("Pos".to_string(), Value::List(vec![Value::Double(position.x()), Value::Double(position.y()), Value::Double(position.z())])),
+        ("Rotation".to_string(), Value::List(vec![Value::Float(position.yaw() as f32), Value::Float(position.pitch() as f32)])),
+        ("UUID".to_string(), Value::IntArray(IntArray::new(u128_to_nbt_uuid(base.uuid()).to_vec()))),
+        ("id".to_string(), Value::String(entity.id().to_string())),
+    ]);
+    map
+}
+
+// Player files store the hotbar and main inventory in slots 0-35, armor in 100-103 and the offhand in -106.
+// In the Inventory they're 0-35, 36-39 and 40 like in vanilla's player inventory
+fn player_inventory_slot(slot: i8) -> Option<i8> {
+    match slot {
+        0..=35 => Some(slot),
+        100..=103 => Some(slot - 64),
+        -106 => Some(40),
+        _ => None
+    }
+}
+
+pub fn parse_nbt_player(mut map: HashMap<String, Value>, name: Option<String>) -> Option<PlayerEntity> {
+    if let Some(Value::List(items)) = map.get_mut("Inventory") {
+        items.retain_mut(|item| match item {
+            Value::Compound(item) => match item.get("Slot") {
+                Some(Value::Byte(slot)) => match player_inventory_slot(*slot) {
+                    Some(slot) => { item.insert("Slot".to_string(), Value::Byte(slot)); true },
+                    None => false
+                },
+                _ => false
+            },
+            _ => false
+        });
+    }
+    let inventory = take_inventory(&mut map, "Inventory", PLAYER_INVENTORY_SLOT_COUNT);
+    let game_mode = take_i32(&mut map, "playerGameType").and_then(GameMode::from_id).unwrap_or(GameMode::Survival);
+    let dimension = match map.get("Dimension") {
+        Some(Value::String(d)) => d.strip_prefix("minecraft:").unwrap_or(d).to_string(),
+        _ => "overworld".to_string()
+    };
+    map.insert("id".to_string(), Value::String(PLAYER_ENTITY_ID.to_string()));
+
+    let nbt_entity: NBTEntity = fastnbt::from_value(&Value::Compound(map)).ok()?;
+    let Value::IntArray(uuid) = &nbt_entity.uuid else { return None };
+    let uuid = nbt_uuid_to_u128(<[i32; 4]>::try_from(&**uuid).ok()?);
+    let base = GenericEntity::new(
+        Tick::new(nbt_entity.air_left.max(0) as usize),
+        nbt_entity.distance_fallen,
+        Tick::new(nbt_entity.fire_ticks_left.max(0) as usize),
+        nbt_entity.is_invulnerable,
+        <(f64, f64, f64)>::from(nbt_entity.motion),
+        nbt_entity.is_on_ground,
+        EntityPosition::new(nbt_entity.position[0], nbt_entity.position[1], nbt_entity.position[2], nbt_entity.rotation[0], nbt_entity.rotation[1], LassoString::from(dimension.as_str())),
+        uuid
+    );
+
+    Some(PlayerEntity::new(base, name, game_mode, inventory, Properties::new(nbt_entity.others)))
+}
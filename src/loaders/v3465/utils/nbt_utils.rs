@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use fastnbt::Value;
-use crate::loaders::v3465::utils::entity_utils::{parse_nbt_entity, parse_raw_nbt_entity};
+use crate::loaders::v3465::utils::entity_utils::{parse_nbt_entity, parse_raw_nbt_entity, write_nbt_entity};
 use crate::models::block_entity::block_entity::GenericBlockEntity;
 use crate::models::block_entity::prelude::*;
 use crate::models::other::inventory::{Inventory, Item};
@@ -140,6 +140,54 @@ pub fn parse_sign_text_block(map: Option<HashMap<String, Value>>) -> SignTextBlo
     SignTextBlock::new(false, MinecraftColor::Black, Vec::new())
 }
 
+// Writing, the inverse of the parsing above
+pub fn write_nbt_item(slot: Option<usize>, item: &Item) -> Value {
+    let mut map = item.properties().raw().clone();
+    map.insert("id".to_string(), Value::String(item.id().to_string()));
+    map.insert("Count".to_string(), Value::Byte(item.count() as i8));
+    if let Some(slot) = slot { map.insert("Slot".to_string(), Value::Byte(slot as i8)); }
+    Value::Compound(map)
+}
+
+pub fn write_inventory(inventory: &Inventory) -> Value {
+    let mut slots = inventory.get_all().iter().collect::<Vec<_>>();
+    slots.sort_by_key(|(slot, _)| **slot);
+    Value::List(slots.into_iter().map(|(slot, item)| write_nbt_item(Some(*slot), item)).collect())
+}
+
+pub fn write_spawner_spawn_data(data: &SpawnerSpawnData) -> Value {
+    let mut map = HashMap::from([("entity".to_string(), Value::Compound(write_nbt_entity(data.entity())))]);
+    if let Some(rules) = data.spawn_rules() {
+        let range = |r: &LightLimitRange| fastnbt::to_value(r).unwrap_or(Value::Compound(HashMap::new()));
+        map.insert("custom_spawn_rules".to_string(), Value::Compound(HashMap::from([
+            ("block_light_limit".to_string(), range(&rules.block_light_range)),
+            ("sky_light_limit".to_string(), range(&rules.sky_light_range)),
+        ])));
+    }
+    if let Some(equipment) = data.equipment() {
+        map.insert("equipment".to_string(), Value::Compound(HashMap::from([
+            ("loot_table".to_string(), Value::String(equipment.loot_table().to_string())),
+            ("slot_drop_chances".to_string(), fastnbt::to_value(equipment.drop_chances()).unwrap_or(Value::Compound(HashMap::new()))),
+        ])));
+    }
+    Value::Compound(map)
+}
+
+pub fn write_spawner_spawn_potentials(potentials: &[SpawnPotential]) -> Value {
+    Value::List(potentials.iter().map(|p| Value::Compound(HashMap::from([
+        ("weight".to_string(), Value::Int(p.weight())),
+        ("data".to_string(), write_spawner_spawn_data(p.data())),
+    ]))).collect())
+}
+
+pub fn write_sign_text_block(text: &SignTextBlock) -> Value {
+    Value::Compound(HashMap::from([
+        ("has_glowing_text".to_string(), Value::Byte(text.is_glowing_text() as i8)),
+        ("color".to_string(), Value::String(text.dyed_color().name().to_string())),
+        ("messages".to_string(), Value::List(text.lines().iter().map(|l| Value::String(l.to_json_string())).collect())),
+    ]))
+}
+
 // NBT Utils
 pub fn take_string(map: &mut HashMap<String, Value>, key: &str) -> Option<String> {
     map.remove(key).and_then(|v| match v {
@@ -41,9 +41,6 @@ match entity {
 
 
 TODO: Writers - have dirty region list on each dimension and then world will have save_dirty_as() save_dirty() save_all_as() save_all()
-TODO: In the future add an extension that will allow for more structure control like (pesudo) `(structure as Village).houses()` - it will be a wrapper around the generic structure and do in place calcs with cache (a bunch of wrappers)
-
-TODO: Make sure biomes are exact - look at edge of biome and check
 
 TODO: Parser optimizations - noticed creating chunk::new is the slowest operation by more than 3 times than everything together. Probably the stores allocating so much memory, figure out how to store more compact?
 TODO: Load all needed things
@@ -71,7 +68,7 @@ fn main() {
 
         w.register_regions();
         let s = Instant::now();
-        w.load_region(region_position);
+        w.load_region(region_position).unwrap();
         let e = s.elapsed();
         println!("Took {:?}", e)
     });
@@ -1,14 +1,18 @@
+use std::collections::HashMap;
+use crate::constants::blocks::block_entity_id;
 use crate::models::block_entity::types::command_block::CommandBlockBlockEntity;
 use crate::models::block_entity::types::cooker::cooker::CookerBlockEntity;
 use crate::models::block_entity::types::lectern::LecternBlockEntity;
 use crate::models::block_entity::types::sign::SignBlockEntity;
 use crate::models::block_entity::types::spawner::SpawnerBlockEntity;
 use crate::models::block_entity::types::storage_container::storage_container::StorageContainerBlockEntity;
+use crate::models::other::inventory::Inventory;
+use crate::models::other::mojang_data::text_component::TextComponent;
 use crate::models::other::properties::Properties;
 use crate::models::positions::whole_position::Position;
-use crate::traits::block_entity::BlockEntityTrait;
+use crate::traits::block_entity::{BlockEntityTrait, LockableContainer, StorageContainerTrait};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GenericBlockEntity {
     id: String,
     position: Position,
@@ -32,7 +36,7 @@ impl GenericBlockEntity {
     pub fn set_id(&mut self, id: String) { self.id = id }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum BlockEntity {
     StorageContainer(StorageContainerBlockEntity),
     Cooker(CookerBlockEntity),
@@ -70,6 +74,12 @@ impl BlockEntityTrait for BlockEntity {
 }
 
 impl BlockEntity {
+    // The empty block entity a freshly placed block gets, like the game creates it. None if the block has none
+    pub fn default_for(block: &str, position: Position) -> Option<BlockEntity> {
+        let id = block_entity_id(block)?;
+        Some(BlockEntity::Other(GenericBlockEntity::new(id.to_string(), position, Properties::new(HashMap::new()))))
+    }
+
     pub fn as_storage(&self) -> Option<&StorageContainerBlockEntity> {
         match self {
             BlockEntity::StorageContainer(s) => Some(s), _ => None
@@ -125,3 +135,37 @@ impl BlockEntity {
         }
     }
 }
+
+// Data shared between different kinds of block entities
+impl BlockEntity {
+    pub fn items(&self) -> Option<&Inventory> {
+        match self {
+            BlockEntity::StorageContainer(s) => Some(s.items()),
+            BlockEntity::Cooker(CookerBlockEntity::Furnace(f)) => Some(f.items()),
+            BlockEntity::Cooker(CookerBlockEntity::Campfire(c)) => Some(c.items()),
+            BlockEntity::Cooker(CookerBlockEntity::BrewingStand(b)) => Some(b.items()),
+            _ => None
+        }
+    }
+
+    pub fn custom_name(&self) -> Option<&TextComponent> {
+        match self {
+            BlockEntity::StorageContainer(s) => s.custom_name(),
+            BlockEntity::Cooker(CookerBlockEntity::Furnace(f)) => f.custom_name(),
+            BlockEntity::Cooker(CookerBlockEntity::BrewingStand(b)) => b.custom_name(),
+            BlockEntity::CommandBlock(c) => c.custom_name(),
+            _ => None
+        }
+    }
+
+    // None if the block entity can't be locked or has no lock
+    pub fn lock(&self) -> Option<&str> {
+        let lockable: &dyn LockableContainer = match self {
+            BlockEntity::StorageContainer(s) => s,
+            BlockEntity::Cooker(CookerBlockEntity::Furnace(f)) => f,
+            BlockEntity::Cooker(CookerBlockEntity::BrewingStand(b)) => b,
+            _ => return None
+        };
+        if lockable.is_locked() { Some(lockable.key()) } else { None }
+    }
+}
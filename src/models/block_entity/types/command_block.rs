@@ -3,7 +3,7 @@ use crate::models::other::mojang_data::text_component::TextComponent;
 use crate::models::other::tick::Tick;
 use crate::traits::block_entity::BlockEntityTrait;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CommandBlockBlockEntity {
     base: GenericBlockEntity,
 
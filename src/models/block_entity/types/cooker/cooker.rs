@@ -8,7 +8,7 @@ use crate::traits::block_entity::BlockEntityTrait;
 // Furnace - furnace, smoker, blast furnace
 // campfire - campfire, soul campfire,
 // brewing stand
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum CookerBlockEntity {
     Furnace(FurnaceBlockEntity),
     Campfire(CampfireBlockEntity),
@@ -5,7 +5,7 @@ use crate::models::other::mojang_data::text_component::TextComponent;
 use crate::models::other::tick::Tick;
 use crate::traits::block_entity::{BlockEntityTrait, LockableContainer};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BrewingStandBlockEntity {
     base: GenericBlockEntity,
     items: Inventory,
@@ -5,7 +5,7 @@ use crate::models::other::tick::Tick;
 use crate::traits::block_entity::BlockEntityTrait;
 use crate::types::ItemSlot;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CampfireBlockEntity {
     base: GenericBlockEntity,
     ticks_spent_cooking: Vec<Tick>,
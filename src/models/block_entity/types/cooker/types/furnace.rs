@@ -5,7 +5,7 @@ use crate::models::other::mojang_data::text_component::TextComponent;
 use crate::models::other::tick::Tick;
 use crate::traits::block_entity::{BlockEntityTrait, LockableContainer};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FurnaceBlockEntity {
     base: GenericBlockEntity,
     items: Inventory,
@@ -37,6 +37,7 @@ impl FurnaceBlockEntity {
 
     pub fn items(&self) -> &Inventory { &self.items }
     pub fn cook_history(&self) -> &HashMap<String, i32> { &self.cook_history }
+    pub fn custom_name(&self) -> Option<&TextComponent> { self.custom_name.as_ref() }
     pub fn current_fuel_ticks_left(&self) -> &Tick { &self.current_fuel_ticks_left }
     pub fn current_item_cooking_ticks_spent(&self) -> &Tick { &self.current_item_cooking_ticks_spent }
     pub fn total_cooking_ticks_for_current_item(&self) -> &Tick { &self.total_cooking_ticks_for_current_item }
@@ -2,7 +2,7 @@ use crate::models::block_entity::block_entity::GenericBlockEntity;
 use crate::models::other::inventory::Item;
 use crate::traits::block_entity::BlockEntityTrait;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LecternBlockEntity {
     base: GenericBlockEntity,
     book: Option<Item>,
@@ -4,7 +4,7 @@ use crate::models::other::mojang_data::color::MinecraftColor;
 use crate::models::other::mojang_data::text_component::TextComponent;
 use crate::traits::block_entity::BlockEntityTrait;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SignTextBlock {
     is_glowing_text: bool,
     dyed_color: MinecraftColor,
@@ -17,10 +17,16 @@ impl SignTextBlock {
             is_glowing_text, dyed_color, lines,
         }
     }
+
+    pub fn is_glowing_text(&self) -> bool { self.is_glowing_text }
+    pub fn dyed_color(&self) -> &MinecraftColor { &self.dyed_color }
+    pub fn lines(&self) -> &Vec<TextComponent> { &self.lines }
+    // Lines joined with '\n'
+    pub fn text(&self) -> String { self.lines.iter().map(|l| l.text().as_str()).collect::<Vec<&str>>().join("\n") }
 }
 
 // sign, hanging sign
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SignBlockEntity {
     base: GenericBlockEntity,
     is_waxed: bool,
@@ -1,10 +1,11 @@
 use serde::{Deserialize, Serialize};
 use crate::models::block_entity::block_entity::GenericBlockEntity;
 use crate::models::entity::entity::Entity;
+use crate::models::other::properties::Properties;
 use crate::models::other::tick::Tick;
 use crate::traits::block_entity::BlockEntityTrait;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct LightLimitRange {
     pub min_inclusive: i32,
     pub max_inclusive: i32,
@@ -20,13 +21,13 @@ impl LightLimitRange {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CustomSpawnRules {
     pub block_light_range: LightLimitRange,
     pub sky_light_range: LightLimitRange,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct EquipmentDropChances {
     pub feet: f32,
     pub legs: f32,
@@ -51,7 +52,7 @@ impl EquipmentDropChances {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SpawnEquipment {
     loot_table: String, // TODO: Load the loot table and have it as a ref in a generalized structure
     drop_chances: EquipmentDropChances
@@ -72,7 +73,7 @@ impl SpawnEquipment {
     pub fn set_drop_chances(&mut self, drop_chances: EquipmentDropChances) { self.drop_chances = drop_chances; }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SpawnerSpawnData {
     entity: Entity,
     spawn_rules: Option<CustomSpawnRules>,
@@ -95,7 +96,7 @@ impl SpawnerSpawnData {
     pub fn set_equipment(&mut self, equipment: Option<SpawnEquipment>) { self.equipment = equipment; }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SpawnPotential {
     weight: i32,
     data: SpawnerSpawnData
@@ -105,9 +106,12 @@ impl SpawnPotential {
     pub fn new(weight: i32, data: SpawnerSpawnData) -> Self {
         SpawnPotential { weight, data }
     }
+
+    pub fn weight(&self) -> i32 { self.weight }
+    pub fn data(&self) -> &SpawnerSpawnData { &self.data }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SpawnerBlockEntity {
     base: GenericBlockEntity,
 
@@ -167,10 +171,18 @@ impl SpawnerBlockEntity {
     pub fn set_required_player_range(&mut self, required_player_range: i16) { self.required_player_range = required_player_range; }
     pub fn set_spawn_batch_size(&mut self, spawn_batch_size: i16) { self.spawn_batch_size = spawn_batch_size; }
     pub fn set_spawn_range(&mut self, spawn_range: i16) { self.spawn_range = spawn_range; }
-    pub fn set_next_spawn_data(&mut self, spawn_data: SpawnerSpawnData) { self.next_spawn_data = spawn_data; }
-    pub fn add_potential(&mut self, spawn_potential: SpawnPotential) { self.spawn_potentials.push(spawn_potential); }
-    pub fn remove_potential(&mut self, ind: usize) { self.spawn_potentials.remove(ind); }
-    pub fn clear_potentials(&mut self) { self.spawn_potentials.clear(); }
+    pub fn set_next_spawn_data(&mut self, spawn_data: SpawnerSpawnData) { self.next_spawn_data = spawn_data; self.drop_raw("SpawnData"); }
+    pub fn add_potential(&mut self, spawn_potential: SpawnPotential) { self.spawn_potentials.push(spawn_potential); self.drop_raw("SpawnPotentials"); }
+    pub fn remove_potential(&mut self, ind: usize) { self.spawn_potentials.remove(ind); self.drop_raw("SpawnPotentials"); }
+    pub fn clear_potentials(&mut self) { self.spawn_potentials.clear(); self.drop_raw("SpawnPotentials"); }
+
+    // The spawn data is also kept as it was read in the properties, the entities in it rarely have everything the
+    // model needs. That's what gets saved until it's changed here. The properties can be shared with clones, so they're
+    // copied instead of changed in place
+    fn drop_raw(&mut self, key: &str) {
+        let mut raw = self.base.properties().raw().clone();
+        if raw.remove(key).is_some() { self.base.set_properties(Properties::new(raw)); }
+    }
 }
 
 impl BlockEntityTrait for SpawnerBlockEntity {
@@ -3,13 +3,14 @@ use crate::models::block_entity::types::storage_container::types::chiseled_books
 use crate::models::block_entity::types::storage_container::types::hopper::HopperBlockEntity;
 use crate::models::block_entity::types::storage_container::types::standard_container::StandardStorageContainerBlockEntity;
 use crate::models::other::inventory::Inventory;
-use crate::traits::block_entity::{BlockEntityTrait, StorageContainerTrait};
+use crate::models::other::mojang_data::text_component::TextComponent;
+use crate::traits::block_entity::{BlockEntityTrait, LockableContainer, StorageContainerTrait};
 
 // Normal - chest, trapped chest, barrel, shulker box,
 // Spitter - dispenser, dropper
 // hopper
 // chiseled bookshelf
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum StorageContainerBlockEntity {
     Normal(StandardStorageContainerBlockEntity),
     Spitter(StandardStorageContainerBlockEntity),
@@ -40,24 +41,83 @@ impl BlockEntityTrait for StorageContainerBlockEntity {
 }
 
 impl StorageContainerTrait for StorageContainerBlockEntity {
-
     fn items(&self) -> &Inventory {
-        let this: &dyn StorageContainerTrait = self;
-        this.items()
+        match self {
+            StorageContainerBlockEntity::Normal(a) => a.items(),
+            StorageContainerBlockEntity::Spitter(a) => a.items(),
+            StorageContainerBlockEntity::Hopper(a) => a.items(),
+            StorageContainerBlockEntity::ChiseledBookshelf(a) => a.items(),
+        }
     }
 
     fn items_mut(&mut self) -> &mut Inventory {
-        let this: &mut dyn StorageContainerTrait = self;
-        this.items_mut()
+        match self {
+            StorageContainerBlockEntity::Normal(a) => a.items_mut(),
+            StorageContainerBlockEntity::Spitter(a) => a.items_mut(),
+            StorageContainerBlockEntity::Hopper(a) => a.items_mut(),
+            StorageContainerBlockEntity::ChiseledBookshelf(a) => a.items_mut(),
+        }
     }
 
     fn set_items(&mut self, items: Inventory) {
-        let this: &mut dyn StorageContainerTrait = self;
-        this.set_items(items)
+        match self {
+            StorageContainerBlockEntity::Normal(a) => a.set_items(items),
+            StorageContainerBlockEntity::Spitter(a) => a.set_items(items),
+            StorageContainerBlockEntity::Hopper(a) => a.set_items(items),
+            StorageContainerBlockEntity::ChiseledBookshelf(a) => a.set_items(items),
+        }
+    }
+}
+
+impl LockableContainer for StorageContainerBlockEntity {
+    fn is_locked(&self) -> bool {
+        match self {
+            StorageContainerBlockEntity::Normal(a) => a.is_locked(),
+            StorageContainerBlockEntity::Spitter(a) => a.is_locked(),
+            StorageContainerBlockEntity::Hopper(a) => a.is_locked(),
+            StorageContainerBlockEntity::ChiseledBookshelf(a) => a.is_locked(),
+        }
+    }
+
+    fn key(&self) -> &str {
+        match self {
+            StorageContainerBlockEntity::Normal(a) => a.key(),
+            StorageContainerBlockEntity::Spitter(a) => a.key(),
+            StorageContainerBlockEntity::Hopper(a) => a.key(),
+            StorageContainerBlockEntity::ChiseledBookshelf(a) => a.key(),
+        }
+    }
+
+    fn set_key(&mut self, key: String) {
+        match self {
+            StorageContainerBlockEntity::Normal(a) => a.set_key(key),
+            StorageContainerBlockEntity::Spitter(a) => a.set_key(key),
+            StorageContainerBlockEntity::Hopper(a) => a.set_key(key),
+            StorageContainerBlockEntity::ChiseledBookshelf(a) => a.set_key(key),
+        }
+    }
+
+    fn remove_lock(&mut self) {
+        match self {
+            StorageContainerBlockEntity::Normal(a) => a.remove_lock(),
+            StorageContainerBlockEntity::Spitter(a) => a.remove_lock(),
+            StorageContainerBlockEntity::Hopper(a) => a.remove_lock(),
+            StorageContainerBlockEntity::ChiseledBookshelf(a) => a.remove_lock(),
+        }
     }
 }
 
 impl StorageContainerBlockEntity {
+    // chiseled bookshelves can't be named
+    pub fn custom_name(&self) -> Option<&TextComponent> {
+        match self {
+            StorageContainerBlockEntity::Normal(a) => a.custom_name().as_ref(),
+            StorageContainerBlockEntity::Spitter(a) => a.custom_name().as_ref(),
+            StorageContainerBlockEntity::Hopper(a) => a.custom_name().as_ref(),
+            StorageContainerBlockEntity::ChiseledBookshelf(_) => None,
+        }
+    }
+
     pub fn as_normal(&self) -> Option<&StandardStorageContainerBlockEntity> {
         match self {
             StorageContainerBlockEntity::Normal(a) => Some(a),
@@ -67,7 +127,7 @@ impl StorageContainerBlockEntity {
 
     pub fn as_spitter(&self) -> Option<&StandardStorageContainerBlockEntity> {
         match self {
-            StorageContainerBlockEntity::Normal(a) => Some(a),
+            StorageContainerBlockEntity::Spitter(a) => Some(a),
             _ => None,
         }
     }
@@ -85,4 +145,34 @@ impl StorageContainerBlockEntity {
             _ => None,
         }
     }
-}
\ No newline at end of file
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::models::other::inventory::Item;
+    use crate::models::other::properties::Properties;
+    use crate::models::positions::whole_position::Position;
+
+    fn dropper() -> StorageContainerBlockEntity {
+        let base = GenericBlockEntity::new("minecraft:dropper".to_string(), Position::new("overworld".into(), 0, 0, 0), Properties::new(HashMap::new()));
+        let mut items = Inventory::new(9);
+        items.set_item(4, Item::new("minecraft:arrow".to_string(), 16, Properties::new(HashMap::new())));
+        StorageContainerBlockEntity::Spitter(StandardStorageContainerBlockEntity::new(base, None, None, items))
+    }
+
+    #[test]
+    fn dispatches_to_the_variant() {
+        let mut dropper = dropper();
+        assert!(dropper.as_spitter().is_some() && dropper.as_normal().is_none());
+        assert!(dropper.items().get_at(4).is_some());
+
+        dropper.items_mut().set_item(0, Item::new("minecraft:stone".to_string(), 1, Properties::new(HashMap::new())));
+        assert_eq!(dropper.items().get_all().len(), 2);
+        dropper.set_items(Inventory::new(9));
+        assert!(dropper.items().get_all().is_empty());
+    }
+}
@@ -5,7 +5,7 @@ use crate::models::other::inventory::Inventory;
 use crate::models::other::mojang_data::text_component::TextComponent;
 use crate::traits::block_entity::{BlockEntityTrait, LockableContainer, StorageContainerTrait};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ChiseledBookshelfBlockEntity {
     base: StandardStorageContainerBlockEntity, // this doesnt have a custom name so its a waste but better DX
     last_interacted_slot: i32
@@ -4,7 +4,7 @@ use crate::models::other::inventory::Inventory;
 use crate::models::other::mojang_data::text_component::TextComponent;
 use crate::traits::block_entity::{BlockEntityTrait, LockableContainer, StorageContainerTrait};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct HopperBlockEntity {
     base: StandardStorageContainerBlockEntity,
     transfer_cooldown: i32
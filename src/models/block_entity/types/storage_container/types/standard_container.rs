@@ -5,7 +5,7 @@ use crate::traits::block_entity::{BlockEntityTrait, LockableContainer, StorageCo
 
 
 // TODO: loot table for chest - look in wiki
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct StandardStorageContainerBlockEntity {
     base: GenericBlockEntity,
     lock: Option<String>,
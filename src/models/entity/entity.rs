@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use crate::constants::constants::PLAYER_INVENTORY_SLOT_COUNT;
+use crate::constants::constants::{PLAYER_ENTITY_ID, PLAYER_INVENTORY_SLOT_COUNT};
 use crate::models::other::inventory::Inventory;
 use crate::models::other::lasso_string::LassoString;
 use crate::models::other::properties::Properties;
@@ -23,9 +23,19 @@ pub enum EntityType {
     Mob
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    Survival,
+    Creative,
+    Adventure,
+    Spectator
+}
+
 #[derive(Debug, Clone)]
-pub struct PlayerEntity { // TODO
+pub struct PlayerEntity {
     base: GenericEntity,
+    name: Option<String>, // not stored in the player file, comes from usercache.json when there is one
+    game_mode: GameMode,
     inventory: Inventory,
     extra: Properties
 }
@@ -84,6 +94,8 @@ impl Entity {
                 Entity::Player(
                     PlayerEntity {
                         base: generic,
+                        name: None,
+                        game_mode: GameMode::Survival,
                         inventory: Inventory::new(PLAYER_INVENTORY_SLOT_COUNT),
                         extra
                     }
@@ -114,10 +126,46 @@ impl Entity {
             Entity::Mob(n) => &mut n.base
         }
     }
+
+    pub fn id(&self) -> &str {
+        match self {
+            Entity::Player(_) => PLAYER_ENTITY_ID,
+            Entity::Mob(m) => m.id()
+        }
+    }
+
+    pub fn properties(&self) -> &Properties {
+        match self {
+            Entity::Player(p) => &p.extra,
+            Entity::Mob(m) => &m.extra
+        }
+    }
+}
+
+impl GameMode {
+    pub fn from_id(id: i32) -> Option<GameMode> {
+        match id {
+            0 => Some(GameMode::Survival),
+            1 => Some(GameMode::Creative),
+            2 => Some(GameMode::Adventure),
+            3 => Some(GameMode::Spectator),
+            _ => None
+        }
+    }
+
+    pub fn id(&self) -> i32 { *self as i32 }
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Survival => "survival",
+            GameMode::Creative => "creative",
+            GameMode::Adventure => "adventure",
+            GameMode::Spectator => "spectator",
+        }
+    }
 }
 
 impl GenericEntity {
-    fn new(air_remaining: Tick, distance_fallen: f32, fire_ticks_left: Tick, is_invulnerable: bool, velocity_vector: (f64,f64,f64), is_on_ground: bool, position: EntityPosition, uuid: u128) -> Self {
+    pub fn new(air_remaining: Tick, distance_fallen: f32, fire_ticks_left: Tick, is_invulnerable: bool, velocity_vector: (f64,f64,f64), is_on_ground: bool, position: EntityPosition, uuid: u128) -> Self {
         Self {
             air_remaining,
             distance_fallen,
@@ -137,6 +185,7 @@ impl GenericEntity {
     pub fn velocity_vector(&self) -> &(f64, f64, f64) { &self.velocity_vector }
     pub fn is_on_ground(&self) -> &bool { &self.is_on_ground }
     pub fn position(&self) -> &EntityPosition { &self.position }
+    pub fn uuid(&self) -> u128 { self.uuid }
 
     pub fn set_air_remaining(&mut self, remaining: Tick) { self.air_remaining = remaining; }
     pub fn set_distance_fallen(&mut self, remaining: f32) { self.distance_fallen = remaining; }
@@ -145,6 +194,23 @@ impl GenericEntity {
     pub fn set_velocity_vector(&mut self, vector: (f64, f64, f64)) { self.velocity_vector = vector; }
     pub fn set_is_on_ground(&mut self, is_on_ground: bool) { self.is_on_ground = is_on_ground; }
     pub fn set_position(&mut self, position: EntityPosition) { self.position = position; }
+    pub fn set_uuid(&mut self, uuid: u128) { self.uuid = uuid; }
+}
+
+impl PlayerEntity {
+    pub fn new(base: GenericEntity, name: Option<String>, game_mode: GameMode, inventory: Inventory, extra: Properties) -> Self {
+        Self { base, name, game_mode, inventory, extra }
+    }
+
+    pub fn base(&self) -> &GenericEntity { &self.base }
+    pub fn name(&self) -> Option<&str> { self.name.as_deref() }
+    pub fn game_mode(&self) -> GameMode { self.game_mode }
+    pub fn inventory(&self) -> &Inventory { &self.inventory }
+    pub fn properties(&self) -> &Properties { &self.extra }
+
+    pub fn set_name(&mut self, name: Option<String>) { self.name = name; }
+    pub fn set_game_mode(&mut self, game_mode: GameMode) { self.game_mode = game_mode; }
+    pub fn inventory_mut(&mut self) -> &mut Inventory { &mut self.inventory }
 }
 
 impl MobEntity {
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fmt;
 use fastnbt::Value;
 use crate::models::positions::entity_position::EntityPosition;
 use crate::models::positions::whole_position::Position;
@@ -37,11 +38,74 @@ impl ComparableValue {
     }
 }
 
+fn write_text(f: &mut fmt::Formatter<'_>, text: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in text.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            _ => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+// {:?} keeps the fraction so the value reads back as a float, NaN is spelled the way the parser takes it
+fn write_float(f: &mut fmt::Formatter<'_>, n: f64) -> fmt::Result {
+    if n.is_nan() { f.write_str("nan") } else { write!(f, "{:?}", n) }
+}
+
+// Same syntax Filter::from_filter_string reads
+impl fmt::Display for ComparableValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ComparableValue::Null => f.write_str("null"),
+            ComparableValue::Bool(b) => write!(f, "{}", b),
+            ComparableValue::Int(n) => write!(f, "{}", n),
+            ComparableValue::Float(n) => write_float(f, *n),
+            ComparableValue::Text(s) => write_text(f, s),
+            ComparableValue::Position(p) => write!(f, "pos({}, {}, {}, {})", p.x(), p.y(), p.z(), p.dimension()),
+            ComparableValue::EntityPosition(p) => {
+                f.write_str("epos(")?;
+                for n in [p.x(), p.y(), p.z()] { write_float(f, n)?; f.write_str(", ")?; }
+                write!(f, "{})", p.dimension())
+            }
+            ComparableValue::BoundingBox(a, b) => write!(
+                f, "box({}, {})",
+                ComparableValue::Position(a.clone()), ComparableValue::Position(b.clone())
+            ),
+            ComparableValue::List(values) => {
+                f.write_str("[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 { f.write_str(", ")?; }
+                    write!(f, "{}", value)?;
+                }
+                f.write_str("]")
+            }
+            ComparableValue::Map(values) => {
+                let mut keys = values.keys().collect::<Vec<_>>();
+                keys.sort();
+                f.write_str("{")?;
+                for (i, key) in keys.into_iter().enumerate() {
+                    if i > 0 { f.write_str(", ")?; }
+                    write_text(f, key)?;
+                    write!(f, ": {}", values[key])?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
 impl PartialOrd for ComparableValue {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         match (self, other) {
             (ComparableValue::Int(x), ComparableValue::Int(y)) => x.partial_cmp(y),
             (ComparableValue::Float(x), ComparableValue::Float(y)) => x.partial_cmp(y),
+            (ComparableValue::Int(x), ComparableValue::Float(y)) => (*x as f64).partial_cmp(y),
+            (ComparableValue::Float(x), ComparableValue::Int(y)) => x.partial_cmp(&(*y as f64)),
             (ComparableValue::Text(x), ComparableValue::Text(y)) => x.partial_cmp(y),
             _ => None,
         }
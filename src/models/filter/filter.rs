@@ -1,9 +1,48 @@
-use crate::models::entity::entity::Entity;
+use std::collections::HashMap;
+use std::fmt;
+use fastnbt::Value;
+use crate::models::block_entity::block_entity::BlockEntity;
+use crate::constants::constants::PLAYER_ENTITY_ID;
+use crate::models::entity::entity::{Entity, GenericEntity, PlayerEntity};
 pub use crate::models::filter::comparable_value::ComparableValue;
+use crate::models::filter::filter_keys::FilterKey;
 use crate::models::filter::filter_operations::FilterOperation;
+use crate::models::filter::filter_parser::{display_key, parse_filter, FilterParseError};
 use crate::models::filter::local_structure::LocalStructure;
+use crate::models::other::inventory::Inventory;
+use crate::models::other::mojang_data::text_component::TextComponent;
+use crate::models::other::properties::Properties;
+use crate::models::positions::whole_position::Position;
+use crate::models::world::block::PaletteBlock;
 use crate::models::world::fulls::full_block::FullBlock;
 use crate::models::world::fulls::full_entity::FullEntity;
+use crate::models::world_structures::generic_structure::GenericChildStructure;
+use crate::traits::block_entity::BlockEntityTrait;
+use crate::utils::generic_utils::uuid_to_string;
+
+// What the entity keys read, borrowed so a player can be matched without wrapping a copy of it in an Entity
+struct EntityView<'e> {
+    id: &'e str,
+    base: &'e GenericEntity,
+    properties: &'e Properties,
+    player: Option<&'e PlayerEntity>,
+}
+
+impl<'e> From<&'e Entity> for EntityView<'e> {
+    fn from(entity: &'e Entity) -> Self {
+        let player = match entity {
+            Entity::Player(p) => Some(p),
+            Entity::Mob(_) => None
+        };
+        Self { id: entity.id(), base: entity.base(), properties: entity.properties(), player }
+    }
+}
+
+impl<'e> From<&'e PlayerEntity> for EntityView<'e> {
+    fn from(player: &'e PlayerEntity) -> Self {
+        Self { id: PLAYER_ENTITY_ID, base: player.base(), properties: player.properties(), player: Some(player) }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub enum Filter<'a> {
@@ -12,12 +51,31 @@ pub enum Filter<'a> {
     Or(Vec<Filter<'a>>),
     Not(Vec<Filter<'a>>),
 
-    LocalStructure(&'a LocalStructure)
+    LocalStructure(&'a LocalStructure),
+    InStructurePiece(&'a GenericChildStructure)
 }
 
 impl<'a> Filter<'a> {
-    pub fn from_filter_string(filter_string: &str) -> Filter<'a> {
-        unimplemented!()
+    // Syntax is documented in filter_parser.rs, e.g. `id == minecraft:chest && (y < 0 || biome == "minecraft:deep_dark")`
+    pub fn from_filter_string(filter_string: &'a str) -> Result<Filter<'a>, FilterParseError> {
+        parse_filter(filter_string)
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Filter::Or(filters) if filters.len() > 1 => 0,
+            Filter::And(filters) if filters.len() > 1 => 1,
+            _ => 2
+        }
+    }
+
+    fn fmt_child(&self, child: &Filter, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // nested and/or of the same kind keep their parentheses so the tree parses back the same
+        if child.precedence() <= self.precedence() {
+            write!(f, "({})", child)
+        } else {
+            write!(f, "{}", child)
+        }
     }
 
     fn block_key_to_value(k: &str, b: &FullBlock) -> ComparableValue {
@@ -26,9 +84,17 @@ impl<'a> Filter<'a> {
             "key:y" => ComparableValue::Int(b.position().y() as i64),
             "key:z" => ComparableValue::Int(b.position().z() as i64),
             "key:pos" => ComparableValue::Position(b.position().clone()),
-            "key:id" => ComparableValue::Text(b.id().to_owned()),
+            "key:id" => ComparableValue::Text(b.full_name().into_owned()),
+            "key:biome" => match b.biome() {
+                Some(biome) => ComparableValue::Text(biome.to_string()),
+                None => ComparableValue::Null
+            },
+            _ if k.starts_with("key:") => match b.data() {
+                Some(block_entity) => Self::block_entity_key_to_value(k, &block_entity),
+                None => ComparableValue::Null
+            },
             _ => {
-                match b.states().borrow().all().get(k) {
+                match b.states().read().unwrap().all().get(k) {
                     Some(p) => ComparableValue::from_nbt_value(p),
                     None => { ComparableValue::Null }
                 }
@@ -36,39 +102,107 @@ impl<'a> Filter<'a> {
         }
     }
 
-    fn entity_key_to_value(k: &str, e: &FullEntity) -> ComparableValue {
+    // Paths with a '*' part give a list of every value found, see Properties::get_all
+    fn nbt_path_to_value(props: &Properties, path: &str) -> ComparableValue {
+        if path.split('.').any(|part| part == "*") {
+            return ComparableValue::List(props.get_all(path).into_iter().map(ComparableValue::from_nbt_value).collect());
+        }
+        props.get(path).map(ComparableValue::from_nbt_value).unwrap_or(ComparableValue::Null)
+    }
+
+    // ITEMS, ITEM_COUNTS, TOTAL_ITEM_COUNT and ITEM_COUNT_PREFIX keys, for block entities and players
+    fn inventory_key_to_value(k: &str, inventory: Option<&Inventory>) -> Option<ComparableValue> {
+        let value = |f: &dyn Fn(&Inventory) -> ComparableValue| Some(inventory.map(f).unwrap_or(ComparableValue::Null));
+        if let Some(item_id) = k.strip_prefix(FilterKey::ITEM_COUNT_PREFIX) {
+            let item_id = if item_id.contains(':') { item_id.to_string() } else { format!("minecraft:{}", item_id) };
+            return value(&|i| ComparableValue::Int(i.count_of(&item_id) as i64));
+        }
+
         match k {
-            "key:x" => ComparableValue::Int(e.entity().base().position().x() as i64),
-            "key:y" => ComparableValue::Int(e.entity().base().position().y() as i64),
-            "key:z" => ComparableValue::Int(e.entity().base().position().z() as i64),
-            "key:pos" => ComparableValue::EntityPosition(e.entity().base().position().clone()),
-            "key:id" => {
-                match e.entity() {
-                    Entity::Player(_) => { todo!() } // Need to do Player logic
-                    Entity::Mob(m) => ComparableValue::Text(m.id().to_owned())
+            FilterKey::ITEMS => value(&|inventory| {
+                let mut slots = inventory.get_all().iter().collect::<Vec<_>>();
+                slots.sort_by_key(|(slot, _)| **slot);
+                ComparableValue::List(slots.into_iter().map(|(_, item)| ComparableValue::Text(item.id().to_string())).collect())
+            }),
+            FilterKey::ITEM_COUNTS => value(&|inventory| {
+                let mut counts = HashMap::new();
+                for item in inventory.get_all().values() {
+                    *counts.entry(item.id().to_string()).or_insert(0) += item.count() as i64;
                 }
+                ComparableValue::Map(counts.into_iter().map(|(id, count)| (id, ComparableValue::Int(count))).collect())
+            }),
+            FilterKey::TOTAL_ITEM_COUNT => value(&|i| ComparableValue::Int(i.total_count() as i64)),
+            _ => None
+        }
+    }
+
+    fn block_entity_key_to_value(k: &str, be: &BlockEntity) -> ComparableValue {
+        let text = |t: Option<&TextComponent>| t.map(|t| ComparableValue::Text(t.text().clone())).unwrap_or(ComparableValue::Null);
+        if let Some(value) = Filter::inventory_key_to_value(k, be.items()) {
+            return value;
+        }
+        if let Some(path) = k.strip_prefix(FilterKey::NBT_PREFIX) {
+            return Filter::nbt_path_to_value(be.base().properties(), path);
+        }
+
+        match k {
+            FilterKey::CUSTOM_NAME => text(be.custom_name()),
+            FilterKey::SPAWNER_ENTITY => match be.as_spawner() {
+                Some(s) => ComparableValue::Text(s.next_spawn_data().entity().id().to_owned()),
+                None => ComparableValue::Null
             },
-            _ => {
-                let props = {
-                    match e.entity() {
-                        Entity::Player(p) => { todo!() } // Need to do Player logic
-                        Entity::Mob(m) => { m.properties() }
-                    }
-                };
-
-                match props.get(k) {
-                    Some(p) => ComparableValue::from_nbt_value(p),
-                    None => { ComparableValue::Null }
-                }
-            }
+            FilterKey::SIGN_FRONT => be.as_sign().map(|s| ComparableValue::Text(s.front_text().text())).unwrap_or(ComparableValue::Null),
+            FilterKey::SIGN_BACK => be.as_sign().map(|s| ComparableValue::Text(s.back_text().text())).unwrap_or(ComparableValue::Null),
+            FilterKey::COMMAND => be.as_command_block().map(|c| ComparableValue::Text(c.command().clone())).unwrap_or(ComparableValue::Null),
+            FilterKey::LOCK => be.lock().map(ComparableValue::from).unwrap_or(ComparableValue::Null),
+            _ => ComparableValue::Null
         }
     }
 
+    fn entity_key_to_value(k: &str, e: &EntityView) -> ComparableValue {
+        let (base, props, player) = (e.base, e.properties, e.player);
+        if let Some(value) = Filter::inventory_key_to_value(k, player.map(|p| p.inventory())) {
+            return value;
+        }
+        if let Some(path) = k.strip_prefix(FilterKey::NBT_PREFIX) {
+            return Filter::nbt_path_to_value(props, path);
+        }
+
+        match k {
+            FilterKey::X_POSITION => ComparableValue::Int(base.position().x() as i64),
+            FilterKey::Y_POSITION => ComparableValue::Int(base.position().y() as i64),
+            FilterKey::Z_POSITION => ComparableValue::Int(base.position().z() as i64),
+            FilterKey::POSITION => ComparableValue::EntityPosition(base.position().clone()),
+            FilterKey::ID => ComparableValue::Text(e.id.to_owned()),
+            FilterKey::HEALTH => props.get("Health").map(ComparableValue::from_nbt_value).unwrap_or(ComparableValue::Null),
+            FilterKey::CUSTOM_NAME => match props.get("CustomName") {
+                Some(Value::String(name)) => ComparableValue::Text(TextComponent::from_string(&name).text().clone()),
+                _ => ComparableValue::Null
+            },
+            FilterKey::UUID => ComparableValue::Text(uuid_to_string(base.uuid())),
+            FilterKey::VELOCITY => {
+                let (x, y, z) = *base.velocity_vector();
+                ComparableValue::List(vec![ComparableValue::Float(x), ComparableValue::Float(y), ComparableValue::Float(z)])
+            },
+            FilterKey::PASSENGERS => ComparableValue::List(props.get_all("Passengers.*.id").into_iter().map(ComparableValue::from_nbt_value).collect()),
+            FilterKey::NAME => player.and_then(|p| p.name()).map(ComparableValue::from).unwrap_or(ComparableValue::Null),
+            FilterKey::GAME_MODE => player.map(|p| ComparableValue::from(p.game_mode().name())).unwrap_or(ComparableValue::Null),
+            _ => Filter::nbt_path_to_value(props, k)
+        }
+    }
+
+    // Neighbouring blocks are unknown here, so a LocalStructure with offsets other than (0, 0, 0) never matches.
+    // Selection::find_blocks gives the filter access to them
     pub fn matches_block(&self, block: &FullBlock) -> bool {
+        self.matches_block_with(block, &mut |_| None)
+    }
+
+    // lookup returns the block at a world position
+    pub fn matches_block_with<L>(&self, block: &FullBlock, lookup: &mut L) -> bool where L: FnMut(&Position) -> Option<PaletteBlock> {
         match self {
             Filter::Compare(key, op, val) => {
                 let actual_val = Filter::block_key_to_value(key, block);
-                let result = op.eval(val, &actual_val);
+                let result = op.eval(&actual_val, val);
                 if let Some(result) = result {
                     result
                 } else {
@@ -78,31 +212,56 @@ impl<'a> Filter<'a> {
             }
             Filter::And(filters) => {
                 for filter in filters {
-                    if !filter.matches_block(&block) { return false; }
+                    if !filter.matches_block_with(block, lookup) { return false; }
                 }
                 true
             }
             Filter::Or(filters) => {
                 for filter in filters {
-                    if filter.matches_block(&block) { return true; }
+                    if filter.matches_block_with(block, lookup) { return true; }
                 }
                 false
             }
             Filter::Not(filters) => {
                 for filter in filters {
-                    if filter.matches_block(&block) { return false; }
+                    if filter.matches_block_with(block, lookup) { return false; }
                 }
                 true
             }
-            Filter::LocalStructure(_) => { todo!() }
+            Filter::LocalStructure(structure) => {
+                let anchor = block.palette_block();
+                structure.variants().iter().any(|variant| variant.iter().all(|(offset, expected)| {
+                    let actual = if (offset.dx, offset.dy, offset.dz) == (0, 0, 0) { Some(anchor.clone()) } else {
+                        lookup(&(block.position().clone() + (offset.dx as i32, offset.dy as i32, offset.dz as i32)))
+                    };
+                    actual.is_some_and(|actual| expected.matches(&actual, structure.matches_states()))
+                }))
+            }
+            Filter::InStructurePiece(piece) => piece.contains(block.position()),
         }
     }
 
-    pub fn matches_entity(&self, entity: &FullEntity) -> bool {
+    // How far around a block the filter looks
+    pub fn reach(&self) -> i32 {
+        match self {
+            Filter::And(filters) | Filter::Or(filters) | Filter::Not(filters) => filters.iter().map(|f| f.reach()).max().unwrap_or(0),
+            Filter::LocalStructure(structure) => structure.reach(),
+            _ => 0
+        }
+    }
+
+    pub fn matches_entity(&self, entity: &FullEntity) -> bool { self.matches_entity_data(entity.entity()) }
+
+    // For entities that aren't in a world's entity store
+    pub fn matches_entity_data(&self, entity: &Entity) -> bool { self.matches_entity_view(&entity.into()) }
+    // World::players
+    pub fn matches_player(&self, player: &PlayerEntity) -> bool { self.matches_entity_view(&player.into()) }
+
+    fn matches_entity_view(&self, entity: &EntityView) -> bool {
         match self {
             Filter::Compare(key, op, val) => {
                 let actual_val = Filter::entity_key_to_value(key, entity);
-                let result = op.eval(val, &actual_val);
+                let result = op.eval(&actual_val, val);
                 if let Some(result) = result {
                     result
                 } else {
@@ -112,23 +271,211 @@ impl<'a> Filter<'a> {
             }
             Filter::And(filters) => {
                 for filter in filters {
-                    if !filter.matches_entity(entity) { return false; }
+                    if !filter.matches_entity_view(entity) { return false; }
                 }
                 true
             }
             Filter::Or(filters) => {
                 for filter in filters {
-                    if filter.matches_entity(entity) { return true; }
+                    if filter.matches_entity_view(entity) { return true; }
                 }
                 false
             }
             Filter::Not(filters) => {
                 for filter in filters {
-                    if filter.matches_entity(entity) { return false; }
+                    if filter.matches_entity_view(entity) { return false; }
                 }
                 true
             }
+            Filter::InStructurePiece(piece) => {
+                let pos = entity.base.position();
+                piece.contains(&Position::new(*pos.dimension(), pos.i_x(), pos.i_y(), pos.i_z()))
+            }
             _ => false
         }
     }
-}
\ No newline at end of file
+}
+
+// Prints the filter in the from_filter_string syntax, structure filters have no syntax and cant be parsed back
+impl fmt::Display for Filter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::Compare(key, op, val) => write!(f, "{} {} {}", display_key(key), op, val),
+            Filter::And(filters) if filters.is_empty() => f.write_str("all"),
+            Filter::Or(filters) if filters.is_empty() => f.write_str("none"),
+            Filter::And(filters) | Filter::Or(filters) => {
+                let separator = if matches!(self, Filter::And(_)) { " && " } else { " || " };
+                for (i, filter) in filters.iter().enumerate() {
+                    if i > 0 { f.write_str(separator)?; }
+                    self.fmt_child(filter, f)?;
+                }
+                Ok(())
+            }
+            // Not matches when none of the filters match
+            Filter::Not(filters) => match filters.as_slice() {
+                [] => f.write_str("!none"),
+                [filter] if filter.precedence() == 2 => write!(f, "!{}", filter),
+                [filter] => write!(f, "!({})", filter),
+                _ => write!(f, "!({})", Filter::Or(filters.clone())),
+            },
+            Filter::LocalStructure(_) => f.write_str("<local structure>"),
+            Filter::InStructurePiece(piece) => write!(f, "<in structure piece {}>", piece.id()),
+        }
+    }
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::constants::versions::VersionManager;
+    use crate::models::block_entity::block_entity::GenericBlockEntity;
+    use crate::models::block_entity::types::storage_container::storage_container::StorageContainerBlockEntity;
+    use crate::models::block_entity::types::storage_container::types::standard_container::StandardStorageContainerBlockEntity;
+    use crate::models::other::inventory::{Inventory, Item};
+    use crate::models::other::properties::Properties;
+    use crate::models::world::fulls::full_block::BlockBuilder;
+    use crate::models::world::world::World;
+    use crate::types::WorldKind;
+
+    #[test]
+    fn block_entity_keys() {
+        let world = World::new("".into(), VersionManager::get("1.20.1", WorldKind::Singleplayer));
+        let position = Position::new("overworld".into(), 1, -5, 2);
+        let mut items = Inventory::new(27);
+        items.set_item(0, Item::new("minecraft:diamond".to_string(), 64, Properties::new(HashMap::new())));
+        items.set_item(3, Item::new("minecraft:diamond".to_string(), 10, Properties::new(HashMap::new())));
+        items.set_item(5, Item::new("minecraft:stick".to_string(), 1, Properties::new(HashMap::new())));
+        let base = GenericBlockEntity::new("minecraft:chest".to_string(), position.clone(), Properties::new(HashMap::from([
+            ("LootTable".to_string(), fastnbt::Value::String("minecraft:chests/simple_dungeon".to_string()))
+        ])));
+        let chest = BlockEntity::StorageContainer(StorageContainerBlockEntity::Normal(
+            StandardStorageContainerBlockEntity::new(base, Some("key".to_string()), Some(TextComponent::new("Loot")), items)
+        ));
+        let block = FullBlock::new_with_data(&world, PaletteBlock::from("minecraft:chest"), Some(Arc::new(chest)), position, None);
+
+        let matches = |query: &str| Filter::from_filter_string(query).unwrap().matches_block(&block);
+        assert!(matches("id == minecraft:chest && key:item_count:minecraft:diamond > 64"));
+        assert!(!matches("key:item_count:diamond > 74"));
+        assert!(matches("items contains minecraft:stick && item_counts contains minecraft:diamond && total_item_count == 75"));
+        assert!(matches("custom_name == Loot && lock == key && key:nbt:LootTable == minecraft:chests/simple_dungeon"));
+        assert!(matches("y < 0 && command == null && sign_front == null"));
+    }
+
+    #[test]
+    fn in_structure_piece() {
+        use crate::models::entity::entity::EntityType;
+        use crate::models::other::tick::Tick;
+        use crate::models::positions::entity_position::EntityPosition;
+        use crate::models::world_structures::generic_structure::BoundingBox;
+
+        let world = World::new("".into(), VersionManager::get("1.20.1", WorldKind::Singleplayer));
+        let pos = |x, y, z| Position::new("overworld".into(), x, y, z);
+        let piece = GenericChildStructure::new("minecraft:nefb", BoundingBox::new(&pos(-5, 40, -5), &pos(-1, 45, -1)), Properties::new(HashMap::new()));
+        let filter = Filter::InStructurePiece(&piece);
+        let block_at = |p: Position| BlockBuilder::new(world.clone()).named("minecraft:nether_bricks").at(&p).build();
+        assert!(filter.matches_block(&block_at(pos(-1, 45, -1))));
+        assert!(filter.matches_block(&block_at(pos(-5, 40, -5))));
+        assert!(!filter.matches_block(&block_at(pos(0, 45, -1))));
+        assert!(!filter.matches_block(&block_at(Position::new("the_nether".into(), -3, 42, -3))));
+
+        // entities count by the block they stand in, -0.5 is in block -1
+        let entity_at = |x: f64, y: f64, z: f64| Entity::new(EntityType::Mob, Tick::new(0), 0.0, Tick::new(0), false, (0.0, 0.0, 0.0), true,
+            EntityPosition::new(x, y, z, 0.0, 0.0, "overworld".into()), 1, Properties::new(HashMap::new()));
+        assert!(filter.matches_entity_data(&entity_at(-0.5, 45.9, -0.5)));
+        assert!(!filter.matches_entity_data(&entity_at(0.5, 42.0, -0.5)));
+        assert!(!filter.matches_entity_data(&entity_at(-2.5, 46.0, -2.5)));
+    }
+
+    #[test]
+    fn text_and_numeric_operations() {
+        let world = World::new("".into(), VersionManager::get("1.20.1", WorldKind::Singleplayer));
+        let block = BlockBuilder::new(world).named("minecraft:oak_log").at(&Position::new("overworld".into(), 2, 0, -7)).build();
+
+        let matches = |query: &str| Filter::from_filter_string(query).unwrap().matches_block(&block);
+        assert!(matches("id matches \"minecraft:.*_log\" && id like \"*:oak_*\" && id starts_with minecraft: && id ends_with _log"));
+        assert!(!matches("id matches \".*_log_\" || id matches \"(\""));
+        assert!(matches("id != minecraft:stone && id in [minecraft:birch_log, minecraft:oak_log]"));
+        assert!(matches("y between [0, 5] && !y between_exclusive [0, 5] && z between [-7.5, -6.5]"));
+        assert!(matches("y == 0.0 && y < 0.5 && x in [1, 2.0] && x > 1.5 && x <= 2"));
+    }
+
+    // eval gets the block / entity value first and the filter value second, with them swapped "y < 10" means 10 < y
+    #[test]
+    fn operand_order() {
+        use crate::models::entity::entity::EntityType;
+        use crate::models::other::tick::Tick;
+        use crate::models::positions::entity_position::EntityPosition;
+
+        let world = World::new("".into(), VersionManager::get("1.20.1", WorldKind::Singleplayer));
+        let block = BlockBuilder::new(world).named("minecraft:oak_log").at(&Position::new("overworld".into(), 2, 40, -7)).build();
+        let matches = |query: &str| Filter::from_filter_string(query).unwrap().matches_block(&block);
+        assert!(matches("y < 50") && !matches("y > 50") && matches("y >= 40") && !matches("y <= 39"));
+        assert!(matches("id contains oak") && !matches("id within oak"));
+        assert!(matches("id within \"minecraft:oak_log_stripped\"") && !matches("id contains \"minecraft:oak_log_stripped\""));
+        assert!(matches("pos within box(pos(0, 0, -10), pos(5, 50, 0))"));
+        assert!(matches("id starts_with minecraft: && !id starts_with \"minecraft:oak_log_stripped\""));
+
+        let extra = Properties::new(HashMap::from([("Health".to_string(), fastnbt::Value::Float(5.0))]));
+        let zombie = Entity::new(EntityType::Mob, Tick::new(0), 0.0, Tick::new(0), false, (0.0, 0.0, 0.0), true,
+            EntityPosition::new(0.5, 64.0, 0.5, 0.0, 0.0, "overworld".into()), 1, extra);
+        let matches = |query: &str| Filter::from_filter_string(query).unwrap().matches_entity_data(&zombie);
+        assert!(matches("health < 10 && health > 1 && y <= 64 && !y < 64"));
+        assert!(!matches("health > 10") && !matches("health < 1"));
+    }
+
+    #[test]
+    fn entity_keys() {
+        use fastnbt::Value::{Compound, Float, List, String as Str};
+        use crate::models::entity::entity::{GameMode, GenericEntity, MobEntity, PlayerEntity};
+        use crate::models::other::tick::Tick;
+        use crate::models::positions::entity_position::EntityPosition;
+
+        let compound = |entries: Vec<(&str, Value)>| Compound(entries.into_iter().map(|(k, v)| (k.to_string(), v)).collect());
+        let book = |enchantment: &str| compound(vec![("sell", compound(vec![
+            ("id", Str("minecraft:enchanted_book".to_string())),
+            ("tag", compound(vec![("StoredEnchantments", List(vec![compound(vec![("id", Str(enchantment.to_string()))])]))]))
+        ]))]);
+        let extra = Properties::new(HashMap::from([
+            ("Health".to_string(), Float(20.0)),
+            ("CustomName".to_string(), Str("{\"text\":\"Librarian Bob\"}".to_string())),
+            ("Offers".to_string(), compound(vec![("Recipes", List(vec![book("minecraft:unbreaking"), book("minecraft:mending")]))])),
+            ("Attributes".to_string(), List(vec![compound(vec![("Base", Value::Double(0.5))]), compound(vec![("Base", Value::Double(48.0))])])),
+            ("Passengers".to_string(), List(vec![compound(vec![("id", Str("minecraft:chicken".to_string()))])])),
+        ]));
+        let position = EntityPosition::new(3.5, 64.0, -2.5, 0.0, 0.0, "overworld".into());
+        let villager = Entity::Mob(MobEntity::new("minecraft:villager".to_string(), Tick::new(300), 0.0, Tick::new(0), false, (0.0, -0.08, 0.0), true, position.clone(), 0x069a79f444e94726a5befca90e38aaf5, extra));
+
+        let matches = |entity: &Entity, query: &str| Filter::from_filter_string(query).unwrap().matches_entity_data(entity);
+        assert!(matches(&villager, "id == minecraft:villager && custom_name != null && Offers.Recipes.*.sell.tag.StoredEnchantments.*.id contains minecraft:mending"));
+        assert!(matches(&villager, "custom_name == \"Librarian Bob\" && health >= 20 && uuid == \"069a79f4-44e9-4726-a5be-fca90e38aaf5\""));
+        assert!(matches(&villager, "Attributes.*.Base contains 48 && `Attributes.0.Base` == 0.5 && passengers contains minecraft:chicken"));
+        assert!(matches(&villager, "velocity == [0, -0.08, 0] && name == null && items == null"));
+        assert!(!matches(&villager, "Offers.Recipes.*.sell.tag.StoredEnchantments.*.id contains minecraft:sharpness"));
+
+        let mut inventory = Inventory::new(41);
+        inventory.set_item(40, Item::new("minecraft:shield".to_string(), 1, Properties::new(HashMap::new())));
+        let base = GenericEntity::new(Tick::new(300), 0.0, Tick::new(0), false, (0.0, 0.0, 0.0), true, position, 1);
+        let player = PlayerEntity::new(base, Some("Steve".to_string()), GameMode::Creative, inventory, Properties::new(HashMap::new()));
+        for query in ["id == minecraft:player && name == Steve && game_mode == creative && key:item_count:shield == 1", "passengers == [] && health == null"] {
+            assert!(Filter::from_filter_string(query).unwrap().matches_player(&player), "query: {}", query);
+        }
+        assert!(!Filter::from_filter_string("name == Alex").unwrap().matches_player(&player));
+        let player = Entity::Player(player);
+        assert!(matches(&player, "id == minecraft:player && name == Steve && game_mode == creative && key:item_count:shield == 1"));
+    }
+
+    // "y < 50" has to mean the block's y is below 50, not 50 below the block's y
+    #[test]
+    fn compares_the_block_value_first() {
+        let world = World::new("".into(), VersionManager::get("1.20.1", WorldKind::Singleplayer));
+        let block = FullBlock::new_with_data(&world, PaletteBlock::from("minecraft:oak_log"), None, Position::new("overworld".into(), 2, 40, -7), None);
+
+        let matches = |query: &str| Filter::from_filter_string(query).unwrap().matches_block(&block);
+        assert!(matches("y < 50") && !matches("y > 50") && matches("y >= 40") && !matches("y <= 39"));
+        assert!(matches("id contains oak") && !matches("id within oak"));
+        assert!(matches("id within \"minecraft:oak_log_stripped\"") && !matches("id contains \"minecraft:oak_log_stripped\""));
+    }
+}
@@ -1,9 +1,39 @@
 // If adding keys here, also add key to Filter::key_to_block_key, etc.
 pub mod FilterKey { // TODO: Expand on this...
-    pub const X_POSITION: &'static str = "key:x";
-    pub const Y_POSITION: &'static str = "key:y";
-    pub const Z_POSITION: &'static str = "key:z";
+    pub const X_POSITION: &str = "key:x";
+    pub const Y_POSITION: &str = "key:y";
+    pub const Z_POSITION: &str = "key:z";
 
-    pub const POSITION: &'static str = "key:pos";
-    pub const ID: &'static str = "key:id";
-}
\ No newline at end of file
+    pub const POSITION: &str = "key:pos";
+    pub const ID: &str = "key:id";
+    pub const BIOME: &str = "key:biome";
+
+    // Block entity data, Null for blocks without (the right kind of) block entity
+    pub const ITEMS: &str = "key:items"; // list of item ids
+    pub const ITEM_COUNTS: &str = "key:item_counts"; // map of item id to count, summed over slots
+    pub const TOTAL_ITEM_COUNT: &str = "key:total_item_count";
+    pub const CUSTOM_NAME: &str = "key:custom_name";
+    pub const SPAWNER_ENTITY: &str = "key:spawner_entity";
+    pub const SIGN_FRONT: &str = "key:sign_front"; // lines joined with '\n'
+    pub const SIGN_BACK: &str = "key:sign_back";
+    pub const COMMAND: &str = "key:command";
+    pub const LOCK: &str = "key:lock";
+
+    // Entity data. ITEMS, ITEM_COUNTS, TOTAL_ITEM_COUNT, CUSTOM_NAME and the prefixes below work for entities too,
+    // the inventory ones only for players
+    pub const HEALTH: &str = "key:health";
+    pub const UUID: &str = "key:uuid"; // "069a79f4-44e9-4726-a5be-fca90e38aaf5"
+    pub const VELOCITY: &str = "key:velocity"; // [x, y, z]
+    pub const PASSENGERS: &str = "key:passengers"; // list of entity ids
+    pub const NAME: &str = "key:name"; // player name
+    pub const GAME_MODE: &str = "key:game_mode"; // "survival", "creative", "adventure" or "spectator"
+
+    // Followed by an item id, e.g. "key:item_count:minecraft:diamond"
+    pub const ITEM_COUNT_PREFIX: &str = "key:item_count:";
+    // Followed by a path into the (block) entity's extra nbt, e.g. "key:nbt:LootTable" or "key:nbt:Attributes.*.Base"
+    // where '*' goes into every element
+    pub const NBT_PREFIX: &str = "key:nbt:";
+
+    pub fn item_count(item_id: &str) -> String { format!("{}{}", ITEM_COUNT_PREFIX, item_id) }
+    pub fn nbt(path: &str) -> String { format!("{}{}", NBT_PREFIX, path) }
+}
@@ -1,28 +1,83 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use regex::Regex;
 use crate::models::filter::filter::ComparableValue;
+use crate::utils::generic_utils::glob_matches;
 use crate::utils::position_utils::is_position_within_bounding_box;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum FilterOperation {
     Equals,
+    NotEquals,
     LessThan,
     GreaterThan,
     LessThanEquals,
     GreaterThanEquals,
+    Between { inclusive: bool }, // value is [low, high]
+    In, // value is a list
     Contains,
-    Within
+    Within,
+    StartsWith,
+    EndsWith,
+    Regex, // has to match the whole text
+    Glob, // '*' and '?'
+}
+
+// A filter only has a few patterns, more than this means filters keep being made with new ones (e.g. from user input)
+const REGEX_CACHE_SIZE: usize = 64;
+
+thread_local! {
+    // filters are evaluated once per block, compiling the same pattern every time would be way too slow
+    static REGEX_CACHE: RefCell<HashMap<String, Option<Regex>>> = RefCell::new(HashMap::new());
+}
+
+fn regex_matches(pattern: &str, text: &str) -> Option<bool> {
+    REGEX_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if let Some(regex) = cache.get(pattern) { return regex.as_ref().map(|r| r.is_match(text)); }
+        if cache.len() >= REGEX_CACHE_SIZE { cache.clear(); }
+        let regex = Regex::new(&format!("^(?:{})$", pattern)).ok();
+        let matches = regex.as_ref().map(|r| r.is_match(text));
+        cache.insert(pattern.to_string(), regex);
+        matches
+    })
+}
+
+// Ints and floats are compared by value, everything else needs the same type
+fn values_equal(a: &ComparableValue, b: &ComparableValue) -> bool {
+    match (a, b) {
+        (ComparableValue::Int(_), ComparableValue::Float(_)) | (ComparableValue::Float(_), ComparableValue::Int(_)) => a.partial_cmp(b) == Some(Ordering::Equal),
+        (ComparableValue::List(a), ComparableValue::List(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_equal(a, b)),
+        _ => a == b
+    }
 }
 
 impl FilterOperation {
+    // a is the value of the block / entity, b the value in the filter
     pub fn eval(&self, a: &ComparableValue, b: &ComparableValue) -> Option<bool> {
         match self {
-            FilterOperation::Equals             => Some(a == b),
+            FilterOperation::Equals             => Some(values_equal(a, b)),
+            FilterOperation::NotEquals          => Some(!values_equal(a, b)),
             FilterOperation::LessThan           => Some(a <  b),
             FilterOperation::GreaterThan        => Some(a >  b),
             FilterOperation::LessThanEquals     => Some(a <= b),
             FilterOperation::GreaterThanEquals  => Some(a >= b),
+            FilterOperation::Between { inclusive } => match b {
+                ComparableValue::List(bounds) if bounds.len() == 2 => {
+                    let (low, high) = (a.partial_cmp(&bounds[0])?, a.partial_cmp(&bounds[1])?);
+                    Some(if *inclusive { low != Ordering::Less && high != Ordering::Greater } else { low == Ordering::Greater && high == Ordering::Less })
+                }
+                _ => None
+            },
+            FilterOperation::In => match b {
+                ComparableValue::List(list) => Some(list.iter().any(|x| values_equal(a, x))),
+                _ => None
+            },
             FilterOperation::Contains => match (a, b) {
                 (ComparableValue::Text(s), ComparableValue::Text(sub)) => Some(s.contains(sub)),
-                (ComparableValue::List(list), v) => Some(list.iter().any(|x| x == v)),
+                (ComparableValue::List(list), v) => Some(list.iter().any(|x| values_equal(x, v))),
                 (ComparableValue::Map(map), v) => Some(map.keys().any(|x| match v {
                     ComparableValue::Text(vs) => vs == x,
                     _ => false,
@@ -35,7 +90,55 @@ impl FilterOperation {
                     Some(is_position_within_bounding_box(&p, s, e))
                 },
                 _ => None
-            }
+            },
+            FilterOperation::StartsWith | FilterOperation::EndsWith | FilterOperation::Regex | FilterOperation::Glob => match (a, b) {
+                (ComparableValue::Text(text), ComparableValue::Text(pattern)) => match self {
+                    FilterOperation::StartsWith => Some(text.starts_with(pattern.as_str())),
+                    FilterOperation::EndsWith => Some(text.ends_with(pattern.as_str())),
+                    FilterOperation::Regex => regex_matches(pattern, text),
+                    _ => Some(glob_matches(pattern, text)),
+                },
+                _ => None
+            },
         }
     }
-}
\ No newline at end of file
+}
+
+impl fmt::Display for FilterOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FilterOperation::Equals => "==",
+            FilterOperation::NotEquals => "!=",
+            FilterOperation::LessThan => "<",
+            FilterOperation::GreaterThan => ">",
+            FilterOperation::LessThanEquals => "<=",
+            FilterOperation::GreaterThanEquals => ">=",
+            FilterOperation::Between { inclusive: true } => "between",
+            FilterOperation::Between { inclusive: false } => "between_exclusive",
+            FilterOperation::In => "in",
+            FilterOperation::Contains => "contains",
+            FilterOperation::Within => "within",
+            FilterOperation::StartsWith => "starts_with",
+            FilterOperation::EndsWith => "ends_with",
+            FilterOperation::Regex => "matches",
+            FilterOperation::Glob => "like",
+        })
+    }
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regex_cache_stays_bounded() {
+        for i in 0..REGEX_CACHE_SIZE * 3 {
+            assert_eq!(regex_matches(&format!("block_{}", i), &format!("block_{}", i)), Some(true));
+            assert!(REGEX_CACHE.with(|cache| cache.borrow().len()) <= REGEX_CACHE_SIZE);
+        }
+        assert_eq!(regex_matches("block_1", "block_12"), Some(false));
+        assert_eq!(regex_matches("(", "("), None);
+    }
+}
@@ -0,0 +1,504 @@
+use std::collections::HashMap;
+use std::fmt;
+use crate::models::filter::comparable_value::ComparableValue;
+use crate::models::filter::filter::Filter;
+use crate::models::filter::filter_keys::FilterKey;
+use crate::models::filter::filter_operations::FilterOperation;
+use crate::models::positions::entity_position::EntityPosition;
+use crate::models::positions::whole_position::Position;
+
+/*
+Filter query syntax (see Filter::from_filter_string):
+
+    expr     := or
+    or       := and (("||" | "or") and)*
+    and      := not (("&&" | "and") not)*
+    not      := ("!" | "not") not | "(" expr ")" | "all" | "none" | compare
+    compare  := key op literal
+    key      := x | y | z | pos | id | biome | key:<name> | <block state / nbt path> | `any key`
+              | items | item_counts | total_item_count | custom_name | spawner_entity
+              | sign_front | sign_back | command | lock | key:item_count:<item id> | key:nbt:<path>
+              | health | uuid | velocity | passengers | name | game_mode
+              (nbt paths are dotted, "HandItems.0.id", a '*' part gives a list of all matches, "Attributes.*.Base")
+    op       := == | = | != | < | > | <= | >= | contains | within | in | between | between_exclusive
+              | starts_with | ends_with | matches (regex, whole text) | like (glob, '*' and '?')
+    literal  := 12 | -1.5 | inf | -inf | nan | "text" | 'text' | minecraft:stone | true | false | null
+              | pos(x, y, z[, dimension]) | epos(x, y, z[, dimension]) | box(pos(..), pos(..))
+              | [literal, ...] | {"key": literal, ...}
+
+e.g. id == minecraft:chest && (y < 0 || biome == "minecraft:deep_dark") && !waterlogged == "true"
+     id == minecraft:chest && key:item_count:minecraft:diamond > 64
+     id like "*_ore" && y between [-64, 16] && biome in [minecraft:plains, minecraft:forest]
+     id == minecraft:villager && custom_name != null && Offers.Recipes.*.sell.tag.StoredEnchantments.*.id contains minecraft:mending
+*/
+
+const DEFAULT_DIMENSION: &str = "overworld";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterParseError {
+    pub message: String,
+    pub column: usize, // 1 based, in chars
+}
+
+impl fmt::Display for FilterParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.column)
+    }
+}
+
+impl std::error::Error for FilterParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token<'a> {
+    Ident(&'a str),
+    Quoted(&'a str), // backtick key
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Symbol(&'static str),
+    End,
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(s) => write!(f, "'{}'", s),
+            Token::Quoted(s) => write!(f, "`{}`", s),
+            Token::Str(s) => write!(f, "{:?}", s),
+            Token::Int(n) => write!(f, "{}", n),
+            Token::Float(n) => write!(f, "{}", n),
+            Token::Symbol(s) => write!(f, "'{}'", s),
+            Token::End => write!(f, "end of input"),
+        }
+    }
+}
+
+// longest first so "<=" wins over "<"
+const SYMBOLS: &[&str] = &["==", "!=", "<=", ">=", "&&", "||", "=", "<", ">", "!", "(", ")", "[", "]", "{", "}", ",", ":"];
+
+fn is_ident_start(c: char) -> bool { c.is_ascii_alphabetic() || c == '_' }
+fn is_ident_char(c: char) -> bool { c.is_ascii_alphanumeric() || matches!(c, '_' | ':' | '.' | '/' | '*') }
+
+fn error<T>(message: impl Into<String>, column: usize) -> Result<T, FilterParseError> {
+    Err(FilterParseError { message: message.into(), column })
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token<'_>, usize)>, FilterParseError> {
+    let mut tokens = Vec::new();
+    let chars: Vec<(usize, char)> = input.char_indices().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (start, c) = chars[i];
+        let column = i + 1;
+
+        if c.is_whitespace() { i += 1; continue; }
+
+        if is_ident_start(c) {
+            while i < chars.len() && is_ident_char(chars[i].1) { i += 1; }
+            let end = chars.get(i).map(|(b, _)| *b).unwrap_or(input.len());
+            let token = match &input[start..end] {
+                "inf" => Token::Float(f64::INFINITY),
+                "nan" => Token::Float(f64::NAN),
+                word => Token::Ident(word),
+            };
+            tokens.push((token, column));
+            continue;
+        }
+
+        if c == '-' && input[start + 1..].starts_with("inf") && !input[start + 4..].starts_with(is_ident_char) {
+            tokens.push((Token::Float(f64::NEG_INFINITY), column));
+            i += 4;
+            continue;
+        }
+
+        if c.is_ascii_digit() || (c == '-' && chars.get(i + 1).is_some_and(|(_, n)| n.is_ascii_digit())) {
+            i += 1;
+            let mut is_float = false;
+            while i < chars.len() {
+                let n = chars[i].1;
+                if n.is_ascii_digit() { i += 1; }
+                else if n == '.' && !is_float && chars.get(i + 1).is_some_and(|(_, d)| d.is_ascii_digit()) { is_float = true; i += 1; }
+                else if (n == 'e' || n == 'E') && chars.get(i + 1).is_some_and(|(_, d)| d.is_ascii_digit() || *d == '-') { is_float = true; i += 2; }
+                else { break; }
+            }
+            let end = chars.get(i).map(|(b, _)| *b).unwrap_or(input.len());
+            let text = &input[start..end];
+            let token = if is_float {
+                text.parse().map(Token::Float).map_err(|_| FilterParseError { message: format!("Invalid number '{}'", text), column })?
+            } else {
+                text.parse().map(Token::Int).map_err(|_| FilterParseError { message: format!("Invalid number '{}'", text), column })?
+            };
+            tokens.push((token, column));
+            continue;
+        }
+
+        if c == '"' || c == '\'' {
+            let mut value = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return error("Unterminated string", column),
+                    Some((_, '\\')) => {
+                        match chars.get(i + 1) {
+                            Some((_, 'n')) => value.push('\n'),
+                            Some((_, 't')) => value.push('\t'),
+                            Some((_, e)) => value.push(*e),
+                            None => return error("Unterminated string", column),
+                        }
+                        i += 2;
+                    }
+                    Some((_, q)) if *q == c => { i += 1; break; }
+                    Some((_, other)) => { value.push(*other); i += 1; }
+                }
+            }
+            tokens.push((Token::Str(value), column));
+            continue;
+        }
+
+        if c == '`' {
+            let Some(offset) = chars[i + 1..].iter().position(|(_, n)| *n == '`') else {
+                return error("Unterminated key", column);
+            };
+            let end = chars[i + 1 + offset].0;
+            tokens.push((Token::Quoted(&input[start + 1..end]), column));
+            i += offset + 2;
+            continue;
+        }
+
+        let rest = &input[start..];
+        match SYMBOLS.iter().find(|s| rest.starts_with(**s)) {
+            Some(symbol) => {
+                tokens.push((Token::Symbol(symbol), column));
+                i += symbol.chars().count();
+            }
+            None => return error(format!("Unexpected character '{}'", c), column),
+        }
+    }
+
+    tokens.push((Token::End, chars.len() + 1));
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token<'a>, usize)>,
+    current: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> &Token<'a> { &self.tokens[self.current].0 }
+    fn column(&self) -> usize { self.tokens[self.current].1 }
+
+    fn advance(&mut self) -> Token<'a> {
+        let token = self.tokens[self.current].0.clone();
+        if self.current < self.tokens.len() - 1 { self.current += 1; }
+        token
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool { matches!(self.peek(), Token::Symbol(s) if *s == symbol) }
+    fn is_word(&self, word: &str) -> bool { matches!(self.peek(), Token::Ident(s) if s.eq_ignore_ascii_case(word)) }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), FilterParseError> {
+        if self.is_symbol(symbol) { self.advance(); return Ok(()); }
+        error(format!("Expected '{}' but found {}", symbol, self.peek()), self.column())
+    }
+
+    fn parse_or(&mut self) -> Result<Filter<'a>, FilterParseError> {
+        let mut filters = vec![self.parse_and()?];
+        while self.is_symbol("||") || self.is_word("or") {
+            self.advance();
+            filters.push(self.parse_and()?);
+        }
+        Ok(if filters.len() == 1 { filters.pop().unwrap() } else { Filter::Or(filters) })
+    }
+
+    fn parse_and(&mut self) -> Result<Filter<'a>, FilterParseError> {
+        let mut filters = vec![self.parse_not()?];
+        while self.is_symbol("&&") || self.is_word("and") {
+            self.advance();
+            filters.push(self.parse_not()?);
+        }
+        Ok(if filters.len() == 1 { filters.pop().unwrap() } else { Filter::And(filters) })
+    }
+
+    fn parse_not(&mut self) -> Result<Filter<'a>, FilterParseError> {
+        if self.is_symbol("!") || self.is_word("not") {
+            self.advance();
+            return Ok(Filter::Not(vec![self.parse_not()?]));
+        }
+        if self.is_symbol("(") {
+            self.advance();
+            let filter = self.parse_or()?;
+            self.expect_symbol(")")?;
+            return Ok(filter);
+        }
+        if self.is_word("all") { self.advance(); return Ok(Filter::And(Vec::new())); }
+        if self.is_word("none") { self.advance(); return Ok(Filter::Or(Vec::new())); }
+        self.parse_compare()
+    }
+
+    fn parse_compare(&mut self) -> Result<Filter<'a>, FilterParseError> {
+        let column = self.column();
+        let key = match self.advance() {
+            Token::Ident(name) => builtin_key(name).unwrap_or(name),
+            Token::Quoted(name) => name,
+            other => return error(format!("Expected a key but found {}", other), column),
+        };
+
+        let column = self.column();
+        let operation = match self.advance() {
+            Token::Symbol("==") | Token::Symbol("=") => FilterOperation::Equals,
+            Token::Symbol("!=") => FilterOperation::NotEquals,
+            Token::Symbol("<") => FilterOperation::LessThan,
+            Token::Symbol(">") => FilterOperation::GreaterThan,
+            Token::Symbol("<=") => FilterOperation::LessThanEquals,
+            Token::Symbol(">=") => FilterOperation::GreaterThanEquals,
+            Token::Ident(word) => match word.to_ascii_lowercase().as_str() {
+                "contains" => FilterOperation::Contains,
+                "within" => FilterOperation::Within,
+                "between" => FilterOperation::Between { inclusive: true },
+                "between_exclusive" => FilterOperation::Between { inclusive: false },
+                "in" => FilterOperation::In,
+                "starts_with" => FilterOperation::StartsWith,
+                "ends_with" => FilterOperation::EndsWith,
+                "matches" => FilterOperation::Regex,
+                "like" => FilterOperation::Glob,
+                _ => return error(format!("Unknown operation '{}'", word), column),
+            },
+            other => return error(format!("Expected an operation after '{}' but found {}", key, other), column),
+        };
+
+        let column = self.column();
+        let value = self.parse_literal()?;
+        match (operation, &value) {
+            (FilterOperation::Between { .. }, ComparableValue::List(bounds)) if bounds.len() == 2 => {},
+            (FilterOperation::Between { .. }, _) => return error(format!("'{}' takes a [low, high] list", operation), column),
+            (FilterOperation::In, ComparableValue::List(_)) => {},
+            (FilterOperation::In, _) => return error("'in' takes a list", column),
+            _ => {}
+        }
+        Ok(Filter::Compare(key, operation, value))
+    }
+
+    fn parse_literal(&mut self) -> Result<ComparableValue, FilterParseError> {
+        let column = self.column();
+        match self.advance() {
+            Token::Int(n) => Ok(ComparableValue::Int(n)),
+            Token::Float(n) => Ok(ComparableValue::Float(n)),
+            Token::Str(s) => Ok(ComparableValue::Text(s)),
+            Token::Symbol("[") => {
+                let mut values = Vec::new();
+                if !self.is_symbol("]") {
+                    loop {
+                        values.push(self.parse_literal()?);
+                        if !self.is_symbol(",") { break; }
+                        self.advance();
+                    }
+                }
+                self.expect_symbol("]")?;
+                Ok(ComparableValue::List(values))
+            }
+            Token::Symbol("{") => {
+                let mut values = HashMap::new();
+                if !self.is_symbol("}") {
+                    loop {
+                        let key_column = self.column();
+                        let key = match self.advance() {
+                            Token::Str(s) => s,
+                            other => return error(format!("Expected a quoted map key but found {}", other), key_column),
+                        };
+                        self.expect_symbol(":")?;
+                        values.insert(key, self.parse_literal()?);
+                        if !self.is_symbol(",") { break; }
+                        self.advance();
+                    }
+                }
+                self.expect_symbol("}")?;
+                Ok(ComparableValue::Map(values))
+            }
+            Token::Ident(word) if self.is_symbol("(") => self.parse_constructor(word, column),
+            Token::Ident(word) => Ok(match word {
+                "true" => ComparableValue::Bool(true),
+                "false" => ComparableValue::Bool(false),
+                "null" => ComparableValue::Null,
+                _ => ComparableValue::Text(word.to_string()),
+            }),
+            other => error(format!("Expected a value but found {}", other), column),
+        }
+    }
+
+    fn parse_constructor(&mut self, name: &str, column: usize) -> Result<ComparableValue, FilterParseError> {
+        self.expect_symbol("(")?;
+        let value = match name {
+            "pos" | "epos" => {
+                let mut coords = Vec::with_capacity(3);
+                for i in 0..3 {
+                    if i > 0 { self.expect_symbol(",")?; }
+                    coords.push(self.parse_number(name == "pos")?);
+                }
+                let dimension = if self.is_symbol(",") {
+                    self.advance();
+                    let dimension_column = self.column();
+                    match self.advance() {
+                        Token::Ident(s) => s.to_string(),
+                        Token::Str(s) => s,
+                        other => return error(format!("Expected a dimension but found {}", other), dimension_column),
+                    }
+                } else { DEFAULT_DIMENSION.to_string() };
+
+                if name == "pos" {
+                    ComparableValue::Position(Position::new(dimension.as_str().into(), coords[0] as i32, coords[1] as i32, coords[2] as i32))
+                } else {
+                    ComparableValue::EntityPosition(EntityPosition::new(coords[0], coords[1], coords[2], 0.0, 0.0, dimension.as_str().into()))
+                }
+            }
+            "box" => {
+                let corner_column = self.column();
+                let corner1 = self.parse_literal()?;
+                self.expect_symbol(",")?;
+                let corner2 = self.parse_literal()?;
+                match (corner1, corner2) {
+                    (ComparableValue::Position(a), ComparableValue::Position(b)) => ComparableValue::BoundingBox(a, b),
+                    _ => return error("box() takes two pos() corners", corner_column),
+                }
+            }
+            _ => return error(format!("Unknown value type '{}'", name), column),
+        };
+        self.expect_symbol(")")?;
+        Ok(value)
+    }
+
+    fn parse_number(&mut self, integer: bool) -> Result<f64, FilterParseError> {
+        let column = self.column();
+        match self.advance() {
+            Token::Int(n) => Ok(n as f64),
+            Token::Float(n) if !integer => Ok(n),
+            other => error(format!("Expected {} but found {}", if integer { "an integer" } else { "a number" }, other), column),
+        }
+    }
+}
+
+fn builtin_key(name: &str) -> Option<&'static str> {
+    match name {
+        "x" => Some(FilterKey::X_POSITION),
+        "y" => Some(FilterKey::Y_POSITION),
+        "z" => Some(FilterKey::Z_POSITION),
+        "pos" => Some(FilterKey::POSITION),
+        "id" => Some(FilterKey::ID),
+        "biome" => Some(FilterKey::BIOME),
+        "items" => Some(FilterKey::ITEMS),
+        "item_counts" => Some(FilterKey::ITEM_COUNTS),
+        "total_item_count" => Some(FilterKey::TOTAL_ITEM_COUNT),
+        "custom_name" => Some(FilterKey::CUSTOM_NAME),
+        "spawner_entity" => Some(FilterKey::SPAWNER_ENTITY),
+        "sign_front" => Some(FilterKey::SIGN_FRONT),
+        "sign_back" => Some(FilterKey::SIGN_BACK),
+        "command" => Some(FilterKey::COMMAND),
+        "lock" => Some(FilterKey::LOCK),
+        "health" => Some(FilterKey::HEALTH),
+        "uuid" => Some(FilterKey::UUID),
+        "velocity" => Some(FilterKey::VELOCITY),
+        "passengers" => Some(FilterKey::PASSENGERS),
+        "name" => Some(FilterKey::NAME),
+        "game_mode" => Some(FilterKey::GAME_MODE),
+        _ => None
+    }
+}
+
+// Inverse of builtin_key, for printing filters back
+pub(crate) fn display_key(key: &str) -> String {
+    let short = key.strip_prefix("key:").filter(|short| builtin_key(short) == Some(key));
+    let name = short.unwrap_or(key);
+
+    let is_ident = name.chars().next().is_some_and(is_ident_start) && name.chars().all(is_ident_char);
+    let is_reserved = ["and", "or", "not", "all", "none", "inf", "nan"].iter().any(|word| name.eq_ignore_ascii_case(word));
+    if short.is_some() || (is_ident && !is_reserved && builtin_key(name).is_none()) {
+        name.to_string()
+    } else {
+        format!("`{}`", name)
+    }
+}
+
+pub fn parse_filter(input: &str) -> Result<Filter<'_>, FilterParseError> {
+    let mut parser = Parser { tokens: tokenize(input)?, current: 0 };
+    let filter = parser.parse_or()?;
+    if *parser.peek() != Token::End {
+        return error(format!("Unexpected {}", parser.peek()), parser.column());
+    }
+    Ok(filter)
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn precedence_and_keys() {
+        let filter = parse_filter("id == minecraft:chest && (y < -10 || biome = 'minecraft:plains') || !facing == north").unwrap();
+        let Filter::Or(or) = &filter else { panic!("expected or, got {:?}", filter) };
+        assert_eq!(or.len(), 2);
+        let Filter::And(and) = &or[0] else { panic!("expected and") };
+        assert!(matches!(&and[0], Filter::Compare(FilterKey::ID, FilterOperation::Equals, ComparableValue::Text(t)) if t == "minecraft:chest"));
+        assert!(matches!(&and[1], Filter::Or(inner) if inner.len() == 2));
+        assert!(matches!(&or[1], Filter::Not(not) if matches!(&not[0], Filter::Compare("facing", _, _))));
+    }
+
+    // Floats by their bits so NaN equals itself and -0.0 isn't 0.0
+    fn same_value(a: &ComparableValue, b: &ComparableValue) -> bool {
+        match (a, b) {
+            (ComparableValue::Float(a), ComparableValue::Float(b)) => a.to_bits() == b.to_bits(),
+            (ComparableValue::EntityPosition(a), ComparableValue::EntityPosition(b)) =>
+                [a.x(), a.y(), a.z()].map(f64::to_bits) == [b.x(), b.y(), b.z()].map(f64::to_bits) && a.dimension() == b.dimension(),
+            (ComparableValue::List(a), ComparableValue::List(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_value(a, b)),
+            (ComparableValue::Map(a), ComparableValue::Map(b)) => a.len() == b.len() && a.iter().all(|(k, v)| b.get(k).is_some_and(|w| same_value(v, w))),
+            _ => a == b,
+        }
+    }
+
+    fn same_filter(a: &Filter, b: &Filter) -> bool {
+        let all = |a: &Vec<Filter>, b: &Vec<Filter>| a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_filter(a, b));
+        match (a, b) {
+            (Filter::Compare(ka, oa, va), Filter::Compare(kb, ob, vb)) => ka == kb && oa == ob && same_value(va, vb),
+            (Filter::And(a), Filter::And(b)) | (Filter::Or(a), Filter::Or(b)) | (Filter::Not(a), Filter::Not(b)) => all(a, b),
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let queries = [
+            "id == \"minecraft:stone\" && (y < -10 || y >= 3.5)",
+            "!(x > 1 && z <= 2) || pos within box(pos(0, -64, 0, overworld), pos(15, 320, 15, overworld))",
+            "`my key` contains [1, 2.5, \"a\\\"b\"] && Items.0.id == {\"count\": 3, \"id\": null}",
+            "(x == 1 || x == 2) || all",
+            "id != stone && y between [0, 10.5] && biome in [a, b] && x between_exclusive [1, 2] && id like \"*_ore\"",
+            "id matches \"minecraft:.*_log\" || id starts_with minecraft || id ends_with _log",
+            "y > -inf && y < inf && health != nan && velocity == -0.0 && `inf` in [inf, \"inf\", -1e300, 2.5e-8]",
+            "pos within box(pos(-1, 0, -0, the_end), pos(1, 2, 3, overworld)) || pos == epos(-0.0, nan, -inf)",
+        ];
+        for query in queries {
+            let parsed = parse_filter(query).unwrap();
+            let printed = parsed.to_string();
+            let reparsed = parse_filter(&printed).unwrap();
+            assert!(same_filter(&parsed, &reparsed), "query: {}\nprinted: {}\n{:?}\n{:?}", query, printed, parsed, reparsed);
+            assert_eq!(printed, reparsed.to_string());
+        }
+        let Filter::And(and) = parse_filter("y > -inf && health != nan && velocity == -0.0").unwrap() else { panic!("expected and") };
+        assert!(matches!(&and[0], Filter::Compare(_, _, ComparableValue::Float(n)) if *n == f64::NEG_INFINITY));
+        assert!(matches!(&and[1], Filter::Compare(_, _, ComparableValue::Float(n)) if n.is_nan()));
+        assert!(matches!(&and[2], Filter::Compare(_, _, ComparableValue::Float(n)) if *n == 0.0 && n.is_sign_negative()));
+        assert_eq!(parse_filter("id=stone and not y<0").unwrap().to_string(), "id == \"stone\" && !y < 0");
+    }
+
+    #[test]
+    fn errors_have_columns() {
+        assert_eq!(parse_filter("id == ").unwrap_err().column, 7);
+        assert_eq!(parse_filter("(x == 1").unwrap_err().column, 8);
+        assert_eq!(parse_filter("x ?? 1").unwrap_err().column, 3);
+        assert_eq!(parse_filter("pos == pos(1, 2)").unwrap_err().column, 16);
+        assert_eq!(parse_filter("y between 5").unwrap_err().column, 11);
+        assert_eq!(parse_filter("y among [1]").unwrap_err().column, 3);
+    }
+}
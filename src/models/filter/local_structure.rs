@@ -1,5 +1,8 @@
+use std::sync::OnceLock;
 use std::collections::HashMap;
 use crate::models::world::block::PaletteBlock;
+use crate::models::world::transform::{transform_block, transform_xz, Mirror, Rotation};
+use crate::utils::generic_utils::glob_matches;
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct Offset {
@@ -19,15 +22,51 @@ impl From<(i16, i16, i16)> for Offset {
     }
 }
 
+impl Offset {
+    pub fn transformed(&self, rotation: Rotation, mirror: Mirror) -> Offset {
+        let (dx, dz) = transform_xz(self.dx as i32, self.dz as i32, rotation, mirror);
+        Offset { dx: dx as i16, dy: self.dy, dz: dz as i16 }
+    }
+}
+
+// A block name can use '*' and '?' (e.g. "minecraft:*_planks"). A block without states matches any states,
+// otherwise only the given states are compared
+#[derive(Clone, Debug)]
+pub enum PatternBlock {
+    Any,
+    Block(PaletteBlock),
+}
+
+impl PatternBlock {
+    pub fn matches(&self, block: &PaletteBlock, match_states: bool) -> bool {
+        match self {
+            PatternBlock::Any => true,
+            PatternBlock::Block(expected) => {
+                let (expected_name, actual_name) = (expected.full_name(), block.full_name());
+                let name_matches = if expected_name.contains(['*', '?']) { glob_matches(&expected_name, &actual_name) } else { expected_name == actual_name };
+                if !name_matches { return false; }
+                if !match_states { return true; }
+
+                let actual_states = block.properties().raw();
+                expected.properties().raw().iter().all(|(key, value)| actual_states.get(key) == Some(value))
+            }
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct LocalStructure {
-    block_map: HashMap<Offset, PaletteBlock>,
+    block_map: HashMap<Offset, PatternBlock>,
+    rotations: bool,
+    mirrors: bool,
+    match_states: bool,
+
+    variants: OnceLock<Vec<Vec<(Offset, PatternBlock)>>>,
 }
 
 impl LocalStructure {
     pub fn new() -> Self {
-        Self { block_map: HashMap::new() }
+        Self { block_map: HashMap::new(), rotations: false, mirrors: false, match_states: true, variants: OnceLock::new() }
     }
 
     pub fn add<O, B>(mut self, offset: O, block: B) -> Self
@@ -35,11 +74,98 @@ impl LocalStructure {
         O: Into<Offset>,
         B: Into<PaletteBlock>,
     {
-        self.block_map.insert(offset.into(), block.into());
+        self.block_map.insert(offset.into(), PatternBlock::Block(block.into()));
+        self.variants = OnceLock::new();
+        self
+    }
+
+    // The offset has to exist (be inside the world) but can be anything
+    pub fn add_any<O: Into<Offset>>(mut self, offset: O) -> Self {
+        self.block_map.insert(offset.into(), PatternBlock::Any);
+        self.variants = OnceLock::new();
         self
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = (Offset, &PaletteBlock)> { self.block_map.iter().map(|(k, v)| (*k, v)) }
+    // Also match the structure turned by 90, 180 and 270 degrees around the y axis
+    pub fn with_rotations(mut self) -> Self { self.rotations = true; self.variants = OnceLock::new(); self }
+    // Also match the mirrored structure (and its rotations if with_rotations is set)
+    pub fn with_mirrors(mut self) -> Self { self.mirrors = true; self.variants = OnceLock::new(); self }
+    // Only compare block names
+    pub fn ignoring_states(mut self) -> Self { self.match_states = false; self }
+
+    // The given blocks, offsets added with add_any are left out (see patterns)
+    pub fn iter(&self) -> impl Iterator<Item = (Offset, &PaletteBlock)> {
+        self.block_map.iter().filter_map(|(k, v)| match v { PatternBlock::Block(b) => Some((*k, b)), PatternBlock::Any => None })
+    }
+    pub fn patterns(&self) -> impl Iterator<Item = (Offset, &PatternBlock)> { self.block_map.iter().map(|(k, v)| (*k, v)) }
     pub fn len(&self) -> usize { self.block_map.len() }
     pub fn is_empty(&self) -> bool { self.block_map.is_empty() }
-}
\ No newline at end of file
+    pub fn matches_states(&self) -> bool { self.match_states }
+
+    // The structure in every orientation it should be matched in, states of the blocks are turned with it
+    pub fn variants(&self) -> &[Vec<(Offset, PatternBlock)>] {
+        self.variants.get_or_init(|| {
+            let rotations: &[Rotation] = if self.rotations { &Rotation::ALL } else { &[Rotation::None] };
+            let mirrors: &[Mirror] = if self.mirrors { &[Mirror::None, Mirror::LeftRight] } else { &[Mirror::None] };
+
+            let mut variants = Vec::with_capacity(rotations.len() * mirrors.len());
+            for mirror in mirrors {
+                for rotation in rotations {
+                    variants.push(self.block_map.iter().map(|(offset, block)| {
+                        let block = match block {
+                            PatternBlock::Block(b) => PatternBlock::Block(transform_block(b, *rotation, *mirror)),
+                            PatternBlock::Any => PatternBlock::Any,
+                        };
+                        (offset.transformed(*rotation, *mirror), block)
+                    }).collect());
+                }
+            }
+            variants
+        })
+    }
+
+    // Largest distance of an offset on any axis, how far the matcher has to look around a block
+    pub fn reach(&self) -> i32 {
+        self.block_map.keys().map(|o| (o.dx as i32).abs().max((o.dy as i32).abs()).max((o.dz as i32).abs())).max().unwrap_or(0)
+    }
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastnbt::Value;
+    use crate::constants::versions::VersionManager;
+    use crate::models::filter::filter::Filter;
+    use crate::models::positions::whole_position::Position;
+    use crate::models::world::fulls::full_block::BlockBuilder;
+    use crate::models::world::world::World;
+    use crate::types::WorldKind;
+
+    fn stairs(facing: &str) -> PaletteBlock {
+        PaletteBlock::new("minecraft:stone_stairs", Some(HashMap::from([("facing".to_string(), Value::String(facing.to_string()))])))
+    }
+
+    #[test]
+    fn matches_rotated_structure() {
+        let world = World::new("".into(), VersionManager::get("1.20.1", WorldKind::Singleplayer));
+        let anchor = BlockBuilder::new(world).named("minecraft:obsidian").at(&Position::new("overworld".into(), 0, 0, 0)).build();
+        let around = HashMap::from([((0, 0, 1), stairs("south")), ((0, 1, 0), PaletteBlock::from("minecraft:dirt"))]);
+        let mut lookup = |p: &Position| around.get(&(p.x(), p.y(), p.z())).cloned();
+
+        let structure = LocalStructure::new().add((0, 0, 0), "minecraft:obsidian").add((1, 0, 0), stairs("east")).add_any((0, 1, 0));
+        assert_eq!((structure.iter().count(), structure.patterns().count()), (2, 3));
+        assert!(!Filter::LocalStructure(&structure).matches_block_with(&anchor, &mut lookup));
+
+        let rotated = structure.clone().with_rotations();
+        assert!(Filter::LocalStructure(&rotated).matches_block_with(&anchor, &mut lookup));
+
+        // rotated offset but unrotated state
+        let wrong_state = LocalStructure::new().add((0, 0, 1), stairs("east")).with_rotations();
+        assert!(!Filter::LocalStructure(&wrong_state).matches_block_with(&anchor, &mut lookup));
+        let any_state = LocalStructure::new().add((0, 0, 1), "minecraft:*_stairs").add((0, 0, 0), "obsidian");
+        assert!(Filter::LocalStructure(&any_state).matches_block_with(&anchor, &mut lookup));
+        assert!(Filter::LocalStructure(&wrong_state.ignoring_states()).matches_block_with(&anchor, &mut lookup));
+    }
+}
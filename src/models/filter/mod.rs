@@ -1,5 +1,7 @@
 pub mod filter;
 pub mod filter_operations;
+pub mod filter_parser;
 pub mod comparable_value;
 pub mod filter_keys;
 pub mod local_structure;
+pub mod query_plan;
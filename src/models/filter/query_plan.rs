@@ -0,0 +1,281 @@
+use crate::models::filter::comparable_value::ComparableValue;
+use crate::models::filter::filter::Filter;
+use crate::models::filter::filter_keys::FilterKey;
+use crate::models::filter::filter_operations::FilterOperation;
+use crate::models::filter::local_structure::PatternBlock;
+use crate::models::other::fast_set::FastSet;
+use crate::models::world::block::PaletteBlock;
+use crate::utils::generic_utils::glob_matches;
+
+const ANY_RANGE: (i32, i32) = (i32::MIN, i32::MAX);
+
+fn clamp_coordinate(n: f64) -> i32 { n.clamp(i32::MIN as f64, i32::MAX as f64) as i32 }
+
+fn number(value: &ComparableValue) -> Option<f64> {
+    match value {
+        ComparableValue::Int(n) => Some(*n as f64),
+        ComparableValue::Float(n) => Some(*n),
+        _ => None
+    }
+}
+
+// What a block has to look like for a filter to possibly match it, worked out once before iterating so
+// Selection::find_blocks can skip whole chunks and sections. A plan only rules out blocks that can't match,
+// everything it allows still goes through the filter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryPlan {
+    ids: Option<Vec<String>>, // None is any id, names can use '*' and '?' (from LocalStructure). Matched against full names
+    excluded_ids: Vec<String>,
+    bounds: [(i32, i32); 3], // inclusive x, y, z
+}
+
+impl QueryPlan {
+    pub fn any() -> Self {
+        Self { ids: None, excluded_ids: Vec::new(), bounds: [ANY_RANGE; 3] }
+    }
+
+    pub fn nothing() -> Self {
+        Self { ids: Some(Vec::new()), ..Self::any() }
+    }
+
+    pub fn from_filter(filter: &Filter) -> Self {
+        match filter {
+            Filter::Compare(key, op, value) => Self::from_compare(key, *op, value),
+            Filter::And(filters) => filters.iter().fold(Self::any(), |plan, f| plan.intersect(Self::from_filter(f))),
+            Filter::Or(filters) => filters.iter().fold(Self::nothing(), |plan, f| plan.union(Self::from_filter(f))),
+            Filter::Not(filters) => {
+                // only "not this id" is simple enough to keep
+                let mut plan = Self::any();
+                for f in filters {
+                    match f {
+                        Filter::Compare(FilterKey::ID, FilterOperation::Equals, ComparableValue::Text(id)) => plan.excluded_ids.push(id.clone()),
+                        Filter::Compare(FilterKey::ID, FilterOperation::In, ComparableValue::List(values)) => {
+                            for value in values {
+                                let ComparableValue::Text(id) = value else { return Self::any() };
+                                plan.excluded_ids.push(id.clone());
+                            }
+                        },
+                        _ => return Self::any()
+                    }
+                }
+                plan
+            }
+            Filter::LocalStructure(structure) => {
+                let anchor = structure.patterns().find(|(offset, _)| (offset.dx, offset.dy, offset.dz) == (0, 0, 0));
+                match anchor {
+                    Some((_, PatternBlock::Block(block))) => Self { ids: Some(vec![block.full_name().into_owned()]), ..Self::any() },
+                    _ => Self::any()
+                }
+            }
+            Filter::InStructurePiece(piece) => {
+                let (min, max) = (piece.bounding_box().min(), piece.bounding_box().max());
+                Self { bounds: [(min.x(), max.x()), (min.y(), max.y()), (min.z(), max.z())], ..Self::any() }
+            }
+        }
+    }
+
+    fn from_compare(key: &str, op: FilterOperation, value: &ComparableValue) -> Self {
+        let axis = match key {
+            FilterKey::X_POSITION => 0,
+            FilterKey::Y_POSITION => 1,
+            FilterKey::Z_POSITION => 2,
+            FilterKey::ID => return match (op, value) {
+                (FilterOperation::Equals, ComparableValue::Text(id)) => Self { ids: Some(vec![id.clone()]), ..Self::any() },
+                (FilterOperation::Glob, ComparableValue::Text(pattern)) => Self { ids: Some(vec![pattern.clone()]), ..Self::any() },
+                (FilterOperation::NotEquals, ComparableValue::Text(id)) => Self { excluded_ids: vec![id.clone()], ..Self::any() },
+                (FilterOperation::In, ComparableValue::List(values)) if values.iter().all(|v| matches!(v, ComparableValue::Text(_))) => {
+                    let ids = values.iter().filter_map(|v| if let ComparableValue::Text(id) = v { Some(id.clone()) } else { None }).collect();
+                    Self { ids: Some(ids), ..Self::any() }
+                },
+                _ => Self::any()
+            },
+            FilterKey::POSITION => return match (op, value) {
+                (FilterOperation::Equals, ComparableValue::Position(p)) => Self { bounds: [(p.x(), p.x()), (p.y(), p.y()), (p.z(), p.z())], ..Self::any() },
+                (FilterOperation::Within, ComparableValue::BoundingBox(a, b)) => Self {
+                    bounds: [(a.x().min(b.x()), a.x().max(b.x())), (a.y().min(b.y()), a.y().max(b.y())), (a.z().min(b.z()), a.z().max(b.z()))],
+                    ..Self::any()
+                },
+                _ => Self::any()
+            },
+            _ => return Self::any()
+        };
+
+        // coordinates are whole numbers, so a float bound is rounded towards the side that keeps more
+        let (floor, ceil) = match value {
+            ComparableValue::Int(n) => (clamp_coordinate(*n as f64), clamp_coordinate(*n as f64)),
+            ComparableValue::Float(n) => (clamp_coordinate(n.floor()), clamp_coordinate(n.ceil())),
+            ComparableValue::List(bounds) if bounds.len() == 2 => {
+                let (Some(low), Some(high)) = (number(&bounds[0]), number(&bounds[1])) else { return Self::any() };
+                let mut plan = Self::any();
+                plan.bounds[axis] = match op {
+                    FilterOperation::Between { .. } => (clamp_coordinate(low.ceil()), clamp_coordinate(high.floor())),
+                    _ => ANY_RANGE
+                };
+                return plan;
+            }
+            _ => return Self::any()
+        };
+        let range = match op {
+            FilterOperation::Equals => (ceil, floor),
+            FilterOperation::LessThan => (i32::MIN, if floor == ceil { floor.saturating_sub(1) } else { floor }),
+            FilterOperation::LessThanEquals => (i32::MIN, floor),
+            FilterOperation::GreaterThan => (if floor == ceil { ceil.saturating_add(1) } else { ceil }, i32::MAX),
+            FilterOperation::GreaterThanEquals => (ceil, i32::MAX),
+            _ => ANY_RANGE
+        };
+        let mut plan = Self::any();
+        plan.bounds[axis] = range;
+        plan
+    }
+
+    fn intersect(self, other: QueryPlan) -> QueryPlan {
+        let ids = match (self.ids, other.ids) {
+            (Some(a), Some(b)) => {
+                let mut ids = Vec::new();
+                for p in &a {
+                    for q in &b {
+                        let kept = match (p.contains(['*', '?']), q.contains(['*', '?'])) {
+                            _ if p == q => Some(p),
+                            (false, true) => glob_matches(q, p).then_some(p),
+                            (true, false) => glob_matches(p, q).then_some(q),
+                            (true, true) => Some(p), // can't intersect two patterns, p is still a superset
+                            (false, false) => None,
+                        };
+                        if let Some(id) = kept && !ids.contains(id) { ids.push(id.clone()); }
+                    }
+                }
+                Some(ids)
+            },
+            (a, None) => a,
+            (None, b) => b,
+        };
+        let mut excluded_ids = self.excluded_ids;
+        excluded_ids.extend(other.excluded_ids);
+
+        let mut bounds = self.bounds;
+        for (bound, other) in bounds.iter_mut().zip(other.bounds) {
+            *bound = (bound.0.max(other.0), bound.1.min(other.1));
+        }
+        QueryPlan { ids, excluded_ids, bounds }
+    }
+
+    fn union(self, other: QueryPlan) -> QueryPlan {
+        if self.is_empty() { return other; }
+        if other.is_empty() { return self; }
+
+        let ids = match (self.ids, other.ids) {
+            (Some(mut a), Some(b)) => { a.extend(b); Some(a) },
+            _ => None
+        };
+        let excluded_ids = self.excluded_ids.into_iter().filter(|id| other.excluded_ids.contains(id)).collect();
+
+        let mut bounds = self.bounds;
+        for (bound, other) in bounds.iter_mut().zip(other.bounds) {
+            *bound = (bound.0.min(other.0), bound.1.max(other.1));
+        }
+        QueryPlan { ids, excluded_ids, bounds }
+    }
+
+    pub fn ids(&self) -> Option<&Vec<String>> { self.ids.as_ref() }
+    pub fn excluded_ids(&self) -> &Vec<String> { &self.excluded_ids }
+    pub fn bounds(&self) -> &[(i32, i32); 3] { &self.bounds }
+    pub fn restricts_ids(&self) -> bool { self.ids.is_some() || !self.excluded_ids.is_empty() }
+
+    // Nothing can match
+    pub fn is_empty(&self) -> bool {
+        self.ids.as_ref().is_some_and(|ids| ids.is_empty()) || self.bounds.iter().any(|(min, max)| min > max)
+    }
+
+    pub fn matches_id(&self, id: &str) -> bool {
+        if self.excluded_ids.iter().any(|excluded| excluded == id) { return false; }
+        match &self.ids {
+            Some(ids) => ids.iter().any(|pattern| {
+                if pattern.contains(['*', '?']) { glob_matches(pattern, id) } else { pattern == id }
+            }),
+            None => true
+        }
+    }
+
+    // Per palette index, whether blocks with it can match. None when every block can
+    pub fn palette_mask(&self, palette: &FastSet<PaletteBlock>) -> Option<Vec<bool>> {
+        if !self.restricts_ids() { return None; }
+        Some(palette.iter().map(|block| self.matches_id(&block.full_name())).collect())
+    }
+
+    // The chunk starting at (world_x, world_z)
+    pub fn may_contain_chunk(&self, world_x: i32, world_z: i32, chunk_size: i32) -> bool {
+        let overlaps = |(min, max): (i32, i32), start: i32| start <= max && start.saturating_add(chunk_size - 1) >= min;
+        overlaps(self.bounds[0], world_x) && overlaps(self.bounds[2], world_z)
+    }
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::versions::VersionManager;
+    use crate::models::other::tick::Tick;
+    use crate::models::positions::chunk_position::ChunkPosition;
+    use crate::models::world::chunk::Chunk;
+    use crate::models::world::dimension::Dimension;
+    use crate::models::world::fulls::full_block::BlockBuilder;
+    use crate::models::world::world::World;
+    use crate::traits::access::prelude::BlockReader;
+    use crate::types::WorldKind;
+
+    #[test]
+    fn plan_from_filter() {
+        let plan = QueryPlan::from_filter(&Filter::from_filter_string("id == minecraft:diamond_ore && y < 16 && (x >= 0 || x > 5)").unwrap());
+        assert_eq!(plan.ids(), Some(&vec!["minecraft:diamond_ore".to_string()]));
+        assert_eq!(plan.bounds(), &[(0, i32::MAX), (i32::MIN, 15), ANY_RANGE]);
+        assert!(!plan.may_contain_chunk(-16, 0, 16));
+
+        let plan = QueryPlan::from_filter(&Filter::from_filter_string("(id == minecraft:stone || waterlogged == true) && !id == minecraft:air").unwrap());
+        assert_eq!(plan.ids(), None);
+        assert!(!plan.matches_id("minecraft:air") && plan.matches_id("minecraft:dirt"));
+
+        assert!(QueryPlan::from_filter(&Filter::from_filter_string("id == minecraft:stone && id == minecraft:dirt").unwrap()).is_empty());
+        assert!(QueryPlan::from_filter(&Filter::from_filter_string("y > 5 && y < 3").unwrap()).is_empty());
+
+        let plan = QueryPlan::from_filter(&Filter::from_filter_string("id like \"*_ore\" && id != minecraft:iron_ore && y between [-8.5, 2.5] && x < 0.5").unwrap());
+        assert!(plan.matches_id("minecraft:gold_ore") && !plan.matches_id("minecraft:iron_ore") && !plan.matches_id("minecraft:stone"));
+        assert_eq!(plan.bounds(), &[(i32::MIN, 0), (-8, 2), ANY_RANGE]);
+    }
+
+    #[test]
+    fn find_blocks_skips_without_changing_results() {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new("".into(), version.clone());
+        let mut locked = world.lock().unwrap();
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        for (x, z) in [(0, 0), (1, 0), (-1, 3)] {
+            let mut chunk = Chunk::new(ChunkPosition::new(x, z, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version);
+            chunk.set_block_at_index(((70 + 64) * 16 + 2) * 16 + 3, PaletteBlock::new("minecraft:stone", None));
+            if x == 1 { chunk.set_block_at_index((14 * 16 + 4) * 16 + 5, PaletteBlock::new("minecraft:diamond_ore", None)); }
+            // made without the namespace, both the plan and the filter see it as minecraft:gold_block
+            if x == -1 { chunk.set_block_at_index((14 * 16 + 4) * 16 + 5, PaletteBlock::new("gold_block", None)); }
+            dimension.set_chunk(chunk);
+        }
+        locked.set_dimension("overworld".into(), dimension);
+
+        let mut found = Vec::new();
+        locked.select().find_blocks(Filter::from_filter_string("id == minecraft:diamond_ore").unwrap(), |b| { found.push(b.position().clone()); true });
+        assert_eq!(found.iter().map(|p| (p.x(), p.y(), p.z())).collect::<Vec<_>>(), vec![(21, -50, 4)]);
+
+        let mut count = 0;
+        locked.select().find_blocks(Filter::from_filter_string("id == minecraft:stone && x >= 0 && y > 60").unwrap(), |_| { count += 1; true });
+        assert_eq!(count, 2);
+
+        // a plan that restricts ids and one that doesn't find the same blocks
+        for query in ["id == minecraft:gold_block", "id == minecraft:gold_block || waterlogged == true", "id like \"minecraft:gold_*\""] {
+            let mut found = Vec::new();
+            locked.select().find_blocks(Filter::from_filter_string(query).unwrap(), |b| { found.push(b.position().clone()); true });
+            assert_eq!(found.iter().map(|p| (p.x(), p.y(), p.z())).collect::<Vec<_>>(), vec![(-11, -50, 52)], "query: {}", query);
+        }
+        // and a block that's only built, not read from a palette, is matched by the filter the same way
+        let built = BlockBuilder::new(world.clone()).named("gold_block").build();
+        let filter = Filter::from_filter_string("id == minecraft:gold_block").unwrap();
+        assert!(filter.matches_block(&built) && QueryPlan::from_filter(&filter).matches_id(&built.full_name()));
+    }
+}
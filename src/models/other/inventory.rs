@@ -15,6 +15,13 @@ impl Item {
             id, count, properties
         }
     }
+
+    pub fn id(&self) -> &str { &self.id }
+    pub fn count(&self) -> u32 { self.count }
+    pub fn properties(&self) -> &Properties { &self.properties }
+
+    pub fn set_id(&mut self, id: String) { self.id = id; }
+    pub fn set_count(&mut self, count: u32) { self.count = count; }
 }
 
 #[derive(Debug, Clone)]
@@ -36,6 +43,17 @@ impl Inventory {
         self.items.get(&slot)
     }
 
+    pub fn size(&self) -> usize { self.size }
+
+    // Summed over all slots
+    pub fn count_of(&self, id: &str) -> u32 {
+        self.items.values().filter(|i| i.id == id).map(|i| i.count).sum()
+    }
+
+    pub fn total_count(&self) -> u32 {
+        self.items.values().map(|i| i.count).sum()
+    }
+
     pub fn add_item(&mut self, item: Item) -> bool {
         for s in 0..self.size {
             if self.items.contains_key(&s) && self.items[&s].id == item.id  {
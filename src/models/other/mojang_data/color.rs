@@ -1,4 +1,4 @@
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum MinecraftColor {
     Black,
     White,
@@ -18,6 +18,30 @@ pub enum MinecraftColor {
     Red
 }
 
+impl MinecraftColor {
+    // The dye name the game writes
+    pub fn name(&self) -> &'static str {
+        match self {
+            MinecraftColor::Black => "black",
+            MinecraftColor::White => "white",
+            MinecraftColor::Orange => "orange",
+            MinecraftColor::Magenta => "magenta",
+            MinecraftColor::LightBlue => "light_blue",
+            MinecraftColor::Yellow => "yellow",
+            MinecraftColor::Lime => "lime",
+            MinecraftColor::Pink => "pink",
+            MinecraftColor::Gray => "gray",
+            MinecraftColor::LightGray => "light_gray",
+            MinecraftColor::Cyan => "cyan",
+            MinecraftColor::Purple => "purple",
+            MinecraftColor::Blue => "blue",
+            MinecraftColor::Brown => "brown",
+            MinecraftColor::Green => "green",
+            MinecraftColor::Red => "red",
+        }
+    }
+}
+
 impl From<&str> for MinecraftColor {
     fn from(value: &str) -> Self {
         match value {
@@ -25,12 +49,12 @@ impl From<&str> for MinecraftColor {
             "white" => MinecraftColor::White,
             "orange" => MinecraftColor::Orange,
             "magenta" => MinecraftColor::Magenta,
-            "lightblue" => MinecraftColor::LightBlue,
+            "lightblue" | "light_blue" => MinecraftColor::LightBlue,
             "yellow" => MinecraftColor::Yellow,
             "lime" => MinecraftColor::Lime,
             "pink" => MinecraftColor::Pink,
             "gray" => MinecraftColor::Gray,
-            "lightgray" => MinecraftColor::LightGray,
+            "lightgray" | "light_gray" => MinecraftColor::LightGray,
             "cyan" => MinecraftColor::Cyan,
             "purple" => MinecraftColor::Purple,
             "blue" => MinecraftColor::Blue,
@@ -1,10 +1,10 @@
 use std::collections::HashMap;
 use fastnbt::Value as NBTValue;
 use serde::Deserialize;
-use serde_json::Value;
+use serde_json::{Map, Value};
 use crate::models::other::mojang_data::color::MinecraftColor;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct TextComponentEvent {
     action: String,
     value: NBTValue,
@@ -18,11 +18,20 @@ impl TextComponentEvent {
             value: NBTValue::deserialize(val).unwrap_or(NBTValue::Compound(HashMap::new())),
         }
     }
+
+    pub fn to_json(&self) -> Value {
+        let mut v = match serde_json::to_value(&self.value) {
+            Ok(Value::Object(v)) => v,
+            _ => Map::new()
+        };
+        v.insert("action".to_string(), Value::String(self.action.clone()));
+        Value::Object(v)
+    }
 }
 
 
 // TODO: https://minecraft.wiki/w/Text_component_format more accuracy
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TextComponent {
     text: String,
     color: MinecraftColor,
@@ -51,9 +60,9 @@ impl TextComponent {
     }
 
     pub fn from_string(text: &str) -> Self {
-        if let Ok(mut json) = serde_json::from_str::<Value>(text) {
-            let v = json.as_object_mut().unwrap();
-            TextComponent {
+        // names can also be a plain json string, or not json at all
+        match serde_json::from_str::<Value>(text) {
+            Ok(Value::Object(mut v)) => TextComponent {
                 text: v.remove("text").and_then(|val| val.as_str().map(str::to_owned)).unwrap_or_default(),
                 color: v.remove("color").and_then(|val| Some(MinecraftColor::from(val.as_str()))).unwrap_or(MinecraftColor::Black),
                 bold: v.remove("bold").and_then(|val| val.as_bool()).unwrap_or(false),
@@ -63,10 +72,23 @@ impl TextComponent {
                 strikethrough: v.remove("strikethrough").and_then(|val| val.as_bool()).unwrap_or(false),
                 hover_event: v.remove("hover_event").and_then(|val| Some(TextComponentEvent::from_json(val))),
                 click_event: v.remove("click_event").and_then(|val| Some(TextComponentEvent::from_json(val))),
-            }
-        } else {
-            Self::new(text)
+            },
+            Ok(Value::String(s)) => Self::new(&s),
+            _ => Self::new(text)
+        }
+    }
+
+    // What from_string reads back, black and unset styles are left out
+    pub fn to_json_string(&self) -> String {
+        let mut v = Map::new();
+        v.insert("text".to_string(), Value::String(self.text.clone()));
+        if !matches!(self.color, MinecraftColor::Black) { v.insert("color".to_string(), Value::String(self.color.name().to_string())); }
+        for (key, set) in [("bold", self.bold), ("italic", self.italic), ("underline", self.underline), ("obfuscated", self.obfuscated), ("strikethrough", self.strikethrough)] {
+            if set { v.insert(key.to_string(), Value::Bool(true)); }
         }
+        if let Some(event) = &self.hover_event { v.insert("hover_event".to_string(), event.to_json()); }
+        if let Some(event) = &self.click_event { v.insert("click_event".to_string(), event.to_json()); }
+        Value::Object(v).to_string()
     }
 
     pub fn is_bold(&self) -> bool { self.bold }
@@ -53,6 +53,34 @@ impl Properties {
         Some(current_node.clone())
     }
 
+    // Like get, but a '*' part goes into every element of a list or every value of a compound,
+    // e.g. "Attributes.*.Base". Returns all values found
+    pub fn get_all(&self, path: &str) -> Vec<Value> {
+        let mut parts = path.split('.');
+        let props = self.raw();
+        let mut current = match parts.next() {
+            Some("*") => props.values().collect::<Vec<_>>(),
+            Some(part) => props.get(part).into_iter().collect(),
+            None => return Vec::new(),
+        };
+
+        for part in parts {
+            let mut next = Vec::new();
+            for node in current {
+                match (node, part) {
+                    (Value::List(a), "*") => next.extend(a.iter()),
+                    (Value::Compound(m), "*") => next.extend(m.values()),
+                    (Value::List(a), _) => next.extend(part.parse::<usize>().ok().and_then(|i| a.get(i))),
+                    (Value::Compound(m), _) => next.extend(m.get(part)),
+                    _ => {}
+                }
+            }
+            current = next;
+        }
+
+        current.into_iter().cloned().collect()
+    }
+
     pub fn set(&mut self, path: &str, value: Value) -> Option<bool> {
         let mut parts = path.split('.').peekable();
         let mut props = self.raw();
@@ -7,7 +7,7 @@ pub struct Tick {
 }
 
 impl Tick {
-    fn get_tick(&self) -> usize {
+    pub fn get_tick(&self) -> usize {
         self.tick
     }
 
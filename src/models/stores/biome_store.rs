@@ -2,6 +2,7 @@
 
 use crate::constants::constants::BIOME_CELL_SIZE;
 use crate::models::other::fast_set::FastSet;
+use crate::models::other::lasso_string::LassoString;
 use crate::models::positions::whole_position::Position;
 use crate::traits::misc::store::StoreLike;
 
@@ -91,8 +92,7 @@ impl BiomeStore {
         let mut p = FastSet::with_capacity(size);
         p.insert("cubicle:null".to_string());
 
-        let mut v = Vec::with_capacity(total_biomes as usize);
-        unsafe { v.set_len(total_biomes as usize); }
+        let v = vec![0; total_biomes as usize];
 
         BiomeStore {
             palette: p,
@@ -119,10 +119,63 @@ impl BiomeStore {
     #[inline]
     pub fn indices_slice_mut(&mut self) -> &mut [usize] { <Self as StoreLike<String>>::indices_slice_mut(self) }
 
-    pub fn get_biome_at_block_position(&self, mut relative_position: Position) -> Option<String> {
-        relative_position.set_x(relative_position.x() / BIOME_CELL_SIZE);
-        relative_position.set_y(relative_position.y() / BIOME_CELL_SIZE);
-        relative_position.set_z(relative_position.z() / BIOME_CELL_SIZE);
-        self.get_biome_at_position(relative_position)
+    #[inline]
+    fn cells_per_side(&self) -> usize { (self.chunk_size / BIOME_CELL_SIZE) as usize }
+
+    // x and z can be world or chunk relative (only the position inside the chunk is used), y is the world y
+    pub fn biome_index_at_block(&self, x: i32, y: i32, z: i32) -> Option<usize> {
+        let cells = self.cells_per_side();
+        let cell_y = (y - self.lowest_y).div_euclid(BIOME_CELL_SIZE);
+        if cell_y < 0 { return None; }
+        let cell_x = x.rem_euclid(self.chunk_size) / BIOME_CELL_SIZE;
+        let cell_z = z.rem_euclid(self.chunk_size) / BIOME_CELL_SIZE;
+        let index = (cell_y as usize * cells + cell_z as usize) * cells + cell_x as usize;
+        if index >= self.indices.len() { None } else { Some(index) }
+    }
+
+    pub fn get_biome_at_block_position(&self, position: Position) -> Option<String> {
+        let index = self.biome_index_at_block(position.x(), position.y(), position.z())?;
+        self.get_biome_at_index(index)
+    }
+
+    pub fn set_biome_at_block_position(&mut self, position: &Position, biome: String) -> bool {
+        match self.biome_index_at_block(position.x(), position.y(), position.z()) {
+            Some(index) => self.set_biome_at_index(index, biome),
+            None => false
+        }
+    }
+
+    // Palette as interned strings, cheap to hand out per block while iterating
+    pub fn interned_palette(&self) -> Vec<Option<LassoString>> {
+        self.palette.iter().map(|b| if b == "cubicle:null" { None } else { Some(LassoString::from(b.as_str())) }).collect()
+    }
+
+    // Swaps every cell of `from` to `to` at the palette level, returns the amount of cells changed
+    pub fn replace_biome(&mut self, from: &str, to: &str) -> usize {
+        let Some(from_index) = self.palette.iter().position(|b| b != "cubicle:null" && b == from) else { return 0; };
+        let to_index = self.add_biome_to_palette(to.to_string());
+        if from_index == to_index { return 0; }
+
+        let mut count = 0;
+        for index in self.indices.iter_mut().filter(|i| **i == from_index) {
+            *index = to_index;
+            count += 1;
+        }
+        count
+    }
+}
+
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_store_reads_as_null_biomes() {
+        let store = BiomeStore::new(16, -64, 320);
+        assert!(store.indices_slice().iter().all(|i| *i == 0));
+        assert_eq!(store.get_biome_at_index(0), None);
     }
 }
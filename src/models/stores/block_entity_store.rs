@@ -1,10 +1,10 @@
-use std::rc::Rc;
+use std::sync::Arc;
 use crate::models::block_entity::prelude::*;
 use crate::models::positions::whole_position::Position;
 
 #[derive(Debug)]
 pub struct BlockEntityStore {
-    block_entities: Vec<Rc<BlockEntity>>, // is this fastest even if theres a chunk full of chests?
+    block_entities: Vec<Arc<BlockEntity>>, // is this fastest even if theres a chunk full of chests?
 }
 
 impl BlockEntityStore {
@@ -15,7 +15,7 @@ impl BlockEntityStore {
         BlockEntityStore { block_entities: Vec::with_capacity(capacity) }
     }
 
-    pub fn get_at_world_position(&self, pos: &Position) -> Option<Rc<BlockEntity>> {
+    pub fn get_at_world_position(&self, pos: &Position) -> Option<Arc<BlockEntity>> {
         for block_entity in self.block_entities.iter() {
             if block_entity.base().position() == pos {
                 return Some(block_entity.clone())
@@ -24,19 +24,31 @@ impl BlockEntityStore {
         None
     }
 
-    pub fn get_all(&self) -> &Vec<Rc<BlockEntity>> { &self.block_entities }
+    pub fn get_all(&self) -> &Vec<Arc<BlockEntity>> { &self.block_entities }
 
     pub fn set_at_world_position(&mut self, ent: BlockEntity) {
         for (ind, block_entity) in self.block_entities.iter().enumerate() {
             if block_entity.base().position() == ent.base().position() {
-                self.block_entities.insert(ind, Rc::new(ent));
+                self.block_entities[ind] = Arc::new(ent);
                 return;
             }
         }
-        self.block_entities.push(Rc::new(ent));
+        self.block_entities.push(Arc::new(ent));
+    }
+
+    pub fn remove_at_world_position(&mut self, pos: &Position) -> Option<Arc<BlockEntity>> {
+        let ind = self.block_entities.iter().position(|b| b.base().position() == pos)?;
+        Some(self.block_entities.swap_remove(ind))
+    }
+
+    // Removes and returns every block entity the predicate matches
+    pub fn remove_where<F>(&mut self, mut remove: F) -> Vec<Arc<BlockEntity>> where F: FnMut(&BlockEntity) -> bool {
+        let (removed, kept) = std::mem::take(&mut self.block_entities).into_iter().partition(|block_entity| remove(block_entity));
+        self.block_entities = kept;
+        removed
     }
 
     pub unsafe fn add_unchecked(&mut self, ent: BlockEntity) {
-        self.block_entities.push(Rc::new(ent));
+        self.block_entities.push(Arc::new(ent));
     }
 }
@@ -12,6 +12,7 @@ use crate::traits::misc::store::StoreLike;
 pub struct BlockStore {
     palette: FastSet<PaletteBlock>,
     indices: Vec<usize>,
+    revision: u64, // bumped on every change, so cached data about the blocks (e.g. BlockIndex) can tell it's stale
 
     chunk_size: i32,
     lowest_y: i32
@@ -29,12 +30,12 @@ impl BlockStore {
         let mut p = FastSet::with_capacity(size);
         p.insert(PaletteBlock::new_null());
 
-        let mut v = Vec::with_capacity(total_blocks);
-        unsafe { v.set_len(total_blocks) };
+        let v = vec![0; total_blocks]; // 0 is the null block, so unloaded sections read as nothing
 
         BlockStore {
             palette: p,
             indices: v,
+            revision: 0,
 
             chunk_size, lowest_y
         }
@@ -56,9 +57,53 @@ impl BlockStore {
     pub fn indices_slice(&self) -> &[usize] { <Self as StoreLike<PaletteBlock>>::indices_slice(self) }
     #[inline]
     pub fn indices_slice_mut(&mut self) -> &mut [usize] { <Self as StoreLike<PaletteBlock>>::indices_slice_mut(self) }
+    pub fn chunk_size(&self) -> i32 { self.chunk_size }
+    pub fn lowest_y(&self) -> i32 { self.lowest_y }
+    pub fn revision(&self) -> u64 { self.revision }
     pub fn blocks(&self) -> impl Iterator<Item=PaletteBlock> {
         self.indices.iter().map(|i| self.palette[*i].clone())
     }
+    // The palette index of every block, in block index order. Nothing is cloned, for tight loops over the palette
+    pub fn palette_indices(&self) -> impl Iterator<Item=usize> + '_ { self.indices.iter().copied() }
+    // The sections from the bottom up, section_height blocks tall (VersionData::section_height)
+    pub fn sections(&self, section_height: i32) -> impl Iterator<Item=SectionView<'_>> {
+        let section_size = (self.chunk_size * self.chunk_size * section_height) as usize;
+        let first_y = self.lowest_y.div_euclid(section_height);
+        self.indices.chunks(section_size).enumerate().map(move |(i, indices)| SectionView {
+            y: first_y + i as i32,
+            start: i * section_size,
+            indices,
+            palette: &self.palette,
+        })
+    }
+}
+
+// One section of a BlockStore, see BlockStore::sections
+#[derive(Debug, Clone, Copy)]
+pub struct SectionView<'s> {
+    y: i32, // in sections, like the yPos of the section in the chunk nbt
+    start: usize, // block index of the section's first block
+    indices: &'s [usize],
+    palette: &'s FastSet<PaletteBlock>,
+}
+
+impl<'s> SectionView<'s> {
+    pub fn y(&self) -> i32 { self.y }
+    pub fn start(&self) -> usize { self.start }
+    pub fn indices(&self) -> &'s [usize] { self.indices }
+    pub fn palette(&self) -> &'s FastSet<PaletteBlock> { self.palette }
+    pub fn palette_indices(&self) -> impl Iterator<Item=usize> + 's { self.indices.iter().copied() }
+    // (block index in the chunk, palette index)
+    pub fn indexed(&self) -> impl Iterator<Item=(usize, usize)> + 's {
+        let start = self.start;
+        self.indices.iter().enumerate().map(move |(offset, index)| (start + offset, *index))
+    }
+    // The palette index when the whole section is one block, e.g. all air
+    pub fn uniform(&self) -> Option<usize> {
+        let first = *self.indices.first()?;
+        self.indices.iter().all(|i| *i == first).then_some(first)
+    }
+    pub fn contains(&self, palette_index: usize) -> bool { self.indices.contains(&palette_index) }
 }
 
 impl StoreLike<PaletteBlock> for BlockStore {
@@ -74,6 +119,7 @@ impl StoreLike<PaletteBlock> for BlockStore {
         if index >= self.indices.len() { return false; }
         if palette_index >= self.palette.len() { return false; }
         self.indices[index] = palette_index;
+        self.revision += 1;
         true
     }
 
@@ -90,6 +136,7 @@ impl StoreLike<PaletteBlock> for BlockStore {
             );
         }
         self.indices[start_index..end_index].copy_from_slice(palette_indices);
+        self.revision += 1;
     }
 
     fn get_palette_index_of_item(&self, block: &PaletteBlock) -> Option<usize> {
@@ -110,6 +157,7 @@ impl StoreLike<PaletteBlock> for BlockStore {
             self.palette.insert(block);
         }
         self.indices[index] = palette_index;
+        self.revision += 1;
         true
     }
 
@@ -134,5 +182,20 @@ impl StoreLike<PaletteBlock> for BlockStore {
     fn indices_slice(&self) -> &[usize] { &self.indices }
 
     #[inline]
-    fn indices_slice_mut(&mut self) -> &mut [usize] { &mut self.indices }
+    fn indices_slice_mut(&mut self) -> &mut [usize] { self.revision += 1; &mut self.indices }
+}
+
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_store_reads_as_null_blocks() {
+        let store = BlockStore::new(16, -64, 320);
+        assert!(store.indices_slice().iter().all(|i| *i == 0));
+        assert!(store.get_block_at_index(store.indices_slice().len() - 1).is_none());
+    }
 }
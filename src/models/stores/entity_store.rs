@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use crate::models::entity::entity::Entity;
 
 pub type EntityStoreKey = usize;
@@ -43,6 +44,20 @@ impl EntityStore {
         self.entities.swap_remove(*entity_id)
     }
 
+    // Removes all of keys at once, the others keep their order. Returns the new key of every old key, None if removed
+    pub fn remove_keys(&mut self, keys: &HashSet<EntityStoreKey>) -> Vec<Option<EntityStoreKey>> {
+        let mut new_keys = Vec::with_capacity(self.entities.len());
+        let (mut key, mut next) = (0, 0);
+        self.entities.retain(|_| {
+            let keep = !keys.contains(&key);
+            new_keys.push(keep.then_some(next));
+            if keep { next += 1; }
+            key += 1;
+            keep
+        });
+        new_keys
+    }
+
     pub fn count(&self) -> usize { self.entities.len() }
 
     pub fn set(&mut self, entity_id: &EntityStoreKey, entity: Entity) -> bool {
@@ -1,21 +1,59 @@
+use std::collections::HashMap;
 use std::sync::Arc;
+use fastnbt::{LongArray, Value};
 use crate::constants::versions::Version;
+use crate::models::world::block::PaletteBlock;
 use crate::types::HeightmapKind;
+use crate::utils::generic_utils::bit_length;
 use crate::utils::position_utils::world_position_to_relative_chunk_position;
 
 const HEIGHTMAP_COUNT: usize = 4;
 
 impl HeightmapKind {
+    pub const ALL: [HeightmapKind; HEIGHTMAP_COUNT] = [
+        HeightmapKind::MotionBlocking,
+        HeightmapKind::MotionBlockingNoLeaves,
+        HeightmapKind::Ground,
+        HeightmapKind::SkyExposed,
+    ];
+
     fn as_index(self) -> usize {
         self as usize
     }
+
+    #[inline]
+    pub fn mask(self) -> u8 { 1 << self.as_index() }
+
+    pub fn nbt_name(self) -> &'static str {
+        match self {
+            HeightmapKind::MotionBlocking => "MOTION_BLOCKING",
+            HeightmapKind::MotionBlockingNoLeaves => "MOTION_BLOCKING_NO_LEAVES",
+            HeightmapKind::Ground => "OCEAN_FLOOR",
+            HeightmapKind::SkyExposed => "WORLD_SURFACE",
+        }
+    }
+}
+
+// Bit set of all the heightmap kinds this block counts for (see HeightmapKind::mask)
+pub fn heightmap_mask(block: &PaletteBlock) -> u8 {
+    if block.is_air() { return 0; }
+
+    let blocks_motion = block.blocks_motion();
+    let motion_blocking = blocks_motion || block.is_fluid();
+
+    let mut mask = HeightmapKind::SkyExposed.mask();
+    if blocks_motion { mask |= HeightmapKind::Ground.mask(); }
+    if motion_blocking { mask |= HeightmapKind::MotionBlocking.mask(); }
+    if motion_blocking && !block.is_leaves() { mask |= HeightmapKind::MotionBlockingNoLeaves.mask(); }
+    mask
 }
 
 #[derive(Debug)]
 pub struct Heightmap {
     distances_from_bottom: Vec<i64>, // x fast, z slow
     lowest_y: i64,
-    chunk_size: i32
+    chunk_size: i32,
+    bits_per_entry: u32,
 }
 
 impl Heightmap {
@@ -24,6 +62,7 @@ impl Heightmap {
             distances_from_bottom: vec![0; (version.data.chunk_size * version.data.chunk_size) as usize],
             lowest_y: version.data.lowest_y as i64,
             chunk_size: version.data.chunk_size,
+            bits_per_entry: bit_length(version.data.highest_y - version.data.lowest_y + 1),
         }
     }
 
@@ -40,18 +79,44 @@ impl Heightmap {
             for _ in 0..entries_per_long {
                 if current_entry_count == max_entries { return; }
                 let height_from_bottom = shifted_value & mask;
-                *self.distances_from_bottom.get_unchecked_mut(current_entry_count) = height_from_bottom;
+                unsafe { *self.distances_from_bottom.get_unchecked_mut(current_entry_count) = height_from_bottom; }
                 shifted_value >>= bits_per_entry;
                 current_entry_count += 1;
             }
         }
     }
 
+    // Packs the heightmap back the way it is saved, entries dont cross long boundaries
+    pub fn to_longs(&self) -> Vec<i64> {
+        let entries_per_long = (u64::BITS / self.bits_per_entry) as usize;
+        let mask: u64 = (1 << self.bits_per_entry) - 1;
+
+        self.distances_from_bottom.chunks(entries_per_long).map(|entries| {
+            let mut long: u64 = 0;
+            for (i, distance) in entries.iter().enumerate() {
+                long |= (*distance as u64 & mask) << (i as u32 * self.bits_per_entry);
+            }
+            long as i64
+        }).collect()
+    }
+
     pub fn get_highest_y_at_position(&self, x: i32, z: i32) -> i32 {
         let (x, _, z) = world_position_to_relative_chunk_position(x, 0, z, self.chunk_size);
         let index = (z * self.chunk_size + x) as usize;
         (self.distances_from_bottom[index] + self.lowest_y - 1) as i32
     }
+
+    pub fn set_highest_y_at_position(&mut self, x: i32, z: i32, y: i32) {
+        let (x, _, z) = world_position_to_relative_chunk_position(x, 0, z, self.chunk_size);
+        let index = (z * self.chunk_size + x) as usize;
+        self.distances_from_bottom[index] = (y as i64 - self.lowest_y + 1).max(0);
+    }
+
+    // column index is z * chunk_size + x
+    #[inline]
+    pub fn distance_at_column(&self, column: usize) -> i64 { self.distances_from_bottom[column] }
+    #[inline]
+    pub fn set_distance_at_column(&mut self, column: usize, distance: i64) { self.distances_from_bottom[column] = distance; }
 }
 
 #[derive(Debug)]
@@ -75,4 +140,10 @@ impl HeightmapStore {
     pub fn get_kind_mut(&mut self, kind: HeightmapKind) -> &mut Heightmap {
         &mut self.heightmap_map[kind.as_index()]
     }
-}
\ No newline at end of file
+
+    pub fn to_nbt(&self) -> HashMap<String, Value> {
+        HeightmapKind::ALL.iter().map(|kind| {
+            (kind.nbt_name().to_string(), Value::LongArray(LongArray::new(self.get_kind(*kind).to_longs())))
+        }).collect()
+    }
+}
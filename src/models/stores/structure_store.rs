@@ -1,6 +1,7 @@
 use std::collections::HashMap;
+use crate::models::positions::whole_position::Position;
 use crate::models::world::dimension::Dimension;
-use crate::models::world_structures::generic_structure::GenericParentStructure;
+use crate::models::world_structures::generic_structure::{BoundingBox, GenericParentStructure};
 
 #[derive(Debug)]
 pub struct StructureStoreReference {
@@ -58,6 +59,29 @@ impl StructureStore {
         }
         structures
     }
+
+    // Structures with a piece containing the position (not just their whole bounding box)
+    pub fn structures_at(&self, position: &Position) -> Vec<&GenericParentStructure> {
+        self.structures.values().flatten().filter(|st| st.contains(position)).collect()
+    }
+
+    pub fn structures_intersecting(&self, bounding_box: &BoundingBox) -> Vec<&GenericParentStructure> {
+        self.structures.values().flatten().filter(|st| st.intersects(bounding_box)).collect()
+    }
+
+    // Distance is measured to the closest piece of each structure
+    pub fn nearest_structure(&self, id: &str, from: &Position) -> Option<&GenericParentStructure> {
+        self.structures.values().flatten()
+            .filter(|st| st.id() == id)
+            .filter_map(|st| {
+                st.children().iter()
+                    .filter_map(|child| child.bounding_box().distance_squared(from))
+                    .min()
+                    .map(|distance| (distance, st))
+            })
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, st)| st)
+    }
 }
 
 impl StructureStoreReference {
@@ -71,4 +95,43 @@ impl StructureStoreReference {
     pub fn get<'a>(&self, dimension: &'a Dimension) -> Option<&'a GenericParentStructure> {
         dimension.structure_store().get_structure_by_store_reference(self)
     }
-}
\ No newline at end of file
+}
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::other::properties::Properties;
+    use crate::models::positions::chunk_position::ChunkPosition;
+    use crate::models::world_structures::generic_structure::GenericChildStructure;
+
+    fn pos(x: i32, y: i32, z: i32) -> Position { Position::new("overworld".into(), x, y, z) }
+
+    fn structure(id: &str, chunk: (i32, i32), pieces: &[(Position, Position)]) -> GenericParentStructure {
+        let children = pieces.iter().map(|(a, b)| GenericChildStructure::new("piece", BoundingBox::new(a, b), Properties::new(HashMap::new()))).collect();
+        GenericParentStructure::new(ChunkPosition::new(chunk.0, chunk.1, "overworld".into()), id, children, Properties::new(HashMap::new()))
+    }
+
+    #[test]
+    fn spatial_queries() {
+        let mut store = StructureStore::new();
+        store.add_structure(structure("minecraft:village_plains", (-2, -2), &[(pos(-40, 60, -40), pos(-20, 70, -20)), (pos(-19, 60, -30), pos(-10, 65, -25))]));
+        store.add_structure(structure("minecraft:village_plains", (3, 3), &[(pos(40, 60, 40), pos(60, 70, 60))]));
+        store.add_structure(structure("minecraft:mineshaft", (-1, -1), &[(pos(-30, 20, -30), pos(-1, 40, -1))]));
+
+        assert_eq!(store.structures_at(&pos(-25, 65, -25)).len(), 1);
+        assert_eq!(store.structures_at(&pos(-15, 62, -27))[0].id(), "minecraft:village_plains");
+        assert!(store.structures_at(&pos(-15, 62, -20)).is_empty());
+        assert_eq!(store.structures_at(&pos(-1, 40, -1))[0].id(), "minecraft:mineshaft");
+
+        let ids = |bb: &BoundingBox| { let mut ids = store.structures_intersecting(bb).iter().map(|s| s.id().clone()).collect::<Vec<_>>(); ids.sort(); ids };
+        assert_eq!(ids(&BoundingBox::new(&pos(-25, 0, -25), &pos(-21, 100, -21))), vec!["minecraft:mineshaft", "minecraft:village_plains"]);
+        assert_eq!(ids(&BoundingBox::new(&pos(-9, 30, -30), &pos(0, 60, -25))), vec!["minecraft:mineshaft"]);
+        assert!(ids(&BoundingBox::new(&pos(0, 0, 0), &pos(39, 100, 39))).is_empty());
+
+        let nearest = store.nearest_structure("minecraft:village_plains", &pos(5, 64, 5)).unwrap();
+        assert_eq!(nearest.chunk_position().position(), (-2, -2));
+        assert_eq!(store.nearest_structure("minecraft:village_plains", &pos(30, 64, 30)).unwrap().chunk_position().position(), (3, 3));
+        assert!(store.nearest_structure("minecraft:stronghold", &pos(0, 0, 0)).is_none());
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::HashSet;
+use crate::models::other::lasso_string::LassoString;
+use crate::models::positions::chunk_position::ChunkPosition;
+use crate::models::positions::whole_position::Position;
+use crate::models::world::fulls::block_state_types::Direction;
+use crate::models::world::shape::Shape;
+
+type BlockBounds = ((i32, i32, i32), (i32, i32, i32)); // inclusive min and max corners
+
+// A set of blocks, possibly in multiple dimensions, made of shapes and chunks. Doesn't need a world, so areas can be
+// built and combined freely and then selected with SelectionBuilder::with_area or the Selection set operations
+#[derive(Debug, Clone, PartialEq)]
+pub enum Area {
+    Shape(Shape),
+    // Whole chunk columns, between min_y and max_y
+    Chunks { chunks: HashSet<ChunkPosition>, chunk_size: i32, min_y: i32, max_y: i32 },
+    Union(Vec<Area>),
+    Intersection(Vec<Area>),
+    Difference(Box<Area>, Box<Area>),
+    Shifted(Box<Area>, (i32, i32, i32)),
+    // Blocks at most negative / positive blocks away from the area, per axis
+    Expanded { area: Box<Area>, negative: (i32, i32, i32), positive: (i32, i32, i32) },
+    // Blocks that stay in the area when moved up to negative / positive blocks, per axis
+    Contracted { area: Box<Area>, negative: (i32, i32, i32), positive: (i32, i32, i32) },
+}
+
+impl From<Shape> for Area {
+    fn from(shape: Shape) -> Self { Area::Shape(shape) }
+}
+
+fn direction_offsets(amount: i32, directions: &[Direction]) -> ((i32, i32, i32), (i32, i32, i32)) {
+    let (mut negative, mut positive) = ((0, 0, 0), (0, 0, 0));
+    for direction in directions {
+        match direction {
+            Direction::West => negative.0 = amount,
+            Direction::East => positive.0 = amount,
+            Direction::Down => negative.1 = amount,
+            Direction::Up => positive.1 = amount,
+            Direction::North => negative.2 = amount,
+            Direction::South => positive.2 = amount,
+        }
+    }
+    (negative, positive)
+}
+
+fn add(a: (i32, i32, i32), b: (i32, i32, i32)) -> (i32, i32, i32) { (a.0 + b.0, a.1 + b.1, a.2 + b.2) }
+fn sub(a: (i32, i32, i32), b: (i32, i32, i32)) -> (i32, i32, i32) { (a.0 - b.0, a.1 - b.1, a.2 - b.2) }
+fn non_negative(a: (i32, i32, i32)) -> (i32, i32, i32) { (a.0.max(0), a.1.max(0), a.2.max(0)) }
+
+fn merge_bounds(a: Option<BlockBounds>, b: Option<BlockBounds>) -> Option<BlockBounds> {
+    match (a, b) {
+        (Some((a_min, a_max)), Some((b_min, b_max))) => Some((
+            (a_min.0.min(b_min.0), a_min.1.min(b_min.1), a_min.2.min(b_min.2)),
+            (a_max.0.max(b_max.0), a_max.1.max(b_max.1), a_max.2.max(b_max.2)),
+        )),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+fn overlap_bounds(a: BlockBounds, b: BlockBounds) -> Option<BlockBounds> {
+    let min = (a.0.0.max(b.0.0), a.0.1.max(b.0.1), a.0.2.max(b.0.2));
+    let max = (a.1.0.min(b.1.0), a.1.1.min(b.1.1), a.1.2.min(b.1.2));
+    if min.0 > max.0 || min.1 > max.1 || min.2 > max.2 { None } else { Some((min, max)) }
+}
+
+// Every offset between -negative and positive
+fn offsets(negative: (i32, i32, i32), positive: (i32, i32, i32)) -> impl Iterator<Item = (i32, i32, i32)> {
+    (-negative.0..=positive.0).flat_map(move |dx| (-negative.1..=positive.1).flat_map(move |dy| (-negative.2..=positive.2).map(move |dz| (dx, dy, dz))))
+}
+
+impl Area {
+    pub fn chunks<I: IntoIterator<Item = ChunkPosition>>(chunks: I, chunk_size: i32, min_y: i32, max_y: i32) -> Area {
+        Area::Chunks { chunks: chunks.into_iter().collect(), chunk_size, min_y, max_y }
+    }
+
+    pub fn union(self, other: Area) -> Area {
+        match self {
+            Area::Union(mut areas) => { areas.push(other); Area::Union(areas) }
+            area => Area::Union(vec![area, other])
+        }
+    }
+
+    pub fn intersect(self, other: Area) -> Area {
+        match self {
+            Area::Intersection(mut areas) => { areas.push(other); Area::Intersection(areas) }
+            area => Area::Intersection(vec![area, other])
+        }
+    }
+
+    pub fn subtract(self, other: Area) -> Area { Area::Difference(Box::new(self), Box::new(other)) }
+
+    pub fn shifted(self, offset: (i32, i32, i32)) -> Area {
+        match self {
+            Area::Shifted(area, previous) => Area::Shifted(area, add(previous, offset)),
+            area => Area::Shifted(Box::new(area), offset)
+        }
+    }
+
+    // Negative amounts count as 0. A cuboid just gets bigger. Cuboids, chunks and their unions and intersections are
+    // checked in one go, other areas at every offset that stays in their bounds, so keep it small for those
+    pub fn expanded(self, negative: (i32, i32, i32), positive: (i32, i32, i32)) -> Area {
+        let (negative, positive) = (non_negative(negative), non_negative(positive));
+        match self {
+            Area::Shape(Shape::Cuboid { dimension, min, max }) => Area::Shape(Shape::Cuboid { dimension, min: sub(min, negative), max: add(max, positive) }),
+            area => Area::Expanded { area: Box::new(area), negative, positive }
+        }
+    }
+
+    pub fn contracted(self, negative: (i32, i32, i32), positive: (i32, i32, i32)) -> Area {
+        let (negative, positive) = (non_negative(negative), non_negative(positive));
+        match self {
+            Area::Shape(Shape::Cuboid { dimension, min, max }) => {
+                // an empty cuboid is fine, it has no bounds
+                Area::Shape(Shape::Cuboid { dimension, min: add(min, negative), max: sub(max, positive) })
+            }
+            area => Area::Contracted { area: Box::new(area), negative, positive }
+        }
+    }
+
+    // e.g. expand(2, &[Direction::Up, Direction::East]) grows the area by 2 blocks upwards and towards +x
+    pub fn expand(self, amount: i32, directions: &[Direction]) -> Area {
+        let (negative, positive) = direction_offsets(amount, directions);
+        self.expanded(negative, positive)
+    }
+
+    pub fn contract(self, amount: i32, directions: &[Direction]) -> Area {
+        let (negative, positive) = direction_offsets(amount, directions);
+        self.contracted(negative, positive)
+    }
+
+    // Whole chunk columns of every chunk the area touches, between the area's lowest and highest block
+    pub fn outset_to_chunks(self, chunk_size: i32) -> Area {
+        let mut outset = self.dimensions().into_iter().filter_map(|dimension| {
+            let (min, max) = self.bounds(&dimension)?;
+            let chunks = self.chunk_positions(chunk_size).into_iter().filter(|c| *c.dimension() == dimension);
+            Some(Area::chunks(chunks, chunk_size, min.1, max.1))
+        }).collect::<Vec<_>>();
+        if outset.len() == 1 { outset.remove(0) } else { Area::Union(outset) }
+    }
+
+    pub fn dimensions(&self) -> HashSet<LassoString> {
+        match self {
+            Area::Shape(shape) => HashSet::from([*shape.dimension()]),
+            Area::Chunks { chunks, .. } => chunks.iter().map(|c| *c.dimension()).collect(),
+            Area::Union(areas) | Area::Intersection(areas) => areas.iter().flat_map(|a| a.dimensions()).collect(),
+            Area::Difference(area, _) | Area::Shifted(area, _) | Area::Expanded { area, .. } | Area::Contracted { area, .. } => area.dimensions(),
+        }
+    }
+
+    // Bounding box of the blocks in a dimension, None if there are none. Not always tight
+    pub fn bounds(&self, dimension: &LassoString) -> Option<BlockBounds> {
+        match self {
+            Area::Shape(shape) => if shape.dimension() == dimension { shape.bounds() } else { None },
+            Area::Chunks { chunks, chunk_size, min_y, max_y } => {
+                if min_y > max_y { return None; }
+                chunks.iter().filter(|c| c.dimension() == dimension)
+                    .map(|c| Some(((c.x() * chunk_size, *min_y, c.z() * chunk_size), (c.x() * chunk_size + chunk_size - 1, *max_y, c.z() * chunk_size + chunk_size - 1))))
+                    .reduce(merge_bounds).flatten()
+            }
+            Area::Union(areas) => areas.iter().map(|a| a.bounds(dimension)).reduce(merge_bounds).flatten(),
+            Area::Intersection(areas) => {
+                let mut bounds = areas.iter().map(|a| a.bounds(dimension));
+                let first = bounds.next()??;
+                bounds.try_fold(first, |acc, b| overlap_bounds(acc, b?))
+            }
+            Area::Difference(area, _) => area.bounds(dimension),
+            Area::Shifted(area, offset) => area.bounds(dimension).map(|(min, max)| (add(min, *offset), add(max, *offset))),
+            Area::Expanded { area, negative, positive } => area.bounds(dimension).map(|(min, max)| (sub(min, *negative), add(max, *positive))),
+            Area::Contracted { area, negative, positive } => {
+                let (min, max) = area.bounds(dimension)?;
+                let bounds = (add(min, *negative), sub(max, *positive));
+                overlap_bounds(bounds, bounds)
+            }
+        }
+    }
+
+    pub fn contains(&self, position: &Position) -> bool { self.contains_at(position.dimension(), position.x(), position.y(), position.z()) }
+
+    pub fn contains_at(&self, dimension: &LassoString, x: i32, y: i32, z: i32) -> bool {
+        match self {
+            Area::Shape(shape) => shape.dimension() == dimension && shape.contains_xyz(x, y, z),
+            Area::Chunks { chunks, chunk_size, min_y, max_y } => {
+                y >= *min_y && y <= *max_y && chunks.contains(&ChunkPosition::new(x.div_euclid(*chunk_size), z.div_euclid(*chunk_size), *dimension))
+            }
+            Area::Union(areas) => areas.iter().any(|a| a.contains_at(dimension, x, y, z)),
+            Area::Intersection(areas) => !areas.is_empty() && areas.iter().all(|a| a.contains_at(dimension, x, y, z)),
+            Area::Difference(area, removed) => area.contains_at(dimension, x, y, z) && !removed.contains_at(dimension, x, y, z),
+            Area::Shifted(area, offset) => area.contains_at(dimension, x - offset.0, y - offset.1, z - offset.2),
+            Area::Expanded { area, negative, positive } => area.contains_any_in(dimension, sub((x, y, z), *positive), add((x, y, z), *negative)),
+            Area::Contracted { area, negative, positive } => {
+                offsets(*negative, *positive).all(|(dx, dy, dz)| area.contains_at(dimension, x + dx, y + dy, z + dz))
+            }
+        }
+    }
+
+    // Whether any block between the (inclusive) corners is in the area, exactly unlike intersects_box
+    fn contains_any_in(&self, dimension: &LassoString, min: (i32, i32, i32), max: (i32, i32, i32)) -> bool {
+        match self {
+            Area::Shape(Shape::Cuboid { dimension: own, min: own_min, max: own_max }) => {
+                own == dimension && overlap_bounds((min, max), (*own_min, *own_max)).is_some()
+            }
+            // the columns can be checked exactly too
+            Area::Chunks { .. } => self.intersects_box(dimension, min, max),
+            Area::Union(areas) => areas.iter().any(|a| a.contains_any_in(dimension, min, max)),
+            Area::Shifted(area, offset) => area.contains_any_in(dimension, sub(min, *offset), sub(max, *offset)),
+            Area::Expanded { area, negative, positive } => area.contains_any_in(dimension, sub(min, *positive), add(max, *negative)),
+            // cuboids just make the box smaller, one other area left can be checked on its own
+            Area::Intersection(areas) if !areas.is_empty() => {
+                let mut clipped = (min, max);
+                let mut rest = Vec::new();
+                for area in areas {
+                    match area {
+                        Area::Shape(Shape::Cuboid { dimension: own, min: own_min, max: own_max }) => {
+                            if own != dimension { return false; }
+                            let Some(overlap) = overlap_bounds(clipped, (*own_min, *own_max)) else { return false };
+                            clipped = overlap;
+                        }
+                        area => rest.push(area),
+                    }
+                }
+                match rest.as_slice() {
+                    [] => true,
+                    [area] => area.contains_any_in(dimension, clipped.0, clipped.1),
+                    _ => self.contains_any_at_each(dimension, clipped.0, clipped.1),
+                }
+            }
+            _ => self.contains_any_at_each(dimension, min, max),
+        }
+    }
+
+    // contains_any_in for any area, every block of the box that's in the bounds
+    fn contains_any_at_each(&self, dimension: &LassoString, min: (i32, i32, i32), max: (i32, i32, i32)) -> bool {
+        let Some(bounds) = self.bounds(dimension) else { return false };
+        let Some((min, max)) = overlap_bounds((min, max), bounds) else { return false };
+        offsets((0, 0, 0), sub(max, min)).any(|(dx, dy, dz)| self.contains_at(dimension, min.0 + dx, min.1 + dy, min.2 + dz))
+    }
+
+    // Whether any block between the (inclusive) corners may be in the area, can give false positives
+    pub fn intersects_box(&self, dimension: &LassoString, min: (i32, i32, i32), max: (i32, i32, i32)) -> bool {
+        match self {
+            Area::Shape(shape) => shape.dimension() == dimension && shape.intersects_box(min, max),
+            Area::Chunks { chunks, chunk_size, min_y, max_y } => {
+                if max.1 < *min_y || min.1 > *max_y { return false; }
+                let (from_x, to_x) = (min.0.div_euclid(*chunk_size), max.0.div_euclid(*chunk_size));
+                let (from_z, to_z) = (min.2.div_euclid(*chunk_size), max.2.div_euclid(*chunk_size));
+                if (to_x - from_x + 1) as i64 * (to_z - from_z + 1) as i64 > chunks.len() as i64 {
+                    return chunks.iter().any(|c| c.dimension() == dimension && (from_x..=to_x).contains(&c.x()) && (from_z..=to_z).contains(&c.z()));
+                }
+                (from_x..=to_x).any(|cx| (from_z..=to_z).any(|cz| chunks.contains(&ChunkPosition::new(cx, cz, *dimension))))
+            }
+            Area::Union(areas) => areas.iter().any(|a| a.intersects_box(dimension, min, max)),
+            Area::Intersection(areas) => !areas.is_empty() && areas.iter().all(|a| a.intersects_box(dimension, min, max)),
+            Area::Difference(area, _) | Area::Contracted { area, .. } => area.intersects_box(dimension, min, max),
+            Area::Shifted(area, offset) => area.intersects_box(dimension, sub(min, *offset), sub(max, *offset)),
+            Area::Expanded { area, negative, positive } => area.intersects_box(dimension, sub(min, *positive), add(max, *negative)),
+        }
+    }
+
+    // Chunks the area can't go outside of, when it's made of chunk sets. Saves scanning the bounding box of
+    // selections of far apart chunks
+    fn candidate_chunks(&self, chunk_size: i32) -> Option<HashSet<ChunkPosition>> {
+        match self {
+            Area::Chunks { chunks, chunk_size: size, .. } if *size == chunk_size => Some(chunks.clone()),
+            Area::Intersection(areas) => areas.iter().find_map(|a| a.candidate_chunks(chunk_size)),
+            Area::Union(areas) => areas.iter().map(|a| a.candidate_chunks(chunk_size)).reduce(|a, b| Some(a?.union(&b?).cloned().collect())).flatten(),
+            Area::Difference(area, _) | Area::Contracted { area, .. } => area.candidate_chunks(chunk_size),
+            _ => None
+        }
+    }
+
+    // Chunks that may have blocks of the area
+    pub fn chunk_positions(&self, chunk_size: i32) -> Vec<ChunkPosition> {
+        let column = |chunk_x: i32, chunk_z: i32, min_y: i32, max_y: i32| {
+            let (x, z) = (chunk_x * chunk_size, chunk_z * chunk_size);
+            ((x, min_y, z), (x + chunk_size - 1, max_y, z + chunk_size - 1))
+        };
+        if let Some(candidates) = self.candidate_chunks(chunk_size) {
+            return candidates.into_iter().filter(|c| {
+                let Some((min, max)) = self.bounds(c.dimension()) else { return false };
+                let (from, to) = column(c.x(), c.z(), min.1, max.1);
+                self.intersects_box(c.dimension(), from, to)
+            }).collect();
+        }
+
+        let mut positions = Vec::new();
+        for dimension in self.dimensions() {
+            let Some((min, max)) = self.bounds(&dimension) else { continue };
+            for chunk_x in min.0.div_euclid(chunk_size)..=max.0.div_euclid(chunk_size) {
+                for chunk_z in min.2.div_euclid(chunk_size)..=max.2.div_euclid(chunk_size) {
+                    let (from, to) = column(chunk_x, chunk_z, min.1, max.1);
+                    if self.intersects_box(&dimension, from, to) {
+                        positions.push(ChunkPosition::new(chunk_x, chunk_z, dimension));
+                    }
+                }
+            }
+        }
+        positions
+    }
+
+    // Amount of blocks of the area in a dimension that are between the (inclusive) corners
+    pub fn volume_in(&self, dimension: &LassoString, min: (i32, i32, i32), max: (i32, i32, i32)) -> usize {
+        match self {
+            Area::Shape(shape) => if shape.dimension() == dimension { shape.volume_in(min, max) } else { 0 },
+            _ => {
+                let Some((min, max)) = self.bounds(dimension).and_then(|b| overlap_bounds(b, (min, max))) else { return 0 };
+                let mut count = 0;
+                for x in min.0..=max.0 {
+                    for z in min.2..=max.2 {
+                        count += (min.1..=max.1).filter(|y| self.contains_at(dimension, x, *y, z)).count();
+                    }
+                }
+                count
+            }
+        }
+    }
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::versions::VersionManager;
+    use crate::models::other::tick::Tick;
+    use crate::models::world::chunk::Chunk;
+    use crate::models::world::dimension::Dimension;
+    use crate::models::world::selection::SelectionBuilder;
+    use crate::models::world::world::World;
+    use crate::traits::access::prelude::BlockReader;
+    use crate::types::WorldKind;
+
+    fn pos(x: i32, y: i32, z: i32) -> Position { Position::new("overworld".into(), x, y, z) }
+    fn cuboid(from: (i32, i32, i32), to: (i32, i32, i32)) -> Area { Area::Shape(Shape::cuboid(&pos(from.0, from.1, from.2), &pos(to.0, to.1, to.2))) }
+
+    #[test]
+    fn area_algebra() {
+        let overworld = LassoString::from("overworld");
+        let a = cuboid((0, 0, 0), (9, 0, 9));
+        let b = cuboid((5, 0, 5), (14, 0, 14));
+        assert_eq!(a.clone().union(b.clone()).volume_in(&overworld, (-100, -100, -100), (100, 100, 100)), 175);
+        assert_eq!(a.clone().intersect(b.clone()).volume_in(&overworld, (-100, -100, -100), (100, 100, 100)), 25);
+        assert_eq!(a.clone().subtract(b.clone()).volume_in(&overworld, (-100, -100, -100), (100, 100, 100)), 75);
+        assert!(a.clone().shifted((20, 1, 0)).contains(&pos(29, 1, 9)) && !a.clone().shifted((20, 1, 0)).contains(&pos(9, 0, 9)));
+
+        // cuboids are resized directly, other areas are checked around each block
+        assert_eq!(a.clone().expand(2, &[Direction::Up, Direction::East]).bounds(&overworld), Some(((0, 0, 0), (11, 2, 9))));
+        let ring = a.clone().subtract(cuboid((1, 0, 1), (8, 0, 8)));
+        assert!(ring.clone().expand(1, &[Direction::South]).contains(&pos(4, 0, 1)));
+        assert!(!ring.clone().expand(1, &[Direction::South]).contains(&pos(4, 0, 2)));
+        assert_eq!(ring.clone().contract(1, &[Direction::East, Direction::West]).volume_in(&overworld, (0, 0, 0), (9, 0, 9)), 16);
+        assert_eq!(a.clone().contract(5, &[Direction::East, Direction::West]).bounds(&overworld), None);
+
+        // too far to check every offset
+        let far = 100_000;
+        let chunks = Area::chunks([ChunkPosition::new(2, 0, overworld)], 16, 0, 10);
+        let grown = chunks.clone().expanded((far, 0, 0), (0, far, 0));
+        assert!(grown.contains(&pos(32 - far, 10 + far, 15)) && !grown.contains(&pos(31 - far, 0, 0)) && !grown.contains(&pos(32, 0, 16)));
+        let crossing = a.clone().intersect(b.clone()).intersect(chunks.clone().shifted((-32, 0, 0))).expanded((0, 0, far), (0, 0, 0));
+        assert!(crossing.contains(&pos(5, 0, 5 - far)) && crossing.contains(&pos(9, 0, 9)) && !crossing.contains(&pos(4, 0, 0)));
+        assert!(a.clone().union(chunks).expanded((far, far, far), (far, far, far)).contains(&pos(far, far, far)));
+
+        let outset = b.outset_to_chunks(16);
+        assert_eq!(outset.chunk_positions(16).len(), 1);
+        assert!(outset.contains(&pos(15, 0, 0)) && !outset.contains(&pos(15, 1, 0)) && !outset.contains(&pos(16, 0, 0)));
+        assert!(!a.contains(&Position::new("the_nether".into(), 0, 0, 0)));
+    }
+
+    #[test]
+    fn selection_set_operations() {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new("".into(), version.clone());
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        for x in 0..3 {
+            dimension.set_chunk(Chunk::new(ChunkPosition::new(x, 0, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+        }
+        world.lock().unwrap().set_dimension("overworld".into(), dimension);
+        let mut locked = world.lock().unwrap();
+
+        let work_zone = cuboid((0, 0, 0), (40, 9, 15));
+        let protected = Area::Shape(Shape::sphere(&pos(20, 5, 8), 3.0));
+        let selection = SelectionBuilder::new_owned(&mut locked, version.clone()).with_area(work_zone).build().subtract(&protected);
+        assert_eq!(selection.chunk_count(), 3);
+        assert_eq!(selection.block_count(), 41 * 10 * 16 - Shape::sphere(&pos(20, 5, 8), 3.0).volume_in((-100, -100, -100), (100, 100, 100)));
+        assert!(!selection.contains(&pos(20, 5, 8)) && selection.contains(&pos(20, 9, 8)));
+
+        // moved out of the loaded chunks, except for its first blocks
+        let shifted = selection.shift((40, 0, 0));
+        assert_eq!(shifted.chunk_count(), 1);
+        assert_eq!(shifted.block_count(), 8 * 10 * 16);
+
+        let whole = shifted.outset_to_chunks();
+        assert_eq!(whole.block_count(), 16 * 10 * 16);
+        let grown = whole.expand(2, &[Direction::Up]).intersect(&cuboid((32, 0, 0), (32, 100, 0)));
+        assert_eq!(grown.block_count(), 12);
+    }
+}
@@ -1,7 +1,9 @@
+use std::borrow::Cow;
 use std::cmp::PartialEq;
 use std::collections::HashMap;
 use std::fmt;
 use fastnbt::Value;
+use crate::constants::blocks::{AIR_BLOCKS, FLUID_BLOCKS, NON_MOTION_BLOCKING_BLOCKS, NON_MOTION_BLOCKING_SUFFIXES};
 use crate::models::other::properties::Properties;
 // TODO: Add proper errors, using an error struct
 // TODO: Comparison of blocks is very slow since it has to check the whole extra thing - make some kind of hash?
@@ -45,6 +47,32 @@ impl PaletteBlock {
     pub fn properties(&self) -> &Properties { &self.extra }
     pub fn properties_mut(&mut self) -> &mut Properties { &mut self.extra }
     pub fn is_null(&self) -> bool { self.null_flag }
+
+    pub fn full_name(&self) -> Cow<'_, str> {
+        if self.name.contains(':') { Cow::Borrowed(&self.name) }
+        else { Cow::Owned(format!("minecraft:{}", self.name)) }
+    }
+
+    // Whether the block is the namespaced id, without building the full name of an un-namespaced block
+    fn is_id(&self, id: &str) -> bool {
+        if self.name.contains(':') { self.name == id } else { id.strip_prefix("minecraft:") == Some(self.name.as_str()) }
+    }
+    fn is_any_of(&self, ids: &[&str]) -> bool { ids.iter().any(|id| self.is_id(id)) }
+
+    pub fn is_air(&self) -> bool { self.null_flag || self.is_any_of(AIR_BLOCKS) }
+    pub fn is_leaves(&self) -> bool { self.name.ends_with("_leaves") }
+    pub fn is_waterlogged(&self) -> bool {
+        match self.extra.get("waterlogged") {
+            Some(Value::String(s)) => s == "true",
+            Some(Value::Byte(b)) => b != 0,
+            _ => false
+        }
+    }
+    pub fn is_fluid(&self) -> bool { self.is_any_of(FLUID_BLOCKS) || self.is_waterlogged() }
+    pub fn blocks_motion(&self) -> bool {
+        if self.is_air() { return false; }
+        !self.is_any_of(NON_MOTION_BLOCKING_BLOCKS) && !NON_MOTION_BLOCKING_SUFFIXES.iter().any(|s| self.name.ends_with(s))
+    }
 }
 
 impl fmt::Debug for PaletteBlock {
@@ -237,4 +265,14 @@ mod tests {
         let b = PaletteBlock::new("minecraft:stone", Some(fewer));
         assert_ne!(a, b);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn classifies_bare_and_namespaced_names() {
+        assert!(PaletteBlock::new("cave_air", None).is_air());
+        assert!(PaletteBlock::new("minecraft:cave_air", None).is_air());
+        assert!(!PaletteBlock::new("other:cave_air", None).is_air());
+        assert!(PaletteBlock::new("water", None).is_fluid());
+        assert!(!PaletteBlock::new("torch", None).blocks_motion());
+        assert!(PaletteBlock::new("stone", None).blocks_motion());
+    }
+}
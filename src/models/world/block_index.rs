@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+use serde::{Deserialize, Serialize};
+use crate::constants::constants::REGION_CHUNK_LINE;
+use crate::models::other::region::Region;
+use crate::models::positions::chunk_position::ChunkPosition;
+use crate::models::stores::block_store::BlockStore;
+use crate::models::world::chunk::Chunk;
+
+const BLOCK_INDEX_FORMAT: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedChunk {
+    x: i32,
+    z: i32,
+    counts: HashMap<String, u32>,
+
+    // BlockStore::revision of the loaded chunk these counts are for, None until the chunk is loaded
+    #[serde(skip)]
+    revision: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedRegion {
+    dimension: String,
+    x: i32,
+    z: i32,
+    modified: u64, // region file modification time in ms, a different one means the counts are outdated
+    chunks: Vec<IndexedChunk>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BlockIndexFile {
+    format: u32,
+    with_states: bool,
+    regions: Vec<IndexedRegion>,
+}
+
+// Where every block id occurs (chunk positions and counts), per region file. Built while regions are loaded and
+// saved next to the world, so a block can be located without decompressing the regions again.
+// Keys are block ids, or ids with states like "minecraft:chest[facing=north,type=single]" when with_states is set
+#[derive(Debug, Clone)]
+pub struct BlockIndex {
+    with_states: bool,
+    regions: HashMap<(String, i32, i32), IndexedRegion>,
+}
+
+pub fn region_modified_time(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64)
+}
+
+// Block id of an index key, without the states
+pub fn key_id(key: &str) -> &str { key.split_once('[').map(|(id, _)| id).unwrap_or(key) }
+
+fn region_key(region: &Region) -> (String, i32, i32) {
+    (region.position.dimension().to_string(), region.position.x(), region.position.z())
+}
+
+impl BlockIndex {
+    pub fn new(with_states: bool) -> Self {
+        Self { with_states, regions: HashMap::new() }
+    }
+
+    // Regions that are missing or changed since the index was saved are dropped, they're indexed again when loaded
+    pub fn load(path: &Path, regions: &[Region]) -> io::Result<Self> {
+        let file: BlockIndexFile = serde_json::from_slice(&fs::read(path)?)?;
+        if file.format != BLOCK_INDEX_FORMAT {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unknown block index format {}", file.format)));
+        }
+
+        let mut index = Self::new(file.with_states);
+        for indexed in file.regions {
+            let key = (indexed.dimension.clone(), indexed.x, indexed.z);
+            let current = regions.iter()
+                .filter(|r| region_key(r) == key)
+                .any(|r| region_modified_time(&r.path) == Some(indexed.modified));
+            if current { index.regions.insert(key, indexed); }
+        }
+        Ok(index)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let file = BlockIndexFile { format: BLOCK_INDEX_FORMAT, with_states: self.with_states, regions: self.regions.values().cloned().collect() };
+        fs::write(path, serde_json::to_vec(&file)?)
+    }
+
+    pub fn with_states(&self) -> bool { self.with_states }
+    pub fn region_count(&self) -> usize { self.regions.len() }
+
+    pub fn is_region_current(&self, region: &Region) -> bool {
+        self.regions.get(&region_key(region)).is_some_and(|r| region_modified_time(&region.path) == Some(r.modified))
+    }
+
+    // Called with the chunks of a freshly parsed region. Counts are only redone if the region file changed
+    pub fn update_region(&mut self, region: &Region, chunks: &[Chunk]) {
+        let with_states = self.with_states;
+        if self.is_region_current(region) {
+            let indexed = self.regions.get_mut(&region_key(region)).unwrap();
+            for chunk in chunks {
+                let (x, z) = chunk.position().position();
+                match indexed.chunks.iter_mut().find(|c| (c.x, c.z) == (x, z)) {
+                    Some(c) => c.revision = Some(chunk.block_store().revision()),
+                    None => indexed.chunks.push(index_chunk(chunk, with_states)),
+                }
+            }
+            return;
+        }
+
+        let modified = region_modified_time(&region.path).unwrap_or(0);
+        let (dimension, x, z) = region_key(region);
+        let chunks = chunks.iter().map(|c| index_chunk(c, with_states)).collect();
+        self.regions.insert((dimension.clone(), x, z), IndexedRegion { dimension, x, z, modified, chunks });
+    }
+
+    pub fn remove_region(&mut self, region: &Region) { self.regions.remove(&region_key(region)); }
+
+    // After the chunks were written into the region file: their counts are redone and the region is current again.
+    // known is whether the index had the whole file before the save (current, or the file is new), if it didn't the
+    // region is dropped, it's indexed again when it's loaded
+    pub fn update_saved_region(&mut self, region: &Region, known: bool, chunks: &[&Chunk]) {
+        let key = region_key(region);
+        if !known { self.regions.remove(&key); return; }
+
+        let with_states = self.with_states;
+        let modified = region_modified_time(&region.path).unwrap_or(0);
+        let (dimension, x, z) = key.clone();
+        let indexed = self.regions.entry(key).or_insert_with(|| IndexedRegion { dimension, x, z, modified, chunks: Vec::new() });
+        indexed.modified = modified;
+        for chunk in chunks {
+            let (x, z) = chunk.position().position();
+            indexed.chunks.retain(|c| (c.x, c.z) != (x, z));
+            indexed.chunks.push(index_chunk(chunk, with_states));
+        }
+    }
+
+    // The loaded chunk was replaced or dropped without saving, the counts stay those of the file but don't describe
+    // whatever is loaded there next (revisions start over with each chunk)
+    pub fn detach_chunk(&mut self, chunk_position: &ChunkPosition) {
+        let line = REGION_CHUNK_LINE as i32;
+        let key = (chunk_position.dimension().to_string(), chunk_position.x().div_euclid(line), chunk_position.z().div_euclid(line));
+        let Some(region) = self.regions.get_mut(&key) else { return };
+        if let Some(chunk) = region.chunks.iter_mut().find(|c| (c.x, c.z) == chunk_position.position()) { chunk.revision = None; }
+    }
+
+    // The counts of a chunk, if they're known to still be right. revision is the loaded chunk's BlockStore::revision
+    pub fn chunk_counts(&self, chunk_position: &ChunkPosition, revision: Option<u64>) -> Option<&HashMap<String, u32>> {
+        let line = REGION_CHUNK_LINE as i32;
+        let (region_x, region_z) = (chunk_position.x().div_euclid(line), chunk_position.z().div_euclid(line));
+        let region = self.regions.get(&(chunk_position.dimension().to_string(), region_x, region_z))?;
+        let chunk = region.chunks.iter().find(|c| (c.x, c.z) == chunk_position.position())?;
+        match revision {
+            Some(revision) if chunk.revision != Some(revision) => None,
+            _ => Some(&chunk.counts)
+        }
+    }
+
+    // Every indexed chunk containing the block, id can have states if the index has them
+    pub fn locate(&self, id: &str) -> Vec<(ChunkPosition, u32)> {
+        let mut found = Vec::new();
+        for region in self.regions.values() {
+            for chunk in &region.chunks {
+                let count = count_in(&chunk.counts, id);
+                if count > 0 { found.push((ChunkPosition::new(chunk.x, chunk.z, region.dimension.as_str().into()), count)); }
+            }
+        }
+        found
+    }
+}
+
+pub fn count_in(counts: &HashMap<String, u32>, id: &str) -> u32 {
+    let with_states = id.contains('[');
+    counts.iter().filter(|(key, _)| if with_states { key.as_str() == id } else { key_id(key) == id }).map(|(_, count)| count).sum()
+}
+
+pub fn count_blocks(store: &BlockStore, with_states: bool) -> HashMap<String, u32> {
+    let palette = store.palette();
+    let mut per_index = vec![0u32; palette.len()];
+    for i in store.indices_slice() { per_index[*i] += 1; }
+
+    let mut counts = HashMap::new();
+    for (block, count) in palette.iter().zip(per_index) {
+        if count == 0 || block.is_null() { continue; }
+        let key = if with_states && !block.properties().raw().is_empty() {
+            let raw = block.properties().raw();
+            let mut states = raw.iter().map(|(k, v)| match v {
+                fastnbt::Value::String(s) => format!("{}={}", k, s),
+                other => format!("{}={:?}", k, other),
+            }).collect::<Vec<_>>();
+            states.sort();
+            format!("{}[{}]", block.full_name(), states.join(","))
+        } else { block.full_name().into_owned() };
+        *counts.entry(key).or_insert(0) += count;
+    }
+    counts
+}
+
+fn index_chunk(chunk: &Chunk, with_states: bool) -> IndexedChunk {
+    let (x, z) = chunk.position().position();
+    IndexedChunk { x, z, counts: count_blocks(chunk.block_store(), with_states), revision: Some(chunk.block_store().revision()) }
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastnbt::Value;
+    use crate::constants::versions::VersionManager;
+    use crate::models::other::region::RegionType;
+    use crate::models::other::tick::Tick;
+    use crate::models::world::block::PaletteBlock;
+    use crate::types::WorldKind;
+
+    #[test]
+    fn index_save_and_invalidate() {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let dir = std::env::temp_dir().join(format!("cubicle_block_index_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let region_path = dir.join("r.-1.0.mca");
+        fs::write(&region_path, [0u8; 8]).unwrap();
+        let region = Region { position: ChunkPosition::new(-1, 0, "overworld".into()), path: region_path.clone(), region_type: RegionType::Block };
+
+        let mut chunk = Chunk::new(ChunkPosition::new(-3, 5, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version);
+        let facing = |f: &str| Some(HashMap::from([("facing".to_string(), Value::String(f.to_string()))]));
+        chunk.set_block_at_index(0, PaletteBlock::new("minecraft:beacon", None));
+        chunk.set_block_at_index(1, PaletteBlock::new("minecraft:chest", facing("north")));
+        chunk.set_block_at_index(2, PaletteBlock::new("minecraft:chest", facing("south")));
+
+        let mut index = BlockIndex::new(true);
+        index.update_region(&region, std::slice::from_ref(&chunk));
+        assert_eq!(index.locate("minecraft:beacon"), vec![(ChunkPosition::new(-3, 5, "overworld".into()), 1)]);
+        assert_eq!(index.locate("minecraft:chest")[0].1, 2);
+        assert_eq!(index.locate("minecraft:chest[facing=south]")[0].1, 1);
+
+        let revision = Some(chunk.block_store().revision());
+        assert!(index.chunk_counts(chunk.position(), revision).is_some());
+        chunk.set_block_at_index(3, PaletteBlock::new("minecraft:beacon", None));
+        assert!(index.chunk_counts(chunk.position(), Some(chunk.block_store().revision())).is_none());
+
+        let index_path = dir.join("index.json");
+        index.save(&index_path).unwrap();
+        let loaded = BlockIndex::load(&index_path, std::slice::from_ref(&region)).unwrap();
+        assert_eq!(loaded.locate("minecraft:beacon").len(), 1);
+
+        let older = Region { path: dir.join("missing.mca"), ..region };
+        assert_eq!(BlockIndex::load(&index_path, &[older]).unwrap().region_count(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn locate_follows_saves_and_replaced_chunks() {
+        use crate::models::positions::whole_position::Position;
+        use crate::models::world::dimension::Dimension;
+        use crate::models::world::generator::{ChunkGenerator, FlatLayer};
+        use crate::models::world::world::{SaveOptions, World};
+
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let dir = std::env::temp_dir().join(format!("cubicle_block_index_locate_{}", std::process::id()));
+        let position = ChunkPosition::new(0, 0, "overworld".into());
+        {
+            let world = World::new(dir.clone(), version.clone());
+            let mut dimension = Dimension::new("overworld".into(), version.clone());
+            let mut chunk = Chunk::new(position.clone(), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version);
+            chunk.set_block_at_index(0, PaletteBlock::new("minecraft:beacon", None));
+            dimension.set_chunk(chunk);
+            let mut locked = world.lock().unwrap();
+            locked.set_dimension("overworld".into(), dimension);
+            locked.save(&SaveOptions::new()).unwrap();
+        }
+
+        let world = World::new(dir.clone(), version.clone());
+        let mut locked = world.lock().unwrap();
+        locked.register_regions();
+        locked.enable_block_index(false);
+        locked.load_chunk(&position).unwrap().unwrap();
+        assert_eq!(locked.locate("minecraft:beacon"), vec![(position.clone(), 1)]);
+
+        // after a save the index has the new counts, also once the chunk is gone and when it's loaded again
+        locked.transaction(|tx| tx.set_block(&Position::new("overworld".into(), 0, -64, 0), PaletteBlock::from("minecraft:stone"))).unwrap();
+        locked.save(&SaveOptions::new()).unwrap();
+        assert!(locked.unload_chunk(&position, None).unwrap());
+        assert!(locked.locate("minecraft:beacon").is_empty());
+        assert_eq!(locked.locate("minecraft:stone"), vec![(position.clone(), 1)]);
+        locked.load_chunk(&position).unwrap().unwrap();
+        assert!(locked.locate("minecraft:beacon").is_empty());
+
+        // a replaced chunk is counted as it is, dropping it without saving brings back what's on disk
+        locked.replace_chunk(position.clone(), &ChunkGenerator::flat(vec![FlatLayer::new(PaletteBlock::new("minecraft:beacon", None), 1)]));
+        assert_eq!(locked.locate("minecraft:beacon"), vec![(position.clone(), 256)]);
+        assert!(locked.locate("minecraft:stone").is_empty());
+        assert!(locked.unload_chunk(&position, None).unwrap());
+        assert!(locked.locate("minecraft:beacon").is_empty());
+        assert_eq!(locked.locate("minecraft:stone"), vec![(position, 1)]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
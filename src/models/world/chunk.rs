@@ -1,15 +1,41 @@
+use std::collections::HashSet;
 use std::sync::Arc;
+use crate::constants::blocks::{block_entity_id, is_poi_block};
 use crate::constants::versions::Version;
+use crate::models::block_entity::block_entity::BlockEntity;
 use crate::models::other::tick::Tick;
 use crate::models::positions::chunk_position::ChunkPosition;
 use crate::models::positions::whole_position::Position;
 use crate::models::stores::biome_store::BiomeStore;
 use crate::models::stores::block_entity_store::BlockEntityStore;
-use crate::models::stores::block_store::BlockStore;
+use crate::models::stores::block_store::{BlockStore, SectionView};
 use crate::models::stores::entity_store::EntityStoreKey;
-use crate::models::stores::heightmap_store::HeightmapStore;
+use crate::models::stores::heightmap_store::{heightmap_mask, HeightmapStore};
+use crate::models::world::block::PaletteBlock;
 use crate::models::stores::structure_store::StructureStoreReference;
 use crate::models::world::tile_tick::TileTick;
+use crate::traits::block_entity::BlockEntityTrait;
+use crate::types::HeightmapKind;
+use crate::utils::position_utils::block_index_to_block_position;
+
+// What a bulk block edit did to a chunk
+#[derive(Debug, Default)]
+pub struct ChunkEdit {
+    changes: Vec<(usize, usize, usize)>, // (block index, old palette index, new palette index)
+    replaced: HashSet<usize>, // the changes that became a different block id
+    removed_block_entities: Vec<Arc<BlockEntity>>,
+    added_block_entities: Vec<Arc<BlockEntity>>, // defaults of the replaced blocks that have one
+    removed_tile_ticks: Vec<TileTick>,
+}
+
+impl ChunkEdit {
+    pub fn changes(&self) -> &Vec<(usize, usize, usize)> { &self.changes }
+    pub fn replaced(&self) -> &HashSet<usize> { &self.replaced }
+    pub fn removed_block_entities(&self) -> &Vec<Arc<BlockEntity>> { &self.removed_block_entities }
+    pub fn added_block_entities(&self) -> &Vec<Arc<BlockEntity>> { &self.added_block_entities }
+    pub fn removed_tile_ticks(&self) -> &Vec<TileTick> { &self.removed_tile_ticks }
+    pub fn count(&self) -> usize { self.changes.len() }
+}
 
 #[derive(Debug)]
 pub struct Chunk {
@@ -27,6 +53,10 @@ pub struct Chunk {
     entity_keys: Vec<EntityStoreKey>,
 
     structures: Vec<StructureStoreReference>,
+    dirty: bool, // changed since it was loaded or saved, set by everything that hands out the stores mutably
+    replaced_blocks: HashSet<usize>, // POI blocks that became a different block since it was saved, their POI records go when it's saved
+    entities_dirty: bool, // an entity was added, removed or changed since it was loaded or saved
+    entities_loaded: bool, // false when the chunk was loaded without its entities, they can't be saved then
 }
 
 impl Chunk {
@@ -75,12 +105,17 @@ impl Chunk {
 
             entity_keys: Vec::new(),
             structures: Vec::new(),
+            dirty: true,
+            replaced_blocks: HashSet::new(),
+            entities_dirty: false,
+            entities_loaded: true,
         }
     }
 
     pub fn position(&self) -> &ChunkPosition {
         &self.position
     }
+    pub fn data_version(&self) -> i32 { self.data_version }
     pub fn inhabited_time(&self) -> &Tick {
         &self.inhabited_time
     }
@@ -92,18 +127,37 @@ impl Chunk {
     }
 
     pub fn stores(&self) -> (&BlockStore, &BiomeStore, &HeightmapStore, &BlockEntityStore) { (&self.block_store, &self.biome_store, &self.heightmap_store, &self.block_entity_store) }
-    pub fn stores_mut(&mut self) -> (&mut BlockStore, &mut BiomeStore, &mut HeightmapStore, &mut BlockEntityStore) { (&mut self.block_store, &mut self.biome_store, &mut self.heightmap_store, &mut self.block_entity_store) }
+    pub fn stores_mut(&mut self) -> (&mut BlockStore, &mut BiomeStore, &mut HeightmapStore, &mut BlockEntityStore) { self.dirty = true; (&mut self.block_store, &mut self.biome_store, &mut self.heightmap_store, &mut self.block_entity_store) }
     pub fn block_store(&self) -> &BlockStore {
         &self.block_store
     }
-    pub fn block_store_mut(&mut self) -> &mut BlockStore { &mut self.block_store }
+    pub fn block_store_mut(&mut self) -> &mut BlockStore { self.dirty = true; &mut self.block_store }
     pub fn biome_store(&self) -> &BiomeStore { &self.biome_store }
-    pub fn biome_store_mut(&mut self) -> &mut BiomeStore { &mut self.biome_store }
+    pub fn biome_store_mut(&mut self) -> &mut BiomeStore { self.dirty = true; &mut self.biome_store }
     pub fn heightmap_store(&self) -> &HeightmapStore { &self.heightmap_store }
-    pub fn heightmap_store_mut(&mut self) -> &mut HeightmapStore { &mut self.heightmap_store }
+    pub fn heightmap_store_mut(&mut self) -> &mut HeightmapStore { self.dirty = true; &mut self.heightmap_store }
     pub fn block_entities_store(&self) -> &BlockEntityStore { &self.block_entity_store }
-    pub fn block_entities_store_mut(&mut self) -> &mut BlockEntityStore { &mut self.block_entity_store }
+    pub fn block_entities_store_mut(&mut self) -> &mut BlockEntityStore { self.dirty = true; &mut self.block_entity_store }
     pub fn tile_ticks(&self) -> &Vec<TileTick> { &self.tile_ticks }
+    pub fn palette_indices(&self) -> impl Iterator<Item=usize> + '_ { self.block_store.palette_indices() }
+    pub fn sections(&self, section_height: i32) -> impl Iterator<Item=SectionView<'_>> { self.block_store.sections(section_height) }
+
+    pub fn is_dirty(&self) -> bool { self.dirty }
+    pub fn mark_dirty(&mut self) { self.dirty = true; }
+    pub fn has_unsaved_changes(&self) -> bool { self.dirty || self.entities_dirty }
+    // After it was written to (or read from) disk
+    pub fn mark_clean(&mut self) { self.dirty = false; self.entities_dirty = false; self.replaced_blocks.clear(); }
+    pub fn replaced_blocks(&self) -> &HashSet<usize> { &self.replaced_blocks }
+    pub fn block_position(&self, index: usize) -> Position {
+        block_index_to_block_position(&self.position, index, self.block_store.chunk_size(), self.block_store.lowest_y())
+    }
+
+    // Rough bytes the chunk takes, the index arrays are most of it
+    pub fn estimated_memory(&self) -> usize {
+        let indices = (self.block_store.indices_slice().len() + self.biome_store.indices_slice().len()) * size_of::<usize>();
+        let palettes = (self.block_store.palette().len() + self.biome_store.palette().len()) * 64;
+        size_of::<Chunk>() + indices + palettes + self.block_entity_store.get_all().len() * 256 + self.tile_ticks.len() * size_of::<TileTick>()
+    }
 
     pub fn entity_keys(&self) -> &Vec<EntityStoreKey> { &self.entity_keys }
     pub fn entity_count(&self) -> usize { self.entity_keys.len() }
@@ -113,22 +167,41 @@ impl Chunk {
 
     pub fn set_inhabited_time(&mut self, inhabited_time: Tick) {
         self.inhabited_time = inhabited_time;
+        self.dirty = true;
     }
     pub fn set_last_update(&mut self, last_update: Tick) {
         self.last_update = last_update;
+        self.dirty = true;
     }
     pub fn set_status(&mut self, status: &str) {
         self.status = status.to_string();
+        self.dirty = true;
     }
     pub fn set_block_store(&mut self, block_store: BlockStore) {
         self.block_store = block_store;
+        self.dirty = true;
     }
+    pub fn entities_dirty(&self) -> bool { self.entities_dirty }
+    pub fn mark_entities_dirty(&mut self) { self.entities_dirty = true; }
+    pub fn entities_loaded(&self) -> bool { self.entities_loaded }
+    pub fn set_entities_loaded(&mut self, loaded: bool) { self.entities_loaded = loaded; }
+    // Keys of entities read from disk, the entities aren't changed by this
     pub fn set_entities(&mut self, entity_keys: Vec<EntityStoreKey>) { self.entity_keys = entity_keys; }
 
-    pub fn add_entity(&mut self, entity_key: EntityStoreKey) { self.entity_keys.push(entity_key); }
+    pub fn add_entity(&mut self, entity_key: EntityStoreKey) { self.entity_keys.push(entity_key); self.entities_dirty = true; }
+    pub fn remove_entity(&mut self, entity_key: EntityStoreKey) -> bool {
+        let Some(ind) = self.entity_keys.iter().position(|k| *k == entity_key) else { return false };
+        self.entity_keys.swap_remove(ind);
+        self.entities_dirty = true;
+        true
+    }
+    pub fn replace_entity_key(&mut self, old: EntityStoreKey, new: EntityStoreKey) {
+        for key in self.entity_keys.iter_mut().filter(|k| **k == old) { *key = new; }
+    }
     pub fn add_structure(&mut self, structure: StructureStoreReference) { self.structures.push(structure); }
 
     pub fn set_tile_tick(&mut self, tile_tick: TileTick) {
+        self.dirty = true;
         for (ind, tt) in self.tile_ticks.iter().enumerate() {
             if tt.position() == tile_tick.position() {
                 self.tile_ticks[ind] = tile_tick;
@@ -139,14 +212,225 @@ impl Chunk {
         self.tile_ticks.push(tile_tick);
     }
 
-    pub fn remove_tile_tick_at(&mut self, position: &Position) {
+    pub fn remove_tile_tick_at(&mut self, position: &Position) -> Option<TileTick> {
+        self.dirty = true;
         for (ind, tt) in self.tile_ticks.iter().enumerate() {
             if tt.position() == position {
-                self.tile_ticks.swap_remove(ind);
-                return;
+                return Some(self.tile_ticks.swap_remove(ind));
             }
         }
+        None
+    }
+
+    // Use this instead of going through the block store directly so the heightmaps, block entities and tile ticks stay
+    // in sync with the blocks
+    pub fn set_block_at_index(&mut self, index: usize, block: PaletteBlock) -> bool {
+        let Some(old) = self.block_store.indices_slice().get(index).copied() else { return false };
+        if !self.block_store.set_block_at_index(index, block) { return false; }
+        self.dirty = true;
+        let palette = self.block_store.palette();
+        let old_name = palette[old].full_name();
+        if old_name != palette[self.block_store.indices_slice()[index]].full_name() {
+            if is_poi_block(&old_name) { self.replaced_blocks.insert(index); }
+            self.replace_block_entities(&HashSet::from([index]));
+        }
+        self.update_heightmaps_at_index(index);
+        true
+    }
+
+    // Puts the block entity on the block at index, it's moved to the block's position. Nothing is placed when it isn't
+    // the block entity that block has
+    pub fn set_block_entity_at_index(&mut self, index: usize, mut block_entity: BlockEntity) -> bool {
+        let Some(palette_index) = self.block_store.indices_slice().get(index).copied() else { return false };
+        if block_entity_id(&self.block_store.palette()[palette_index].full_name()) != Some(block_entity.base().id()) { return false; }
+        block_entity.base_mut().set_position(self.block_position(index));
+        self.dirty = true;
+        self.block_entity_store.set_at_world_position(block_entity);
+        true
+    }
+
+    // Bulk version of set_block_at_index: choose picks one of blocks for every index, each block is looked up in
+    // the palette once and heightmaps are updated once at the end. Block entities and tile ticks of blocks that become
+    // a different block id are removed and the new block gets its default block entity. Returns how many blocks changed
+    pub fn set_blocks_at_indices<F>(&mut self, indices: &[usize], blocks: &[PaletteBlock], choose: F) -> usize where F: FnMut(usize) -> usize {
+        self.edit_blocks_at_indices(indices, blocks, choose).count()
+    }
+
+    // Same as set_blocks_at_indices but returns what was changed and removed, so it can be recorded or undone
+    pub fn edit_blocks_at_indices<F>(&mut self, indices: &[usize], blocks: &[PaletteBlock], mut choose: F) -> ChunkEdit where F: FnMut(usize) -> usize {
+        let mut palette_indices = vec![None; blocks.len()];
+        let mut changed = Vec::with_capacity(indices.len()); // (index, old palette index, new palette index)
+        for index in indices {
+            let Some(current) = self.block_store.indices_slice().get(*index).copied() else { continue };
+            let choice = choose(*index);
+            let palette_index = *palette_indices[choice].get_or_insert_with(|| self.block_store.add_block_to_palette(blocks[choice].clone()));
+            if current != palette_index { changed.push((*index, current, palette_index)); }
+        }
+        if changed.is_empty() { return ChunkEdit::default(); }
+        self.dirty = true;
+
+        let store = self.block_store.indices_slice_mut();
+        for (index, _, palette_index) in &changed { store[*index] = *palette_index; }
+
+        let replaced = {
+            let names = self.block_store.palette().iter().map(|b| b.full_name()).collect::<Vec<_>>();
+            let replaced = changed.iter().filter(|(_, old, new)| names[*old] != names[*new]).collect::<Vec<_>>();
+            self.replaced_blocks.extend(replaced.iter().filter(|(_, old, _)| is_poi_block(&names[*old])).map(|(index, _, _)| *index));
+            replaced.into_iter().map(|(index, _, _)| *index).collect::<HashSet<_>>()
+        };
+        let (removed_block_entities, added_block_entities, removed_tile_ticks) =
+            if replaced.is_empty() { (Vec::new(), Vec::new(), Vec::new()) } else { self.replace_block_entities(&replaced) };
+
+        // past one block per column a full rescan is cheaper than walking columns down block by block
+        let layer_size = (self.block_store.chunk_size() * self.block_store.chunk_size()) as usize;
+        if changed.len() > layer_size { self.recalculate_heightmaps(); }
+        else { for (index, _, _) in &changed { self.update_heightmaps_at_index(*index); } }
+        ChunkEdit { changes: changed, replaced, removed_block_entities, added_block_entities, removed_tile_ticks }
     }
 
-    pub fn recalculate_heightmaps(&mut self) { todo!() } // TODO: Also needs to be called per column when a block changed or something
+    // The blocks at replaced became a different block: their block entities and tile ticks are removed and the new
+    // blocks get their default block entity. Returns the removed block entities, the added ones and the removed tile ticks
+    fn replace_block_entities(&mut self, replaced: &HashSet<usize>) -> (Vec<Arc<BlockEntity>>, Vec<Arc<BlockEntity>>, Vec<TileTick>) {
+        let (mut removed, mut removed_tile_ticks) = (Vec::new(), Vec::new());
+        if !self.block_entity_store.get_all().is_empty() || !self.tile_ticks.is_empty() {
+            let (chunk_size, lowest_y) = (self.block_store.chunk_size(), self.block_store.lowest_y());
+            let kept = |position: &Position| {
+                let (x, y, z) = (position.x().rem_euclid(chunk_size), position.y() - lowest_y, position.z().rem_euclid(chunk_size));
+                y < 0 || !replaced.contains(&((y as usize * chunk_size as usize + z as usize) * chunk_size as usize + x as usize))
+            };
+            removed = self.block_entity_store.remove_where(|block_entity| !kept(block_entity.base().position()));
+            let (kept_tile_ticks, removed_ticks) = std::mem::take(&mut self.tile_ticks).into_iter().partition(|tile_tick| kept(tile_tick.position()));
+            self.tile_ticks = kept_tile_ticks;
+            removed_tile_ticks = removed_ticks;
+        }
+
+        // whether a palette entry has a block entity is only looked up once
+        let mut added = Vec::new();
+        let mut has_block_entity = vec![None; self.block_store.palette().len()];
+        for index in replaced {
+            let palette_index = self.block_store.indices_slice()[*index];
+            let block = &self.block_store.palette()[palette_index];
+            if !*has_block_entity[palette_index].get_or_insert_with(|| block_entity_id(&block.full_name()).is_some()) { continue; }
+            let Some(block_entity) = BlockEntity::default_for(&block.full_name(), self.block_position(*index)) else { continue };
+            added.push(Arc::new(block_entity.clone()));
+            self.block_entity_store.set_at_world_position(block_entity);
+        }
+        (removed, added, removed_tile_ticks)
+    }
+
+    pub fn set_block_at_indices(&mut self, indices: &[usize], block: PaletteBlock) -> usize {
+        self.set_blocks_at_indices(indices, &[block], |_| 0)
+    }
+
+    fn palette_heightmap_masks(&self) -> Vec<u8> {
+        self.block_store.palette().iter().map(heightmap_mask).collect()
+    }
+
+    pub fn recalculate_heightmaps(&mut self) {
+        self.dirty = true;
+        let masks = self.palette_heightmap_masks();
+        let indices = self.block_store.indices_slice();
+        let layer_size = (self.block_store.chunk_size() * self.block_store.chunk_size()) as usize;
+        let height = indices.len() / layer_size;
+        let all_kinds = HeightmapKind::ALL.iter().fold(0u8, |acc, k| acc | k.mask());
+
+        for column in 0..layer_size {
+            let mut remaining = all_kinds;
+            let mut distances = [0i64; HeightmapKind::ALL.len()];
+
+            for y in (0..height).rev() {
+                let found = masks[indices[y * layer_size + column]] & remaining;
+                if found == 0 { continue; }
+                for (i, kind) in HeightmapKind::ALL.iter().enumerate() {
+                    if found & kind.mask() != 0 { distances[i] = (y + 1) as i64; }
+                }
+                remaining &= !found;
+                if remaining == 0 { break; }
+            }
+
+            for (i, kind) in HeightmapKind::ALL.iter().enumerate() {
+                self.heightmap_store.get_kind_mut(*kind).set_distance_at_column(column, distances[i]);
+            }
+        }
+    }
+
+    // Updates the column of a single changed block, only rescans downwards if the top block of a heightmap was removed
+    pub fn update_heightmaps_at_index(&mut self, index: usize) {
+        let layer_size = (self.block_store.chunk_size() * self.block_store.chunk_size()) as usize;
+        let (y, column) = (index / layer_size, index % layer_size);
+        let indices = self.block_store.indices_slice();
+        let palette = self.block_store.palette();
+        let block_mask = heightmap_mask(&palette[indices[index]]);
+
+        for kind in HeightmapKind::ALL {
+            let current = self.heightmap_store.get_kind(kind).distance_at_column(column);
+            let new_distance = if block_mask & kind.mask() != 0 {
+                current.max((y + 1) as i64)
+            } else if current == (y + 1) as i64 {
+                (0..y).rev()
+                    .find(|below| heightmap_mask(&palette[indices[below * layer_size + column]]) & kind.mask() != 0)
+                    .map(|below| (below + 1) as i64)
+                    .unwrap_or(0)
+            } else { continue };
+            self.heightmap_store.get_kind_mut(kind).set_distance_at_column(column, new_distance);
+        }
+    }
+}
+
+
+/// --------- TESTS ---------
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::versions::VersionManager;
+    use crate::types::WorldKind;
+
+    fn empty_chunk() -> Chunk {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        Chunk::new(ChunkPosition::new(0, 0, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version)
+    }
+
+    fn index(x: usize, y: i32, z: usize) -> usize { ((y + 64) as usize * 16 + z) * 16 + x }
+
+    #[test]
+    fn recalculate_heightmaps_classifies_blocks() {
+        let mut chunk = empty_chunk();
+        chunk.set_block_at_index(index(0, 10, 0), PaletteBlock::new("minecraft:stone", None));
+        chunk.set_block_at_index(index(0, 11, 0), PaletteBlock::new("minecraft:water", None));
+        chunk.set_block_at_index(index(0, 12, 0), PaletteBlock::new("minecraft:oak_leaves", None));
+        chunk.set_block_at_index(index(0, 13, 0), PaletteBlock::new("minecraft:torch", None));
+        chunk.recalculate_heightmaps();
+
+        let hm = chunk.heightmap_store();
+        assert_eq!(hm.get_kind(HeightmapKind::SkyExposed).get_highest_y_at_position(0, 0), 13);
+        assert_eq!(hm.get_kind(HeightmapKind::MotionBlocking).get_highest_y_at_position(0, 0), 12);
+        assert_eq!(hm.get_kind(HeightmapKind::MotionBlockingNoLeaves).get_highest_y_at_position(0, 0), 11);
+        assert_eq!(hm.get_kind(HeightmapKind::Ground).get_highest_y_at_position(0, 0), 12);
+        assert_eq!(hm.get_kind(HeightmapKind::Ground).get_highest_y_at_position(1, 0), -65);
+    }
+
+    #[test]
+    fn incremental_update_matches_full_recalculation() {
+        let mut chunk = empty_chunk();
+        chunk.set_block_at_index(index(3, 0, 5), PaletteBlock::new("minecraft:stone", None));
+        chunk.set_block_at_index(index(3, 40, 5), PaletteBlock::new("minecraft:dirt", None));
+        chunk.set_block_at_index(index(3, 40, 5), PaletteBlock::new("minecraft:air", None));
+
+        let incremental = chunk.heightmap_store().get_kind(HeightmapKind::Ground).to_longs();
+        assert_eq!(chunk.heightmap_store().get_kind(HeightmapKind::Ground).get_highest_y_at_position(3, 5), 0);
+        chunk.recalculate_heightmaps();
+        assert_eq!(incremental, chunk.heightmap_store().get_kind(HeightmapKind::Ground).to_longs());
+    }
+
+    #[test]
+    fn heightmap_longs_roundtrip() {
+        let mut chunk = empty_chunk();
+        chunk.set_block_at_index(index(15, 200, 15), PaletteBlock::new("minecraft:stone", None));
+        let longs = chunk.heightmap_store().get_kind(HeightmapKind::MotionBlocking).to_longs();
+        assert_eq!(longs.len(), 37);
+
+        let mut other = empty_chunk();
+        unsafe { other.heightmap_store_mut().get_kind_mut(HeightmapKind::MotionBlocking).set_via_longs(longs); }
+        assert_eq!(other.heightmap_store().get_kind(HeightmapKind::MotionBlocking).get_highest_y_at_position(15, 15), 200);
+    }
 }
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use crate::models::positions::chunk_position::ChunkPosition;
+use crate::models::world::world::SaveOptions;
+
+#[derive(Debug, Clone, Default)]
+pub struct CacheOptions {
+    memory_budget: Option<usize>, // estimated bytes of the cached chunks, see Chunk::estimated_memory
+    max_chunks: Option<usize>,
+    save: SaveOptions, // changed chunks are saved with these before they're evicted
+}
+
+impl CacheOptions {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn memory_budget(&self) -> Option<usize> { self.memory_budget }
+    pub fn max_chunks(&self) -> Option<usize> { self.max_chunks }
+    pub fn save(&self) -> &SaveOptions { &self.save }
+
+    pub fn with_memory_budget(mut self, bytes: usize) -> Self { self.memory_budget = Some(bytes); self }
+    pub fn with_max_chunks(mut self, chunks: usize) -> Self { self.max_chunks = Some(chunks); self }
+    pub fn with_save(mut self, options: SaveOptions) -> Self { self.save = options; self }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CacheEntry {
+    last_used: u64,
+    memory: usize,
+}
+
+// Which loaded chunks were used last and how much memory they take. The chunks themselves stay in their dimension,
+// World evicts them, see World::enable_chunk_cache
+#[derive(Debug, Clone)]
+pub struct ChunkCache {
+    options: CacheOptions,
+    entries: HashMap<ChunkPosition, CacheEntry>,
+    clock: u64,
+    memory: usize,
+    evicted: usize,
+    paused: usize, // nothing is evicted while a query holds on to chunks it resolved up front
+}
+
+impl ChunkCache {
+    pub fn new(options: CacheOptions) -> Self {
+        Self { options, entries: HashMap::new(), clock: 0, memory: 0, evicted: 0, paused: 0 }
+    }
+
+    pub fn options(&self) -> &CacheOptions { &self.options }
+    pub fn chunk_count(&self) -> usize { self.entries.len() }
+    pub fn memory(&self) -> usize { self.memory }
+    pub fn evicted(&self) -> usize { self.evicted }
+    pub fn contains(&self, position: &ChunkPosition) -> bool { self.entries.contains_key(position) }
+
+    pub fn is_over_budget(&self) -> bool { self.exceeds(self.memory, self.entries.len()) }
+
+    fn exceeds(&self, memory: usize, count: usize) -> bool {
+        self.options.memory_budget.is_some_and(|budget| memory > budget) || self.options.max_chunks.is_some_and(|max| count > max)
+    }
+
+    // Marks the chunk as just used, memory is None when it couldn't be measured (the chunk was locked)
+    pub(crate) fn touch(&mut self, position: &ChunkPosition, memory: Option<usize>) {
+        self.clock += 1;
+        let clock = self.clock;
+        let entry = self.entries.entry(position.clone()).or_insert(CacheEntry { last_used: clock, memory: 0 });
+        entry.last_used = clock;
+        if let Some(memory) = memory {
+            self.memory = self.memory - entry.memory + memory;
+            entry.memory = memory;
+        }
+    }
+
+    pub(crate) fn pause(&mut self) { self.paused += 1; }
+    pub(crate) fn resume(&mut self) { self.paused = self.paused.saturating_sub(1); }
+
+    pub(crate) fn forget(&mut self, position: &ChunkPosition) {
+        if let Some(entry) = self.entries.remove(position) { self.memory -= entry.memory; }
+    }
+
+    pub(crate) fn record_eviction(&mut self, position: &ChunkPosition) {
+        self.forget(position);
+        self.evicted += 1;
+    }
+
+    // The least recently used chunks that have to go for the cache to fit its budget. The chunk used last always
+    // stays, so the chunk that was just loaded can't be evicted right away
+    pub(crate) fn eviction_candidates(&self) -> Vec<ChunkPosition> {
+        if self.paused > 0 { return Vec::new(); }
+        let mut entries = self.entries.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|(_, entry)| entry.last_used);
+        entries.pop();
+
+        let (mut memory, mut count) = (self.memory, self.entries.len());
+        let mut candidates = Vec::new();
+        for (position, entry) in entries {
+            if !self.exceeds(memory, count) { break; }
+            memory -= entry.memory;
+            count -= 1;
+            candidates.push(position.clone());
+        }
+        candidates
+    }
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::io;
+    use std::process;
+    use crate::constants::versions::VersionManager;
+    use crate::models::entity::entity::{Entity, EntityType};
+    use crate::models::filter::filter::Filter;
+    use crate::models::other::properties::Properties;
+    use crate::models::other::tick::Tick;
+    use crate::models::positions::entity_position::EntityPosition;
+    use crate::models::positions::whole_position::Position;
+    use crate::models::world::block::PaletteBlock;
+    use crate::models::world::chunk::Chunk;
+    use crate::models::world::dimension::Dimension;
+    use crate::models::world::selection::SelectionBuilder;
+    use crate::models::world::world::World;
+    use crate::traits::access::prelude::BlockReader;
+    use crate::types::WorldKind;
+
+    fn pos(x: i32, y: i32, z: i32) -> Position { Position::new("overworld".into(), x, y, z) }
+
+    #[test]
+    fn evicts_least_recently_used_chunks() {
+        let directory = std::env::temp_dir().join(format!("cubicle_cache_test_{}", process::id()));
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        {
+            let world = World::new(directory.clone(), version.clone());
+            let mut dimension = Dimension::new("overworld".into(), version.clone());
+            for x in 0..4 {
+                dimension.set_chunk(Chunk::new(ChunkPosition::new(x, 0, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+            }
+            let mut locked = world.lock().unwrap();
+            locked.set_dimension("overworld".into(), dimension);
+            locked.transaction(|tx| { for x in 0..4 { tx.set_block(&pos(x * 16, 0, 0), PaletteBlock::from("minecraft:glass")); } }).unwrap();
+            assert_eq!(locked.save(&SaveOptions::new()).unwrap(), 4);
+        }
+
+        let world = World::new(directory.clone(), version.clone());
+        let mut locked = world.lock().unwrap();
+        locked.register_regions();
+        locked.enable_chunk_cache(CacheOptions::new().with_max_chunks(2)).unwrap();
+
+        // the selection loads its chunks from disk as it goes, older ones are evicted
+        let glass = Filter::from_filter_string("id == minecraft:glass").unwrap();
+        let mut selection = SelectionBuilder::new(&mut locked, &version).cuboid(&pos(0, -64, 0), &pos(63, 0, 15)).build();
+        assert_eq!(selection.chunk_count(), 4);
+        let mut found = 0;
+        selection.find_blocks(glass.clone(), |_| { found += 1; true });
+        assert_eq!(found, 4);
+        assert_eq!(selection.replace(glass, &PaletteBlock::from("minecraft:stone")), 4);
+        assert_eq!(locked.dimension(&"overworld".into()).unwrap().chunk_count(), 2);
+        assert_eq!(locked.chunk_cache().unwrap().chunk_count(), 2);
+        assert!(locked.chunk_cache().unwrap().evicted() >= 2);
+
+        // the evicted chunks were saved before they went, the loaded ones are saved when they're unloaded
+        assert_eq!(locked.unload_region(&ChunkPosition::new(0, 0, "overworld".into()), Some(&SaveOptions::new())).unwrap(), 2);
+        assert_eq!(locked.chunk_cache().unwrap().chunk_count(), 0);
+        let chunk = locked.load_chunk(&ChunkPosition::new(3, 0, "overworld".into())).unwrap().unwrap();
+        assert_eq!(chunk.lock().unwrap().block_store().get_block_at_index(64 * 256).unwrap().id(), "stone");
+        assert!(locked.unload_chunk(&ChunkPosition::new(3, 0, "overworld".into()), None).unwrap());
+        assert!(locked.load_chunk(&ChunkPosition::new(9, 0, "overworld".into())).unwrap().is_none());
+
+        // a region that can't be read is an error, not a missing chunk
+        fs::write(directory.join("region").join("r.0.0.mca"), [0, 0, 2, 1]).unwrap();
+        assert!(locked.load_chunk(&ChunkPosition::new(3, 0, "overworld".into())).is_err());
+        assert!(locked.dimension(&"overworld".into()).unwrap().chunk((3, 0)).is_none());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn evicting_saves_entity_changes_and_detaches_the_index() {
+        let directory = std::env::temp_dir().join(format!("cubicle_cache_entities_test_{}", process::id()));
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        {
+            let world = World::new(directory.clone(), version.clone());
+            let mut dimension = Dimension::new("overworld".into(), version.clone());
+            for x in 0..3 {
+                dimension.set_chunk(Chunk::new(ChunkPosition::new(x, 0, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+            }
+            let mut locked = world.lock().unwrap();
+            locked.set_dimension("overworld".into(), dimension);
+            locked.transaction(|tx| { for x in 0..3 { tx.set_block(&pos(x * 16, 0, 0), PaletteBlock::from("minecraft:glass")); } }).unwrap();
+            assert_eq!(locked.save(&SaveOptions::new()).unwrap(), 3);
+        }
+
+        let world = World::new(directory.clone(), version.clone());
+        let mut locked = world.lock().unwrap();
+        locked.register_regions();
+        locked.enable_block_index(false);
+        locked.enable_chunk_cache(CacheOptions::new().with_max_chunks(1)).unwrap();
+        let first = ChunkPosition::new(0, 0, "overworld".into());
+        let chunk = locked.load_chunk(&first).unwrap().unwrap();
+        let revision = chunk.lock().unwrap().block_store().revision();
+        assert!(locked.block_index().unwrap().chunk_counts(&first, Some(revision)).is_some());
+
+        // only the entities changed, the chunk is saved anyway before it's evicted
+        locked.dimension_mut(&"overworld".into()).unwrap().add_entity(Entity::new(EntityType::Mob, Tick::new(300), 0.0, Tick::new(0), false, (0.0, 0.0, 0.0), true,
+            EntityPosition::new(8.5, 0.0, 8.5, 0.0, 0.0, "overworld".into()), 42, Properties::new(HashMap::new())));
+        locked.load_chunk(&ChunkPosition::new(1, 0, "overworld".into())).unwrap().unwrap();
+        assert!(locked.take_eviction_error().is_none());
+        assert!(locked.dimension(&"overworld".into()).unwrap().chunk((0, 0)).is_none());
+        assert!(locked.block_index().unwrap().chunk_counts(&first, Some(revision)).is_none());
+        let chunk = locked.load_chunk(&first).unwrap().unwrap();
+        assert_eq!(chunk.lock().unwrap().entity_count(), 1);
+
+        // entity changes of a chunk loaded without its entities can't be saved, it stays loaded
+        chunk.lock().unwrap().set_entities_loaded(false);
+        chunk.lock().unwrap().mark_entities_dirty();
+        locked.load_chunk(&ChunkPosition::new(2, 0, "overworld".into())).unwrap().unwrap();
+        assert_eq!(locked.take_eviction_error().unwrap().kind(), io::ErrorKind::InvalidInput);
+        assert!(locked.dimension(&"overworld".into()).unwrap().chunk((0, 0)).is_some());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
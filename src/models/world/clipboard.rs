@@ -0,0 +1,501 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use fastnbt::{IntArray, Value};
+use crate::models::block_entity::prelude::*;
+use crate::models::entity::entity::Entity;
+use crate::models::other::properties::Properties;
+use crate::models::positions::chunk_position::ChunkPosition;
+use crate::models::positions::entity_position::EntityPosition;
+use crate::models::positions::whole_position::Position;
+use crate::models::world::block::PaletteBlock;
+use crate::models::world::pattern::Pattern;
+use crate::models::world::selection::Selection;
+use crate::models::world::tile_tick::TileTick;
+use crate::models::world::transform::{transform_block, transform_xz, transform_xz_f64, Mirror, Rotation};
+use crate::models::world::world::World;
+use crate::traits::block_entity::BlockEntityTrait;
+use crate::types::ChunkType;
+use crate::utils::generic_utils::random_uuid;
+use crate::utils::position_utils::{block_index_to_block_position, block_position_to_chunk_pos_and_block_index};
+
+type Offset = (i32, i32, i32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasteOptions {
+    rotation: Rotation,
+    mirror: Mirror,
+    include_air: bool, // air in the clipboard replaces what's there, otherwise only solid blocks are pasted
+    include_entities: bool,
+}
+
+impl Default for PasteOptions {
+    fn default() -> Self {
+        Self { rotation: Rotation::None, mirror: Mirror::None, include_air: true, include_entities: true }
+    }
+}
+
+impl PasteOptions {
+    pub fn rotation(&self) -> Rotation { self.rotation }
+    pub fn mirror(&self) -> Mirror { self.mirror }
+    pub fn include_air(&self) -> bool { self.include_air }
+    pub fn include_entities(&self) -> bool { self.include_entities }
+
+    pub fn rotated(mut self, rotation: Rotation) -> Self { self.rotation = rotation; self }
+    pub fn mirrored(mut self, mirror: Mirror) -> Self { self.mirror = mirror; self }
+    pub fn with_air(mut self, include_air: bool) -> Self { self.include_air = include_air; self }
+    pub fn with_entities(mut self, include_entities: bool) -> Self { self.include_entities = include_entities; self }
+}
+
+// Blocks, block entities, entities and tile ticks of a selection, kept relative to the origin they were copied from
+// so they can be pasted anywhere (and in any dimension), rotated and mirrored around that origin
+#[derive(Debug, Clone)]
+pub struct Clipboard {
+    blocks: Vec<(Offset, PaletteBlock)>,
+    block_entities: HashMap<Offset, BlockEntity>,
+    entities: Vec<((f64, f64, f64), Entity)>,
+    tile_ticks: HashMap<Offset, TileTick>,
+}
+
+fn offset_of(position: &Position, origin: &Position) -> Offset {
+    (position.x() - origin.x(), position.y() - origin.y(), position.z() - origin.z())
+}
+
+// Minecraft yaw: 0 is south, 90 west, 180 north, 270 east
+fn transform_yaw(yaw: f64, rotation: Rotation, mirror: Mirror) -> f64 {
+    let mirrored = match mirror {
+        Mirror::None => yaw,
+        Mirror::LeftRight => 180.0 - yaw,
+        Mirror::FrontBack => -yaw,
+    };
+    let turned = mirrored + 90.0 * rotation.quarter_turns() as f64;
+    (turned + 180.0).rem_euclid(360.0) - 180.0
+}
+
+// Block positions kept in block entity data: beehive flowers, end gateway exits and those of the bees in a hive. Int
+// arrays since 1.20.5, {X, Y, Z} compounds before
+const NESTED_POSITION_KEYS: [&str; 6] = ["flower_pos", "FlowerPos", "hive_pos", "HivePos", "exit_portal", "ExitPortal"];
+
+fn move_nested_positions(compound: &mut HashMap<String, Value>, moved: &impl Fn(Offset) -> Offset) {
+    for (key, value) in compound.iter_mut() {
+        if NESTED_POSITION_KEYS.contains(&key.as_str()) {
+            match value {
+                Value::IntArray(array) if array.len() == 3 => {
+                    let (x, y, z) = moved((array[0], array[1], array[2]));
+                    *value = Value::IntArray(IntArray::new(vec![x, y, z]));
+                }
+                Value::Compound(position) => {
+                    if let (Some(Value::Int(x)), Some(Value::Int(y)), Some(Value::Int(z))) = (position.get("X"), position.get("Y"), position.get("Z")) {
+                        let (x, y, z) = moved((*x, *y, *z));
+                        position.extend([("X".to_string(), Value::Int(x)), ("Y".to_string(), Value::Int(y)), ("Z".to_string(), Value::Int(z))]);
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        match value {
+            Value::Compound(inner) => move_nested_positions(inner, moved),
+            Value::List(values) => for value in values { if let Value::Compound(inner) = value { move_nested_positions(inner, moved) } },
+            _ => {}
+        }
+    }
+}
+
+impl Clipboard {
+    // Copies every selected (non null) block, including air, with what's attached to it. Offsets are from origin
+    pub fn copy(selection: &mut Selection, origin: &Position) -> Clipboard {
+        let chunk_size = selection.version().data.chunk_size;
+        let lowest_y = selection.version().data.lowest_y;
+        let mut clipboard = Clipboard { blocks: Vec::new(), block_entities: HashMap::new(), entities: Vec::new(), tile_ticks: HashMap::new() };
+
+        for chunk_pos in selection.chunk_positions() {
+            let Some(chunk) = selection.chunk(chunk_pos.clone()) else { continue };
+            let chunk = chunk.lock().unwrap();
+            let block_store = chunk.block_store();
+            let palette = block_store.palette();
+            for (index, palette_index) in block_store.indices_slice().iter().enumerate() {
+                let block = &palette[*palette_index];
+                if block.is_null() { continue; }
+                let position = block_index_to_block_position(&chunk_pos, index, chunk_size, lowest_y);
+                if !selection.contains(&position) { continue; }
+                clipboard.blocks.push((offset_of(&position, origin), block.clone()));
+            }
+            for block_entity in chunk.block_entities_store().get_all() {
+                let position = block_entity.base().position();
+                if !selection.contains(position) { continue; }
+                // clones share their data, later edits in the world shouldn't show up in the clipboard
+                let mut block_entity = (**block_entity).clone();
+                let properties = Properties::new(block_entity.base().properties().raw().clone());
+                block_entity.base_mut().set_properties(properties);
+                clipboard.block_entities.insert(offset_of(position, origin), block_entity);
+            }
+            for tile_tick in chunk.tile_ticks() {
+                if selection.contains(tile_tick.position()) { clipboard.tile_ticks.insert(offset_of(tile_tick.position(), origin), tile_tick.clone()); }
+            }
+        }
+
+        for entity in selection.entity_snapshots() {
+            let position = entity.base().position();
+            let relative = (position.x() - origin.x() as f64, position.y() - origin.y() as f64, position.z() - origin.z() as f64);
+            clipboard.entities.push((relative, entity));
+        }
+        clipboard
+    }
+
+    pub fn blocks(&self) -> &Vec<(Offset, PaletteBlock)> { &self.blocks }
+    pub fn block_entities(&self) -> &HashMap<Offset, BlockEntity> { &self.block_entities }
+    pub fn entities(&self) -> &Vec<((f64, f64, f64), Entity)> { &self.entities }
+    pub fn tile_ticks(&self) -> &HashMap<Offset, TileTick> { &self.tile_ticks }
+    pub fn block_count(&self) -> usize { self.blocks.len() }
+    pub fn is_empty(&self) -> bool { self.blocks.is_empty() && self.entities.is_empty() }
+
+    // Inclusive offset corners of the copied blocks
+    pub fn bounds(&self) -> Option<(Offset, Offset)> {
+        let mut blocks = self.blocks.iter().map(|(offset, _)| *offset);
+        let first = blocks.next()?;
+        Some(blocks.fold((first, first), |(min, max), o| {
+            ((min.0.min(o.0), min.1.min(o.1), min.2.min(o.2)), (max.0.max(o.0), max.1.max(o.1), max.2.max(o.2)))
+        }))
+    }
+
+    // Every copied block becomes the pattern's block at its offset, like Selection::fill does in the world. Block
+    // entities and tile ticks of blocks that become a different block are dropped. Returns how many blocks changed
+    pub fn fill(&mut self, pattern: impl Into<Pattern>) -> usize {
+        let pattern = pattern.into();
+        let mut changed = 0;
+        for (offset, block) in &mut self.blocks {
+            let filled = pattern.block_at(offset.0, offset.1, offset.2);
+            if filled == *block { continue; }
+            if filled.full_name() != block.full_name() {
+                self.block_entities.remove(offset);
+                self.tile_ticks.remove(offset);
+            }
+            *block = filled;
+            changed += 1;
+        }
+        changed
+    }
+
+    fn target(at: &Position, offset: Offset, options: &PasteOptions) -> Position {
+        let (x, z) = transform_xz(offset.0, offset.2, options.rotation, options.mirror);
+        Position::new(*at.dimension(), at.x() + x, at.y() + offset.1, at.z() + z)
+    }
+
+    // Where a copied entity ends up, turned around the center of the origin block like the blocks are
+    fn entity_target(at: &Position, relative: (f64, f64, f64), entity: &Entity, options: &PasteOptions) -> EntityPosition {
+        let (x, z) = transform_xz_f64(relative.0 - 0.5, relative.2 - 0.5, options.rotation, options.mirror);
+        let old = entity.base().position();
+        EntityPosition::new(
+            at.x() as f64 + x + 0.5, at.y() as f64 + relative.1, at.z() as f64 + z + 0.5,
+            transform_yaw(old.yaw(), options.rotation, options.mirror), old.pitch(), *at.dimension()
+        )
+    }
+
+    // Pastes with the copy origin placed at `at`. The chunks it goes into are loaded first (World::load_chunk) and
+    // kept until it's done, blocks in chunks that don't exist on disk either are skipped. A chunk that can't be read
+    // is an error and nothing is pasted. Whatever block entity or tile tick was at a replaced block is removed.
+    // Returns how many blocks were placed
+    pub fn paste(&self, world: &mut World, at: &Position, options: &PasteOptions) -> io::Result<usize> {
+        let version = world.version();
+        let (chunk_size, lowest_y, highest_y) = (version.data.chunk_size, version.data.lowest_y, version.data.highest_y);
+        let mut targets = self.blocks.iter()
+            .filter(|(_, block)| options.include_air || !block.is_air())
+            .map(|(offset, _)| Self::target(at, *offset, options))
+            .filter(|target| target.y() >= lowest_y && target.y() < highest_y)
+            .map(|target| block_position_to_chunk_pos_and_block_index(&target, chunk_size, lowest_y).0)
+            .collect::<HashSet<_>>();
+        if options.include_entities {
+            targets.extend(self.entities.iter().map(|(relative, entity)| {
+                let position = Self::entity_target(at, *relative, entity, options);
+                ChunkPosition::new(position.i_x().div_euclid(chunk_size), position.i_z().div_euclid(chunk_size), *at.dimension())
+            }));
+        }
+
+        world.pause_eviction();
+        let mut chunks = HashMap::new();
+        for chunk_pos in targets {
+            match world.load_chunk(&chunk_pos) {
+                Ok(chunk) => { chunks.insert(chunk_pos.position(), chunk); }
+                Err(e) => {
+                    let resumed = world.resume_eviction();
+                    world.keep_eviction_error(resumed);
+                    return Err(e);
+                }
+            }
+        }
+        let placed = self.paste_into(world, at, options, &chunks);
+        drop(chunks);
+        let resumed = world.resume_eviction();
+        world.keep_eviction_error(resumed);
+        Ok(placed)
+    }
+
+    fn paste_into(&self, world: &mut World, at: &Position, options: &PasteOptions, chunks: &HashMap<(i32, i32), Option<ChunkType>>) -> usize {
+        let version = world.version();
+        let (chunk_size, lowest_y, highest_y) = (version.data.chunk_size, version.data.lowest_y, version.data.highest_y);
+        let Some(dimension) = world.dimension_mut(at.dimension()) else { return 0 };
+
+        // recorded into the edit history once the dimension isn't borrowed anymore
+        let (mut block_changes, mut block_entity_changes, mut tile_tick_changes, mut added_entities) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        let mut placed = 0;
+        for (offset, block) in &self.blocks {
+            if !options.include_air && block.is_air() { continue; }
+            let target = Self::target(at, *offset, options);
+            if target.y() < lowest_y || target.y() >= highest_y { continue; }
+            let (chunk_pos, index) = block_position_to_chunk_pos_and_block_index(&target, chunk_size, lowest_y);
+            let Some(Some(chunk)) = chunks.get(&chunk_pos.position()) else { continue };
+            let mut chunk = chunk.lock().unwrap();
+
+            let before = chunk.block_store().palette()[chunk.block_store().indices_slice()[index]].clone();
+            let after = transform_block(block, options.rotation, options.mirror);
+            // removed first, setting a different block would put a default block entity there
+            let removed = chunk.block_entities_store_mut().remove_at_world_position(&target).map(|b| (*b).clone());
+            let removed_tile_tick = chunk.remove_tile_tick_at(&target);
+            if !chunk.set_block_at_index(index, after.clone()) { continue; }
+            placed += 1;
+            block_changes.push((target.clone(), before, after));
+            let added = self.block_entities.get(offset).map(|block_entity| {
+                let mut block_entity = block_entity.clone();
+                // positions inside are moved like the block entity itself. The copy gets its own data, clones share it
+                let copied_at = block_entity.base().position().clone();
+                let moved = |p: Offset| {
+                    let moved = Self::target(at, (offset.0 + p.0 - copied_at.x(), offset.1 + p.1 - copied_at.y(), offset.2 + p.2 - copied_at.z()), options);
+                    (moved.x(), moved.y(), moved.z())
+                };
+                let mut properties = block_entity.base().properties().raw().clone();
+                move_nested_positions(&mut properties, &moved);
+                block_entity.base_mut().set_properties(Properties::new(properties));
+                block_entity.base_mut().set_position(target.clone());
+                chunk.block_entities_store_mut().set_at_world_position(block_entity.clone());
+                block_entity
+            });
+            if removed.is_some() || added.is_some() { block_entity_changes.push((target.clone(), removed, added)); }
+            let added_tile_tick = self.tile_ticks.get(offset).map(|tile_tick| {
+                let mut tile_tick = tile_tick.clone();
+                tile_tick.set_position(target.clone());
+                chunk.set_tile_tick(tile_tick.clone());
+                tile_tick
+            });
+            if removed_tile_tick.is_some() || added_tile_tick.is_some() { tile_tick_changes.push((target, removed_tile_tick, added_tile_tick)); }
+        }
+
+        let entities = if options.include_entities { self.entities.as_slice() } else { &[] };
+        for (relative, entity) in entities {
+            let position = Self::entity_target(at, *relative, entity, options);
+            let chunk_key = (position.i_x().div_euclid(chunk_size), position.i_z().div_euclid(chunk_size));
+            let Some(Some(chunk)) = chunks.get(&chunk_key) else { continue };
+
+            let mut entity = entity.clone();
+            let velocity = *entity.base().velocity_vector();
+            let (velocity_x, velocity_z) = transform_xz_f64(velocity.0, velocity.2, options.rotation, options.mirror);
+            entity.base_mut().set_velocity_vector((velocity_x, velocity.1, velocity_z));
+            entity.base_mut().set_position(position);
+            entity.base_mut().set_uuid(random_uuid());
+            added_entities.push(entity.clone());
+            let key = dimension.entity_store_mut().add_entity(entity);
+            chunk.lock().unwrap().add_entity(key);
+        }
+
+        let history = world.history_mut();
+        history.begin("paste");
+        for (position, before, after) in block_changes { history.record_block(&position, &before, &after); }
+        for (position, before, after) in block_entity_changes { history.record_block_entity(&position, before, after); }
+        for (position, before, after) in tile_tick_changes { history.record_tile_tick(&position, before, after); }
+        for entity in added_entities { history.record_entity(*at.dimension(), entity.base().uuid(), None, Some(entity)); }
+        history.commit();
+        placed
+    }
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use fastnbt::Value;
+    use crate::constants::versions::VersionManager;
+    use crate::models::block_entity::block_entity::GenericBlockEntity;
+    use crate::models::entity::entity::EntityType;
+    use crate::models::other::properties::Properties;
+    use crate::models::other::tick::Tick;
+    use crate::models::positions::chunk_position::ChunkPosition;
+    use crate::models::world::chunk::Chunk;
+    use crate::models::world::dimension::Dimension;
+    use crate::models::world::selection::SelectionBuilder;
+    use crate::models::world::tile_tick::TileTickType;
+    use crate::models::world::world::SaveOptions;
+    use crate::types::WorldKind;
+    use std::fs;
+
+    fn state(block: &PaletteBlock, key: &str) -> Option<String> {
+        match block.properties().raw().get(key) { Some(Value::String(s)) => Some(s.clone()), _ => None }
+    }
+
+    #[test]
+    fn copy_and_paste_rotated() {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new("".into(), version.clone());
+        let mut dim = Dimension::new("overworld".into(), version.clone());
+        for (x, z) in [(0, 0), (1, 0), (-1, 0), (0, 1), (0, -1), (-1, -1)] {
+            dim.set_chunk(Chunk::new(ChunkPosition::new(x, z, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+        }
+        let mut locked = world.lock().unwrap();
+        locked.set_dimension("overworld".into(), dim);
+
+        // a stair facing east at (3, 0, 1) with a chest next to it, a tile tick and a cow looking south
+        let pos = |x, y, z| Position::new("overworld".into(), x, y, z);
+        let set = |world: &mut World, p: &Position, block: PaletteBlock| {
+            let (chunk_pos, index) = block_position_to_chunk_pos_and_block_index(p, 16, -64);
+            world.dimension(p.dimension()).unwrap().chunk(chunk_pos.position()).unwrap().lock().unwrap().set_block_at_index(index, block);
+        };
+        let states = |pairs: &[(&str, &str)]| Some(pairs.iter().map(|(k, v)| (k.to_string(), Value::String(v.to_string()))).collect::<HashMap<_, _>>());
+        set(&mut locked, &pos(3, 0, 1), PaletteBlock::new("minecraft:oak_stairs", states(&[("facing", "east"), ("shape", "inner_left"), ("half", "top")])));
+        set(&mut locked, &pos(2, 0, 1), PaletteBlock::new("minecraft:chest", states(&[("facing", "north"), ("type", "left")])));
+        set(&mut locked, &pos(4, 1, 3), PaletteBlock::new("minecraft:air", None));
+        set(&mut locked, &pos(1, 0, 0), PaletteBlock::new("minecraft:beehive", states(&[("facing", "north")])));
+        {
+            let chunk = locked.dimension(&"overworld".into()).unwrap().chunk((0, 0)).unwrap();
+            let mut chunk = chunk.lock().unwrap();
+            let properties = Properties::new(HashMap::from([("CustomName".to_string(), Value::String("box".to_string()))]));
+            chunk.block_entities_store_mut().set_at_world_position(BlockEntity::Other(GenericBlockEntity::new("minecraft:chest".to_string(), pos(2, 0, 1), properties)));
+            chunk.set_tile_tick(TileTick::new(pos(3, 0, 1), 0, 5, TileTickType::BLOCK));
+            // the hive's flower and its bee's flower are the stair
+            let xyz = |x, y, z| Value::Compound(HashMap::from([("X".to_string(), Value::Int(x)), ("Y".to_string(), Value::Int(y)), ("Z".to_string(), Value::Int(z))]));
+            let bee = Value::Compound(HashMap::from([("EntityData".to_string(), Value::Compound(HashMap::from([("FlowerPos".to_string(), xyz(3, 0, 1))])))]));
+            let properties = Properties::new(HashMap::from([
+                ("flower_pos".to_string(), Value::IntArray(IntArray::new(vec![3, 0, 1]))), ("Bees".to_string(), Value::List(vec![bee])),
+            ]));
+            chunk.block_entities_store_mut().set_at_world_position(BlockEntity::Other(GenericBlockEntity::new("minecraft:beehive".to_string(), pos(1, 0, 0), properties)));
+        }
+        let cow = Entity::new(EntityType::Mob, Tick::new(0), 0.0, Tick::new(0), false, (0.0, 0.0, 0.5), true,
+            EntityPosition::new(3.5, 1.0, 2.5, 0.0, 0.0, "overworld".into()), 7, Properties::new(HashMap::new()));
+        let key = locked.dimension_mut(&"overworld".into()).unwrap().entity_store_mut().add_entity(cow);
+        locked.dimension(&"overworld".into()).unwrap().chunk((0, 0)).unwrap().lock().unwrap().add_entity(key);
+
+        let clipboard = {
+            let mut selection = SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(1, 0, 0), &pos(4, 1, 3)).build();
+            Clipboard::copy(&mut selection, &pos(1, 0, 1))
+        };
+        assert_eq!(clipboard.block_count(), 4); // blocks that were never set are null and aren't copied
+        assert_eq!(clipboard.block_entities().len(), 2);
+        assert_eq!(clipboard.tile_ticks().len(), 1);
+        assert_eq!(clipboard.entities().len(), 1);
+        assert_eq!(clipboard.bounds(), Some(((0, 0, -1), (3, 1, 2))));
+
+        // origin goes to (-5, 10, -5), the stair offset (2, 0, 0) becomes (0, 0, 2) after a clockwise turn
+        let options = PasteOptions::default().rotated(Rotation::Clockwise90);
+        assert_eq!(clipboard.paste(&mut locked, &pos(-5, 10, -5), &options).unwrap(), 4);
+
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version.clone()).all_chunks().build();
+        let block_at = |selection: &mut Selection, p: &Position| {
+            let (chunk_pos, index) = block_position_to_chunk_pos_and_block_index(p, 16, -64);
+            selection.chunk(chunk_pos).unwrap().lock().unwrap().block_store().get_block_at_index(index).unwrap()
+        };
+        let stair = block_at(&mut selection, &pos(-5, 10, -3));
+        assert_eq!(stair.full_name(), "minecraft:oak_stairs");
+        assert_eq!(state(&stair, "facing").as_deref(), Some("south"));
+        assert_eq!(state(&stair, "shape").as_deref(), Some("inner_left"));
+        assert_eq!(state(&stair, "half").as_deref(), Some("top"));
+        let chest = block_at(&mut selection, &pos(-5, 10, -4));
+        assert_eq!(state(&chest, "facing").as_deref(), Some("east"));
+        assert_eq!(state(&chest, "type").as_deref(), Some("left"));
+
+        let chunk = selection.chunk(ChunkPosition::new(-1, -1, "overworld".into())).unwrap();
+        let chunk = chunk.lock().unwrap();
+        let block_entity = chunk.block_entities_store().get_at_world_position(&pos(-5, 10, -4)).unwrap();
+        assert_eq!(block_entity.base().properties().get("CustomName"), Some(Value::String("box".to_string())));
+        assert_eq!(chunk.tile_ticks()[0].position(), &pos(-5, 10, -3));
+        // the hive at offset (0, 0, -1) goes to offset (1, 0, 0), and still points at the stair
+        let hive = chunk.block_entities_store().get_at_world_position(&pos(-4, 10, -5)).unwrap();
+        assert_eq!(hive.base().properties().get("flower_pos"), Some(Value::IntArray(IntArray::new(vec![-5, 10, -3]))));
+        let Some(Value::List(bees)) = hive.base().properties().get("Bees") else { panic!("no bees") };
+        let Value::Compound(bee) = &bees[0] else { panic!("not a bee") };
+        let Some(Value::Compound(flower)) = bee.get("EntityData").and_then(|d| if let Value::Compound(d) = d { d.get("FlowerPos").cloned() } else { None }) else { panic!("no flower") };
+        assert_eq!((flower.get("X"), flower.get("Y"), flower.get("Z")), (Some(&Value::Int(-5)), Some(&Value::Int(10)), Some(&Value::Int(-3))));
+        // the clipboard's copy is untouched
+        assert_eq!(clipboard.block_entities()[&(0, 0, -1)].base().properties().get("flower_pos"), Some(Value::IntArray(IntArray::new(vec![3, 0, 1]))));
+        drop(chunk);
+
+        let cow = selection.entity_snapshots().into_iter().find(|e| e.base().position().y() == 11.0).unwrap();
+        assert_eq!((cow.base().position().x(), cow.base().position().z()), (-5.5, -2.5));
+        assert_eq!(cow.base().position().yaw(), 90.0);
+        assert_eq!(*cow.base().velocity_vector(), (-0.5, 0.0, 0.0));
+        assert_ne!(cow.base().uuid(), 7);
+        drop(selection);
+
+        // mirrored paste swaps the chest half and stair corner, and leaves old contents where the clipboard has air
+        let options = PasteOptions::default().mirrored(Mirror::LeftRight).with_air(false).with_entities(false);
+        assert_eq!(clipboard.paste(&mut locked, &pos(-10, 20, -10), &options).unwrap(), 3);
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version).all_chunks().build();
+        let stair = block_at(&mut selection, &pos(-8, 20, -10));
+        assert_eq!(state(&stair, "shape").as_deref(), Some("inner_right"));
+        let chest = block_at(&mut selection, &pos(-9, 20, -10));
+        assert_eq!(state(&chest, "facing").as_deref(), Some("south"));
+        assert_eq!(state(&chest, "type").as_deref(), Some("right"));
+
+        // the chest keeps its contents when it stays a chest, the rest loses theirs
+        let mut filled = clipboard.clone();
+        assert_eq!(filled.fill(Pattern::parse("chest[facing=north|south]").unwrap()), 4);
+        assert!(filled.blocks().iter().all(|(_, b)| b.full_name() == "minecraft:chest"));
+        assert_eq!(filled.block_entities().keys().collect::<Vec<_>>(), vec![&(1, 0, 0)]);
+        assert!(filled.tile_ticks().is_empty());
+    }
+
+    #[test]
+    fn paste_loads_the_target_chunks() {
+        let directory = std::env::temp_dir().join(format!("cubicle_paste_test_{}", std::process::id()));
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let pos = |x, y, z| Position::new("overworld".into(), x, y, z);
+        {
+            let world = World::new(directory.clone(), version.clone());
+            let mut dimension = Dimension::new("overworld".into(), version.clone());
+            for x in [0, 2] {
+                dimension.set_chunk(Chunk::new(ChunkPosition::new(x, 0, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+            }
+            let mut locked = world.lock().unwrap();
+            locked.set_dimension("overworld".into(), dimension);
+            SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(1, 0, 1), &pos(2, 0, 1)).build().fill(&PaletteBlock::from("minecraft:glass"));
+            assert_eq!(locked.save(&SaveOptions::new()).unwrap(), 2);
+        }
+
+        let world = World::new(directory.clone(), version.clone());
+        let mut locked = world.lock().unwrap();
+        locked.register_regions();
+        let clipboard = {
+            let mut selection = SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(1, 0, 1), &pos(2, 0, 1)).build();
+            Clipboard::copy(&mut selection, &pos(1, 0, 1))
+        };
+        assert!(locked.dimension(&"overworld".into()).unwrap().chunk((2, 0)).is_none());
+
+        // the chunk on disk is read first, the one that doesn't exist anywhere gets nothing
+        assert_eq!(clipboard.paste(&mut locked, &pos(33, 5, 1), &PasteOptions::default()).unwrap(), 2);
+        let chunk = locked.dimension(&"overworld".into()).unwrap().chunk((2, 0)).unwrap();
+        assert_eq!(chunk.lock().unwrap().block_store().get_block_at_index(((69 * 16) + 1) * 16 + 2).unwrap().id(), "glass");
+        assert_eq!(clipboard.paste(&mut locked, &pos(200, 5, 1), &PasteOptions::default()).unwrap(), 0);
+
+        // a region that can't be read stops the paste
+        fs::write(directory.join("region").join("r.0.0.mca"), [0, 0, 2, 1]).unwrap();
+        assert!(clipboard.paste(&mut locked, &pos(65, 5, 1), &PasteOptions::default()).is_err());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn yaw_transforms() {
+        assert_eq!(transform_yaw(0.0, Rotation::Clockwise180, Mirror::None), -180.0);
+        assert_eq!(transform_yaw(90.0, Rotation::CounterClockwise90, Mirror::None), 0.0);
+        assert_eq!(transform_yaw(90.0, Rotation::None, Mirror::FrontBack), -90.0);
+        assert_eq!(transform_yaw(30.0, Rotation::None, Mirror::LeftRight), 150.0);
+        assert_eq!(transform_yaw(30.0, Rotation::Clockwise90, Mirror::LeftRight), -120.0);
+    }
+
+    #[test]
+    fn random_uuids_are_version_4() {
+        let (a, b) = (random_uuid(), random_uuid());
+        assert_ne!(a, b);
+        for uuid in [a, b] {
+            assert_eq!((uuid >> 76) & 0xF, 4);
+            assert_eq!((uuid >> 62) & 0x3, 2);
+        }
+    }
+}
@@ -1,6 +1,7 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 use crate::constants::versions::Version;
+use crate::models::entity::entity::Entity;
 use crate::models::other::lasso_string::LassoString;
 use crate::models::stores::entity_store::{EntityStore, EntityStoreKey};
 use crate::models::world::chunk::Chunk;
@@ -8,6 +9,7 @@ use crate::models::stores::structure_store::StructureStore;
 use crate::models::world::selection::{Selection, SelectionBuilder};
 use crate::models::world::world::World;
 use crate::types::{ChunkType, WorldType};
+use crate::utils::position_utils::world_position_to_chunk_position;
 
 pub struct Dimension {
     dimension_id: LassoString,
@@ -30,6 +32,8 @@ impl Dimension {
         }
     }
 
+    pub fn id(&self) -> &LassoString { &self.dimension_id }
+    pub fn version(&self) -> &Arc<Version> { &self.version }
     pub fn structure_store(&self) -> &StructureStore { &self.structure_store }
     pub fn structure_store_mut(&mut self) -> &mut StructureStore { &mut self.structure_store }
     pub fn entity_store(&self) -> &EntityStore { &self.entity_store }
@@ -43,6 +47,48 @@ impl Dimension {
     pub fn chunk_count(&self) -> usize { self.chunks.len() }
     pub fn entity_count(&self) -> usize { self.entity_store.count() }
 
+    // Adds the entity to the store and to the loaded chunk it stands in, if there is one
+    pub fn add_entity(&mut self, entity: Entity) -> EntityStoreKey {
+        let position = entity.base().position();
+        let chunk_pos = world_position_to_chunk_position(position.x(), position.z(), self.version.data.chunk_size);
+        let key = self.entity_store.add_entity(entity);
+        if let Some(chunk) = self.chunk(chunk_pos) { chunk.lock().unwrap().add_entity(key); }
+        key
+    }
+    // The store swap-removes, so whichever chunk referenced the last key is pointed at the freed one
+    pub fn remove_entity(&mut self, key: EntityStoreKey) -> Option<Entity> {
+        if key >= self.entity_store.count() { return None; }
+        let last = self.entity_store.count() - 1;
+        let entity = self.entity_store.remove(&key);
+        for chunk in self.chunks.values() {
+            let mut chunk = chunk.lock().unwrap();
+            chunk.remove_entity(key);
+            if last != key { chunk.replace_entity_key(last, key); }
+        }
+        Some(entity)
+    }
+    // Replaces the entity at key, it's moved to the loaded chunk it stands in now. False if there's nothing at key
+    pub fn set_entity(&mut self, key: EntityStoreKey, entity: Entity) -> bool {
+        if key >= self.entity_store.count() { return false; }
+        let position = entity.base().position();
+        let chunk_pos = world_position_to_chunk_position(position.x(), position.z(), self.version.data.chunk_size);
+        self.entity_store.set(&key, entity);
+        let held_by = self.chunks.iter().find(|(_, c)| c.lock().unwrap().entity_keys().contains(&key)).map(|(p, c)| (*p, c.clone()));
+        match (held_by, self.chunk(chunk_pos)) {
+            (Some((held_pos, held)), Some(chunk)) if held_pos != chunk_pos => {
+                held.lock().unwrap().remove_entity(key);
+                chunk.lock().unwrap().add_entity(key);
+            },
+            (Some((_, held)), _) => held.lock().unwrap().mark_entities_dirty(),
+            (None, Some(chunk)) => chunk.lock().unwrap().add_entity(key),
+            (None, None) => {}
+        }
+        true
+    }
+    pub fn entity_key_by_uuid(&self, uuid: u128) -> Option<EntityStoreKey> {
+        self.entity_store.get_all().position(|e| e.base().uuid() == uuid)
+    }
+
     pub fn set_chunk(&mut self, chunk: Chunk) -> ChunkType {
         let pos = chunk.position().position();
         let arced_chunk = Arc::new(Mutex::new(chunk));
@@ -53,7 +99,20 @@ impl Dimension {
         for chunk in chunks { self.set_chunk(chunk); }
     }
     pub fn delete_chunk(&mut self, chunk_position: (i32, i32)) -> Option<ChunkType> {
-        self.chunks.remove(&chunk_position)
+        self.unload_chunks(&[chunk_position]).pop()
+    }
+    // Removes the chunks together with their entities, the entities of the other chunks get their new keys
+    pub fn unload_chunks(&mut self, chunk_positions: &[(i32, i32)]) -> Vec<ChunkType> {
+        let removed = chunk_positions.iter().filter_map(|p| self.chunks.remove(p)).collect::<Vec<_>>();
+        let keys = removed.iter().flat_map(|c| c.lock().unwrap().entity_keys().clone()).collect::<HashSet<_>>();
+        if keys.is_empty() { return removed; }
+        let new_keys = self.entity_store.remove_keys(&keys);
+        for chunk in self.chunks.values() {
+            let mut chunk = chunk.lock().unwrap();
+            let entity_keys = chunk.entity_keys().iter().filter_map(|k| new_keys[*k]).collect();
+            chunk.set_entities(entity_keys);
+        }
+        removed
     }
 
     pub fn select<'r, 'a>(&self, world: &'r mut World<'a>) -> Selection<'r, 'a> {
@@ -1,22 +1,22 @@
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::sync::{Arc, RwLock};
 use crate::models::block_entity::prelude::BlockEntity;
 use crate::models::world::fulls::block_states::BlockStates;
 
 pub struct BlockData {
-    states: Rc<RefCell<BlockStates>>,
-    block_entity: Option<Rc<BlockEntity>>,
+    states: Arc<RwLock<BlockStates>>,
+    block_entity: Option<Arc<BlockEntity>>,
 }
 
 impl BlockData {
-    pub fn new(states: BlockStates, block_entity: Option<Rc<BlockEntity>>) -> Self {
+    pub fn new(states: BlockStates, block_entity: Option<Arc<BlockEntity>>) -> Self {
         BlockData {
-            states: Rc::new(RefCell::new(states)), block_entity
+            states: Arc::new(RwLock::new(states)), block_entity
         }
     }
 
-    pub fn states(&self) -> Rc<RefCell<BlockStates>> { self.states.clone() }
-    pub fn set_states(&mut self, states: BlockStates) { self.states = Rc::new(RefCell::new(states)) }
-    pub fn data(&self) -> Option<Rc<BlockEntity>> { self.block_entity.clone() }
+    pub fn states(&self) -> Arc<RwLock<BlockStates>> { self.states.clone() }
+    pub fn set_states(&mut self, states: BlockStates) { self.states = Arc::new(RwLock::new(states)) }
+    pub fn data(&self) -> Option<Arc<BlockEntity>> { self.block_entity.clone() }
+    pub fn set_data(&mut self, block_entity: Option<Arc<BlockEntity>>) { self.block_entity = block_entity }
 }
 
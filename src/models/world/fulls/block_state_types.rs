@@ -31,6 +31,7 @@ impl Attachment {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Axis {
     X,
     Y,
@@ -118,6 +119,7 @@ impl CreakingHeartState {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WallConnection {
     False,
     Low,
@@ -194,6 +196,7 @@ impl Face {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
     Down,
     East,
@@ -231,6 +234,7 @@ impl Direction {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Half {
     Bottom,
     Lower,
@@ -262,6 +266,7 @@ impl Half {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Hinge {
     Left,
     Right,
@@ -437,6 +442,7 @@ impl Mode {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Orientation {
     DownEast,
     DownNorth,
@@ -545,6 +551,7 @@ impl SculkSensorPhase {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Shape {
     AscendingEast,
     AscendingNorth,
@@ -711,6 +718,7 @@ impl TrialSpawnerState {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BlockType {
     Bottom,
     Double,
@@ -808,4 +816,34 @@ impl VerticalDirection {
             VerticalDirection::Up => "up",
         }.to_string())
     }
-}
\ No newline at end of file
+}
+// Whether value can be the value of the block state key. Keys with a typed state have to be one of its values, the
+// rest only have to be something the game can store (a string, number or bool)
+pub fn is_valid_state(key: &str, value: &Value) -> bool {
+    let value = Some(value);
+    match key {
+        "attachment" => Attachment::from_nbt(value).is_some(),
+        "axis" => Axis::from_nbt(value).is_some(),
+        "type" => BlockType::from_nbt(value).is_some(),
+        "copper_golem_pose" => CopperGolemPose::from_nbt(value).is_some(),
+        "creaking_heart_state" => CreakingHeartState::from_nbt(value).is_some(),
+        "facing" => Direction::from_nbt(value).is_some(),
+        "face" => Face::from_nbt(value).is_some(),
+        "half" => Half::from_nbt(value).is_some(),
+        "hinge" => Hinge::from_nbt(value).is_some(),
+        "instrument" => Instrument::from_nbt(value).is_some(),
+        "leaves" => Leaves::from_nbt(value).is_some(),
+        "mode" => Mode::from_nbt(value).is_some(),
+        "orientation" => Orientation::from_nbt(value).is_some(),
+        "part" => Part::from_nbt(value).is_some(),
+        "sculk_sensor_phase" => SculkSensorPhase::from_nbt(value).is_some(),
+        "shape" => Shape::from_nbt(value).is_some(),
+        "thickness" => Thickness::from_nbt(value).is_some(),
+        "tilt" => Tilt::from_nbt(value).is_some(),
+        "trial_spawner_state" => TrialSpawnerState::from_nbt(value).is_some(),
+        "vault_state" => VaultState::from_nbt(value).is_some(),
+        "vertical_direction" => VerticalDirection::from_nbt(value).is_some(),
+        "north" | "east" | "south" | "west" => WallConnection::from_nbt(value).is_some(),
+        _ => matches!(value, Some(Value::String(_) | Value::Int(_) | Value::Byte(_))),
+    }
+}
@@ -1,14 +1,18 @@
-use std::cell::RefCell;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt;
-use std::rc::Rc;
+use std::sync::{Arc, RwLock};
 use fastnbt::Value;
 use crate::models::block_entity::block_entity::BlockEntity;
+use crate::models::other::lasso_string::LassoString;
 use crate::models::other::properties::Properties;
 use crate::models::positions::whole_position::Position;
 use crate::models::world::block::PaletteBlock;
+use crate::models::world::chunk::Chunk;
 use crate::models::world::fulls::block_data::BlockData;
 use crate::models::world::fulls::block_states::BlockStates;
+use crate::models::world::history::EditHistory;
+use crate::models::world::pattern::Pattern;
 use crate::types::{ChunkType, WorldType};
 use crate::utils::position_utils::block_position_to_chunk_pos_and_block_index;
 
@@ -16,6 +20,7 @@ pub struct FullBlock<'a> {
     name: String,
     extra: BlockData,
     position: Position,
+    biome: Option<LassoString>,
     null_flag: bool,
 
     world_ref: WorldType<'a>,
@@ -31,11 +36,12 @@ impl<'a> FullBlock<'a> {
             ),
             null_flag: false,
             position: Position::new("overworld".into(), 0, 0, 0),
+            biome: None,
             world_ref: world_ref.clone(),
         }
     }
 
-    pub fn new_with_data(world_ref: &WorldType<'a>, mut block: PaletteBlock, block_entity: Option<Rc<BlockEntity>>, position: Position) -> Self{
+    pub fn new_with_data(world_ref: &WorldType<'a>, mut block: PaletteBlock, block_entity: Option<Arc<BlockEntity>>, position: Position, biome: Option<LassoString>) -> Self{
         Self {
             name: block.name().to_string(),
             extra: BlockData::new(
@@ -43,19 +49,25 @@ impl<'a> FullBlock<'a> {
                 block_entity
             ),
             position,
+            biome,
             world_ref: world_ref.clone(),
             null_flag: false,
         }
     }
 
     pub fn id(&self) -> &str { &self.name }
+    // With the namespace even if the block was made without one, the form palettes and the block index use
+    pub fn full_name(&self) -> Cow<'_, str> {
+        if self.name.contains(':') { Cow::Borrowed(&self.name) } else { Cow::Owned(format!("minecraft:{}", self.name)) }
+    }
     pub fn namespace(&self) -> &str { self.name.split(':').nth(0).unwrap_or("") }
     pub fn key(&self) -> &str {self.name.split(':').nth(1).unwrap_or("")}
     pub fn position(&self) -> &Position { &self.position }
-    pub fn states(&self) -> Rc<RefCell<BlockStates>> { self.extra.states() }
-    pub fn data(&self) -> Option<Rc<BlockEntity>> { self.extra.data() }
+    pub fn states(&self) -> Arc<RwLock<BlockStates>> { self.extra.states() }
+    pub fn data(&self) -> Option<Arc<BlockEntity>> { self.extra.data() }
+    pub fn biome(&self) -> Option<LassoString> { self.biome }
     pub fn palette_block(&self) -> PaletteBlock {
-        PaletteBlock::new(&self.name, Some(self.extra.states().borrow().all().raw().clone()))
+        PaletteBlock::new(&self.name, Some(self.extra.states().read().unwrap().all().raw().clone()))
     }
     pub fn parent_chunk(&self) -> Option<(ChunkType, usize)> {
         let mut world = self.world_ref.lock().unwrap();
@@ -74,26 +86,53 @@ impl<'a> FullBlock<'a> {
     pub fn set_id(&mut self, id: &str) { self.name = id.to_string(); }
     pub fn set_position(&mut self, position: Position) { self.position = position; }
     pub fn set_states(&mut self, states: BlockStates) { self.extra.set_states(states); }
+    pub fn set_data(&mut self, block_entity: Option<BlockEntity>) { self.extra.set_data(block_entity.map(Arc::new)); }
     pub fn delete(&mut self) { self.name = "minecraft:air".to_string(); }
+    // Becomes the pattern's block at the block's position, the block entity is kept
+    pub fn set_block(&mut self, pattern: impl Into<Pattern>) {
+        let block = pattern.into().block_at(self.position.x(), self.position.y(), self.position.z());
+        self.name = block.full_name().into_owned();
+        self.extra.set_states(BlockStates::new(Properties::new(block.properties().raw().clone())));
+    }
 
     pub fn commit(&self) -> bool {
         let mut world = self.world_ref.lock().unwrap();
         let ver_data = world.version();
         let (chunk_pos, block_index) = block_position_to_chunk_pos_and_block_index(self.position(), ver_data.data.chunk_size, ver_data.data.lowest_y);
 
-        let dim = world.dimension_mut(self.position.dimension());
-        if dim.is_none() { return false; }
+        let Some(chunk) = world.dimension_mut(self.position.dimension()).and_then(|dim| dim.chunk_mut(chunk_pos.position())) else { return false };
+        let mut locked_chunk = chunk.lock().unwrap();
+        self.write_into(&mut locked_chunk, block_index, world.history_mut())
+    }
 
-        if let Some(chunk) = dim.unwrap().chunk_mut(chunk_pos.position()) {
-            let mut locked_chunk = chunk.lock().unwrap();
-            locked_chunk.block_store_mut().set_block_at_index(block_index, self.palette_block())
-        } else { false }
+    // Writes the block and its block entity, if it's the one the block has, and records both. A block that becomes a
+    // different block loses its old block entity and tile ticks, one that needs a block entity gets the default one
+    pub(crate) fn write_into(&self, chunk: &mut Chunk, index: usize, history: &mut EditHistory) -> bool {
+        let Some(before) = chunk.block_store().indices_slice().get(index).map(|i| chunk.block_store().palette()[*i].clone()) else { return false };
+        let after = self.palette_block();
+        history.begin("set block");
+        let edit = chunk.edit_blocks_at_indices(&[index], std::slice::from_ref(&after), |_| 0);
+        history.record_chunk_edit(chunk, &edit);
+
+        if let Some(block_entity) = self.data() {
+            // a replaced block's old block entity and the default one it got were recorded with the edit
+            let previous = if before.full_name() == after.full_name() {
+                chunk.block_entities_store().get_at_world_position(&self.position).map(|b| (*b).clone())
+            } else { None };
+            if chunk.set_block_entity_at_index(index, (*block_entity).clone()) {
+                let placed = chunk.block_entities_store().get_at_world_position(&self.position).map(|b| (*b).clone());
+                history.record_block_entity(&self.position, previous, placed);
+            }
+        }
+        history.commit();
+        true
     }
 
 }
 
 pub struct BlockBuilder<'a> {
     underlying: FullBlock<'a>,
+    pattern: Option<Pattern>,
 }
 
 impl<'a> fmt::Debug for FullBlock<'a> {
@@ -101,7 +140,7 @@ impl<'a> fmt::Debug for FullBlock<'a> {
         f.debug_struct("Block")
             .field("name", &self.name)
             .field("position", &self.position)
-            .field("extra", &self.extra.states().borrow().all())
+            .field("extra", &self.extra.states().read().unwrap().all())
             .finish()
     }
 }
@@ -120,15 +159,114 @@ impl<'a> BlockBuilder<'a> {
     pub fn new(world_ref: WorldType<'a>) -> Self {
         BlockBuilder {
             underlying: FullBlock::new(&world_ref),
+            pattern: None,
         }
     }
 
     pub fn named(mut self, name: &str) -> Self { self.underlying.name = name.to_string(); self}
     pub fn at(mut self, pos: &Position) -> Self { self.underlying.position = pos.clone(); self }
     pub fn with_states(mut self, states: BlockStates) -> Self { self.underlying.extra.set_states(states); self}
-    pub fn with_property(mut self, path: &str, value: Value) -> Self { self.underlying.extra.states().borrow_mut().all_mut().set(path, value); self }
-    pub fn build(self) -> FullBlock<'a> {
+    pub fn with_property(mut self, path: &str, value: Value) -> Self { self.underlying.extra.states().write().unwrap().all_mut().set(path, value); self }
+    // Only placed on commit when it's the block entity of the block, it's moved to the block's position
+    pub fn with_block_entity(mut self, block_entity: BlockEntity) -> Self { self.underlying.set_data(Some(block_entity)); self }
+    // The block is the pattern's block at the position it ends up at, instead of a name and states
+    pub fn with_pattern(mut self, pattern: impl Into<Pattern>) -> Self { self.pattern = Some(pattern.into()); self }
+    pub fn build(mut self) -> FullBlock<'a> {
+        if let Some(pattern) = self.pattern { self.underlying.set_block(pattern); }
         self.underlying
     }
 }
 
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::process;
+    use crate::constants::constants::ZLIB_COMPRESSION_TYPE;
+    use crate::constants::versions::VersionManager;
+    use crate::loaders::loader_utils::{compress_zlib, handle_chunk_compression, read_region_chunks, write_region_file, RawRegionChunk};
+    use crate::models::block_entity::block_entity::GenericBlockEntity;
+    use crate::models::other::tick::Tick;
+    use crate::models::positions::chunk_position::ChunkPosition;
+    use crate::models::world::dimension::Dimension;
+    use crate::models::world::world::{SaveOptions, World};
+    use crate::traits::block_entity::BlockEntityTrait;
+    use crate::types::WorldKind;
+
+    fn pos(x: i32, y: i32, z: i32) -> Position { Position::new("overworld".into(), x, y, z) }
+
+    fn block_entity_ids(world: &WorldType) -> Vec<String> {
+        let world = world.lock().unwrap();
+        let chunk = world.dimension(&"overworld".into()).unwrap().chunk((0, 0)).unwrap();
+        let chunk = chunk.lock().unwrap();
+        chunk.block_entities_store().get_all().iter().map(|b| b.base().id().to_string()).collect()
+    }
+
+    #[test]
+    fn commit_keeps_block_entities_consistent() {
+        let directory = std::env::temp_dir().join(format!("cubicle_full_block_test_{}", process::id()));
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new(directory.clone(), version.clone());
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        dimension.set_chunk(Chunk::new(ChunkPosition::new(0, 0, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+        world.lock().unwrap().set_dimension("overworld".into(), dimension);
+
+        // a block that needs a block entity gets the default one, a given one is moved onto the block
+        assert!(BlockBuilder::new(world.clone()).named("minecraft:blast_furnace").at(&pos(1, 0, 1)).build().commit());
+        assert_eq!(block_entity_ids(&world), vec!["minecraft:blast_furnace"]);
+        let properties = Properties::new(HashMap::from([("CustomName".to_string(), Value::String("loot".to_string()))]));
+        let chest = BlockEntity::Other(GenericBlockEntity::new("minecraft:chest".to_string(), pos(9, 9, 9), properties));
+        assert!(BlockBuilder::new(world.clone()).named("minecraft:chest").at(&pos(2, 0, 1)).with_block_entity(chest.clone()).build().commit());
+        {
+            let locked = world.lock().unwrap();
+            let chunk = locked.dimension(&"overworld".into()).unwrap().chunk((0, 0)).unwrap();
+            let placed = chunk.lock().unwrap().block_entities_store().get_at_world_position(&pos(2, 0, 1)).unwrap();
+            assert!(placed.base().properties().get("CustomName").is_some());
+        }
+        // the block entity of another block isn't placed
+        assert!(BlockBuilder::new(world.clone()).named("minecraft:stone").at(&pos(3, 0, 1)).with_block_entity(chest).build().commit());
+        assert_eq!(block_entity_ids(&world).len(), 2);
+
+        // replacing the block removes its block entity, undoing brings it back
+        assert!(BlockBuilder::new(world.clone()).named("minecraft:stone").at(&pos(2, 0, 1)).build().commit());
+        assert_eq!(block_entity_ids(&world), vec!["minecraft:blast_furnace"]);
+        assert_eq!(world.lock().unwrap().undo().unwrap().as_deref(), Some("set block"));
+        let mut ids = block_entity_ids(&world);
+        ids.sort();
+        assert_eq!(ids, vec!["minecraft:blast_furnace", "minecraft:chest"]);
+
+        // the POI record of a replaced workstation is removed when the chunk is saved
+        let poi_path = directory.join("poi").join("r.0.0.mca");
+        fs::create_dir_all(poi_path.parent().unwrap()).unwrap();
+        let record = |x: i32| Value::Compound(HashMap::from([
+            ("pos".to_string(), Value::IntArray(fastnbt::IntArray::new(vec![x, 0, 1]))),
+            ("type".to_string(), Value::String("minecraft:armorer".to_string())),
+            ("free_tickets".to_string(), Value::Int(1)),
+        ]));
+        let poi = Value::Compound(HashMap::from([("Sections".to_string(), Value::Compound(HashMap::from([
+            ("0".to_string(), Value::Compound(HashMap::from([
+                ("Valid".to_string(), Value::Byte(1)),
+                ("Records".to_string(), Value::List(vec![record(1), record(5)])),
+            ]))),
+        ])))]));
+        let data = compress_zlib(&fastnbt::to_bytes(&poi).unwrap()).unwrap();
+        write_region_file(&poi_path, &[RawRegionChunk { index: 0, timestamp: 0, compression_type: ZLIB_COMPRESSION_TYPE, data }], false).unwrap();
+
+        // only blocks that have POI records are remembered, the chest replaced above isn't
+        assert!(BlockBuilder::new(world.clone()).named("minecraft:air").at(&pos(1, 0, 1)).build().commit());
+        assert_eq!(world.lock().unwrap().dimension(&"overworld".into()).unwrap().chunk((0, 0)).unwrap().lock().unwrap().replaced_blocks().len(), 1);
+        assert_eq!(world.lock().unwrap().save(&SaveOptions::new()).unwrap(), 1);
+        let raw = read_region_chunks(&poi_path).unwrap().remove(0);
+        let saved: Value = fastnbt::from_bytes(&handle_chunk_compression(raw.compression_type, raw.data).unwrap()).unwrap();
+        let Value::Compound(saved) = saved else { panic!() };
+        let Some(Value::Compound(sections)) = saved.get("Sections") else { panic!() };
+        let Some(Value::Compound(section)) = sections.get("0") else { panic!() };
+        assert!(matches!(section.get("Valid"), Some(Value::Byte(0))));
+        assert!(matches!(section.get("Records"), Some(Value::List(records)) if records.len() == 1));
+        assert!(world.lock().unwrap().dimension(&"overworld".into()).unwrap().chunk((0, 0)).unwrap().lock().unwrap().replaced_blocks().is_empty());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -27,28 +27,38 @@ impl<'a> FullEntity<'a> {
         }
     }
 
+    // From an entity that was read already, doesn't lock the world
+    pub fn from_entity(world_ref: &WorldType<'a>, entity: Entity, entity_key: EntityStoreKey, dimension_id: LassoString) -> Self {
+        Self { entity, world_ref: world_ref.clone(), dimension_id, entity_key }
+    }
+
     pub fn entity(&self) -> &Entity { &self.entity }
     pub fn entity_mut(&mut self) -> &mut Entity { &mut self.entity }
 
     pub fn remove(&self) -> bool {
         let mut world = self.world_ref.lock().unwrap();
-        match world.dimension_mut(&self.dimension_id) {
-            Some(dim) => {
-                dim.entity_store_mut().remove(&self.entity_key);
-                true
-            }
-            None => { false }
-        }
+        let Some(dim) = world.dimension_mut(&self.dimension_id) else { return false };
+        let Some(removed) = dim.remove_entity(self.entity_key) else { return false };
+
+        let history = world.history_mut();
+        history.begin("remove entity");
+        history.record_entity(self.dimension_id, removed.base().uuid(), Some(removed), None);
+        history.commit();
+        true
     }
 
     pub fn commit(&self) -> bool {
         let mut world = self.world_ref.lock().unwrap();
-        match world.dimension_mut(&self.dimension_id) {
-            Some(dim) => {
-                dim.entity_store_mut().set(&self.entity_key, self.entity.clone())
-            }
-            None => { false }
-        }
+        let Some(dim) = world.dimension_mut(&self.dimension_id) else { return false };
+        if self.entity_key >= dim.entity_store().count() { return false; }
+        let before = dim.entity_store().get(self.entity_key).clone();
+        dim.set_entity(self.entity_key, self.entity.clone());
+
+        let history = world.history_mut();
+        history.begin("edit entity");
+        history.record_entity(self.dimension_id, before.base().uuid(), Some(before), Some(self.entity.clone()));
+        history.commit();
+        true
     }
 
 }
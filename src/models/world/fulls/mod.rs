@@ -2,4 +2,4 @@ pub mod full_block;
 pub mod full_entity;
 mod block_data;
 mod block_states;
-mod block_state_types;
\ No newline at end of file
+pub mod block_state_types;
\ No newline at end of file
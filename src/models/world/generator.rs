@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use fastnbt::Value;
+use crate::constants::blocks::block_entity_id;
+use crate::constants::versions::Version;
+use crate::models::block_entity::block_entity::BlockEntity;
+use crate::models::other::tick::Tick;
+use crate::models::positions::chunk_position::ChunkPosition;
+use crate::models::world::block::PaletteBlock;
+use crate::models::world::chunk::Chunk;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PresetParseError {
+    pub message: String,
+}
+
+impl fmt::Display for PresetParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}", self.message) }
+}
+
+impl std::error::Error for PresetParseError {}
+
+fn error<T>(message: impl Into<String>) -> Result<T, PresetParseError> {
+    Err(PresetParseError { message: message.into() })
+}
+
+fn namespaced(id: &str) -> String {
+    if id.contains(':') { id.to_string() } else { format!("minecraft:{}", id) }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlatLayer {
+    block: PaletteBlock,
+    height: u32,
+}
+
+impl FlatLayer {
+    pub fn new(block: PaletteBlock, height: u32) -> Self { Self { block, height } }
+
+    pub fn block(&self) -> &PaletteBlock { &self.block }
+    pub fn height(&self) -> u32 { self.height }
+}
+
+// What chunks that aren't on disk are made of: flat layers from the bottom of the world up and one biome everywhere.
+// No layers is a void world. Nothing else generates, no structures, features or entities
+#[derive(Debug, Clone)]
+pub struct ChunkGenerator {
+    layers: Vec<FlatLayer>,
+    biome: String,
+}
+
+impl ChunkGenerator {
+    pub fn void() -> Self { Self { layers: Vec::new(), biome: "minecraft:the_void".to_string() } }
+    pub fn flat(layers: Vec<FlatLayer>) -> Self { Self { layers, biome: "minecraft:plains".to_string() } }
+    // The game's default superflat world
+    pub fn classic_flat() -> Self {
+        Self::flat(vec![
+            FlatLayer::new(PaletteBlock::new("minecraft:bedrock", None), 1),
+            FlatLayer::new(PaletteBlock::new("minecraft:dirt", None), 2),
+            FlatLayer::new(PaletteBlock::new("minecraft:grass_block", None), 1),
+        ])
+    }
+
+    // A superflat preset like the game's customize screen takes, "minecraft:bedrock,2*minecraft:dirt,minecraft:grass_block;minecraft:plains".
+    // Layers go bottom up, the biome is optional and whatever follows it (the old structure options) is ignored
+    pub fn from_preset(preset: &str) -> Result<Self, PresetParseError> {
+        let mut parts = preset.trim().split(';');
+        let layers = parts.next().unwrap_or("").trim();
+        let mut generator = Self::flat(Vec::new());
+        if !layers.is_empty() {
+            for layer in layers.split(',') {
+                let layer = layer.trim();
+                let (height, block) = match layer.split_once('*') {
+                    Some((height, block)) => match height.trim().parse::<u32>() {
+                        Ok(height) if height > 0 => (height, block.trim()),
+                        _ => return error(format!("invalid layer height in '{}'", layer)),
+                    },
+                    None => (1, layer),
+                };
+                if block.is_empty() || block.contains(['[', '{', ' ']) { return error(format!("invalid layer block '{}'", layer)); }
+                generator.layers.push(FlatLayer::new(PaletteBlock::new(&namespaced(block), None), height));
+            }
+        }
+        if let Some(biome) = parts.next().map(str::trim).filter(|b| !b.is_empty()) { generator.biome = namespaced(biome); }
+        Ok(generator)
+    }
+
+    pub fn with_biome(mut self, biome: &str) -> Self { self.biome = namespaced(biome); self }
+    pub fn with_layer(mut self, layer: FlatLayer) -> Self { self.layers.push(layer); self }
+
+    pub fn layers(&self) -> &Vec<FlatLayer> { &self.layers }
+    pub fn biome(&self) -> &str { &self.biome }
+    pub fn is_void(&self) -> bool { self.layers.is_empty() }
+    pub fn height(&self) -> u32 { self.layers.iter().map(|l| l.height).sum() }
+
+    pub fn preset(&self) -> String {
+        let layers = self.layers.iter()
+            .map(|l| if l.height == 1 { l.block.full_name().to_string() } else { format!("{}*{}", l.height, l.block.full_name()) })
+            .collect::<Vec<_>>();
+        format!("{};{}", layers.join(","), self.biome)
+    }
+
+    // The generator compound level.dat keeps per dimension (Data.WorldGenSettings.dimensions), so the game makes the
+    // chunks that aren't on disk the same way
+    pub fn level_settings(&self) -> Value {
+        let layers = self.layers.iter().map(|l| Value::Compound(HashMap::from([
+            ("block".to_string(), Value::String(l.block.full_name().to_string())),
+            ("height".to_string(), Value::Int(l.height as i32)),
+        ]))).collect();
+        let settings = HashMap::from([
+            ("biome".to_string(), Value::String(self.biome.clone())),
+            ("features".to_string(), Value::Byte(0)),
+            ("lakes".to_string(), Value::Byte(0)),
+            ("layers".to_string(), Value::List(layers)),
+            ("structure_overrides".to_string(), Value::List(Vec::new())),
+        ]);
+        Value::Compound(HashMap::from([
+            ("type".to_string(), Value::String("minecraft:flat".to_string())),
+            ("settings".to_string(), Value::Compound(settings)),
+        ]))
+    }
+
+    // A finished chunk: heightmaps are calculated and it's marked full, so the game loads it as it is. Layers past the
+    // top of the world are cut off
+    pub fn generate(&self, position: ChunkPosition, version: &Arc<Version>) -> Chunk {
+        let mut chunk = Chunk::new(position, version.data.version_data, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), version);
+        let (block_store, biome_store, _, _) = chunk.stores_mut();
+        let biome = biome_store.add_biome_to_palette(self.biome.clone());
+        biome_store.indices_slice_mut().fill(biome);
+
+        let layer_size = (block_store.chunk_size() * block_store.chunk_size()) as usize;
+        let block_count = block_store.indices_slice().len();
+        let mut start = 0;
+        let mut block_entity_layers = Vec::new();
+        for layer in &self.layers {
+            let end = (start + layer.height as usize * layer_size).min(block_count);
+            let palette_index = block_store.add_block_to_palette(layer.block.clone());
+            block_store.indices_slice_mut()[start..end].fill(palette_index);
+            block_entity_layers.push((start..end, layer.block.full_name().to_string()));
+            start = end;
+        }
+        // layers of blocks with a block entity are unusual enough to not bother with bulk inserts. Every position is
+        // only filled once and the chunk started without block entities, so they don't have to be looked up
+        for (indices, block) in block_entity_layers.into_iter().filter(|(_, block)| block_entity_id(block).is_some()) {
+            for index in indices {
+                let block_entity = BlockEntity::default_for(&block, chunk.block_position(index));
+                unsafe { chunk.block_entities_store_mut().add_unchecked(block_entity.unwrap()); }
+            }
+        }
+        chunk.recalculate_heightmaps();
+        chunk
+    }
+}
+
+impl fmt::Display for ChunkGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}", self.preset()) }
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::process;
+    use crate::loaders::loader_utils::{compress_gzip, uncompress_gzip};
+    use crate::constants::versions::VersionManager;
+    use crate::models::positions::whole_position::Position;
+    use crate::models::world::fulls::full_block::BlockBuilder;
+    use crate::models::world::selection::SelectionBuilder;
+    use crate::models::world::world::{SaveOptions, World};
+    use crate::traits::access::prelude::BlockReader;
+    use crate::types::{HeightmapKind, WorldKind};
+
+    #[test]
+    fn generates_flat_and_void_chunks() {
+        let generator = ChunkGenerator::from_preset("minecraft:bedrock,2*dirt,minecraft:grass_block;desert;village").unwrap();
+        assert_eq!(generator.height(), 4);
+        assert_eq!(generator.biome(), "minecraft:desert");
+        assert_eq!(generator.preset(), "minecraft:bedrock,2*minecraft:dirt,minecraft:grass_block;minecraft:desert");
+        assert_eq!(ChunkGenerator::from_preset(&generator.preset()).unwrap().layers(), generator.layers());
+        assert!(ChunkGenerator::from_preset("0*minecraft:stone").is_err());
+        assert!(ChunkGenerator::from_preset("minecraft:stone,,minecraft:dirt").is_err());
+        assert!(ChunkGenerator::from_preset(";minecraft:the_void").unwrap().is_void());
+
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let chunk = generator.generate(ChunkPosition::new(3, -2, "overworld".into()), &version);
+        assert_eq!(chunk.status(), "minecraft:full");
+        assert_eq!(chunk.block_store().get_block_at_index(0).unwrap().id(), "bedrock");
+        assert_eq!(chunk.block_store().get_block_at_index(3 * 256 + 17).unwrap().id(), "grass_block");
+        assert!(chunk.block_store().get_block_at_index(4 * 256).is_none());
+        assert_eq!(chunk.heightmap_store().get_kind(HeightmapKind::MotionBlocking).get_highest_y_at_position(5, 9), -61);
+        assert_eq!(chunk.biome_store().get_biome_at_index(100).as_deref(), Some("minecraft:desert"));
+
+        // with a generator every chunk is available, selections generate the ones they touch
+        let world = World::new("".into(), version.clone());
+        let mut locked = world.lock().unwrap();
+        locked.set_generator(Some(ChunkGenerator::classic_flat()));
+        let pos = |x, y, z| Position::new("overworld".into(), x, y, z);
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(-20, -64, -20), &pos(20, -50, 20)).build();
+        assert_eq!(selection.chunk_count(), 16);
+        assert_eq!(selection.block_at_position(pos(-20, -61, 20)).unwrap().id(), "minecraft:grass_block");
+        assert!(selection.block_at_position(pos(7, -60, 7)).is_none());
+        drop(selection);
+        assert_eq!(locked.dimension(&"overworld".into()).unwrap().chunk_count(), 2);
+
+        let void = locked.replace_chunk(ChunkPosition::new(0, 0, "the_end".into()), &ChunkGenerator::void());
+        assert!(void.lock().unwrap().block_store().palette_indices().all(|i| i == 0));
+        assert!(locked.dimension(&"the_end".into()).is_some());
+        assert!(!locked.chunk_available(&ChunkPosition::new(0, 0, "nowhere".into())));
+        assert!(locked.load_chunk(&ChunkPosition::new(0, 0, "nowhere".into())).unwrap().is_none());
+
+        // the older form makes a chunk with one layer of the block, only in loaded dimensions
+        let stone = || BlockBuilder::new(world.clone()).named("minecraft:stone").build();
+        let chunk = locked.set_chunk(ChunkPosition::new(0, 0, "the_end".into()), stone()).unwrap();
+        assert_eq!(chunk.lock().unwrap().block_store().get_block_at_index(255).unwrap().id(), "stone");
+        assert!(chunk.lock().unwrap().block_store().get_block_at_index(256).is_none());
+        assert!(locked.set_chunk(ChunkPosition::new(0, 0, "nowhere".into()), stone()).is_none());
+    }
+
+    #[test]
+    fn saving_writes_the_generator_into_level_dat() {
+        let directory = std::env::temp_dir().join(format!("cubicle_generator_test_{}", process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let dimension = |kind: &str, generator: &str| Value::Compound(HashMap::from([
+            ("type".to_string(), Value::String(kind.to_string())),
+            ("generator".to_string(), Value::Compound(HashMap::from([("type".to_string(), Value::String(generator.to_string()))]))),
+        ]));
+        let level = HashMap::from([("Data".to_string(), Value::Compound(HashMap::from([
+            ("LevelName".to_string(), Value::String("kept".to_string())),
+            ("WorldGenSettings".to_string(), Value::Compound(HashMap::from([
+                ("seed".to_string(), Value::Long(5)),
+                ("dimensions".to_string(), Value::Compound(HashMap::from([
+                    ("minecraft:overworld".to_string(), dimension("minecraft:overworld", "minecraft:noise")),
+                    ("minecraft:the_nether".to_string(), dimension("minecraft:the_nether", "minecraft:flat")),
+                ]))),
+            ]))),
+        ])))]);
+        fs::write(directory.join("level.dat"), compress_gzip(&fastnbt::to_bytes(&level).unwrap()).unwrap()).unwrap();
+
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new(directory.clone(), version.clone());
+        let mut locked = world.lock().unwrap();
+        locked.set_generator(Some(ChunkGenerator::classic_flat()));
+        locked.generate_chunk(&ChunkPosition::new(0, 0, "overworld".into())).unwrap();
+        locked.generate_chunk(&ChunkPosition::new(0, 0, "the_nether".into())).unwrap();
+        assert_eq!(locked.save(&SaveOptions::new()).unwrap(), 2);
+
+        let saved: Value = fastnbt::from_bytes(&uncompress_gzip(fs::read(directory.join("level.dat")).unwrap()).unwrap()).unwrap();
+        let get = |value: &Value, key: &str| match value { Value::Compound(c) => c.get(key).cloned().unwrap(), _ => panic!() };
+        let data = get(&saved, "Data");
+        assert_eq!(get(&data, "LevelName"), Value::String("kept".to_string()));
+        let settings = get(&data, "WorldGenSettings");
+        assert_eq!(get(&settings, "seed"), Value::Long(5));
+        let dimensions = get(&settings, "dimensions");
+        // a noise world isn't made flat, an older flat generator is replaced and the end made no chunks
+        assert_eq!(get(&dimensions, "minecraft:overworld"), dimension("minecraft:overworld", "minecraft:noise"));
+        let nether = get(&dimensions, "minecraft:the_nether");
+        assert_eq!(get(&nether, "type"), Value::String("minecraft:the_nether".to_string()));
+        assert_eq!(get(&nether, "generator"), ChunkGenerator::classic_flat().level_settings());
+        assert!(matches!(&dimensions, Value::Compound(c) if !c.contains_key("minecraft:the_end")));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,703 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::models::block_entity::block_entity::BlockEntity;
+use crate::models::entity::entity::Entity;
+use crate::models::other::lasso_string::LassoString;
+use crate::models::positions::chunk_position::ChunkPosition;
+use crate::models::positions::whole_position::Position;
+use crate::models::world::block::PaletteBlock;
+use crate::models::world::chunk::{Chunk, ChunkEdit};
+use crate::models::world::tile_tick::TileTick;
+use crate::models::world::world::World;
+use crate::traits::block_entity::BlockEntityTrait;
+use crate::utils::position_utils::{block_index_to_block_position, block_position_to_chunk_pos_and_block_index};
+
+const BLOCK_CHANGE_SIZE: usize = 24;
+const DEFAULT_MAX_TRANSACTIONS: usize = 64;
+const DEFAULT_MAX_CHANGES: usize = 4_000_000; // ~100MB of block changes
+static SPILL_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Blocks are stored as ids into the transaction's own palette so a change is a fixed 24 bytes, which is also how
+// they're written when spilled to disk
+#[derive(Debug, Clone, Copy)]
+struct BlockChange {
+    dimension: u32,
+    x: i32,
+    y: i32,
+    z: i32,
+    before: u32,
+    after: u32,
+}
+
+impl BlockChange {
+    fn to_bytes(self) -> [u8; BLOCK_CHANGE_SIZE] {
+        let mut bytes = [0; BLOCK_CHANGE_SIZE];
+        for (i, value) in [self.dimension, self.x as u32, self.y as u32, self.z as u32, self.before, self.after].into_iter().enumerate() {
+            bytes[i * 4..i * 4 + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let value = |i: usize| u32::from_le_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap());
+        Self { dimension: value(0), x: value(1) as i32, y: value(2) as i32, z: value(3) as i32, before: value(4), after: value(5) }
+    }
+
+    fn key(&self) -> (u32, i32, i32, i32) { (self.dimension, self.x, self.y, self.z) }
+}
+
+#[derive(Debug)]
+struct BlockEntityChange {
+    position: Position,
+    before: Option<BlockEntity>,
+    after: Option<BlockEntity>,
+}
+
+#[derive(Debug)]
+struct TileTickChange {
+    position: Position,
+    before: Option<TileTick>,
+    after: Option<TileTick>,
+}
+
+#[derive(Debug)]
+struct EntityChange {
+    dimension: LassoString,
+    uuid: u128,
+    before: Option<Entity>,
+    after: Option<Entity>,
+}
+
+#[derive(Debug)]
+pub enum HistoryError {
+    Read(io::Error), // the spilled block changes couldn't be read back
+    ChunkNotLoaded(ChunkPosition),
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryError::Read(e) => write!(f, "failed to read the spilled edit history: {}", e),
+            HistoryError::ChunkNotLoaded(chunk) => write!(f, "chunk {}, {} in {} isn't loaded", chunk.x(), chunk.z(), chunk.dimension()),
+        }
+    }
+}
+
+impl Error for HistoryError {}
+
+// One named group of edits, undone and redone as a whole. Changes are kept in the order they were made
+#[derive(Debug)]
+pub struct Transaction {
+    name: String,
+    dimensions: Vec<LassoString>,
+    palette: Vec<PaletteBlock>,
+    blocks: Vec<BlockChange>,
+    spill_file: Option<PathBuf>, // older block changes, blocks holds the ones after them
+    spilled: usize,
+    block_entities: Vec<BlockEntityChange>,
+    tile_ticks: Vec<TileTickChange>,
+    entities: Vec<EntityChange>,
+    overflowed: bool, // went past max_changes on its own, its changes are dropped and it isn't kept
+}
+
+impl Transaction {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            dimensions: Vec::new(),
+            palette: Vec::new(),
+            blocks: Vec::new(),
+            spill_file: None,
+            spilled: 0,
+            block_entities: Vec::new(),
+            tile_ticks: Vec::new(),
+            entities: Vec::new(),
+            overflowed: false,
+        }
+    }
+
+    pub fn name(&self) -> &str { &self.name }
+    pub fn block_change_count(&self) -> usize { self.spilled + self.blocks.len() }
+    pub fn block_entity_change_count(&self) -> usize { self.block_entities.len() }
+    pub fn tile_tick_change_count(&self) -> usize { self.tile_ticks.len() }
+    pub fn entity_change_count(&self) -> usize { self.entities.len() }
+    pub fn is_empty(&self) -> bool { self.block_change_count() == 0 && self.block_entities.is_empty() && self.tile_ticks.is_empty() && self.entities.is_empty() }
+    fn in_memory_changes(&self) -> usize { self.blocks.len() + self.block_entities.len() + self.tile_ticks.len() + self.entities.len() }
+
+    fn palette_id(&mut self, block: &PaletteBlock) -> u32 {
+        match self.palette.iter().position(|b| b == block) {
+            Some(id) => id as u32,
+            None => { self.palette.push(block.clone()); (self.palette.len() - 1) as u32 }
+        }
+    }
+
+    fn dimension_id(&mut self, dimension: &LassoString) -> u32 {
+        match self.dimensions.iter().position(|d| d == dimension) {
+            Some(id) => id as u32,
+            None => { self.dimensions.push(*dimension); (self.dimensions.len() - 1) as u32 }
+        }
+    }
+
+    fn record_block(&mut self, position: &Position, before: &PaletteBlock, after: &PaletteBlock) {
+        let change = BlockChange {
+            dimension: self.dimension_id(position.dimension()),
+            x: position.x(), y: position.y(), z: position.z(),
+            before: self.palette_id(before),
+            after: self.palette_id(after),
+        };
+        self.blocks.push(change);
+    }
+
+    fn record_chunk_edit(&mut self, chunk: &Chunk, edit: &ChunkEdit) {
+        let store = chunk.block_store();
+        let dimension = self.dimension_id(chunk.position().dimension());
+        let mut ids: Vec<Option<u32>> = vec![None; store.palette().len()];
+        for (index, old, new) in edit.changes() {
+            let before = *ids[*old].get_or_insert_with(|| self.palette_id(&store.palette()[*old]));
+            let after = *ids[*new].get_or_insert_with(|| self.palette_id(&store.palette()[*new]));
+            let position = block_index_to_block_position(chunk.position(), *index, store.chunk_size(), store.lowest_y());
+            self.blocks.push(BlockChange { dimension, x: position.x(), y: position.y(), z: position.z(), before, after });
+        }
+        for block_entity in edit.removed_block_entities() {
+            let position = block_entity.base().position().clone();
+            self.block_entities.push(BlockEntityChange { position, before: Some((**block_entity).clone()), after: None });
+        }
+        // after the removed ones, redo puts the defaults back once the old block entities are gone
+        for block_entity in edit.added_block_entities() {
+            let position = block_entity.base().position().clone();
+            self.block_entities.push(BlockEntityChange { position, before: None, after: Some((**block_entity).clone()) });
+        }
+        for tile_tick in edit.removed_tile_ticks() {
+            self.tile_ticks.push(TileTickChange { position: tile_tick.position().clone(), before: Some(tile_tick.clone()), after: None });
+        }
+    }
+
+    fn overflow(&mut self) {
+        if let Some(path) = self.spill_file.take() { let _ = fs::remove_file(path); }
+        self.palette.clear();
+        self.blocks = Vec::new();
+        self.spilled = 0;
+        self.block_entities = Vec::new();
+        self.tile_ticks = Vec::new();
+        self.entities = Vec::new();
+        self.overflowed = true;
+    }
+
+    fn spill(&mut self, directory: &Path) -> io::Result<()> {
+        let path = match &self.spill_file {
+            Some(path) => path.clone(),
+            None => {
+                fs::create_dir_all(directory)?;
+                let counter = SPILL_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+                directory.join(format!("cubicle_history_{}_{}.bin", process::id(), counter))
+            }
+        };
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let mut bytes = Vec::with_capacity(self.blocks.len() * BLOCK_CHANGE_SIZE);
+        for change in &self.blocks { bytes.extend_from_slice(&change.to_bytes()); }
+        file.write_all(&bytes)?;
+
+        self.spill_file = Some(path);
+        self.spilled += self.blocks.len();
+        self.blocks.clear();
+        Ok(())
+    }
+
+    fn all_block_changes(&self) -> io::Result<Vec<BlockChange>> {
+        let mut changes = Vec::with_capacity(self.block_change_count());
+        if let Some(path) = &self.spill_file {
+            let bytes = fs::read(path)?;
+            changes.extend(bytes.chunks_exact(BLOCK_CHANGE_SIZE).map(BlockChange::from_bytes));
+        }
+        changes.extend_from_slice(&self.blocks);
+        Ok(changes)
+    }
+
+    // Every chunk the transaction changes something in
+    fn chunks(&self, changes: &[BlockChange], chunk_size: i32) -> HashSet<ChunkPosition> {
+        let chunk_of = |dimension: &LassoString, x: i32, z: i32| {
+            ChunkPosition::new(x.div_euclid(chunk_size), z.div_euclid(chunk_size), *dimension)
+        };
+        let mut chunks = changes.iter().map(|c| chunk_of(&self.dimensions[c.dimension as usize], c.x, c.z)).collect::<HashSet<_>>();
+        chunks.extend(self.block_entities.iter().map(|c| chunk_of(c.position.dimension(), c.position.x(), c.position.z())));
+        chunks.extend(self.tile_ticks.iter().map(|c| chunk_of(c.position.dimension(), c.position.x(), c.position.z())));
+        for change in &self.entities {
+            for entity in change.before.iter().chain(change.after.iter()) {
+                let position = entity.base().position();
+                chunks.insert(chunk_of(&change.dimension, position.i_x(), position.i_z()));
+            }
+        }
+        chunks
+    }
+
+    // Puts everything back to how it was before the transaction (undo) or after it (redo). Nothing is written if the
+    // spilled changes can't be read back or a chunk it changed isn't loaded anymore
+    fn apply(&self, world: &mut World, undo: bool) -> Result<(), HistoryError> {
+        let changes = self.all_block_changes().map_err(HistoryError::Read)?;
+        let version = world.version();
+        let (chunk_size, lowest_y) = (version.data.chunk_size, version.data.lowest_y);
+        let mut chunks = self.chunks(&changes, chunk_size).into_iter().collect::<Vec<_>>();
+        chunks.sort_by_key(|c| (c.x(), c.z()));
+        if let Some(missing) = chunks.into_iter().find(|c| world.dimension(c.dimension()).and_then(|d| d.chunk(c.position())).is_none()) {
+            return Err(HistoryError::ChunkNotLoaded(missing));
+        }
+
+        // the state a block ends up in is its first before when undoing and its last after when redoing
+        let mut targets = HashMap::new();
+        if undo { for change in changes.iter().rev() { targets.insert(change.key(), change.before); } }
+        else { for change in changes.iter() { targets.insert(change.key(), change.after); } }
+
+        let mut per_chunk: HashMap<(u32, (i32, i32)), HashMap<usize, usize>> = HashMap::new();
+        for ((dimension, x, y, z), id) in targets {
+            let position = Position::new(self.dimensions[dimension as usize], x, y, z);
+            let (chunk_pos, index) = block_position_to_chunk_pos_and_block_index(&position, chunk_size, lowest_y);
+            per_chunk.entry((dimension, chunk_pos.position())).or_default().insert(index, id as usize);
+        }
+        for ((dimension, chunk_pos), blocks) in per_chunk {
+            let Some(chunk) = world.dimension(&self.dimensions[dimension as usize]).and_then(|d| d.chunk(chunk_pos)) else { continue };
+            let indices = blocks.keys().copied().collect::<Vec<_>>();
+            chunk.lock().unwrap().set_blocks_at_indices(&indices, &self.palette, |index| blocks[&index]);
+        }
+
+        let block_entities: Box<dyn Iterator<Item = &BlockEntityChange>> =
+            if undo { Box::new(self.block_entities.iter().rev()) } else { Box::new(self.block_entities.iter()) };
+        for change in block_entities {
+            let (chunk_pos, _) = block_position_to_chunk_pos_and_block_index(&change.position, chunk_size, lowest_y);
+            let Some(chunk) = world.dimension(chunk_pos.dimension()).and_then(|d| d.chunk(chunk_pos.position())) else { continue };
+            let mut chunk = chunk.lock().unwrap();
+            match if undo { &change.before } else { &change.after } {
+                Some(block_entity) => chunk.block_entities_store_mut().set_at_world_position(block_entity.clone()),
+                None => { chunk.block_entities_store_mut().remove_at_world_position(&change.position); }
+            }
+        }
+
+        let tile_ticks: Box<dyn Iterator<Item = &TileTickChange>> =
+            if undo { Box::new(self.tile_ticks.iter().rev()) } else { Box::new(self.tile_ticks.iter()) };
+        for change in tile_ticks {
+            let (chunk_pos, _) = block_position_to_chunk_pos_and_block_index(&change.position, chunk_size, lowest_y);
+            let Some(chunk) = world.dimension(chunk_pos.dimension()).and_then(|d| d.chunk(chunk_pos.position())) else { continue };
+            let mut chunk = chunk.lock().unwrap();
+            match if undo { &change.before } else { &change.after } {
+                Some(tile_tick) => chunk.set_tile_tick(tile_tick.clone()),
+                None => { chunk.remove_tile_tick_at(&change.position); }
+            }
+        }
+
+        let entities: Box<dyn Iterator<Item = &EntityChange>> =
+            if undo { Box::new(self.entities.iter().rev()) } else { Box::new(self.entities.iter()) };
+        for change in entities {
+            let Some(dimension) = world.dimension_mut(&change.dimension) else { continue };
+            // removed and added again rather than set, the entity may be in another chunk in the target state
+            if let Some(key) = dimension.entity_key_by_uuid(change.uuid) { dimension.remove_entity(key); }
+            if let Some(entity) = if undo { &change.before } else { &change.after } { dimension.add_entity(entity.clone()); }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if let Some(path) = &self.spill_file { let _ = fs::remove_file(path); }
+    }
+}
+
+// Undo/redo journal of a world. Edits made through Selection, FullBlock, FullEntity and Clipboard record what they
+// change into the open transaction; begin/commit nest, so a named outer transaction swallows the inner ones. The
+// journal keeps at most max_transactions and drops the oldest ones past max_changes changes kept in memory, the open
+// transaction included. With a spill directory, a transaction's block changes past the threshold (or the limit) are
+// moved to a file instead. A transaction that doesn't fit on its own can't be undone, it's dropped and stops recording
+pub struct EditHistory {
+    enabled: bool,
+    undo: VecDeque<Transaction>,
+    redo: Vec<Transaction>,
+    current: Option<Transaction>,
+    depth: usize,
+
+    max_transactions: usize,
+    max_changes: usize,
+    spill: Option<(PathBuf, usize)>, // (directory, in memory block changes before spilling)
+    spill_error: Option<io::Error>,
+}
+
+impl EditHistory {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            current: None,
+            depth: 0,
+            max_transactions: DEFAULT_MAX_TRANSACTIONS,
+            max_changes: DEFAULT_MAX_CHANGES,
+            spill: None,
+            spill_error: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool { self.enabled }
+    pub fn is_recording(&self) -> bool { self.current.is_some() }
+    pub fn can_undo(&self) -> bool { !self.undo.is_empty() }
+    pub fn can_redo(&self) -> bool { !self.redo.is_empty() }
+    pub fn undo_names(&self) -> Vec<&str> { self.undo.iter().rev().map(|t| t.name()).collect() }
+    pub fn redo_names(&self) -> Vec<&str> { self.redo.iter().rev().map(|t| t.name()).collect() }
+    pub fn max_transactions(&self) -> usize { self.max_transactions }
+    pub fn max_changes(&self) -> usize { self.max_changes }
+    pub fn in_memory_changes(&self) -> usize { self.undo.iter().chain(self.redo.iter()).chain(self.current.iter()).map(|t| t.in_memory_changes()).sum() }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled { self.clear(); }
+    }
+    pub fn set_max_transactions(&mut self, max_transactions: usize) { self.max_transactions = max_transactions.max(1); self.trim(); }
+    pub fn set_max_changes(&mut self, max_changes: usize) { self.max_changes = max_changes; self.trim(); }
+    pub fn set_spill(&mut self, directory: Option<PathBuf>, threshold: usize) { self.spill = directory.map(|d| (d, threshold.max(1))); }
+    // Why spilling stopped, if it failed. The changes it couldn't write are kept in memory
+    pub fn take_spill_error(&mut self) -> Option<io::Error> { self.spill_error.take() }
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.current = None;
+        self.depth = 0;
+    }
+
+    pub fn begin(&mut self, name: &str) {
+        if !self.enabled { return; }
+        self.depth += 1;
+        if self.current.is_none() { self.current = Some(Transaction::new(name)); }
+    }
+
+    // Closes the innermost begin, the transaction is kept once the outermost one is closed
+    pub fn commit(&mut self) {
+        if self.depth == 0 { return; }
+        self.depth -= 1;
+        if self.depth > 0 { return; }
+        let Some(transaction) = self.current.take() else { return };
+        if transaction.overflowed { self.redo.clear(); return; }
+        if transaction.is_empty() { return; }
+        self.redo.clear();
+        self.undo.push_back(transaction);
+        self.trim();
+    }
+
+    // Commits whatever is open, however deep
+    pub fn commit_all(&mut self) {
+        if self.depth > 0 { self.depth = 1; self.commit(); }
+    }
+
+    fn trim(&mut self) {
+        while self.undo.len() > self.max_transactions { self.undo.pop_front(); }
+        while self.in_memory_changes() > self.max_changes {
+            if self.undo.pop_front().is_none() && self.redo.pop().is_none() { break; }
+        }
+    }
+
+    // The open transaction being recorded into, None when there's none or it overflowed
+    fn recording(&mut self) -> Option<&mut Transaction> {
+        self.current.as_mut().filter(|t| !t.overflowed)
+    }
+
+    // Spills at the threshold, past max_changes the open transaction is spilled first, then the oldest ones are
+    // dropped and last the open one itself
+    fn recorded(&mut self) {
+        let over = self.in_memory_changes() > self.max_changes;
+        if let (Some((directory, threshold)), Some(transaction)) = (&self.spill, &mut self.current)
+            && (transaction.blocks.len() >= *threshold || (over && !transaction.blocks.is_empty()))
+            && let Err(e) = transaction.spill(directory) {
+            self.spill = None;
+            self.spill_error = Some(e);
+        }
+        if !over { return; }
+        self.trim();
+        if self.in_memory_changes() <= self.max_changes { return; }
+        if let Some(transaction) = &mut self.current { transaction.overflow(); }
+    }
+
+    pub fn record_block(&mut self, position: &Position, before: &PaletteBlock, after: &PaletteBlock) {
+        if before == after { return; }
+        let Some(transaction) = self.recording() else { return };
+        transaction.record_block(position, before, after);
+        self.recorded();
+    }
+
+    pub fn record_chunk_edit(&mut self, chunk: &Chunk, edit: &ChunkEdit) {
+        let Some(transaction) = self.recording() else { return };
+        transaction.record_chunk_edit(chunk, edit);
+        self.recorded();
+    }
+
+    pub fn record_block_entity(&mut self, position: &Position, before: Option<BlockEntity>, after: Option<BlockEntity>) {
+        if before.is_none() && after.is_none() { return; }
+        let Some(transaction) = self.recording() else { return };
+        transaction.block_entities.push(BlockEntityChange { position: position.clone(), before, after });
+        self.recorded();
+    }
+
+    pub fn record_tile_tick(&mut self, position: &Position, before: Option<TileTick>, after: Option<TileTick>) {
+        if before.is_none() && after.is_none() { return; }
+        let Some(transaction) = self.recording() else { return };
+        transaction.tile_ticks.push(TileTickChange { position: position.clone(), before, after });
+        self.recorded();
+    }
+
+    pub fn record_entity(&mut self, dimension: LassoString, uuid: u128, before: Option<Entity>, after: Option<Entity>) {
+        if before.is_none() && after.is_none() { return; }
+        let Some(transaction) = self.recording() else { return };
+        transaction.entities.push(EntityChange { dimension, uuid, before, after });
+        self.recorded();
+    }
+}
+
+impl Default for EditHistory {
+    fn default() -> Self { Self::new() }
+}
+
+impl<'a> World<'a> {
+    // Reverts the last transaction, returns its name. It stays undoable when it can't be undone
+    pub fn undo(&mut self) -> Result<Option<String>, HistoryError> {
+        self.history_mut().commit_all();
+        let Some(transaction) = self.history_mut().undo.pop_back() else { return Ok(None) };
+        if let Err(e) = transaction.apply(self, true) {
+            self.history_mut().undo.push_back(transaction);
+            return Err(e);
+        }
+        let name = transaction.name().to_string();
+        self.history_mut().redo.push(transaction);
+        Ok(Some(name))
+    }
+
+    // Applies the last undone transaction again, returns its name. It stays redoable when it can't be redone
+    pub fn redo(&mut self) -> Result<Option<String>, HistoryError> {
+        self.history_mut().commit_all();
+        let Some(transaction) = self.history_mut().redo.pop() else { return Ok(None) };
+        if let Err(e) = transaction.apply(self, false) {
+            self.history_mut().redo.push(transaction);
+            return Err(e);
+        }
+        let name = transaction.name().to_string();
+        self.history_mut().undo.push_back(transaction);
+        self.history_mut().trim();
+        Ok(Some(name))
+    }
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::versions::VersionManager;
+    use crate::models::block_entity::block_entity::GenericBlockEntity;
+    use crate::models::entity::entity::EntityType;
+    use crate::models::other::properties::Properties;
+    use crate::models::other::tick::Tick;
+    use crate::models::positions::chunk_position::ChunkPosition;
+    use crate::models::positions::entity_position::EntityPosition;
+    use crate::models::world::dimension::Dimension;
+    use crate::models::world::selection::SelectionBuilder;
+    use crate::models::world::tile_tick::TileTickType;
+    use std::sync::Arc;
+    use crate::traits::access::prelude::EntityWriter;
+    use crate::types::WorldKind;
+
+    fn pos(x: i32, y: i32, z: i32) -> Position { Position::new("overworld".into(), x, y, z) }
+
+    fn block_at(world: &World, p: &Position) -> Option<String> {
+        let (chunk_pos, index) = block_position_to_chunk_pos_and_block_index(p, 16, -64);
+        let chunk = world.dimension(p.dimension())?.chunk(chunk_pos.position())?;
+        let block = chunk.lock().unwrap().block_store().get_block_at_index(index);
+        block.map(|b| b.full_name().to_string())
+    }
+
+    fn block_entity_count(world: &World) -> usize {
+        world.dimension(&"overworld".into()).unwrap().chunk((0, 0)).unwrap().lock().unwrap().block_entities_store().get_all().len()
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new("".into(), version.clone());
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        let chunk = dimension.set_chunk(Chunk::new(ChunkPosition::new(0, 0, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+        {
+            let mut chunk = chunk.lock().unwrap();
+            chunk.set_block_at_index(((64 * 16) + 1) * 16 + 1, PaletteBlock::from("minecraft:chest"));
+            chunk.block_entities_store_mut().set_at_world_position(BlockEntity::Other(GenericBlockEntity::new("minecraft:chest".to_string(), pos(1, 0, 1), Properties::new(HashMap::new()))));
+        }
+        let mut locked = world.lock().unwrap();
+        locked.set_dimension("overworld".into(), dimension);
+
+        let stone = PaletteBlock::from("minecraft:stone");
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(0, 0, 0), &pos(3, 1, 3)).build();
+        assert_eq!(selection.fill(&stone), 32);
+        drop(selection);
+        assert_eq!(locked.history().undo_names(), vec!["fill"]);
+        assert_eq!(block_entity_count(&locked), 0);
+
+        assert_eq!(locked.undo().unwrap().as_deref(), Some("fill"));
+        assert_eq!(block_at(&locked, &pos(1, 0, 1)).as_deref(), Some("minecraft:chest"));
+        assert_eq!(block_at(&locked, &pos(0, 0, 0)), None);
+        assert_eq!(block_entity_count(&locked), 1);
+        assert_eq!(locked.redo().unwrap().as_deref(), Some("fill"));
+        assert_eq!(block_at(&locked, &pos(1, 0, 1)).as_deref(), Some("minecraft:stone"));
+        assert_eq!(block_entity_count(&locked), 0);
+        assert_eq!(locked.redo().unwrap(), None);
+
+        // nested operations end up in the outer transaction
+        locked.history_mut().begin("build");
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(0, 0, 0), &pos(3, 1, 3)).build();
+        selection.fill(&PaletteBlock::from("minecraft:glass"));
+        selection.walls(&PaletteBlock::from("minecraft:dirt"));
+        let cow = Entity::new(EntityType::Mob, Tick::new(0), 0.0, Tick::new(0), false, (0.0, 0.0, 0.0), true,
+            EntityPosition::new(1.5, 2.0, 1.5, 0.0, 0.0, "overworld".into()), 7, Properties::new(HashMap::new()));
+        assert!(selection.set_entity_at_position(cow));
+        drop(selection);
+        locked.history_mut().commit();
+        assert_eq!(locked.history().undo_names(), vec!["build", "fill"]);
+        assert_eq!(block_at(&locked, &pos(1, 1, 1)).as_deref(), Some("minecraft:glass"));
+        assert_eq!(block_at(&locked, &pos(0, 1, 1)).as_deref(), Some("minecraft:dirt"));
+
+        assert_eq!(locked.undo().unwrap().as_deref(), Some("build"));
+        assert_eq!(block_at(&locked, &pos(1, 1, 1)).as_deref(), Some("minecraft:stone"));
+        assert_eq!(block_at(&locked, &pos(0, 1, 1)).as_deref(), Some("minecraft:stone"));
+        assert_eq!(locked.dimension(&"overworld".into()).unwrap().entity_count(), 0);
+        assert_eq!(locked.redo().unwrap().as_deref(), Some("build"));
+        assert_eq!(locked.dimension(&"overworld".into()).unwrap().entity_count(), 1);
+        assert_eq!(locked.dimension(&"overworld".into()).unwrap().chunk((0, 0)).unwrap().lock().unwrap().entity_count(), 1);
+
+        // big transactions go to disk past the threshold and still undo
+        let directory = std::env::temp_dir().join(format!("cubicle_history_test_{}", process::id()));
+        locked.history_mut().set_spill(Some(directory.clone()), 10);
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(0, 0, 0), &pos(3, 1, 3)).build();
+        assert_eq!(selection.fill(&PaletteBlock::from("minecraft:sand")), 32);
+        drop(selection);
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 1);
+        assert_eq!(locked.undo().unwrap().as_deref(), Some("fill"));
+        assert_eq!(block_at(&locked, &pos(1, 1, 1)).as_deref(), Some("minecraft:glass"));
+
+        locked.history_mut().set_max_transactions(1);
+        assert_eq!(locked.history().undo_names(), vec!["build"]);
+        locked.history_mut().clear();
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 0);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn max_changes_holds_while_recording() {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new("".into(), version.clone());
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        dimension.set_chunk(Chunk::new(ChunkPosition::new(0, 0, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+        let mut locked = world.lock().unwrap();
+        locked.set_dimension("overworld".into(), dimension);
+        locked.history_mut().set_max_changes(20);
+        let mut fill = |locked: &mut World, y: i32, block: &str| {
+            SelectionBuilder::new_owned(locked, version.clone()).cuboid(&pos(0, y, 0), &pos(3, y, 3)).build().fill(&PaletteBlock::from(block))
+        };
+
+        // the open transaction counts as well: the older one goes first, then the open one can't be kept either
+        assert_eq!(fill(&mut locked, 0, "minecraft:stone"), 16);
+        locked.history_mut().begin("build");
+        for y in 1..4 {
+            fill(&mut locked, y, "minecraft:glass");
+            assert!(locked.history().in_memory_changes() <= 20);
+        }
+        assert!(locked.history().undo_names().is_empty());
+        locked.history_mut().commit();
+        assert!(!locked.history().can_undo());
+        assert_eq!(block_at(&locked, &pos(1, 3, 1)).as_deref(), Some("minecraft:glass"));
+
+        // spilled block changes don't count, the whole transaction stays undoable
+        let directory = std::env::temp_dir().join(format!("cubicle_history_limit_test_{}", process::id()));
+        locked.history_mut().set_spill(Some(directory.clone()), 1000);
+        locked.history_mut().begin("build");
+        for y in 1..4 { fill(&mut locked, y, "minecraft:dirt"); }
+        locked.history_mut().commit();
+        assert!(locked.history().in_memory_changes() <= 20);
+        assert_eq!(locked.undo().unwrap().as_deref(), Some("build"));
+        assert_eq!(block_at(&locked, &pos(1, 3, 1)).as_deref(), Some("minecraft:glass"));
+        locked.history_mut().clear();
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn undo_and_redo_keep_default_block_entities() {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new("".into(), version.clone());
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        let chunk = dimension.set_chunk(Chunk::new(ChunkPosition::new(0, 0, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+        {
+            let mut chunk = chunk.lock().unwrap();
+            chunk.set_block_at_index(((64 * 16) + 1) * 16 + 1, PaletteBlock::from("minecraft:chest"));
+            chunk.block_entities_store_mut().set_at_world_position(BlockEntity::Other(GenericBlockEntity::new("minecraft:chest".to_string(), pos(1, 0, 1), Properties::new(HashMap::new()))));
+        }
+        let mut locked = world.lock().unwrap();
+        locked.set_dimension("overworld".into(), dimension);
+        let ids = || chunk.lock().unwrap().block_entities_store().get_all().iter().map(|b| b.base().id().to_string()).collect::<Vec<_>>();
+
+        // the barrel gets its default block entity, undo and redo swap the two
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(1, 0, 1), &pos(1, 0, 1)).build();
+        assert_eq!(selection.fill(&PaletteBlock::from("minecraft:barrel")), 1);
+        drop(selection);
+        assert_eq!(ids(), vec!["minecraft:barrel"]);
+        assert_eq!(locked.undo().unwrap().as_deref(), Some("fill"));
+        assert_eq!(ids(), vec!["minecraft:chest"]);
+        assert_eq!(locked.redo().unwrap().as_deref(), Some("fill"));
+        assert_eq!(ids(), vec!["minecraft:barrel"]);
+        assert_eq!(locked.undo().unwrap().as_deref(), Some("fill"));
+        assert_eq!(ids(), vec!["minecraft:chest"]);
+    }
+
+    #[test]
+    fn undo_needs_loaded_chunks_and_restores_tile_ticks() {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new("".into(), version.clone());
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        for x in [0, 1] {
+            let chunk = dimension.set_chunk(Chunk::new(ChunkPosition::new(x, 0, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+            chunk.lock().unwrap().set_block_at_index(((64 * 16) + 1) * 16 + 1, PaletteBlock::from("minecraft:water"));
+            chunk.lock().unwrap().set_tile_tick(TileTick::new(pos(x * 16 + 1, 0, 1), 0, 5, TileTickType::FLUID));
+        }
+        let mut locked = world.lock().unwrap();
+        locked.set_dimension("overworld".into(), dimension);
+
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(0, 0, 0), &pos(17, 0, 1)).build();
+        assert_eq!(selection.fill(&PaletteBlock::from("minecraft:stone")), 36);
+        drop(selection);
+        let tile_ticks = |world: &World, x: i32| world.dimension(&"overworld".into()).unwrap().chunk((x, 0)).map(|c| c.lock().unwrap().tile_ticks().len());
+        assert_eq!((tile_ticks(&locked, 0), tile_ticks(&locked, 1)), (Some(0), Some(0)));
+
+        // nothing is undone while one of the chunks is gone, and the transaction stays
+        let unloaded = locked.dimension_mut(&"overworld".into()).unwrap().delete_chunk((1, 0)).unwrap();
+        assert!(matches!(locked.undo(), Err(HistoryError::ChunkNotLoaded(ref c)) if c.position() == (1, 0)));
+        assert_eq!(block_at(&locked, &pos(1, 0, 1)).as_deref(), Some("minecraft:stone"));
+        assert_eq!(locked.history().undo_names(), vec!["fill"]);
+
+        let unloaded = Arc::try_unwrap(unloaded).unwrap().into_inner().unwrap();
+        locked.dimension_mut(&"overworld".into()).unwrap().set_chunk(unloaded);
+        assert_eq!(locked.undo().unwrap().as_deref(), Some("fill"));
+        assert_eq!(block_at(&locked, &pos(17, 0, 1)).as_deref(), Some("minecraft:water"));
+        assert_eq!((tile_ticks(&locked, 0), tile_ticks(&locked, 1)), (Some(1), Some(1)));
+        assert_eq!(locked.redo().unwrap().as_deref(), Some("fill"));
+        assert_eq!(tile_ticks(&locked, 0), Some(0));
+
+        // spilling into a file that isn't a directory fails, the changes stay in memory
+        let file = std::env::temp_dir().join(format!("cubicle_history_file_{}", process::id()));
+        fs::write(&file, b"").unwrap();
+        locked.history_mut().set_spill(Some(file.join("spill")), 1);
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version).cuboid(&pos(0, 0, 0), &pos(1, 0, 0)).build();
+        assert_eq!(selection.fill(&PaletteBlock::from("minecraft:dirt")), 2);
+        drop(selection);
+        assert!(locked.history_mut().take_spill_error().is_some());
+        assert_eq!(locked.undo().unwrap().as_deref(), Some("fill"));
+        assert_eq!(block_at(&locked, &pos(0, 0, 0)).as_deref(), Some("minecraft:stone"));
+        fs::remove_file(&file).unwrap();
+    }
+}
@@ -5,3 +5,14 @@ pub mod world;
 pub mod selection;
 pub mod fulls;
 pub mod tile_tick;
+pub mod transform;
+pub mod block_index;
+pub mod shape;
+pub mod area;
+pub mod clipboard;
+pub mod pattern;
+pub mod history;
+pub mod transaction;
+pub mod scan;
+pub mod chunk_cache;
+pub mod generator;
@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, OnceLock};
+use fastnbt::Value;
+use crate::models::world::block::PaletteBlock;
+use crate::utils::java_random::JavaRandom;
+
+// What the bulk operations of Selection place. Every random choice is made from the seed and the block position
+// only, so the same pattern always gives the same blocks at the same positions, whatever order they're set in
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Block(PaletteBlock),
+    Weighted { entries: Vec<(Pattern, f64)>, seed: i64 },
+    // every listed state gets one of its values at random, states not listed are kept as they are on the block
+    RandomStates { block: PaletteBlock, states: Vec<(String, Vec<String>)>, seed: i64 },
+    // stops spread evenly from from_y to to_y, blocks between two stops are dithered between them
+    Gradient { from_y: i32, to_y: i32, stops: Vec<Pattern>, seed: i64 },
+    // smooth 3D value noise, scale is roughly the size of a blob in blocks. Every band gets about the same share of
+    // the blocks, the noise is cut at its quantiles
+    Noise { scale: f64, bands: Vec<Pattern>, seed: i64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternParseError {
+    pub message: String,
+}
+
+impl fmt::Display for PatternParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}", self.message) }
+}
+
+impl std::error::Error for PatternParseError {}
+
+fn error<T>(message: impl Into<String>) -> Result<T, PatternParseError> {
+    Err(PatternParseError { message: message.into() })
+}
+
+// Uniform in [0, 1). Vanilla's Mth.getSeed position hash, mixed with the seed
+fn random_at(seed: i64, x: i32, y: i32, z: i32) -> f64 {
+    let hash = (x as i64).wrapping_mul(3129871) ^ (z as i64).wrapping_mul(116129781) ^ y as i64;
+    let hash = hash.wrapping_mul(hash).wrapping_mul(42317861).wrapping_add(hash.wrapping_mul(11));
+    // spread the seed over all bits, close seeds would otherwise start out with close doubles
+    JavaRandom::new(hash ^ seed.wrapping_mul(0x9E3779B97F4A7C15u64 as i64)).next_double()
+}
+
+fn value_noise(seed: i64, x: i32, y: i32, z: i32, scale: f64) -> f64 {
+    let scale = scale.max(1.0);
+    let (x, y, z) = (x as f64 / scale, y as f64 / scale, z as f64 / scale);
+    let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+    let smooth = |t: f64| t * t * (3.0 - 2.0 * t);
+    let (tx, ty, tz) = (smooth(x - x0), smooth(y - y0), smooth(z - z0));
+    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+    let corner = |dx: i32, dy: i32, dz: i32| random_at(seed, x0 as i32 + dx, y0 as i32 + dy, z0 as i32 + dz);
+
+    let bottom = lerp(lerp(corner(0, 0, 0), corner(1, 0, 0), tx), lerp(corner(0, 0, 1), corner(1, 0, 1), tx), tz);
+    let top = lerp(lerp(corner(0, 1, 0), corner(1, 1, 0), tx), lerp(corner(0, 1, 1), corner(1, 1, 1), tx), tz);
+    lerp(bottom, top, ty)
+}
+
+const NOISE_SAMPLES: i32 = 1 << 16;
+const NOISE_QUANTILES: usize = 256;
+// per scale (its bits), filled the first time a scale is used
+static NOISE_QUANTILE_CACHE: OnceLock<Mutex<HashMap<u64, Arc<Vec<f64>>>>> = OnceLock::new();
+
+// Averaging the corners makes values near 0.5 far more common than ones near 0 or 1 (less so the smaller the scale,
+// at 1 the noise is the corners), so the noise is sampled once per scale and its quantiles map it back to uniform
+fn noise_quantiles(scale: f64) -> Arc<Vec<f64>> {
+    let cache = NOISE_QUANTILE_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(quantiles) = cache.lock().unwrap().get(&scale.to_bits()) { return quantiles.clone(); }
+
+    let coordinate = |i: i32, axis: i32| (random_at(-1, i, axis, 0) * 2_000_000.0) as i32 - 1_000_000;
+    let mut samples = (0..NOISE_SAMPLES).map(|i| value_noise(0, coordinate(i, 0), coordinate(i, 1), coordinate(i, 2), scale)).collect::<Vec<_>>();
+    samples.sort_by(|a, b| a.total_cmp(b));
+    let quantiles = Arc::new((0..=NOISE_QUANTILES).map(|q| samples[q * (samples.len() - 1) / NOISE_QUANTILES]).collect::<Vec<_>>());
+    cache.lock().unwrap().insert(scale.to_bits(), quantiles.clone());
+    quantiles
+}
+
+// The share of the noise below value, in [0, 1]
+fn uniform_noise(seed: i64, x: i32, y: i32, z: i32, scale: f64) -> f64 {
+    let value = value_noise(seed, x, y, z, scale);
+    let quantiles = noise_quantiles(scale);
+    let above = quantiles.partition_point(|q| *q <= value);
+    if above == 0 { return 0.0; }
+    if above == quantiles.len() { return 1.0; }
+    let (low, high) = (quantiles[above - 1], quantiles[above]);
+    let t = if high > low { (value - low) / (high - low) } else { 0.0 };
+    ((above - 1) as f64 + t) / NOISE_QUANTILES as f64
+}
+
+fn child_seed(seed: i64, child: usize) -> i64 { seed.wrapping_mul(31).wrapping_add(child as i64 + 1) }
+
+impl Pattern {
+    pub fn block(block: PaletteBlock) -> Pattern { Pattern::Block(block) }
+    pub fn weighted(entries: Vec<(Pattern, f64)>) -> Pattern { Pattern::Weighted { entries, seed: 0 } }
+    pub fn random_states(block: PaletteBlock, states: Vec<(String, Vec<String>)>) -> Pattern { Pattern::RandomStates { block, states, seed: 0 } }
+    pub fn gradient(from_y: i32, to_y: i32, stops: Vec<Pattern>) -> Pattern { Pattern::Gradient { from_y, to_y, stops, seed: 0 } }
+    pub fn noise(scale: f64, bands: Vec<Pattern>) -> Pattern { Pattern::Noise { scale, bands, seed: 0 } }
+
+    // Seeds this pattern and, with seeds derived from it, everything inside it
+    pub fn with_seed(self, seed: i64) -> Pattern {
+        let seed_all = |patterns: Vec<Pattern>| patterns.into_iter().enumerate().map(|(i, p)| p.with_seed(child_seed(seed, i))).collect();
+        match self {
+            Pattern::Block(block) => Pattern::Block(block),
+            Pattern::Weighted { entries, .. } => Pattern::Weighted {
+                entries: entries.into_iter().enumerate().map(|(i, (p, w))| (p.with_seed(child_seed(seed, i)), w)).collect(), seed
+            },
+            Pattern::RandomStates { block, states, .. } => Pattern::RandomStates { block, states, seed },
+            Pattern::Gradient { from_y, to_y, stops, .. } => Pattern::Gradient { from_y, to_y, stops: seed_all(stops), seed },
+            Pattern::Noise { scale, bands, .. } => Pattern::Noise { scale, bands: seed_all(bands), seed },
+        }
+    }
+
+    // WorldEdit like: "60% stone, 30% andesite, 10% cobblestone", "oak_log[axis=x|y|z]" (one of the values at random),
+    // "stone_bricks, mossy_stone_bricks" (equally likely). Entries without a percentage weigh 1
+    pub fn parse(input: &str) -> Result<Pattern, PatternParseError> {
+        let mut entries = Vec::new();
+        let (mut depth, mut start) = (0, 0);
+        for (i, c) in input.char_indices() {
+            match c {
+                '[' => depth += 1,
+                ']' if depth == 0 => return error(format!("Unexpected ']' at {}", i + 1)),
+                ']' => depth -= 1,
+                ',' if depth == 0 => { entries.push(&input[start..i]); start = i + 1; }
+                _ => {}
+            }
+        }
+        if depth != 0 { return error("Missing ']'"); }
+        entries.push(&input[start..]);
+
+        let mut parsed = Vec::new();
+        for entry in entries {
+            let entry = entry.trim();
+            let (weight, block) = match entry.split_once('%') {
+                Some((weight, block)) => match weight.trim().parse::<f64>() {
+                    Ok(weight) if weight >= 0.0 => (weight, block.trim()),
+                    _ => return error(format!("Bad weight in '{}'", entry)),
+                },
+                None => (1.0, entry),
+            };
+            parsed.push((Pattern::parse_block(block)?, weight));
+        }
+        if parsed.len() == 1 { return Ok(parsed.pop().unwrap().0); }
+        Ok(Pattern::weighted(parsed))
+    }
+
+    fn parse_block(input: &str) -> Result<Pattern, PatternParseError> {
+        let (name, states) = match input.split_once('[') {
+            Some((name, rest)) => match rest.strip_suffix(']') {
+                Some(states) => (name.trim(), states),
+                None => return error(format!("Missing ']' in '{}'", input)),
+            },
+            None => (input, ""),
+        };
+        if name.is_empty() || name.contains(char::is_whitespace) { return error(format!("Bad block name '{}'", name)); }
+        let name = if name.contains(':') { name.to_string() } else { format!("minecraft:{}", name) };
+
+        let mut fixed = HashMap::new();
+        let mut random = Vec::new();
+        for state in states.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let Some((key, value)) = state.split_once('=') else { return error(format!("Bad block state '{}'", state)) };
+            let values = value.split('|').map(|v| v.trim().to_string()).collect::<Vec<_>>();
+            if values.iter().any(|v| v.is_empty()) { return error(format!("Bad block state '{}'", state)); }
+            if values.len() == 1 { fixed.insert(key.trim().to_string(), Value::String(values[0].clone())); }
+            else { random.push((key.trim().to_string(), values)); }
+        }
+
+        let block = PaletteBlock::new(&name, Some(fixed));
+        if random.is_empty() { Ok(Pattern::Block(block)) } else { Ok(Pattern::random_states(block, random)) }
+    }
+
+    // How many different blocks the pattern can give
+    pub fn block_count(&self) -> usize {
+        match self {
+            Pattern::Block(_) => 1,
+            Pattern::Weighted { entries, .. } => entries.iter().map(|(p, _)| p.block_count()).sum(),
+            Pattern::RandomStates { states, .. } => states.iter().map(|(_, values)| values.len().max(1)).product(),
+            Pattern::Gradient { stops: patterns, .. } | Pattern::Noise { bands: patterns, .. } => patterns.iter().map(|p| p.block_count()).sum(),
+        }
+    }
+
+    // Every block the pattern can give, choose() returns indices into this. Patterns without any give nothing
+    pub fn blocks(&self) -> Vec<PaletteBlock> {
+        match self {
+            Pattern::Block(block) => vec![block.clone()],
+            Pattern::Weighted { entries, .. } => entries.iter().flat_map(|(p, _)| p.blocks()).collect(),
+            Pattern::RandomStates { block, states, .. } => {
+                let mut blocks = vec![block.properties().raw().clone()];
+                for (key, values) in states.iter().filter(|(_, values)| !values.is_empty()) {
+                    blocks = blocks.into_iter()
+                        .flat_map(|b| values.iter().map(move |v| { let mut b = b.clone(); b.insert(key.clone(), Value::String(v.clone())); b }))
+                        .collect();
+                }
+                blocks.into_iter().map(|states| PaletteBlock::new(&block.full_name(), Some(states))).collect()
+            }
+            Pattern::Gradient { stops: patterns, .. } | Pattern::Noise { bands: patterns, .. } => patterns.iter().flat_map(|p| p.blocks()).collect(),
+        }
+    }
+
+    // Offset of the child's blocks plus the child's own choice
+    fn choose_child<'p>(mut patterns: impl Iterator<Item = &'p Pattern>, child: usize, x: i32, y: i32, z: i32) -> usize {
+        let offset = patterns.by_ref().take(child).map(|p| p.block_count()).sum::<usize>();
+        offset + patterns.next().map(|p| p.choose(x, y, z)).unwrap_or(0)
+    }
+
+    // Which of its patterns one made of others uses at the position, None for the others
+    fn child_at(&self, x: i32, y: i32, z: i32) -> Option<usize> {
+        match self {
+            Pattern::Block(_) | Pattern::RandomStates { .. } => None,
+            Pattern::Weighted { entries, seed } => {
+                let total = entries.iter().map(|(_, w)| w.max(0.0)).sum::<f64>();
+                let mut target = random_at(*seed, x, y, z) * total;
+                for (i, (_, weight)) in entries.iter().enumerate() {
+                    if target < weight.max(0.0) { return Some(i); }
+                    target -= weight.max(0.0);
+                }
+                Some(entries.len().saturating_sub(1))
+            }
+            Pattern::Gradient { from_y, to_y, stops, seed } => {
+                let t = if to_y == from_y { 0.0 } else { ((y - from_y) as f64 / (to_y - from_y) as f64).clamp(0.0, 1.0) };
+                let position = t * stops.len().saturating_sub(1) as f64;
+                let lower = position.floor() as usize;
+                let child = if random_at(*seed, x, y, z) < position - lower as f64 { lower + 1 } else { lower };
+                Some(child.min(stops.len().saturating_sub(1)))
+            }
+            Pattern::Noise { scale, bands, seed } => {
+                let band = (uniform_noise(*seed, x, y, z, *scale) * bands.len() as f64) as usize;
+                Some(band.min(bands.len().saturating_sub(1)))
+            }
+        }
+    }
+
+    // Index into the values of every listed state
+    fn state_values_at(states: &[(String, Vec<String>)], seed: i64, x: i32, y: i32, z: i32) -> impl Iterator<Item = usize> + '_ {
+        states.iter().enumerate().map(move |(i, (_, values))| {
+            let value = (random_at(child_seed(seed, i), x, y, z) * values.len() as f64) as usize;
+            value.min(values.len().saturating_sub(1))
+        })
+    }
+
+    pub fn choose(&self, x: i32, y: i32, z: i32) -> usize {
+        let child = self.child_at(x, y, z).unwrap_or(0);
+        match self {
+            Pattern::Block(_) => 0,
+            Pattern::Weighted { entries, .. } => Pattern::choose_child(entries.iter().map(|(p, _)| p), child, x, y, z),
+            Pattern::RandomStates { states, seed, .. } => Pattern::state_values_at(states, *seed, x, y, z)
+                .zip(states).fold(0, |index, (value, (_, values))| index * values.len().max(1) + value),
+            Pattern::Gradient { stops: patterns, .. } | Pattern::Noise { bands: patterns, .. } => Pattern::choose_child(patterns.iter(), child, x, y, z),
+        }
+    }
+
+    // Same as blocks()[choose()], without making every block. A null block for patterns without any
+    pub fn block_at(&self, x: i32, y: i32, z: i32) -> PaletteBlock {
+        let child = self.child_at(x, y, z).unwrap_or(0);
+        let chosen = match self {
+            Pattern::Block(block) => return block.clone(),
+            Pattern::RandomStates { block, states, seed } => {
+                let mut chosen = block.properties().raw().clone();
+                for (value, (key, values)) in Pattern::state_values_at(states, *seed, x, y, z).zip(states) {
+                    if let Some(value) = values.get(value) { chosen.insert(key.clone(), Value::String(value.clone())); }
+                }
+                return PaletteBlock::new(&block.full_name(), Some(chosen));
+            }
+            Pattern::Weighted { entries, .. } => entries.get(child).map(|(p, _)| p),
+            Pattern::Gradient { stops: patterns, .. } | Pattern::Noise { bands: patterns, .. } => patterns.get(child),
+        };
+        chosen.map(|p| p.block_at(x, y, z)).unwrap_or_else(PaletteBlock::new_null)
+    }
+}
+
+impl From<PaletteBlock> for Pattern {
+    fn from(block: PaletteBlock) -> Self { Pattern::Block(block) }
+}
+
+impl From<&PaletteBlock> for Pattern {
+    fn from(block: &PaletteBlock) -> Self { Pattern::Block(block.clone()) }
+}
+
+impl From<&Pattern> for Pattern {
+    fn from(pattern: &Pattern) -> Self { pattern.clone() }
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weighted_patterns_are_deterministic() {
+        let pattern = Pattern::parse("60% stone, 30% andesite, 10%cobblestone").unwrap().with_seed(42);
+        let blocks = pattern.blocks();
+        assert_eq!(blocks.iter().map(|b| b.full_name().into_owned()).collect::<Vec<_>>(), vec!["minecraft:stone", "minecraft:andesite", "minecraft:cobblestone"]);
+
+        let mut counts = [0; 3];
+        for x in 0..100 {
+            for z in 0..100 { counts[pattern.choose(x, 0, z)] += 1; }
+        }
+        assert!(counts[0] > 5500 && counts[0] < 6500, "{:?}", counts);
+        assert!(counts[2] > 700 && counts[2] < 1300, "{:?}", counts);
+        assert_eq!(pattern.choose(12, 5, -7), pattern.clone().choose(12, 5, -7));
+        let other = Pattern::parse("60% stone, 30% andesite, 10% cobblestone").unwrap().with_seed(43);
+        assert!((0..50).any(|x| pattern.choose(x, 0, 0) != other.choose(x, 0, 0)));
+
+        assert!(Pattern::parse("5x% stone").is_err());
+        assert!(Pattern::parse("stone[axis=y").is_err());
+        assert!(matches!(Pattern::parse("minecraft:stone").unwrap(), Pattern::Block(_)));
+    }
+
+    #[test]
+    fn states_gradients_and_noise() {
+        let logs = Pattern::parse("oak_log[axis=x|y|z, waterlogged=false]").unwrap();
+        let blocks = logs.blocks();
+        assert_eq!(blocks.len(), 3);
+        for x in 0..20 {
+            let block = logs.block_at(x, 0, 0);
+            assert_eq!(block.properties().get("waterlogged"), Some(Value::String("false".to_string())));
+            assert!(block.properties().get("axis").is_some());
+        }
+
+        let gradient = Pattern::gradient(0, 10, vec![Pattern::from(PaletteBlock::from("minecraft:stone")), Pattern::from(PaletteBlock::from("minecraft:dirt"))]);
+        assert_eq!(gradient.choose(3, -5, 3), 0);
+        assert_eq!(gradient.choose(3, 10, 3), 1);
+        let middle = (0..100).filter(|x| gradient.choose(*x, 5, 0) == 1).count();
+        assert!(middle > 30 && middle < 70);
+
+        let noise = Pattern::noise(8.0, vec![Pattern::from(PaletteBlock::from("minecraft:stone")), Pattern::from(PaletteBlock::from("minecraft:gravel"))]).with_seed(7);
+        // neighbours mostly agree, unlike with a weighted mix
+        let same = (0..200).filter(|x| noise.choose(*x, 0, 0) == noise.choose(*x + 1, 0, 0)).count();
+        assert!(same > 170, "{}", same);
+        assert!((0..200).any(|x| noise.choose(x * 8, 0, 0) == 1) && (0..200).any(|x| noise.choose(x * 8, 0, 0) == 0));
+    }
+
+    #[test]
+    fn noise_bands_are_even() {
+        let bands = ["minecraft:stone", "minecraft:dirt", "minecraft:gravel", "minecraft:sand"].map(|id| Pattern::from(PaletteBlock::from(id)));
+        // cut evenly, the outer bands of the raw noise would only get about 10% each from a scale of 2 up
+        for scale in [1.0, 2.5, 8.0, 16.0] {
+            let noise = Pattern::noise(scale, bands.to_vec()).with_seed(3);
+            let mut counts = [0; 4];
+            for x in 0..400 {
+                for y in 0..2 {
+                    for z in 0..400 { counts[noise.choose(x * 3, y * 37, z * 3)] += 1; }
+                }
+            }
+            // every band between 20% and 30%
+            assert!(counts.iter().all(|c| *c * 5 > 400 * 400 * 2 && *c * 10 < 400 * 400 * 2 * 3), "{} {:?}", scale, counts);
+        }
+    }
+
+    #[test]
+    fn block_at_matches_choose() {
+        let mixed = Pattern::parse("30% stone, oak_log[axis=x|y|z, waterlogged=false], 20% granite").unwrap();
+        let nested = Pattern::gradient(0, 16, vec![mixed, Pattern::noise(4.0, vec![
+            Pattern::random_states(PaletteBlock::from("minecraft:stone_slab"), vec![("type".to_string(), vec!["top".to_string(), "bottom".to_string()]), ("waterlogged".to_string(), Vec::new())]),
+            Pattern::from(PaletteBlock::from("minecraft:dirt")),
+        ])]).with_seed(11);
+        let blocks = nested.blocks();
+        for (x, y, z) in (0..16).flat_map(|x| (-2..18).map(move |y| (x, y, x * 3 - y))) {
+            assert!(nested.block_at(x, y, z) == blocks[nested.choose(x, y, z)], "{} {} {}", x, y, z);
+        }
+        assert!(Pattern::weighted(Vec::new()).block_at(0, 0, 0).is_null());
+    }
+}
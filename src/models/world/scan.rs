@@ -0,0 +1,379 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use crate::constants::constants::REGION_CHUNK_LINE;
+use crate::models::other::lasso_string::LassoString;
+use crate::models::other::region::RegionType;
+use crate::models::positions::chunk_position::ChunkPosition;
+use crate::models::world::area::Area;
+use crate::models::world::selection::{Selection, SelectionBuilder};
+use crate::models::world::world::{SaveOptions, World};
+use crate::types::RegionPosition;
+use crate::utils::parallel::worker_count;
+
+pub type ProgressCallback = Arc<dyn Fn(&ScanProgress) + Send + Sync>;
+
+#[derive(Clone)]
+pub struct ScanOptions {
+    concurrency: usize, // regions loaded at once (and parsed in parallel), 0 is one per core
+    save: Option<SaveOptions>, // changed chunks are saved before their region is unloaded, otherwise changes are lost
+    dimensions: Option<Vec<LassoString>>,
+    area: Option<Area>, // only regions touching it are loaded, and blocks and entities outside of it are skipped
+    entities: bool,
+    progress: Option<ProgressCallback>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self { Self { concurrency: 0, save: None, dimensions: None, area: None, entities: true, progress: None } }
+}
+
+impl fmt::Debug for ScanOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScanOptions")
+            .field("concurrency", &self.concurrency)
+            .field("save", &self.save)
+            .field("dimensions", &self.dimensions)
+            .field("area", &self.area)
+            .field("entities", &self.entities)
+            .finish()
+    }
+}
+
+impl ScanOptions {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn concurrency(&self) -> usize { self.concurrency }
+    pub fn save(&self) -> Option<&SaveOptions> { self.save.as_ref() }
+    pub fn dimensions(&self) -> Option<&Vec<LassoString>> { self.dimensions.as_ref() }
+    pub fn area(&self) -> Option<&Area> { self.area.as_ref() }
+    pub fn entities(&self) -> bool { self.entities }
+
+    pub fn with_concurrency(mut self, regions: usize) -> Self { self.concurrency = regions; self }
+    pub fn with_save(mut self, options: SaveOptions) -> Self { self.save = Some(options); self }
+    pub fn in_dimension(mut self, dimension: LassoString) -> Self { self.dimensions.get_or_insert_with(Vec::new).push(dimension); self }
+    pub fn within(mut self, area: Area) -> Self { self.area = Some(area); self }
+    // Entity regions aren't read, block scans get faster
+    pub fn without_entities(mut self) -> Self { self.entities = false; self }
+    // Called on the scanning thread after every region
+    pub fn on_progress<F>(mut self, callback: F) -> Self where F: Fn(&ScanProgress) + Send + Sync + 'static { self.progress = Some(Arc::new(callback)); self }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScanProgress {
+    regions_total: usize,
+    regions_done: usize,
+    chunks_done: usize,
+    chunks_saved: usize,
+    current: Option<RegionPosition>, // the region that was just visited
+}
+
+impl ScanProgress {
+    pub fn regions_total(&self) -> usize { self.regions_total }
+    pub fn regions_done(&self) -> usize { self.regions_done }
+    pub fn chunks_done(&self) -> usize { self.chunks_done }
+    pub fn chunks_saved(&self) -> usize { self.chunks_saved }
+    pub fn current(&self) -> Option<&RegionPosition> { self.current.as_ref() }
+    pub fn fraction(&self) -> f64 {
+        if self.regions_total == 0 { return 1.0; }
+        self.regions_done as f64 / self.regions_total as f64
+    }
+}
+
+// Streaming
+// Visits every region on disk without keeping them loaded, so memory stays at concurrency regions whatever the size of
+// the world. Chunks that were loaded before the scan are visited as they are and stay loaded, so does a region that
+// couldn't be saved
+impl<'a> World<'a> {
+    // visit gets a selection of one region's chunks at a time and returns false to stop. Filters that look at
+    // neighbouring blocks only see the regions that are loaded at the same time
+    pub fn scan_regions<F>(&mut self, options: &ScanOptions, mut visit: F) -> io::Result<ScanProgress> where F: FnMut(&mut Selection<'_, 'a>) -> bool {
+        let regions = self.scan_targets(options);
+        let mut progress = ScanProgress { regions_total: regions.len(), ..Default::default() };
+        let version = self.version();
+
+        for batch in regions.chunks(worker_count(options.concurrency)) {
+            let loaded_here = self.load_region_files(batch, options.concurrency, options.entities).into_iter().collect::<HashSet<_>>();
+
+            for (i, region) in batch.iter().enumerate() {
+                let chunks = self.region_chunk_positions(region);
+                let mut builder = SelectionBuilder::new(self, &version);
+                for chunk in &chunks { builder = builder.with_chunk_position(ChunkPosition::new(chunk.0, chunk.1, *region.dimension())); }
+                if let Some(area) = &options.area { builder = builder.clipped_to(area.clone()); }
+                let keep_going = visit(&mut builder.build());
+
+                let saved = match &options.save {
+                    Some(save) => self.save_dirty_chunks(region, &chunks, save),
+                    None => Ok(0),
+                };
+                let saved = match saved {
+                    Ok(saved) => saved,
+                    Err(e) => {
+                        // the region stays loaded with its changes, so they can still be saved
+                        for rest in &batch[i + 1..] { self.unload_scanned_region(rest, &loaded_here); }
+                        return Err(e);
+                    }
+                };
+                self.unload_scanned_region(region, &loaded_here);
+
+                progress.chunks_done += chunks.len();
+                progress.chunks_saved += saved;
+                progress.regions_done += 1;
+                progress.current = Some(region.clone());
+                if let Some(report) = &options.progress { report(&progress); }
+                if !keep_going {
+                    for rest in &batch[i + 1..] { self.unload_scanned_region(rest, &loaded_here); }
+                    return Ok(progress);
+                }
+            }
+        }
+        Ok(progress)
+    }
+
+    // Registered block regions the options ask for, in a fixed order
+    pub(crate) fn scan_targets(&self, options: &ScanOptions) -> Vec<RegionPosition> {
+        let line = REGION_CHUNK_LINE as i32 * self.version().data.chunk_size;
+        let (lowest_y, highest_y) = (self.version().data.lowest_y, self.version().data.highest_y - 1);
+        let mut regions = self.unloaded_regions().iter()
+            .filter(|r| matches!(r.region_type, RegionType::Block))
+            .map(|r| r.position.clone())
+            .filter(|p| options.dimensions.as_ref().is_none_or(|d| d.contains(p.dimension())))
+            .filter(|p| options.area.as_ref().is_none_or(|area| {
+                let (x, z) = (p.x() * line, p.z() * line);
+                area.intersects_box(p.dimension(), (x, lowest_y, z), (x + line - 1, highest_y, z + line - 1))
+            }))
+            .collect::<Vec<_>>();
+        regions.sort_by(|a, b| (a.dimension().get(), a.x(), a.z()).cmp(&(b.dimension().get(), b.x(), b.z())));
+        regions.dedup();
+        regions
+    }
+
+    // Loaded chunks of a region
+    pub(crate) fn region_chunk_positions(&self, region: &RegionPosition) -> Vec<(i32, i32)> {
+        let line = REGION_CHUNK_LINE as i32;
+        let Some(dimension) = self.dimension(region.dimension()) else { return Vec::new() };
+        dimension.chunk_positions().into_iter()
+            .filter(|(x, z)| x.div_euclid(line) == region.x() && z.div_euclid(line) == region.z())
+            .copied()
+            .collect()
+    }
+
+    pub(crate) fn save_dirty_chunks(&mut self, region: &RegionPosition, chunks: &[(i32, i32)], options: &SaveOptions) -> io::Result<usize> {
+        let Some(dimension) = self.dimension(region.dimension()) else { return Ok(0) };
+        let dirty = chunks.iter()
+            .filter(|p| dimension.chunk(**p).is_some_and(|c| c.lock().unwrap().has_unsaved_changes()))
+            .map(|p| ChunkPosition::new(p.0, p.1, *region.dimension()))
+            .collect::<Vec<_>>();
+        if dirty.is_empty() { return Ok(0); }
+        self.save_chunks(&dirty, options)
+    }
+
+    // Only the chunks the scan loaded, the ones that were loaded before stay
+    pub(crate) fn unload_scanned_region(&mut self, region: &RegionPosition, loaded_here: &HashSet<ChunkPosition>) {
+        let chunks = self.region_chunk_positions(region).into_iter()
+            .filter(|p| loaded_here.contains(&ChunkPosition::new(p.0, p.1, *region.dimension())))
+            .collect::<Vec<_>>();
+        if let Some(dimension) = self.dimension_mut(region.dimension()) { dimension.unload_chunks(&chunks); }
+    }
+}
+
+// The same walk as scan_regions a region at a time, for iterators that can't hand a callback to it (see
+// Selection::iter_blocks). The iterator calls advance when it's done with a region's chunks, and finish when it's
+// finished or dropped before the end. A region that couldn't be saved stays loaded and the walk stops there
+pub(crate) struct RegionCursor {
+    options: ScanOptions,
+    regions: VecDeque<RegionPosition>,
+    current: Option<RegionPosition>,
+    loaded_here: HashSet<ChunkPosition>,
+    progress: ScanProgress,
+}
+
+impl RegionCursor {
+    pub(crate) fn new(world: &World<'_>, options: ScanOptions) -> Self {
+        let regions = world.scan_targets(&options);
+        let progress = ScanProgress { regions_total: regions.len(), ..Default::default() };
+        Self { options, regions: regions.into(), current: None, loaded_here: HashSet::new(), progress }
+    }
+
+    // Finishes the current region and loads the next one, returns its chunks. None once every region was visited
+    pub(crate) fn advance(&mut self, world: &mut World<'_>) -> io::Result<Option<Vec<ChunkPosition>>> {
+        self.finish(world)?;
+        let Some(region) = self.regions.pop_front() else { return Ok(None) };
+        self.loaded_here = world.load_region_files(std::slice::from_ref(&region), self.options.concurrency, self.options.entities).into_iter().collect();
+        let chunks = world.region_chunk_positions(&region).into_iter().map(|(x, z)| ChunkPosition::new(x, z, *region.dimension())).collect();
+        self.current = Some(region);
+        Ok(Some(chunks))
+    }
+
+    // Saves the current region when the options say so, then unloads what was loaded for it. It isn't unloaded when
+    // the save fails, its changes stay in the world
+    pub(crate) fn finish(&mut self, world: &mut World<'_>) -> io::Result<()> {
+        let Some(region) = self.current.take() else { return Ok(()) };
+        let chunks = world.region_chunk_positions(&region);
+        if let Some(save) = &self.options.save { self.progress.chunks_saved += world.save_dirty_chunks(&region, &chunks, save)?; }
+        world.unload_scanned_region(&region, &self.loaded_here);
+        self.loaded_here.clear();
+
+        self.progress.chunks_done += chunks.len();
+        self.progress.regions_done += 1;
+        self.progress.current = Some(region);
+        if let Some(report) = &self.options.progress { report(&self.progress); }
+        Ok(())
+    }
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::process;
+    use std::sync::Mutex;
+    use crate::constants::versions::VersionManager;
+    use crate::models::entity::entity::{Entity, EntityType};
+    use crate::models::filter::filter::Filter;
+    use crate::models::other::properties::Properties;
+    use crate::models::other::tick::Tick;
+    use crate::models::positions::entity_position::EntityPosition;
+    use crate::models::positions::whole_position::Position;
+    use crate::models::world::block::PaletteBlock;
+    use crate::models::world::chunk::Chunk;
+    use crate::models::world::dimension::Dimension;
+    use crate::traits::access::prelude::BlockReader;
+    use crate::types::WorldKind;
+
+    fn pos(x: i32, y: i32, z: i32) -> Position { Position::new("overworld".into(), x, y, z) }
+
+    fn count(selection: &mut Selection, id: &str) -> usize {
+        let mut found = 0;
+        selection.find_blocks(Filter::from_filter_string(&format!("id == {}", id)).unwrap(), |_| { found += 1; true });
+        found
+    }
+
+    #[test]
+    fn streams_regions() {
+        let directory = std::env::temp_dir().join(format!("cubicle_scan_test_{}", process::id()));
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        {
+            let world = World::new(directory.clone(), version.clone());
+            let mut dimension = Dimension::new("overworld".into(), version.clone());
+            for (x, z) in [(0, 0), (1, 0), (32, 0), (-1, -1)] {
+                dimension.set_chunk(Chunk::new(ChunkPosition::new(x, z, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+            }
+            let mut locked = world.lock().unwrap();
+            locked.set_dimension("overworld".into(), dimension);
+            locked.transaction(|tx| {
+                for at in [pos(1, 0, 1), pos(17, 0, 1), pos(520, 0, 3), pos(-3, 0, -3)] { tx.set_block(&at, PaletteBlock::from("minecraft:glass")); }
+            }).unwrap();
+            assert_eq!(locked.save(&SaveOptions::new()).unwrap(), 4);
+        }
+
+        let world = World::new(directory.clone(), version.clone());
+        let mut locked = world.lock().unwrap();
+        assert_eq!(locked.register_regions(), 3);
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let seen = reports.clone();
+        let options = ScanOptions::new().with_concurrency(2).on_progress(move |p| seen.lock().unwrap().push((p.regions_done(), p.regions_total())));
+        let mut found = 0;
+        let progress = locked.scan_regions(&options, |selection| { found += count(selection, "minecraft:glass"); true }).unwrap();
+        assert_eq!(found, 4);
+        assert_eq!(progress.chunks_done(), 4);
+        assert_eq!(*reports.lock().unwrap(), vec![(1, 3), (2, 3), (3, 3)]);
+        assert_eq!(locked.dimension(&"overworld".into()).unwrap().chunk_count(), 0);
+
+        // edits are saved before a region is unloaded, only in the chunks that changed
+        let options = ScanOptions::new().with_save(SaveOptions::new()).within(Area::Shape(crate::models::world::shape::Shape::cuboid(&pos(0, -64, 0), &pos(600, 319, 100))));
+        let progress = locked.scan_regions(&options, |selection| {
+            selection.replace(Filter::from_filter_string("id == minecraft:glass").unwrap(), &PaletteBlock::from("minecraft:stone"));
+            true
+        }).unwrap();
+        assert_eq!((progress.regions_total(), progress.chunks_saved()), (2, 3));
+
+        // queries over all regions stream, a region that was loaded before stays loaded
+        locked.load_region(ChunkPosition::new(-1, -1, "overworld".into())).unwrap();
+        let mut selection = SelectionBuilder::new(&mut locked, &version).all_regions().build();
+        assert!(selection.is_streaming());
+        assert_eq!(count(&mut selection, "minecraft:stone"), 3);
+        assert_eq!(count(&mut selection, "minecraft:glass"), 1);
+        assert_eq!(selection.par_find_blocks(Filter::from_filter_string("id == minecraft:stone").unwrap(), || 0, |n, _| n + 1, |a, b| a + b), 3);
+        assert_eq!(selection.iter_blocks().filter(|b| b.id() == "minecraft:stone").count(), 3);
+        // an iterator dropped halfway unloads the region it was in
+        assert!(selection.iter_blocks().next().is_some());
+        assert_eq!(locked.dimension(&"overworld".into()).unwrap().chunk_count(), 1);
+        let mut selection = SelectionBuilder::new(&mut locked, &version).all_regions().cuboid(&pos(0, 0, 0), &pos(31, 0, 31)).build();
+        assert_eq!(count(&mut selection, "minecraft:stone"), 2);
+        assert_eq!(locked.dimension(&"overworld".into()).unwrap().chunk_count(), 1);
+
+        // a region that can't be saved stays loaded with its changes and the error comes back
+        let blocked = directory.join("region").join("r.1.0.mca");
+        let block = || { fs::remove_file(&blocked).unwrap(); fs::create_dir_all(blocked.join("blocked")).unwrap(); };
+        let options = ScanOptions::new().with_save(SaveOptions::new()).within(Area::Shape(crate::models::world::shape::Shape::cuboid(&pos(512, -64, 0), &pos(600, 319, 100))));
+        let result = locked.scan_regions(&options, |selection| {
+            selection.replace(Filter::from_filter_string("id == minecraft:stone").unwrap(), &PaletteBlock::from("minecraft:glass"));
+            block();
+            true
+        });
+        assert!(result.is_err());
+        let chunk = locked.dimension(&"overworld".into()).unwrap().chunk((32, 0)).unwrap();
+        assert!(chunk.lock().unwrap().is_dirty());
+        assert_eq!(locked.dimension(&"overworld".into()).unwrap().chunk_count(), 2);
+
+        // streaming queries keep the error for take_stream_error
+        fs::remove_dir_all(&blocked).unwrap();
+        assert_eq!(locked.save(&SaveOptions::new()).unwrap(), 2);
+        chunk.lock().unwrap().mark_dirty();
+        let mut selection = SelectionBuilder::new(&mut locked, &version).all_regions_with(ScanOptions::new().with_save(SaveOptions::new())).cuboid(&pos(512, 0, 0), &pos(527, 0, 15)).build();
+        let mut blocked_once = false;
+        selection.blocks(|_| { if !blocked_once { block(); blocked_once = true; } true });
+        assert!(selection.take_stream_error().is_some());
+        assert!(selection.take_stream_error().is_none());
+
+        // and the iterators' finish hands it back
+        fs::remove_dir_all(&blocked).unwrap();
+        assert_eq!(locked.save(&SaveOptions::new()).unwrap(), 2);
+        chunk.lock().unwrap().mark_dirty();
+        let options = ScanOptions::new().with_save(SaveOptions::new()).within(Area::Shape(crate::models::world::shape::Shape::cuboid(&pos(512, 0, 0), &pos(527, 0, 15))));
+        let mut selection = SelectionBuilder::new(&mut locked, &version).all_regions_with(options.clone()).build();
+        let mut blocks = selection.iter_blocks();
+        assert!(blocks.next().is_some());
+        block();
+        assert!(blocks.finish().is_err());
+
+        // a region the walk loaded stays loaded when it can't be saved
+        fs::remove_dir_all(&blocked).unwrap();
+        assert_eq!(locked.save(&SaveOptions::new()).unwrap(), 2);
+        assert_eq!(locked.unload_region(&ChunkPosition::new(1, 0, "overworld".into()), None).unwrap(), 1);
+        let loaded = locked.dimension(&"overworld".into()).unwrap().chunk_count();
+        let mut cursor = RegionCursor::new(&locked, options);
+        assert_eq!(cursor.advance(&mut locked).unwrap().unwrap().len(), 1);
+        locked.dimension(&"overworld".into()).unwrap().chunk((32, 0)).unwrap().lock().unwrap().mark_dirty();
+        block();
+        assert!(cursor.finish(&mut locked).is_err());
+        assert_eq!(locked.dimension(&"overworld".into()).unwrap().chunk_count(), loaded + 1);
+        assert!(locked.dimension(&"overworld".into()).unwrap().chunk((32, 0)).unwrap().lock().unwrap().is_dirty());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn unloading_chunks_drops_their_entities() {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        for x in 0..3 {
+            dimension.set_chunk(Chunk::new(ChunkPosition::new(x, 0, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+        }
+        for i in 0..9u128 {
+            dimension.add_entity(Entity::new(EntityType::Mob, Tick::new(0), 0.0, Tick::new(0), false, (0.0, 0.0, 0.0), true,
+                EntityPosition::new((i % 3) as f64 * 16.0 + 1.0, 0.0, 1.0, 0.0, 0.0, "overworld".into()), i, Properties::new(HashMap::new())));
+        }
+        assert_eq!(dimension.unload_chunks(&[(1, 0)]).len(), 1);
+        assert_eq!(dimension.entity_count(), 6);
+        for x in [0, 2] {
+            let chunk = dimension.chunk((x, 0)).unwrap();
+            let uuids = chunk.lock().unwrap().entity_keys().iter().map(|k| dimension.entity_store().get(*k).base().uuid()).collect::<Vec<_>>();
+            assert_eq!(uuids.len(), 3);
+            assert!(uuids.iter().all(|uuid| (uuid % 3) as i32 == x / 2 * 2));
+        }
+    }
+}
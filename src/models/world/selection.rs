@@ -1,22 +1,39 @@
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::ops::Range;
 use std::sync::{Arc, MutexGuard};
+use crate::constants::constants::BIOME_CELL_SIZE;
 use crate::constants::versions::Version;
 use crate::models::entity::entity::Entity;
 use crate::models::filter::filter::Filter;
+use crate::models::filter::query_plan::QueryPlan;
 use crate::models::other::lasso_string::LassoString;
 use crate::models::positions::chunk_position::ChunkPosition;
 use crate::models::positions::whole_position::Position;
+use crate::models::stores::entity_store::EntityStoreKey;
+use crate::models::world::block_index::key_id;
+use crate::models::world::block::PaletteBlock;
+use crate::models::world::chunk::Chunk;
+use crate::models::world::pattern::Pattern;
+use crate::models::world::scan::{RegionCursor, ScanOptions};
+use crate::models::world::fulls::block_state_types::Direction;
 use crate::models::world::fulls::full_block::FullBlock;
 use crate::models::world::fulls::full_entity::FullEntity;
+use crate::models::world::area::Area;
+use crate::models::world::shape::Shape;
 use crate::models::world::world::World;
 use crate::traits::access::prelude::{BlockReader, BlockWriter, EntityReader, EntityWriter};
 use crate::types::{ChunkType, WorldType};
 use crate::utils::lock_utils::WithLock;
+use crate::utils::parallel::parallel_fold;
 use crate::utils::position_utils::{block_index_to_block_position, block_position_to_chunk_pos_and_block_index, chunk_position_to_world_position, world_position_to_chunk_position};
 
 pub struct Selection<'r, 'a> {
     cached_chunks: HashMap<ChunkPosition, Option<ChunkType>>,
+    clip: Option<Area>, // blocks and entities outside of it are skipped, None means the whole chunks
+    streaming: Option<ScanOptions>, // queries go through every region on disk, see SelectionBuilder::all_regions
+    stream_error: Option<io::Error>,
     world_ref: &'r mut World<'a>,
     version: Arc<Version>,
 }
@@ -24,7 +41,78 @@ pub struct Selection<'r, 'a> {
 // TODO: This takes space and speed and it should just be an abstraction, i dont really like this... Figure out how to make this cheaper
 impl<'r, 'a> Selection<'r, 'a> {
     pub fn new(world: &'r mut World<'a>, version: Arc<Version>) -> Self {
-        Self {cached_chunks: HashMap::new(), world_ref: world, version}
+        Self {cached_chunks: HashMap::new(), clip: None, streaming: None, stream_error: None, world_ref: world, version}
+    }
+
+    pub fn clip(&self) -> Option<&Area> { self.clip.as_ref() }
+    pub fn is_streaming(&self) -> bool { self.streaming.is_some() }
+    // What stopped the last query over all regions (reading or saving a region failed), its result only covers the
+    // regions before that one. A selected chunk that couldn't be read from disk is kept here as well and skipped
+    pub fn take_stream_error(&mut self) -> Option<io::Error> { self.stream_error.take() }
+
+    pub fn contains(&self, position: &Position) -> bool {
+        let chunk_pos = block_position_to_chunk_pos_and_block_index(position, self.version.data.chunk_size, self.version.data.lowest_y).0;
+        self.cached_chunks.contains_key(&chunk_pos) && self.clip.as_ref().is_none_or(|a| a.contains(position))
+    }
+
+    // The selected blocks, to combine with other selections or to select again later
+    pub fn area(&self) -> Area {
+        let chunks = Area::chunks(self.cached_chunks.keys().cloned(), self.version.data.chunk_size, self.version.data.lowest_y, self.version.data.highest_y - 1);
+        match &self.clip {
+            Some(clip) => chunks.intersect(clip.clone()),
+            None => chunks
+        }
+    }
+
+    // Selects exactly the blocks of the area (in loaded chunks or on disk). Chunks that stay selected keep their cached handle
+    fn set_area(&mut self, area: Area) {
+        let chunk_size = self.version.data.chunk_size;
+        let mut chunks = HashMap::new();
+        for chunk_pos in area.chunk_positions(chunk_size) {
+            if !self.world_ref.chunk_available(&chunk_pos) { continue; }
+            let cached = self.cached_chunks.remove(&chunk_pos).flatten();
+            chunks.insert(chunk_pos, cached);
+        }
+        self.cached_chunks = chunks;
+        self.clip = Some(area);
+    }
+
+    // Set operations, the result is a normal selection of the same world
+    pub fn union(mut self, other: &Area) -> Self { let area = self.area().union(other.clone()); self.set_area(area); self }
+    pub fn intersect(mut self, other: &Area) -> Self { let area = self.area().intersect(other.clone()); self.set_area(area); self }
+    pub fn subtract(mut self, other: &Area) -> Self { let area = self.area().subtract(other.clone()); self.set_area(area); self }
+    pub fn shift(mut self, offset: (i32, i32, i32)) -> Self { let area = self.area().shifted(offset); self.set_area(area); self }
+    pub fn expand(mut self, amount: i32, directions: &[Direction]) -> Self { let area = self.area().expand(amount, directions); self.set_area(area); self }
+    pub fn contract(mut self, amount: i32, directions: &[Direction]) -> Self { let area = self.area().contract(amount, directions); self.set_area(area); self }
+    pub fn outset_to_chunks(mut self) -> Self {
+        let area = self.area().outset_to_chunks(self.version.data.chunk_size);
+        self.set_area(area);
+        self
+    }
+
+    // Inclusive block corners of a chunk, over the whole world height
+    fn chunk_box(&self, chunk_pos: &ChunkPosition) -> ((i32, i32, i32), (i32, i32, i32)) {
+        let chunk_size = self.version.data.chunk_size;
+        let (x, z) = chunk_position_to_world_position(chunk_pos.position(), chunk_size);
+        ((x, self.version.data.lowest_y, z), (x + chunk_size - 1, self.version.data.highest_y - 1, z + chunk_size - 1))
+    }
+
+    // Entities are selected when the block they're in is
+    fn entity_in_clip(&self, dimension: &LassoString, entity_key: EntityStoreKey) -> bool {
+        let Some(clip) = &self.clip else { return true };
+        let Some(dim) = self.world_ref.dimension(dimension) else { return false };
+        let position = dim.entity_store().get(entity_key).base().position();
+        clip.contains_at(dimension, position.i_x(), position.i_y(), position.i_z())
+    }
+
+    fn clip_touches_chunk(&self, chunk_pos: &ChunkPosition) -> bool {
+        match &self.clip {
+            Some(clip) => {
+                let (min, max) = self.chunk_box(chunk_pos);
+                clip.intersects_box(chunk_pos.dimension(), min, max)
+            }
+            None => true
+        }
     }
 
     pub fn chunk(&mut self, chunk_position: ChunkPosition) -> Option<ChunkType> {
@@ -35,65 +123,783 @@ impl<'r, 'a> Selection<'r, 'a> {
         self.cached_chunks.len()
     }
 
+    pub fn chunk_positions(&self) -> Vec<ChunkPosition> { self.cached_chunks.keys().cloned().collect() }
+    pub fn version(&self) -> &Arc<Version> { &self.version }
+
+    // Copies of the selected entities, read straight from the entity stores. Unlike entities() this doesn't lock
+    // the world, so it can be used while the world is borrowed through a lock
+    pub fn entity_snapshots(&mut self) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        if self.streaming.is_some() {
+            self.stream_error = self.stream(|selection| { entities.extend(selection.entity_snapshots()); true }).err();
+            return entities;
+        }
+        for chunk_pos in self.chunk_positions() {
+            let dim_id = *chunk_pos.dimension();
+            let Some(chunk) = self.lazy_get_chunk(chunk_pos) else { continue };
+            let keys = chunk.lock().unwrap().entity_keys().clone();
+            let Some(dim) = self.world_ref.dimension(&dim_id) else { continue };
+            for key in keys {
+                if self.entity_in_clip(&dim_id, key) { entities.push(dim.entity_store().get(key).clone()); }
+            }
+        }
+        entities
+    }
+
+    // Runs query on every region in turn, as a selection of that region's (loaded) chunks with the same clip. The
+    // queries that can't return the error keep it for take_stream_error
+    fn stream<F>(&mut self, query: F) -> io::Result<()> where F: FnMut(&mut Selection<'_, 'a>) -> bool {
+        let Some(mut options) = self.streaming.clone() else { return Ok(()) };
+        if let Some(clip) = &self.clip {
+            let area = match options.area() { Some(area) => area.clone().intersect(clip.clone()), None => clip.clone() };
+            options = options.within(area);
+        }
+        self.world_ref.scan_regions(&options, query).map(|_| ())
+    }
+
+    // Selected chunks that aren't loaded are read from disk. With a chunk cache the handle is looked up again every
+    // time, the chunk could have been evicted (and loaded again) since it was cached here
     fn lazy_get_chunk(&mut self, chunk_pos: ChunkPosition) -> Option<ChunkType> {
         match self.cached_chunks.entry(chunk_pos) {
             Entry::Occupied(mut occ) => {
-                let chunk_pos = occ.key().clone();
-                let slot = occ.get_mut();
-                if slot.is_none() {
-                    let c = self.world_ref.dimension_mut(chunk_pos.dimension()).unwrap().chunk_mut(chunk_pos.position());
-                    *slot = c;
+                if occ.get().is_none() || self.world_ref.chunk_cache().is_some() {
+                    let chunk_pos = occ.key().clone();
+                    match self.world_ref.load_chunk(&chunk_pos) {
+                        Ok(Some(c)) => { *occ.get_mut() = Some(c); }
+                        Ok(None) => {}
+                        Err(e) => { self.stream_error = Some(e); }
+                    }
                 }
-                slot.clone()
+                occ.get().clone()
             }
             Entry::Vacant(_) => { None }
         }
     }
 }
 
-impl<'r, 'a> BlockReader<'a> for Selection<'r, 'a> {
+// Biome related
+impl<'r, 'a> Selection<'r, 'a> {
+    pub fn biome_at(&mut self, position: Position) -> Option<String> {
+        let chunk_pos = block_position_to_chunk_pos_and_block_index(&position, self.version.data.chunk_size, self.version.data.lowest_y).0;
+        let chunk = self.lazy_get_chunk(chunk_pos)?;
+        let locked = chunk.lock().unwrap();
+        locked.biome_store().get_biome_at_block_position(position)
+    }
 
-    // callback can return bool. true means continue, false means stop
-    fn blocks<F>(&mut self, mut callback: F) where F: FnMut(FullBlock<'a>) -> bool,
-    {
+    // Biomes are stored per 4x4x4 cell, so every cell the box touches is changed. Returns the amount of cells set.
+    pub fn set_biome_in_box(&mut self, corner1: &Position, corner2: &Position, biome: &str) -> usize {
+        if corner1.dimension() != corner2.dimension() { return 0; }
         let chunk_size = self.version.data.chunk_size;
-        let min_y = self.version.data.lowest_y;
+        let (min_x, max_x) = (corner1.x().min(corner2.x()), corner1.x().max(corner2.x()));
+        let (min_z, max_z) = (corner1.z().min(corner2.z()), corner1.z().max(corner2.z()));
+        let min_y = corner1.y().min(corner2.y()).max(self.version.data.lowest_y);
+        let max_y = corner1.y().max(corner2.y()).min(self.version.data.highest_y - 1);
+
+        let chunk_poses = self.cached_chunks.keys()
+            .filter(|c| c.dimension() == corner1.dimension())
+            .filter(|c| {
+                let (world_x, world_z) = chunk_position_to_world_position(c.position(), chunk_size);
+                world_x <= max_x && world_x + chunk_size > min_x && world_z <= max_z && world_z + chunk_size > min_z
+            })
+            .cloned().collect::<Vec<_>>();
+
+        let cell_range = |min: i32, max: i32, base: i32| {
+            let from = (min.max(base) - base) / BIOME_CELL_SIZE;
+            let to = (max.min(base + chunk_size - 1) - base) / BIOME_CELL_SIZE;
+            from..=to
+        };
 
+        let mut count = 0;
+        for chunk_pos in chunk_poses {
+            let Some(chunk) = self.lazy_get_chunk(chunk_pos) else { continue };
+            let mut locked = chunk.lock().unwrap();
+            let (world_x, world_z) = chunk_position_to_world_position(locked.position().position(), chunk_size);
+            let biome_store = locked.biome_store_mut();
+
+            for cell_y in min_y.div_euclid(BIOME_CELL_SIZE)..=max_y.div_euclid(BIOME_CELL_SIZE) {
+                for cell_z in cell_range(min_z, max_z, world_z) {
+                    for cell_x in cell_range(min_x, max_x, world_x) {
+                        let index = biome_store.biome_index_at_block(cell_x * BIOME_CELL_SIZE, cell_y * BIOME_CELL_SIZE, cell_z * BIOME_CELL_SIZE);
+                        if let Some(index) = index {
+                            if biome_store.set_biome_at_index(index, biome.to_string()) { count += 1; }
+                        }
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    pub fn replace_biome(&mut self, from: &str, to: &str) -> usize {
+        let mut count = 0;
         let chunk_poses = self.cached_chunks.keys().cloned().collect::<Vec<_>>();
         for chunk_pos in chunk_poses {
-            let chunk = match self.lazy_get_chunk(chunk_pos) {
-                Some(c) => c,
-                None => continue,
+            let Some(chunk) = self.lazy_get_chunk(chunk_pos) else { continue };
+            count += chunk.lock().unwrap().biome_store_mut().replace_biome(from, to);
+        }
+        count
+    }
+}
+
+// Block helpers
+const HORIZONTAL_NEIGHBOURS: [(i32, i32, i32); 4] = [(1, 0, 0), (-1, 0, 0), (0, 0, 1), (0, 0, -1)];
+const NEIGHBOURS: [(i32, i32, i32); 6] = [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)];
+
+// Bulk editing with a Pattern, or a single block. Blocks are written straight into the block stores a chunk at a time
+// (see Chunk::set_blocks_at_indices), and every operation decides what to change before changing anything.
+// They return how many blocks changed
+impl<'r, 'a> Selection<'r, 'a> {
+    // (x, y, z) of a block index, x and z relative to the chunk
+    fn index_to_xyz(&self, index: usize) -> (i32, i32, i32) {
+        let chunk_size = self.version.data.chunk_size as usize;
+        ((index % chunk_size) as i32, (index / (chunk_size * chunk_size)) as i32 + self.version.data.lowest_y, ((index / chunk_size) % chunk_size) as i32)
+    }
+
+    fn is_selected_at(&self, dimension: &LassoString, x: i32, y: i32, z: i32) -> bool {
+        if y < self.version.data.lowest_y || y >= self.version.data.highest_y { return false; }
+        let chunk_size = self.version.data.chunk_size;
+        self.cached_chunks.contains_key(&ChunkPosition::new(x.div_euclid(chunk_size), z.div_euclid(chunk_size), *dimension))
+            && self.clip.as_ref().is_none_or(|clip| clip.contains_at(dimension, x, y, z))
+    }
+
+    // The selected block indices of one of the selected chunks, in ascending order
+    fn selected_indices(&self, chunk: &Chunk) -> Vec<usize> {
+        let total = chunk.block_store().indices_slice().len();
+        let Some(clip) = &self.clip else { return (0..total).collect() };
+        let dimension = chunk.position().dimension();
+        let Some((clip_min, clip_max)) = clip.bounds(dimension) else { return Vec::new() };
+
+        let min_y = self.version.data.lowest_y;
+        let layer_size = (self.version.data.chunk_size * self.version.data.chunk_size) as usize;
+        let first = (clip_min.1.max(min_y) - min_y) as usize * layer_size;
+        let last = ((clip_max.1 - min_y + 1).max(0) as usize * layer_size).min(total);
+        let (world_x, world_z) = chunk_position_to_world_position(chunk.position().position(), self.version.data.chunk_size);
+        (first..last).filter(|index| {
+            let (x, y, z) = self.index_to_xyz(*index);
+            clip.contains_at(dimension, world_x + x, y, world_z + z)
+        }).collect()
+    }
+
+    // Records into the world's edit history as one transaction named after the operation
+    fn set_blocks(&mut self, name: &str, edits: Vec<(ChunkPosition, Vec<usize>)>, pattern: &Pattern) -> usize {
+        let blocks = pattern.blocks();
+        if blocks.is_empty() { return 0; }
+        self.world_ref.history_mut().begin(name);
+        let mut changed = 0;
+        for (chunk_pos, indices) in edits {
+            if indices.is_empty() { continue; }
+            let Some(chunk) = self.lazy_get_chunk(chunk_pos.clone()) else { continue };
+            let (world_x, world_z) = chunk_position_to_world_position(chunk_pos.position(), self.version.data.chunk_size);
+            let mut chunk = chunk.lock().unwrap();
+            let edit = chunk.edit_blocks_at_indices(&indices, &blocks, |index| {
+                let (x, y, z) = self.index_to_xyz(index);
+                pattern.choose(world_x + x, y, world_z + z)
+            });
+            self.world_ref.history_mut().record_chunk_edit(&chunk, &edit);
+            changed += edit.count();
+        }
+        self.world_ref.history_mut().commit();
+        changed
+    }
+
+    // Selected blocks that have an unselected neighbour at one of the offsets
+    fn border_edits(&mut self, offsets: &[(i32, i32, i32)]) -> Vec<(ChunkPosition, Vec<usize>)> {
+        let mut edits = Vec::new();
+        for chunk_pos in self.chunk_positions() {
+            let Some(chunk) = self.lazy_get_chunk(chunk_pos.clone()) else { continue };
+            let indices = self.selected_indices(&chunk.lock().unwrap());
+            let (world_x, world_z) = chunk_position_to_world_position(chunk_pos.position(), self.version.data.chunk_size);
+            let border = indices.into_iter().filter(|index| {
+                let (x, y, z) = self.index_to_xyz(*index);
+                offsets.iter().any(|(dx, dy, dz)| !self.is_selected_at(chunk_pos.dimension(), world_x + x + dx, y + dy, world_z + z + dz))
+            }).collect();
+            edits.push((chunk_pos, border));
+        }
+        edits
+    }
+
+    pub fn fill(&mut self, pattern: impl Into<Pattern>) -> usize {
+        let pattern = pattern.into();
+        let mut edits = Vec::new();
+        for chunk_pos in self.chunk_positions() {
+            let Some(chunk) = self.lazy_get_chunk(chunk_pos.clone()) else { continue };
+            let indices = self.selected_indices(&chunk.lock().unwrap());
+            edits.push((chunk_pos, indices));
+        }
+        self.set_blocks("fill", edits, &pattern)
+    }
+
+    // Goes through find_blocks, so the filter can use anything it normally can (neighbours, biomes, block entities...)
+    pub fn replace(&mut self, filter: Filter, pattern: impl Into<Pattern>) -> usize {
+        let (chunk_size, lowest_y) = (self.version.data.chunk_size, self.version.data.lowest_y);
+        let mut edits: HashMap<ChunkPosition, Vec<usize>> = HashMap::new();
+        self.find_blocks(filter, |found| {
+            let (chunk_pos, index) = block_position_to_chunk_pos_and_block_index(found.position(), chunk_size, lowest_y);
+            edits.entry(chunk_pos).or_default().push(index);
+            true
+        });
+        self.set_blocks("replace", edits.into_iter().collect(), &pattern.into())
+    }
+
+    // The sides of the selection, without its top and bottom
+    pub fn walls(&mut self, pattern: impl Into<Pattern>) -> usize {
+        let edits = self.border_edits(&HORIZONTAL_NEIGHBOURS);
+        self.set_blocks("walls", edits, &pattern.into())
+    }
+
+    // Every face of the selection
+    pub fn outline(&mut self, pattern: impl Into<Pattern>) -> usize {
+        let edits = self.border_edits(&NEIGHBOURS);
+        self.set_blocks("outline", edits, &pattern.into())
+    }
+
+    // Empties every solid block whose six neighbours are selected and solid, so what's in the selection is left as
+    // shells one block thick
+    pub fn hollow(&mut self) -> usize {
+        let (chunk_size, lowest_y) = (self.version.data.chunk_size, self.version.data.lowest_y);
+        self.world_ref.pause_eviction();
+        let neighbours = self.chunks_around_selection(1);
+        let mut edits = Vec::new();
+        for chunk_pos in self.chunk_positions() {
+            let Some(chunk) = self.lazy_get_chunk(chunk_pos.clone()) else { continue };
+            let chunk = chunk.lock().unwrap();
+            let dimension = chunk_pos.dimension();
+            let (world_x, world_z) = chunk_position_to_world_position(chunk_pos.position(), chunk_size);
+            let store = chunk.block_store();
+            let solid = store.palette().iter().map(|b| !b.is_air()).collect::<Vec<_>>();
+
+            let is_solid_at = |x: i32, y: i32, z: i32| {
+                if !self.is_selected_at(dimension, x, y, z) { return false; }
+                let (neighbour_pos, index) = block_position_to_chunk_pos_and_block_index(&Position::new(*dimension, x, y, z), chunk_size, lowest_y);
+                if neighbour_pos == chunk_pos { return solid[store.indices_slice()[index]]; }
+                neighbours.get(&neighbour_pos)
+                    .and_then(|c| c.lock().unwrap().block_store().get_block_at_index(index))
+                    .is_some_and(|b| !b.is_air())
             };
+            let enclosed = self.selected_indices(&chunk).into_iter().filter(|index| {
+                if !solid[store.indices_slice()[*index]] { return false; }
+                let (x, y, z) = self.index_to_xyz(*index);
+                NEIGHBOURS.iter().all(|(dx, dy, dz)| is_solid_at(world_x + x + dx, y + dy, world_z + z + dz))
+            }).collect();
+            edits.push((chunk_pos, enclosed));
+        }
+        drop(neighbours);
+        let resumed = self.world_ref.resume_eviction();
+        self.world_ref.keep_eviction_error(resumed);
+        self.set_blocks("hollow", edits, &Pattern::Block(PaletteBlock::new("minecraft:air", None)))
+    }
+
+    // Puts the block on top of the highest solid block of every selected column, where the spot above it is
+    // selected and empty
+    pub fn overlay(&mut self, pattern: impl Into<Pattern>) -> usize {
+        let chunk_size = self.version.data.chunk_size;
+        let layer_size = (chunk_size * chunk_size) as usize;
+        let mut edits = Vec::new();
+        for chunk_pos in self.chunk_positions() {
+            let Some(chunk) = self.lazy_get_chunk(chunk_pos.clone()) else { continue };
+            let chunk = chunk.lock().unwrap();
+            let (world_x, world_z) = chunk_position_to_world_position(chunk_pos.position(), chunk_size);
+            let store = chunk.block_store();
+            let palette = store.palette();
+            let indices = store.indices_slice();
+
+            let mut tops = vec![None; layer_size];
+            for index in self.selected_indices(&chunk) {
+                if !palette[indices[index]].is_air() { tops[index % layer_size] = Some(index); }
+            }
+            let above = tops.into_iter().flatten().map(|top| top + layer_size).filter(|index| {
+                if *index >= indices.len() || !palette[indices[*index]].is_air() { return false; }
+                let (x, y, z) = self.index_to_xyz(*index);
+                self.is_selected_at(chunk_pos.dimension(), world_x + x, y, world_z + z)
+            }).collect();
+            edits.push((chunk_pos, above));
+        }
+        self.set_blocks("overlay", edits, &pattern.into())
+    }
+}
+
+impl<'r, 'a> Selection<'r, 'a> {
+    // Same as blocks() but also gives the (locked) chunk the block is in. Blocks the plan rules out are skipped
+    // without being built: whole chunks outside its bounds or without a matching palette entry, then sections
+    // (e.g. uniform air) and single blocks whose palette index can't match
+    fn blocks_with_chunk<F>(&mut self, plan: &QueryPlan, mut callback: F) where F: FnMut(FullBlock<'a>, &Chunk) -> bool,
+    {
+        // resolved one at a time, with a chunk cache the chunks done with can be evicted as the next ones load
+        for chunk_pos in self.plan_chunk_positions(plan) {
+            let Some(chunk) = self.lazy_get_chunk(chunk_pos) else { continue };
             let actual_chunk = chunk.lock().unwrap();
+            if !self.chunk_blocks(plan, &actual_chunk, &mut callback) { return; }
+        }
+    }
+
+    // The selected chunks the plan can have blocks in
+    fn plan_chunk_positions(&self, plan: &QueryPlan) -> Vec<ChunkPosition> {
+        if plan.is_empty() { return Vec::new(); }
+        let chunk_size = self.version.data.chunk_size;
+        self.cached_chunks.keys()
+            .filter(|chunk_pos| {
+                let world_chunk_position = chunk_position_to_world_position(chunk_pos.position(), chunk_size);
+                plan.may_contain_chunk(world_chunk_position.0, world_chunk_position.1, chunk_size) && self.clip_touches_chunk(chunk_pos)
+            })
+            .cloned()
+            .collect()
+    }
 
-            let mut relative_pos = (0, min_y, 0);
-            let chunk_position = actual_chunk.position();
-            let world_chunk_position = chunk_position_to_world_position(chunk_position.position(), chunk_size);
+    // Same, resolved up front for the par_ scans. Evictions are paused until resume_eviction, the scan holds them all
+    fn plan_chunks(&mut self, plan: &QueryPlan) -> Vec<ChunkType> {
+        self.world_ref.pause_eviction();
+        self.plan_chunk_positions(plan).into_iter().filter_map(|chunk_pos| self.lazy_get_chunk(chunk_pos)).collect()
+    }
+
+    // The blocks of one chunk for blocks_with_chunk, false when the callback stopped
+    fn chunk_blocks<F>(&self, plan: &QueryPlan, actual_chunk: &Chunk, callback: &mut F) -> bool where F: FnMut(FullBlock<'a>, &Chunk) -> bool,
+    {
+        self.chunk_blocks_in(plan, actual_chunk, 0..usize::MAX, callback)
+    }
 
-            for b in actual_chunk.block_store().blocks() {
-                let pos = Position::new(chunk_position.dimension().clone(), world_chunk_position.0 + relative_pos.0, relative_pos.1, world_chunk_position.1 + relative_pos.2);
+    // Only the blocks of the sections in the range (counted from the bottom)
+    fn chunk_blocks_in<F>(&self, plan: &QueryPlan, actual_chunk: &Chunk, sections: Range<usize>, callback: &mut F) -> bool where F: FnMut(FullBlock<'a>, &Chunk) -> bool,
+    {
+        let chunk_size = self.version.data.chunk_size;
+        let min_y = self.version.data.lowest_y;
+        let layer_size = (chunk_size * chunk_size) as usize;
+        let section_size = layer_size * self.version.data.section_height as usize;
+        let (plan_min_y, plan_max_y) = plan.bounds()[1];
+
+        let chunk_position = actual_chunk.position();
+        let world_chunk_position = chunk_position_to_world_position(chunk_position.position(), chunk_size);
+        let (from_y, to_y) = match &self.clip {
+            Some(clip) => match clip.bounds(chunk_position.dimension()) {
+                Some((clip_min, clip_max)) => (plan_min_y.max(clip_min.1), plan_max_y.min(clip_max.1)),
+                None => return true
+            },
+            None => (plan_min_y, plan_max_y)
+        };
+
+        let block_store = actual_chunk.block_store();
+        let palette = block_store.palette();
+        let mask = plan.palette_mask(palette);
+        if mask.as_ref().is_some_and(|m| !m.contains(&true)) { return true; }
+        // palettes can keep entries that aren't used anymore, the block index has the real counts
+        if plan.restricts_ids() {
+            let counts = self.world_ref.block_index().and_then(|i| i.chunk_counts(chunk_position, Some(block_store.revision())));
+            if counts.is_some_and(|counts| !counts.keys().any(|key| plan.matches_id(key_id(key)))) { return true; }
+        }
+
+        let indices = block_store.indices_slice();
+        let first = (from_y.max(min_y) - min_y) as usize * layer_size;
+        let last = if to_y < min_y { 0 } else {
+            ((to_y as i64 - min_y as i64 + 1) as usize).saturating_mul(layer_size).min(indices.len())
+        };
+        let biome_store = actual_chunk.biome_store();
+        let biome_palette = biome_store.interned_palette();
+
+        let first = first.max(sections.start.saturating_mul(section_size));
+        let last = last.min(sections.end.saturating_mul(section_size));
+        let mut section_start = first - first % section_size;
+        while section_start < last {
+            let (start, end) = (section_start.max(first), (section_start + section_size).min(last));
+            section_start += section_size;
+            let section = &indices[start..end];
+            if let Some(mask) = &mask {
+                if !section.iter().any(|i| mask[*i]) { continue; }
+            }
+
+            for (offset, palette_index) in section.iter().enumerate() {
+                if mask.as_ref().is_some_and(|m| !m[*palette_index]) { continue; }
+                let index = start + offset;
+                let relative_pos = ((index % chunk_size as usize) as i32, (index / layer_size) as i32 + min_y, ((index / chunk_size as usize) % chunk_size as usize) as i32);
+                if self.clip.as_ref().is_some_and(|a| !a.contains_at(chunk_position.dimension(), world_chunk_position.0 + relative_pos.0, relative_pos.1, world_chunk_position.1 + relative_pos.2)) { continue; }
+                let pos = Position::new(*chunk_position.dimension(), world_chunk_position.0 + relative_pos.0, relative_pos.1, world_chunk_position.1 + relative_pos.2);
+                let biome = biome_store.biome_index_at_block(relative_pos.0, relative_pos.1, relative_pos.2)
+                    .and_then(|i| biome_palette[biome_store.indices_slice()[i]]);
                 if !callback(FullBlock::new_with_data(
                     &self.world_ref.get(),
-                    b,
+                    palette[*palette_index].clone(),
                     actual_chunk.block_entities_store().get_at_world_position(&pos),
-                    pos
-                )) { return; };
-
-                relative_pos.0 = relative_pos.0 + 1;
-                if relative_pos.0 % chunk_size == 0 {
-                    relative_pos.0 = 0;
-                    relative_pos.2 = relative_pos.2 + 1;
-                    if relative_pos.2 % chunk_size == 0 {
-                        relative_pos.2 = 0;
-                        relative_pos.1 += 1;
+                    pos,
+                    biome
+                ), actual_chunk) { return false; };
+            }
+        }
+        true
+    }
+
+    // Every chunk within reach blocks of the selection, for filters that look at neighbouring blocks. The ones that
+    // aren't loaded are loaded like the selected ones (World::load_chunk), so the caller pauses evictions while it
+    // holds on to them
+    fn chunks_around_selection(&mut self, reach: i32) -> HashMap<ChunkPosition, ChunkType> {
+        let chunk_reach = (reach + self.version.data.chunk_size - 1) / self.version.data.chunk_size;
+        let mut chunks = HashMap::new();
+        for chunk_pos in self.cached_chunks.keys().cloned().collect::<Vec<_>>() {
+            for dx in -chunk_reach..=chunk_reach {
+                for dz in -chunk_reach..=chunk_reach {
+                    let position = ChunkPosition::new(chunk_pos.x() + dx, chunk_pos.z() + dz, *chunk_pos.dimension());
+                    if chunks.contains_key(&position) { continue; }
+                    match self.world_ref.load_chunk(&position) {
+                        Ok(Some(chunk)) => { chunks.insert(position, chunk); }
+                        Ok(None) => {}
+                        Err(e) => { self.stream_error = Some(e); }
                     }
                 }
             }
         }
+        chunks
+    }
+}
+
+// Parallel scans
+// A chunk per worker (World::threads of them). Every worker folds what it finds into its own accumulator, starting
+// from init(), and the accumulators are combined with reduce. The callbacks run while the chunk is locked, so they
+// can't commit blocks or lock the world
+impl<'r, 'a> Selection<'r, 'a> {
+    pub fn par_blocks<T, I, F, R>(&mut self, init: I, fold: F, reduce: R) -> T
+    where T: Send, I: Fn() -> T + Sync, F: Fn(T, FullBlock<'a>) -> T + Sync, R: Fn(T, T) -> T,
+    {
+        if self.streaming.is_none() { return self.par_loaded_blocks(&init, &fold, &reduce); }
+        let mut total = Some(init());
+        self.stream_error = self.stream(|selection| { let part = selection.par_loaded_blocks(&init, &fold, &reduce); total = total.take().map(|t| reduce(t, part)); true }).err();
+        total.unwrap()
+    }
+
+    pub fn par_find_blocks<T, I, F, R>(&mut self, filter: Filter, init: I, fold: F, reduce: R) -> T
+    where T: Send, I: Fn() -> T + Sync, F: Fn(T, FullBlock<'a>) -> T + Sync, R: Fn(T, T) -> T,
+    {
+        if self.streaming.is_none() { return self.par_find_loaded_blocks(&filter, &init, &fold, &reduce); }
+        let mut total = Some(init());
+        self.stream_error = self.stream(|selection| { let part = selection.par_find_loaded_blocks(&filter, &init, &fold, &reduce); total = total.take().map(|t| reduce(t, part)); true }).err();
+        total.unwrap()
+    }
+
+    // Entities are read straight from the entity stores, like entity_snapshots
+    pub fn par_entities<T, I, F, R>(&mut self, init: I, fold: F, reduce: R) -> T
+    where T: Send, I: Fn() -> T + Sync, F: Fn(T, &Entity) -> T + Sync, R: Fn(T, T) -> T,
+    {
+        if self.streaming.is_none() { return self.par_loaded_entities(&init, &fold, &reduce); }
+        let mut total = Some(init());
+        self.stream_error = self.stream(|selection| { let part = selection.par_loaded_entities(&init, &fold, &reduce); total = total.take().map(|t| reduce(t, part)); true }).err();
+        total.unwrap()
+    }
+
+    fn par_loaded_blocks<T, I, F, R>(&mut self, init: &I, fold: &F, reduce: &R) -> T
+    where T: Send, I: Fn() -> T + Sync, F: Fn(T, FullBlock<'a>) -> T + Sync, R: Fn(T, T) -> T,
+    {
+        let plan = QueryPlan::any();
+        let chunks = self.plan_chunks(&plan);
+        let this = &*self;
+        let result = parallel_fold(chunks, self.world_ref.threads(), init, |accumulator, chunk| {
+            let mut accumulator = Some(accumulator);
+            this.chunk_blocks(&plan, &chunk.lock().unwrap(), &mut |block, _| {
+                accumulator = accumulator.take().map(|a| fold(a, block));
+                true
+            });
+            accumulator.unwrap()
+        }, reduce);
+        let resumed = self.world_ref.resume_eviction();
+        self.world_ref.keep_eviction_error(resumed);
+        result
+    }
+
+    fn par_find_loaded_blocks<T, I, F, R>(&mut self, filter: &Filter, init: &I, fold: &F, reduce: &R) -> T
+    where T: Send, I: Fn() -> T + Sync, F: Fn(T, FullBlock<'a>) -> T + Sync, R: Fn(T, T) -> T,
+    {
+        let plan = QueryPlan::from_filter(filter);
+        let reach = filter.reach();
+        let chunks = self.plan_chunks(&plan);
+        let (chunk_size, lowest_y) = (self.version.data.chunk_size, self.version.data.lowest_y);
+        let neighbours = if reach == 0 { HashMap::new() } else { self.chunks_around_selection(reach) };
+        let this = &*self;
+        let result = parallel_fold(chunks, self.world_ref.threads(), init, |accumulator, chunk| {
+            let mut accumulator = Some(accumulator);
+            if reach == 0 {
+                this.chunk_blocks(&plan, &chunk.lock().unwrap(), &mut |block, _| {
+                    if filter.matches_block(&block) { accumulator = accumulator.take().map(|a| fold(a, block)); }
+                    true
+                });
+                return accumulator.unwrap();
+            }
+
+            // Matched once the chunk is unlocked again, two workers each waiting on the other's chunk would deadlock
+            let mut candidates = Vec::new();
+            this.chunk_blocks(&plan, &chunk.lock().unwrap(), &mut |block, _| { candidates.push(block); true });
+            let mut lookup = |position: &Position| {
+                let (chunk_pos, index) = block_position_to_chunk_pos_and_block_index(position, chunk_size, lowest_y);
+                neighbours.get(&chunk_pos)?.lock().unwrap().block_store().get_block_at_index(index)
+            };
+            for block in candidates {
+                if filter.matches_block_with(&block, &mut lookup) { accumulator = accumulator.take().map(|a| fold(a, block)); }
+            }
+            accumulator.unwrap()
+        }, reduce);
+        drop(neighbours);
+        let resumed = self.world_ref.resume_eviction();
+        self.world_ref.keep_eviction_error(resumed);
+        result
+    }
+
+    fn par_loaded_entities<T, I, F, R>(&mut self, init: &I, fold: &F, reduce: &R) -> T
+    where T: Send, I: Fn() -> T + Sync, F: Fn(T, &Entity) -> T + Sync, R: Fn(T, T) -> T,
+    {
+        // an evicted chunk's entity keys would point into the compacted store
+        self.world_ref.pause_eviction();
+        let chunks = self.chunk_positions().into_iter()
+            .filter_map(|chunk_pos| self.lazy_get_chunk(chunk_pos.clone()).map(|chunk| (chunk_pos, chunk)))
+            .collect::<Vec<_>>();
+        let this = &*self;
+        let result = parallel_fold(chunks, self.world_ref.threads(), init, |mut accumulator, (chunk_pos, chunk)| {
+            let keys = chunk.lock().unwrap().entity_keys().clone();
+            let Some(dim) = this.world_ref.dimension(chunk_pos.dimension()) else { return accumulator };
+            for key in keys {
+                if this.entity_in_clip(chunk_pos.dimension(), key) { accumulator = fold(accumulator, dim.entity_store().get(key)); }
+            }
+            accumulator
+        }, reduce);
+        let resumed = self.world_ref.resume_eviction();
+        self.world_ref.keep_eviction_error(resumed);
+        result
+    }
+}
+
+// The queries over the loaded chunks, what a streaming selection runs for every region
+impl<'r, 'a> Selection<'r, 'a> {
+    fn find_loaded_blocks<F>(&mut self, filter: &Filter, callback: &mut F) where F: FnMut(FullBlock<'a>) -> bool {
+        let plan = QueryPlan::from_filter(filter);
+        let reach = filter.reach();
+        if reach == 0 {
+            self.blocks_with_chunk(&plan, |block, _| {
+                if filter.matches_block(&block) {
+                    if !callback(block) { return false; }
+                }
+                return true;
+            });
+            return;
+        }
+
+        // The chunk being iterated is already locked, so its blocks are read through the given reference
+        let chunk_size = self.version.data.chunk_size;
+        let lowest_y = self.version.data.lowest_y;
+        self.world_ref.pause_eviction();
+        let neighbours = self.chunks_around_selection(reach);
+        self.blocks_with_chunk(&plan, |block, chunk| {
+            let mut lookup = |position: &Position| {
+                let (chunk_pos, index) = block_position_to_chunk_pos_and_block_index(position, chunk_size, lowest_y);
+                if &chunk_pos == chunk.position() { return chunk.block_store().get_block_at_index(index); }
+                neighbours.get(&chunk_pos)?.lock().unwrap().block_store().get_block_at_index(index)
+            };
+            if filter.matches_block_with(&block, &mut lookup) {
+                if !callback(block) { return false; }
+            }
+            true
+        });
+        drop(neighbours);
+        let resumed = self.world_ref.resume_eviction();
+        self.world_ref.keep_eviction_error(resumed);
+    }
+
+    fn loaded_entities<F>(&mut self, callback: &mut F) where F: FnMut(FullEntity) -> bool {
+        let chunk_poses = self.cached_chunks.keys().cloned().collect::<Vec<_>>();
+        for chunk_pos in chunk_poses {
+            let dim_id = chunk_pos.dimension().clone();
+            let chunk = match self.lazy_get_chunk(chunk_pos) {
+                Some(c) => c,
+                None => continue,
+            };
+            let actual_chunk = chunk.lock().unwrap();
+            for entity_key in actual_chunk.entity_keys() {
+                if !self.entity_in_clip(&dim_id, *entity_key) { continue; }
+                if !callback(
+                    FullEntity::new(
+                        &self.world_ref.get(),
+                        entity_key.clone(),
+                        dim_id.clone(),
+                    )
+                ) { return; }
+            }
+        }
+    }
+}
+
+// Iterators
+// No lock is held between two calls of next: the blocks of a section (or the entities of a chunk) are read with the
+// chunk locked and handed out once it's unlocked again, so other chunks can be locked while iterating. The iterator
+// borrows the world, so changed blocks and entities are committed after it's gone. Streaming selections load a
+// region at a time like World::scan_regions, finish saves (when the scan options say so) and unloads the region the
+// iterator is in. Dropping the iterator does the same and keeps a failed save for take_stream_error
+impl<'r, 'a> Selection<'r, 'a> {
+    pub fn iter_blocks(&mut self) -> BlockIter<'_, 'r, 'a> {
+        let queue = ChunkQueue::new(self);
+        BlockIter { selection: self, queue, plan: QueryPlan::any(), current: None, buffer: Vec::new().into_iter() }
+    }
+
+    pub fn iter_entities(&mut self) -> EntityIter<'_, 'r, 'a> {
+        let queue = ChunkQueue::new(self);
+        EntityIter { selection: self, queue, buffer: Vec::new().into_iter() }
+    }
+}
+
+// The chunks an iterator goes through, the selected ones or the regions' chunks one region at a time
+struct ChunkQueue {
+    chunks: VecDeque<ChunkPosition>,
+    regions: Option<RegionCursor>,
+    area: Option<Area>, // the scan's area, the selection's clip is checked by the selection
+    error: Option<io::Error>, // a region that couldn't be saved, the walk stopped there
+}
+
+impl ChunkQueue {
+    fn new(selection: &Selection) -> Self {
+        let Some(mut options) = selection.streaming.clone() else {
+            let mut chunks = selection.chunk_positions();
+            chunks.sort_by(|a, b| (a.dimension().get(), a.x(), a.z()).cmp(&(b.dimension().get(), b.x(), b.z())));
+            return Self { chunks: chunks.into(), regions: None, area: None, error: None };
+        };
+        if let Some(clip) = &selection.clip {
+            let area = match options.area() { Some(area) => area.clone().intersect(clip.clone()), None => clip.clone() };
+            options = options.within(area);
+        }
+        let area = options.area().cloned();
+        Self { chunks: VecDeque::new(), regions: Some(RegionCursor::new(selection.world_ref, options)), area, error: None }
+    }
+
+    fn next(&mut self, selection: &mut Selection) -> Option<ChunkPosition> {
+        loop {
+            if let Some(position) = self.chunks.pop_front() {
+                if selection.clip_touches_chunk(&position) { return Some(position); }
+                continue;
+            }
+            let regions = self.regions.as_mut()?;
+            match regions.advance(selection.world_ref) {
+                Ok(chunks) => self.chunks = chunks?.into(),
+                Err(e) => { self.error = Some(e); self.regions = None; return None; }
+            }
+        }
+    }
+
+    // The region's chunks aren't in the selection, they're read from the world
+    fn chunk(&self, selection: &mut Selection, position: &ChunkPosition) -> Option<ChunkType> {
+        match self.regions {
+            Some(_) => selection.world_ref.dimension(position.dimension())?.chunk(position.position()),
+            None => selection.lazy_get_chunk(position.clone()),
+        }
+    }
+
+    fn contains(&self, dimension: &LassoString, x: i32, y: i32, z: i32) -> bool {
+        self.area.as_ref().is_none_or(|area| area.contains_at(dimension, x, y, z))
+    }
+
+    // The error of a region that couldn't be saved while iterating comes first
+    fn finish(&mut self, selection: &mut Selection) -> io::Result<()> {
+        if let Some(e) = self.error.take() { return Err(e); }
+        match self.regions.as_mut() {
+            Some(regions) => regions.finish(selection.world_ref),
+            None => Ok(())
+        }
+    }
+    fn finish_on_drop(&mut self, selection: &mut Selection) {
+        if let Err(e) = self.finish(selection) { selection.stream_error = Some(e); }
+    }
+}
+
+pub struct BlockIter<'s, 'r, 'a> {
+    selection: &'s mut Selection<'r, 'a>,
+    queue: ChunkQueue,
+    plan: QueryPlan,
+    current: Option<(ChunkPosition, usize)>, // the chunk being read and its next section
+    buffer: std::vec::IntoIter<FullBlock<'a>>,
+}
+
+impl<'s, 'r, 'a> Iterator for BlockIter<'s, 'r, 'a> {
+    type Item = FullBlock<'a>;
+
+    fn next(&mut self) -> Option<FullBlock<'a>> {
+        let data = &self.selection.version.data;
+        let section_size = (data.chunk_size * data.chunk_size * data.section_height) as usize;
+        loop {
+            if let Some(block) = self.buffer.next() { return Some(block); }
+            let Some((position, section)) = self.current.take() else {
+                self.current = Some((self.queue.next(self.selection)?, 0));
+                continue;
+            };
+            let Some(chunk) = self.queue.chunk(self.selection, &position) else { continue };
+            let chunk = chunk.lock().unwrap();
+            if section * section_size >= chunk.block_store().indices_slice().len() { continue; }
+
+            let mut blocks = Vec::new();
+            let queue = &self.queue;
+            self.selection.chunk_blocks_in(&self.plan, &chunk, section..section + 1, &mut |block, _| {
+                let at = block.position();
+                if queue.contains(at.dimension(), at.x(), at.y(), at.z()) { blocks.push(block); }
+                true
+            });
+            self.buffer = blocks.into_iter();
+            self.current = Some((position, section + 1));
+        }
+    }
+}
+
+impl<'s, 'r, 'a> BlockIter<'s, 'r, 'a> {
+    pub fn finish(mut self) -> io::Result<()> { self.queue.finish(self.selection) }
+}
+
+impl<'s, 'r, 'a> Drop for BlockIter<'s, 'r, 'a> {
+    fn drop(&mut self) { self.queue.finish_on_drop(self.selection); }
+}
+
+pub struct EntityIter<'s, 'r, 'a> {
+    selection: &'s mut Selection<'r, 'a>,
+    queue: ChunkQueue,
+    buffer: std::vec::IntoIter<FullEntity<'a>>,
+}
+
+impl<'s, 'r, 'a> Iterator for EntityIter<'s, 'r, 'a> {
+    type Item = FullEntity<'a>;
+
+    fn next(&mut self) -> Option<FullEntity<'a>> {
+        loop {
+            if let Some(entity) = self.buffer.next() { return Some(entity); }
+            let position = self.queue.next(self.selection)?;
+            let Some(chunk) = self.queue.chunk(self.selection, &position) else { continue };
+            let keys = chunk.lock().unwrap().entity_keys().clone();
+
+            let world = self.selection.world_ref.get();
+            let dim_id = *position.dimension();
+            let Some(dim) = self.selection.world_ref.dimension(&dim_id) else { continue };
+            let entities = keys.into_iter().filter_map(|key| {
+                let entity = dim.entity_store().get(key);
+                let at = entity.base().position();
+                if !self.selection.entity_in_clip(&dim_id, key) || !self.queue.contains(&dim_id, at.i_x(), at.i_y(), at.i_z()) { return None; }
+                Some(FullEntity::from_entity(&world, entity.clone(), key, dim_id))
+            }).collect::<Vec<_>>();
+            self.buffer = entities.into_iter();
+        }
+    }
+}
+
+impl<'s, 'r, 'a> EntityIter<'s, 'r, 'a> {
+    pub fn finish(mut self) -> io::Result<()> { self.queue.finish(self.selection) }
+}
+
+impl<'s, 'r, 'a> Drop for EntityIter<'s, 'r, 'a> {
+    fn drop(&mut self) { self.queue.finish_on_drop(self.selection); }
+}
+
+impl<'r, 'a> BlockReader<'a> for Selection<'r, 'a> {
+
+    // callback can return bool. true means continue, false means stop
+    fn blocks<F>(&mut self, mut callback: F) where F: FnMut(FullBlock<'a>) -> bool,
+    {
+        if self.streaming.is_some() {
+            self.stream_error = self.stream(|selection| { let mut go_on = true; selection.blocks_with_chunk(&QueryPlan::any(), |block, _| { go_on = callback(block); go_on }); go_on }).err();
+            return;
+        }
+        self.blocks_with_chunk(&QueryPlan::any(), |block, _| callback(block));
     }
 
     fn block_count(&self) -> usize {
+        if let Some(clip) = &self.clip {
+            return self.cached_chunks.keys()
+                .map(|c| { let (min, max) = self.chunk_box(c); clip.volume_in(c.dimension(), min, max) })
+                .sum();
+        }
         let layer_size = (self.version.data.chunk_size * self.version.data.chunk_size) as usize;
         let layers = (self.version.data.lowest_y.abs() + self.version.data.highest_y.abs()) as usize;
 
@@ -104,6 +910,7 @@ impl<'r, 'a> BlockReader<'a> for Selection<'r, 'a> {
         let lowest_y = self.version.data.lowest_y;
         let chunk_size = self.version.data.chunk_size;
 
+        if self.clip.as_ref().is_some_and(|a| !a.contains(&position)) { return None; }
         let (chunk_pos, relative_index) = block_position_to_chunk_pos_and_block_index(&position, chunk_size, lowest_y);
         let ch = self.lazy_get_chunk(chunk_pos);
         match ch {
@@ -111,11 +918,13 @@ impl<'r, 'a> BlockReader<'a> for Selection<'r, 'a> {
                 let lch = ch.lock().unwrap();
                 if let Some(local_block) = lch.block_store().get_block_at_index(relative_index) {
                     let block_position = block_index_to_block_position(lch.position(), relative_index, chunk_size, lowest_y);
+                    let biome = lch.biome_store().get_biome_at_block_position(block_position.clone()).map(LassoString::from);
                     return Some(FullBlock::new_with_data(
                         &self.world_ref.get(),
                         local_block,
                         lch.block_entities_store().get_at_world_position(&block_position),
-                        block_position
+                        block_position,
+                        biome
                     ));
                 }
                 None
@@ -125,50 +934,35 @@ impl<'r, 'a> BlockReader<'a> for Selection<'r, 'a> {
     }
 
     fn find_blocks<F>(&mut self, filter: Filter, mut callback: F) where F: FnMut(FullBlock<'a>) -> bool {
-        // TODO: We can do a ton of optimizations here like using indexes for faster lookup
-        self.blocks(|block| {
-            if filter.matches_block(&block) {
-                if !callback(block) { return false; }
-            }
-            return true;
-        });
+        if self.streaming.is_none() { return self.find_loaded_blocks(&filter, &mut callback); }
+        self.stream_error = self.stream(|selection| { let mut go_on = true; selection.find_loaded_blocks(&filter, &mut |block| { go_on = callback(block); go_on }); go_on }).err();
     }
 }
 
 impl<'r, 'a> EntityReader for Selection<'r, 'a> {
     fn entities<F>(&mut self, mut callback: F) where F: FnMut(FullEntity) -> bool
     {
-        let chunk_poses = self.cached_chunks.keys().cloned().collect::<Vec<_>>();
-        for chunk_pos in chunk_poses {
-            let dim_id = chunk_pos.dimension().clone();
-            let chunk = match self.lazy_get_chunk(chunk_pos) {
-                Some(c) => c,
-                None => continue,
-            };
-            let actual_chunk = chunk.lock().unwrap();
-            for entity_key in actual_chunk.entity_keys() {
-                if !callback(
-                    FullEntity::new(
-                        &self.world_ref.get(),
-                        entity_key.clone(),
-                        dim_id.clone(),
-                    )
-                ) { return; }
-            }
-        }
+        if self.streaming.is_none() { return self.loaded_entities(&mut callback); }
+        self.stream_error = self.stream(|selection| { let mut go_on = true; selection.loaded_entities(&mut |entity| { go_on = callback(entity); go_on }); go_on }).err();
     }
 
     fn entity_count(&mut self) -> usize {
         let mut count = 0;
+        if self.streaming.is_some() {
+            self.stream_error = self.stream(|selection| { count += selection.entity_count(); true }).err();
+            return count;
+        }
         let chunk_poses = self.cached_chunks.keys().cloned().collect::<Vec<_>>();
         for chunk_pos in chunk_poses {
+            let dim_id = *chunk_pos.dimension();
             let chunk = match self.lazy_get_chunk(chunk_pos) {
                 Some(c) => c,
                 None => continue,
             };
             let actual_chunk = chunk.lock().unwrap();
 
-            count += actual_chunk.entity_count();
+            if self.clip.is_none() { count += actual_chunk.entity_count(); continue; }
+            count += actual_chunk.entity_keys().iter().filter(|key| self.entity_in_clip(&dim_id, **key)).count();
         }
         count
     }
@@ -186,11 +980,12 @@ impl<'r, 'a> EntityReader for Selection<'r, 'a> {
 
 impl<'r, 'a> EntityWriter for Selection<'r, 'a> {
     fn set_entity_at_position(&mut self, entity: Entity) -> bool {
-        let dim_id = entity.base().position().dimension();
-        match self.world_ref.dimension_mut(dim_id) {
+        let (dim_id, uuid) = (*entity.base().position().dimension(), entity.base().uuid());
+        let entity_pos = entity.base().position();
+        let chunk_pos = world_position_to_chunk_position(entity_pos.x(), entity_pos.z(), self.version.data.chunk_size);
+        let recorded = entity.clone();
+        let added = match self.world_ref.dimension_mut(&dim_id) {
             Some(dimension) => {
-                let entity_pos = entity.base().position();
-                let chunk_pos = world_position_to_chunk_position(entity_pos.x(), entity_pos.z(), self.version.data.chunk_size);
                 let entity_key = dimension.entity_store_mut().add_entity(entity);
                 let chunk = dimension.chunk_mut(chunk_pos);
                 match chunk {
@@ -201,8 +996,13 @@ impl<'r, 'a> EntityWriter for Selection<'r, 'a> {
                     None => false
                 }
             },
-            None => { false }
-        }
+            None => { return false }
+        };
+        let history = self.world_ref.history_mut();
+        history.begin("add entity");
+        history.record_entity(dim_id, uuid, None, Some(recorded));
+        history.commit();
+        added
     }
 }
 
@@ -210,7 +1010,7 @@ impl<'r, 'a> BlockWriter for Selection<'r, 'a> {
     fn set_block_at_position(&mut self, block: FullBlock) -> bool {
         if let Some((parent, rel_index)) = block.parent_chunk() {
             let mut ch = parent.lock().unwrap();
-            ch.block_store_mut().set_block_at_index(rel_index, block.palette_block());
+            return block.write_into(&mut ch, rel_index, self.world_ref.history_mut());
         }
         false
     }
@@ -237,6 +1037,15 @@ impl<'a, 'r> SelectionBuilder<'a, 'r> {
         self.underlying
     }
 
+    // Every region on disk, loaded or not. The queries (blocks, find_blocks, entities, find_entities, entity_count,
+    // entity_snapshots and the par_ scans) stream through them a region at a time, see World::scan_regions. With an
+    // area only the regions it touches are read. Edits don't stream, use World::scan_regions with saving for those
+    pub fn all_regions(self) -> Self { self.all_regions_with(ScanOptions::new()) }
+    pub fn all_regions_with(mut self, options: ScanOptions) -> Self { self.underlying.streaming = Some(options); self }
+
+    // Clips like with_area but doesn't add the chunks the area touches
+    pub fn clipped_to(mut self, area: Area) -> Self { self.underlying.clip = Some(area); self }
+
 
     pub fn with_chunk_position(mut self, chunk_pos: ChunkPosition) -> Self {
         self.underlying.cached_chunks.insert(chunk_pos, None);
@@ -254,6 +1063,39 @@ impl<'a, 'r> SelectionBuilder<'a, 'r> {
         self
     }
 
+    // Only the blocks and entities inside the area are selected, the chunks it touches are added (loaded or on disk,
+    // those are loaded when they're used). Replaces an earlier area, and also clips whole chunks added with the other methods
+    pub fn with_area(mut self, area: Area) -> Self {
+        let chunk_size = self.underlying.version.data.chunk_size;
+        for chunk_pos in area.chunk_positions(chunk_size) {
+            if self.underlying.world_ref.chunk_available(&chunk_pos) { self.underlying.cached_chunks.entry(chunk_pos).or_insert(None); }
+        }
+        self.underlying.clip = Some(area);
+        self
+    }
+
+    pub fn with_shape(self, shape: Shape) -> Self { self.with_area(Area::Shape(shape)) }
+
+    pub fn cuboid(self, corner1: &Position, corner2: &Position) -> Self { self.with_shape(Shape::cuboid(corner1, corner2)) }
+    pub fn sphere(self, center: &Position, radius: f64) -> Self { self.with_shape(Shape::sphere(center, radius)) }
+    pub fn ellipsoid(self, center: &Position, radii: (f64, f64, f64)) -> Self { self.with_shape(Shape::ellipsoid(center, radii)) }
+    pub fn cylinder(self, base_center: &Position, radius: f64, height: i32) -> Self { self.with_shape(Shape::cylinder(base_center, radius, height)) }
+    pub fn polygon(self, dimension: LassoString, points: Vec<(i32, i32)>, min_y: i32, max_y: i32) -> Self { self.with_shape(Shape::polygon(dimension, points, min_y, max_y)) }
+    // Adds nothing when the points are empty or in different dimensions
+    pub fn convex_hull(self, points: &[Position]) -> Self {
+        match Shape::convex_hull(points) {
+            Some(shape) => self.with_shape(shape),
+            None => self
+        }
+    }
+    // Adds nothing when the blocks are empty or in different dimensions
+    pub fn mask(self, blocks: &[Position]) -> Self {
+        match Shape::mask(blocks) {
+            Some(shape) => self.with_shape(shape),
+            None => self
+        }
+    }
+
     pub fn all_dimension_chunks(mut self, dimension: &LassoString) -> Self {
         {
             let dim = self.underlying.world_ref.dimension(dimension);
@@ -273,3 +1115,319 @@ impl<'a, 'r> SelectionBuilder<'a, 'r> {
         self
     }
 }
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::versions::VersionManager;
+    use crate::models::block_entity::block_entity::{BlockEntity, GenericBlockEntity};
+    use crate::models::entity::entity::EntityType;
+    use crate::models::filter::local_structure::LocalStructure;
+    use crate::models::other::properties::Properties;
+    use crate::models::positions::entity_position::EntityPosition;
+    use crate::models::other::tick::Tick;
+    use crate::models::world::chunk_cache::CacheOptions;
+    use crate::models::world::dimension::Dimension;
+    use crate::models::world::tile_tick::{TileTick, TileTickType};
+    use crate::models::world::world::SaveOptions;
+    use crate::traits::block_entity::BlockEntityTrait;
+    use crate::types::WorldKind;
+
+    fn pos(x: i32, y: i32, z: i32) -> Position { Position::new("overworld".into(), x, y, z) }
+
+    fn count(selection: &mut Selection, id: &str) -> usize {
+        let mut found = 0;
+        selection.find_blocks(Filter::from_filter_string(&format!("id == {}", id)).unwrap(), |_| { found += 1; true });
+        found
+    }
+
+    #[test]
+    fn biomes_at_negative_coordinates_and_section_boundaries() {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new("".into(), version.clone());
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        for (x, z) in [(-1, -1), (0, 0)] {
+            dimension.set_chunk(Chunk::new(ChunkPosition::new(x, z, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+        }
+        let mut locked = world.lock().unwrap();
+        locked.set_dimension("overworld".into(), dimension);
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(-16, -64, -16), &pos(15, 319, 15)).build();
+
+        // x = z = -1 is the last cell of chunk (-1, -1), not the first one of chunk (0, 0)
+        assert_eq!(selection.set_biome_in_box(&pos(-1, 0, -1), &pos(-1, 0, -1), "minecraft:desert"), 1);
+        assert_eq!(selection.biome_at(pos(-4, 3, -4)).as_deref(), Some("minecraft:desert"));
+        assert_eq!(selection.biome_at(pos(-5, 0, -1)), None);
+        assert_eq!(selection.biome_at(pos(0, 0, 0)), None);
+
+        // y 15 and 16 are in different sections, a box over the boundary sets a cell on both sides
+        assert_eq!(selection.set_biome_in_box(&pos(0, 15, 0), &pos(3, 16, 3), "minecraft:swamp"), 2);
+        assert_eq!(selection.biome_at(pos(2, 12, 1)).as_deref(), Some("minecraft:swamp"));
+        assert_eq!(selection.biome_at(pos(2, 19, 1)).as_deref(), Some("minecraft:swamp"));
+        assert_eq!(selection.biome_at(pos(2, 20, 1)), None);
+        assert_eq!(selection.biome_at(pos(2, 11, 1)), None);
+
+        // negative y cells, right below y 0
+        assert_eq!(selection.set_biome_in_box(&pos(-16, -1, -16), &pos(-1, -1, -1), "minecraft:plains"), 16);
+        assert_eq!(selection.biome_at(pos(-9, -4, -9)).as_deref(), Some("minecraft:plains"));
+        assert_eq!(selection.biome_at(pos(-9, 0, -9)), None);
+        assert_eq!(selection.biome_at(pos(-9, -64, -9)), None);
+    }
+
+    #[test]
+    fn bulk_operations() {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new("".into(), version.clone());
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        for (x, z) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            dimension.set_chunk(Chunk::new(ChunkPosition::new(x, z, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+        }
+        {
+            let chunk = dimension.chunk((0, 0)).unwrap();
+            let mut chunk = chunk.lock().unwrap();
+            for at in [pos(12, 2, 12), pos(5, 0, 5)] {
+                chunk.block_entities_store_mut().set_at_world_position(BlockEntity::Other(GenericBlockEntity::new("minecraft:chest".to_string(), at.clone(), Properties::new(HashMap::new()))));
+                chunk.set_tile_tick(TileTick::new(at, 0, 1, TileTickType::BLOCK));
+            }
+        }
+        world.lock().unwrap().set_dimension("overworld".into(), dimension);
+        let mut locked = world.lock().unwrap();
+
+        // 10x5x10 over four chunks
+        let stone = PaletteBlock::from("minecraft:stone");
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(10, 0, 10), &pos(19, 4, 19)).build();
+        assert_eq!(selection.fill(&stone), 500);
+        assert_eq!(selection.fill(&stone), 0);
+        {
+            let chunk = selection.chunk(ChunkPosition::new(0, 0, "overworld".into())).unwrap();
+            let chunk = chunk.lock().unwrap();
+            let block_entities = chunk.block_entities_store().get_all();
+            assert_eq!(block_entities.len(), 1);
+            assert_eq!(block_entities[0].base().position(), &pos(5, 0, 5));
+            assert_eq!(chunk.tile_ticks().len(), 1);
+        }
+
+        assert_eq!(selection.hollow(), 8 * 3 * 8);
+        assert_eq!(count(&mut selection, "minecraft:stone"), 500 - 8 * 3 * 8);
+        assert_eq!(selection.walls(&PaletteBlock::from("minecraft:glass")), 36 * 5);
+        assert_eq!(selection.outline(&stone), 36 * 5);
+        assert_eq!(count(&mut selection, "minecraft:glass"), 0);
+        drop(selection);
+
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(10, 0, 10), &pos(19, 10, 19)).build();
+        assert_eq!(selection.overlay(&PaletteBlock::from("minecraft:snow")), 100);
+        let filter = Filter::from_filter_string("id == minecraft:snow && x < 15").unwrap();
+        assert_eq!(selection.replace(filter, &PaletteBlock::from("minecraft:glowstone")), 50);
+        assert_eq!(count(&mut selection, "minecraft:glowstone"), 50);
+        assert_eq!(selection.block_at_position(pos(10, 5, 10)).unwrap().id(), "minecraft:glowstone");
+
+        let mix = Pattern::parse("50% glass, 50% dirt").unwrap().with_seed(3);
+        assert_eq!(selection.fill(&mix), 10 * 11 * 10);
+        let (glass, dirt) = (count(&mut selection, "minecraft:glass"), count(&mut selection, "minecraft:dirt"));
+        assert_eq!(glass + dirt, 1100);
+        assert!(glass > 400 && dirt > 400);
+        assert_eq!(selection.fill(&mix), 0);
+    }
+
+    #[test]
+    fn parallel_scans() {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new("".into(), version.clone());
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        for x in 0..4 {
+            for z in 0..4 {
+                dimension.set_chunk(Chunk::new(ChunkPosition::new(x, z, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+            }
+        }
+        let mut locked = world.lock().unwrap();
+        locked.set_dimension("overworld".into(), dimension);
+        locked.set_threads(4);
+
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(0, 0, 0), &pos(63, 3, 63)).build();
+        selection.fill(&PaletteBlock::from("minecraft:stone"));
+        drop(selection);
+        for (x, z) in [(1, 1), (20, 40), (47, 15), (62, 62)] {
+            let tower = SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(x, 1, z), &pos(x, 3, z)).build().fill(&PaletteBlock::from("minecraft:glass"));
+            assert_eq!(tower, 3);
+        }
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(0, 0, 0), &pos(63, 3, 63)).build();
+        for i in 0..10 {
+            let entity = Entity::new(EntityType::Mob, Tick::new(0), 0.0, Tick::new(0), false, (0.0, 0.0, 0.0), true,
+                EntityPosition::new(i as f64 * 6.0 + 0.5, 2.0, 3.5, 0.0, 0.0, "overworld".into()), i, Properties::new(HashMap::new()));
+            assert!(selection.set_entity_at_position(entity));
+        }
+
+        let mut sequential = 0;
+        selection.blocks(|_| { sequential += 1; true });
+        assert_eq!(selection.par_blocks(|| 0, |count, _| count + 1, |a, b| a + b), sequential);
+        assert_eq!(sequential, 64 * 64 * 4);
+
+        let glass = Filter::from_filter_string("id == minecraft:glass").unwrap();
+        let mut found = selection.par_find_blocks(glass, Vec::new, |mut found, block| { found.push(block.position().clone()); found }, |mut a, b| { a.extend(b); a });
+        found.sort_by_key(|p| (p.x(), p.y(), p.z()));
+        assert_eq!(found.len(), 12);
+        assert_eq!((found[0].x(), found[0].y(), found[0].z()), (1, 1, 1));
+
+        // neighbours are read from other chunks while other workers hold theirs, the tower at x 47 looks into x 48
+        let structure = LocalStructure::new().add((0, 0, 0), "minecraft:glass").add((0, -1, 0), "minecraft:glass").add((1, 0, 0), "minecraft:stone");
+        let stacked = selection.par_find_blocks(Filter::LocalStructure(&structure), || 0, |count, _| count + 1, |a, b| a + b);
+        assert_eq!(stacked, 8);
+
+        let ids = selection.par_entities(Vec::new, |mut ids, entity| { ids.push(entity.base().uuid()); ids }, |mut a, b| { a.extend(b); a });
+        assert_eq!(ids.len(), 10);
+    }
+
+    #[test]
+    fn neighbours_are_loaded_from_disk() {
+        let directory = std::env::temp_dir().join(format!("cubicle_neighbours_test_{}", std::process::id()));
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        {
+            let world = World::new(directory.clone(), version.clone());
+            let mut dimension = Dimension::new("overworld".into(), version.clone());
+            for x in 0..2 {
+                dimension.set_chunk(Chunk::new(ChunkPosition::new(x, 0, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+            }
+            let mut locked = world.lock().unwrap();
+            locked.set_dimension("overworld".into(), dimension);
+            SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(0, 0, 0), &pos(31, 2, 2)).build().fill(&PaletteBlock::from("minecraft:stone"));
+            SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(15, 3, 0), &pos(15, 3, 0)).build().fill(&PaletteBlock::from("minecraft:glass"));
+            assert_eq!(locked.save(&SaveOptions::new()).unwrap(), 2);
+        }
+        let load = || {
+            let world = World::new(directory.clone(), version.clone());
+            world.lock().unwrap().register_regions();
+            world.lock().unwrap().enable_chunk_cache(CacheOptions::new().with_max_chunks(1)).unwrap();
+            world
+        };
+
+        // only the first chunk is selected, the stone next to the glass is in the second one
+        let world = load();
+        let mut locked = world.lock().unwrap();
+        let structure = LocalStructure::new().add((0, 0, 0), "minecraft:glass").add((1, -1, 0), "minecraft:stone");
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(0, 0, 0), &pos(15, 3, 15)).build();
+        let mut found = 0;
+        selection.find_blocks(Filter::LocalStructure(&structure), |_| { found += 1; true });
+        assert_eq!(found, 1);
+        assert_eq!(selection.par_find_blocks(Filter::LocalStructure(&structure), || 0, |count, _| count + 1, |a, b| a + b), 1);
+        assert!(selection.take_stream_error().is_none());
+        drop(selection);
+        drop(locked);
+
+        // the blocks on both sides of the chunk border are enclosed as well
+        let world = load();
+        let mut locked = world.lock().unwrap();
+        assert_eq!(SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(0, 0, 0), &pos(31, 2, 2)).build().hollow(), 30);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn parallel_region_loading_matches_sequential() {
+        let directory = std::env::temp_dir().join(format!("cubicle_parallel_loading_test_{}", std::process::id()));
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new(directory.clone(), version.clone());
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        // three regions, a few chunks in each
+        let positions = [(-1, 0), (-32, 5), (0, 0), (3, 7), (31, 31), (32, 0), (40, 12)];
+        for (x, z) in positions {
+            dimension.set_chunk(Chunk::new(ChunkPosition::new(x, z, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+        }
+        let mut locked = world.lock().unwrap();
+        locked.set_dimension("overworld".into(), dimension);
+        for (i, (x, z)) in positions.into_iter().enumerate() {
+            let (x, z) = (x * 16 + i as i32, z * 16 + 15 - i as i32);
+            let mut selection = SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(x, 0, z), &pos(x, i as i32, z)).build();
+            selection.fill(&PaletteBlock::from(["minecraft:stone", "minecraft:glass", "minecraft:dirt"][i % 3]));
+            let entity = Entity::new(EntityType::Mob, Tick::new(0), 0.0, Tick::new(0), false, (0.0, 0.0, 0.0), true,
+                EntityPosition::new(x as f64 + 0.5, 70.0, z as f64 + 0.5, 0.0, 0.0, "overworld".into()), i as u128, Properties::new(HashMap::new()));
+            assert!(selection.set_entity_at_position(entity));
+        }
+        assert_eq!(locked.save(&SaveOptions::new()).unwrap(), positions.len());
+        drop(locked);
+
+        // every loaded chunk as (position, its blocks, the uuids of its entities)
+        let summary = |world: &World| {
+            let dimension = world.dimension(&"overworld".into()).unwrap();
+            let mut chunks = dimension.chunks().map(|c| {
+                let chunk = c.lock().unwrap();
+                let blocks = (0..chunk.block_store().indices_slice().len())
+                    .map(|i| chunk.block_store().get_block_at_index(i).unwrap().full_name().to_string())
+                    .filter(|name| name != "minecraft:air")
+                    .collect::<Vec<_>>();
+                let mut uuids = chunk.entity_keys().iter().map(|k| dimension.entity_store().get(*k).base().uuid()).collect::<Vec<_>>();
+                uuids.sort();
+                (chunk.position().position(), blocks, uuids)
+            }).collect::<Vec<_>>();
+            chunks.sort_by_key(|(p, _, _)| *p);
+            chunks
+        };
+        let regions = [(-1, 0), (0, 0), (1, 0)].map(|(x, z)| ChunkPosition::new(x, z, "overworld".into()));
+        let load = |threads: usize, per_region: bool| {
+            let world = World::new(directory.clone(), version.clone());
+            let mut locked = world.lock().unwrap();
+            locked.register_regions();
+            locked.set_threads(threads);
+            if per_region { for region in &regions { locked.load_region(region.clone()).unwrap(); } }
+            else { locked.load_regions(&regions).unwrap(); }
+            summary(&locked)
+        };
+
+        let sequential = load(1, true);
+        assert_eq!(sequential.len(), positions.len());
+        assert!(sequential.iter().all(|(_, blocks, uuids)| !blocks.is_empty() && uuids.len() == 1));
+        assert_eq!(load(4, false), sequential);
+        assert_eq!(load(4, true), sequential);
+        assert_eq!(load(0, false), sequential);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn iterators() {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new("".into(), version.clone());
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        for x in 0..2 {
+            dimension.set_chunk(Chunk::new(ChunkPosition::new(x, 0, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+        }
+        for i in 0..4 {
+            dimension.add_entity(Entity::new(EntityType::Mob, Tick::new(0), 0.0, Tick::new(0), false, (0.0, 0.0, 0.0), true,
+                EntityPosition::new(i as f64 * 8.0 + 0.5, 1.0, 0.5, 0.0, 0.0, "overworld".into()), i, Properties::new(HashMap::new())));
+        }
+        let chunk = dimension.chunk((1, 0)).unwrap();
+        let mut locked = world.lock().unwrap();
+        locked.set_dimension("overworld".into(), dimension);
+
+        SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(0, 0, 0), &pos(31, 1, 0)).build().fill(&PaletteBlock::from("minecraft:stone"));
+        SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(20, 1, 0), &pos(20, 1, 0)).build().fill(&PaletteBlock::from("minecraft:glass"));
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version.clone()).cuboid(&pos(0, 0, 0), &pos(31, 1, 0)).build();
+
+        // no chunk is locked between two blocks
+        let mut blocks = 0;
+        for block in selection.iter_blocks() {
+            assert!(chunk.try_lock().is_ok());
+            blocks += (block.id() == "minecraft:stone") as usize;
+        }
+        assert_eq!(blocks, 63);
+        let first_glass = selection.iter_blocks().find(|b| b.id() == "minecraft:glass").unwrap();
+        assert_eq!(first_glass.position(), &pos(20, 1, 0));
+        // chunk by chunk, then bottom up with x going fastest
+        let expected = (0..2).flat_map(|chunk| (0..2).flat_map(move |_| chunk * 16..chunk * 16 + 16));
+        assert!(selection.iter_blocks().zip(expected).all(|(block, x)| block.position().x() == x));
+        assert_eq!(selection.iter_blocks().count(), 64);
+
+        let uuids = selection.iter_entities().map(|e| e.entity().base().uuid()).collect::<Vec<_>>();
+        assert_eq!(uuids.len(), 4);
+        drop(selection);
+
+        // palette indices, a section at a time
+        let chunk = chunk.lock().unwrap();
+        let sections = chunk.sections(version.data.section_height).collect::<Vec<_>>();
+        assert_eq!(sections.len(), 24);
+        assert_eq!((sections[0].y(), sections[4].y(), sections[4].start()), (-4, 0, 4 * 4096));
+        assert_eq!(sections[0].uniform(), Some(0));
+        assert!(sections[4].uniform().is_none());
+        let stone = chunk.block_store().get_palette_index_of_block(&PaletteBlock::from("minecraft:stone")).unwrap();
+        assert_eq!(sections[4].palette_indices().filter(|i| *i == stone).count(), 31);
+        assert_eq!(chunk.palette_indices().filter(|i| *i == stone).count(), 31);
+    }
+}
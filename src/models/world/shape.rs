@@ -0,0 +1,450 @@
+use std::collections::HashSet;
+use crate::models::other::lasso_string::LassoString;
+use crate::models::positions::chunk_position::ChunkPosition;
+use crate::models::positions::whole_position::Position;
+
+type BlockBounds = ((i32, i32, i32), (i32, i32, i32)); // inclusive min and max corners
+
+// Hull math is done in integers so flat and nearly flat hulls don't depend on an epsilon
+type Vector = (i128, i128, i128);
+// (normal, offset), a point p is inside when normal . p <= offset
+type Plane = (Vector, i128);
+
+// Block precise areas of one dimension. A block is inside when its (integer) position is, like WorldEdit's regions
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Cuboid { dimension: LassoString, min: (i32, i32, i32), max: (i32, i32, i32) },
+    Ellipsoid { dimension: LassoString, center: (f64, f64, f64), radii: (f64, f64, f64) },
+    Cylinder { dimension: LassoString, center: (f64, f64), radii: (f64, f64), min_y: i32, max_y: i32 },
+    Polygon { dimension: LassoString, points: Vec<(i32, i32)>, min_y: i32, max_y: i32 }, // points are (x, z)
+    ConvexHull { dimension: LassoString, points: Vec<(i32, i32, i32)>, planes: Vec<Plane> },
+    // Any set of blocks, given one by one
+    Mask { dimension: LassoString, blocks: HashSet<(i32, i32, i32)>, bounds: BlockBounds },
+}
+
+impl Shape {
+    pub fn cuboid(corner1: &Position, corner2: &Position) -> Shape {
+        Shape::Cuboid {
+            dimension: *corner1.dimension(),
+            min: (corner1.x().min(corner2.x()), corner1.y().min(corner2.y()), corner1.z().min(corner2.z())),
+            max: (corner1.x().max(corner2.x()), corner1.y().max(corner2.y()), corner1.z().max(corner2.z())),
+        }
+    }
+
+    pub fn sphere(center: &Position, radius: f64) -> Shape { Shape::ellipsoid(center, (radius, radius, radius)) }
+
+    pub fn ellipsoid(center: &Position, radii: (f64, f64, f64)) -> Shape {
+        Shape::Ellipsoid { dimension: *center.dimension(), center: (center.x() as f64, center.y() as f64, center.z() as f64), radii }
+    }
+
+    // Goes up from base_center, height blocks tall
+    pub fn cylinder(base_center: &Position, radius: f64, height: i32) -> Shape { Shape::elliptic_cylinder(base_center, (radius, radius), height) }
+
+    pub fn elliptic_cylinder(base_center: &Position, radii: (f64, f64), height: i32) -> Shape {
+        Shape::Cylinder {
+            dimension: *base_center.dimension(),
+            center: (base_center.x() as f64, base_center.z() as f64),
+            radii,
+            min_y: base_center.y(),
+            max_y: base_center.y() + height.max(1) - 1,
+        }
+    }
+
+    // A 2D polygon of (x, z) points extruded from min_y to max_y, blocks on the edges are inside
+    pub fn polygon(dimension: LassoString, points: Vec<(i32, i32)>, min_y: i32, max_y: i32) -> Shape {
+        Shape::Polygon { dimension, points, min_y: min_y.min(max_y), max_y: min_y.max(max_y) }
+    }
+
+    // Smallest convex shape around the points, None if there are none or they're in different dimensions. Points on
+    // a line or a plane give that segment or flat polygon. Built incrementally, O(n²) for n (distinct) points
+    pub fn convex_hull(points: &[Position]) -> Option<Shape> {
+        let dimension = *points.first()?.dimension();
+        if points.iter().any(|p| *p.dimension() != dimension) { return None; }
+        let mut points = points.iter().map(|p| (p.x(), p.y(), p.z())).collect::<Vec<_>>();
+        points.sort();
+        points.dedup();
+        let planes = hull_planes(&points.iter().map(|p| vector(*p)).collect::<Vec<_>>());
+        Some(Shape::ConvexHull { dimension, points, planes })
+    }
+
+    // Exactly the given blocks, None if there are none or they're in different dimensions
+    pub fn mask(blocks: &[Position]) -> Option<Shape> {
+        let dimension = *blocks.first()?.dimension();
+        if blocks.iter().any(|p| *p.dimension() != dimension) { return None; }
+        let blocks = blocks.iter().map(|p| (p.x(), p.y(), p.z())).collect::<HashSet<_>>();
+        let min = blocks.iter().copied().reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.min(b.2)))?;
+        let max = blocks.iter().copied().reduce(|a, b| (a.0.max(b.0), a.1.max(b.1), a.2.max(b.2)))?;
+        Some(Shape::Mask { dimension, blocks, bounds: (min, max) })
+    }
+
+    pub fn dimension(&self) -> &LassoString {
+        match self {
+            Shape::Cuboid { dimension, .. } | Shape::Ellipsoid { dimension, .. } | Shape::Cylinder { dimension, .. }
+            | Shape::Polygon { dimension, .. } | Shape::ConvexHull { dimension, .. } | Shape::Mask { dimension, .. } => dimension
+        }
+    }
+
+    // None for shapes without any block
+    pub fn bounds(&self) -> Option<BlockBounds> {
+        match self {
+            Shape::Cuboid { min, max, .. } => non_empty((*min, *max)),
+            Shape::Ellipsoid { center, radii, .. } => {
+                let axis = |c: f64, r: f64| ((c - r).ceil() as i32, (c + r).floor() as i32);
+                let (x, y, z) = (axis(center.0, radii.0), axis(center.1, radii.1), axis(center.2, radii.2));
+                non_empty(((x.0, y.0, z.0), (x.1, y.1, z.1)))
+            }
+            Shape::Cylinder { center, radii, min_y, max_y, .. } => {
+                let axis = |c: f64, r: f64| ((c - r).ceil() as i32, (c + r).floor() as i32);
+                let (x, z) = (axis(center.0, radii.0), axis(center.1, radii.1));
+                non_empty(((x.0, *min_y, z.0), (x.1, *max_y, z.1)))
+            }
+            Shape::Polygon { points, min_y, max_y, .. } => {
+                let min = points.iter().copied().reduce(|a, b| (a.0.min(b.0), a.1.min(b.1)))?;
+                let max = points.iter().copied().reduce(|a, b| (a.0.max(b.0), a.1.max(b.1)))?;
+                Some(((min.0, *min_y, min.1), (max.0, *max_y, max.1)))
+            }
+            Shape::ConvexHull { points, .. } => {
+                let min = points.iter().copied().reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.min(b.2)))?;
+                let max = points.iter().copied().reduce(|a, b| (a.0.max(b.0), a.1.max(b.1), a.2.max(b.2)))?;
+                Some((min, max))
+            }
+            Shape::Mask { bounds, .. } => Some(*bounds),
+        }
+    }
+
+    pub fn contains(&self, position: &Position) -> bool {
+        *position.dimension() == *self.dimension() && self.contains_xyz(position.x(), position.y(), position.z())
+    }
+
+    // Same as contains, without checking the dimension
+    pub fn contains_xyz(&self, x: i32, y: i32, z: i32) -> bool {
+        let Some((min, max)) = self.bounds() else { return false };
+        if x < min.0 || y < min.1 || z < min.2 || x > max.0 || y > max.1 || z > max.2 { return false; }
+
+        match self {
+            Shape::Cuboid { .. } => true,
+            Shape::Ellipsoid { center, radii, .. } => {
+                let (dx, dy, dz) = ((x as f64 - center.0) / radii.0, (y as f64 - center.1) / radii.1, (z as f64 - center.2) / radii.2);
+                dx * dx + dy * dy + dz * dz <= 1.0
+            }
+            Shape::Cylinder { center, radii, .. } => {
+                let (dx, dz) = ((x as f64 - center.0) / radii.0, (z as f64 - center.1) / radii.1);
+                dx * dx + dz * dz <= 1.0
+            }
+            Shape::Polygon { points, .. } => polygon_contains(points, x, z),
+            Shape::ConvexHull { planes, .. } => planes.iter().all(|(normal, offset)| dot(*normal, vector((x, y, z))) <= *offset),
+            Shape::Mask { blocks, .. } => blocks.contains(&(x, y, z)),
+        }
+    }
+
+    // Whether any block between the (inclusive) corners may be inside. Can give false positives for hulls
+    pub fn intersects_box(&self, min: (i32, i32, i32), max: (i32, i32, i32)) -> bool {
+        let Some((shape_min, shape_max)) = self.bounds() else { return false };
+        let (min, max) = (
+            (min.0.max(shape_min.0), min.1.max(shape_min.1), min.2.max(shape_min.2)),
+            (max.0.min(shape_max.0), max.1.min(shape_max.1), max.2.min(shape_max.2)),
+        );
+        if min.0 > max.0 || min.1 > max.1 || min.2 > max.2 { return false; }
+
+        let closest = |c: f64, low: i32, high: i32| c.round().clamp(low as f64, high as f64);
+        match self {
+            Shape::Cuboid { .. } => true,
+            Shape::Ellipsoid { center, .. } => {
+                self.contains_xyz(closest(center.0, min.0, max.0) as i32, closest(center.1, min.1, max.1) as i32, closest(center.2, min.2, max.2) as i32)
+            }
+            Shape::Cylinder { center, .. } => {
+                self.contains_xyz(closest(center.0, min.0, max.0) as i32, min.1, closest(center.1, min.2, max.2) as i32)
+            }
+            Shape::Polygon { .. } => (min.0..=max.0).any(|x| (min.2..=max.2).any(|z| self.contains_xyz(x, min.1, z))),
+            Shape::ConvexHull { planes, .. } => {
+                let corners = [min.0, max.0].into_iter().flat_map(|x| [min.1, max.1].into_iter().flat_map(move |y| [min.2, max.2].into_iter().map(move |z| vector((x, y, z)))));
+                let corners = corners.collect::<Vec<_>>();
+                planes.iter().all(|(normal, offset)| corners.iter().any(|c| dot(*normal, *c) <= *offset))
+            }
+            Shape::Mask { blocks, .. } => blocks.iter().any(|b| in_box(*b, min, max)),
+        }
+    }
+
+    // Chunks with at least one block of the shape
+    pub fn chunk_positions(&self, chunk_size: i32) -> Vec<ChunkPosition> {
+        if let Shape::Mask { dimension, blocks, .. } = self {
+            let chunks = blocks.iter().map(|b| (b.0.div_euclid(chunk_size), b.2.div_euclid(chunk_size))).collect::<HashSet<_>>();
+            let mut chunks = chunks.into_iter().collect::<Vec<_>>();
+            chunks.sort();
+            return chunks.into_iter().map(|(x, z)| ChunkPosition::new(x, z, *dimension)).collect();
+        }
+        let Some((min, max)) = self.bounds() else { return Vec::new() };
+        let mut chunks = Vec::new();
+        for chunk_x in min.0.div_euclid(chunk_size)..=max.0.div_euclid(chunk_size) {
+            for chunk_z in min.2.div_euclid(chunk_size)..=max.2.div_euclid(chunk_size) {
+                let (x, z) = (chunk_x * chunk_size, chunk_z * chunk_size);
+                if self.intersects_box((x, min.1, z), (x + chunk_size - 1, max.1, z + chunk_size - 1)) {
+                    chunks.push(ChunkPosition::new(chunk_x, chunk_z, *self.dimension()));
+                }
+            }
+        }
+        chunks
+    }
+
+    // Amount of blocks inside that are also between the (inclusive) corners
+    pub fn volume_in(&self, min: (i32, i32, i32), max: (i32, i32, i32)) -> usize {
+        let Some((shape_min, shape_max)) = self.bounds() else { return 0 };
+        let (min, max) = (
+            (min.0.max(shape_min.0), min.1.max(shape_min.1), min.2.max(shape_min.2)),
+            (max.0.min(shape_max.0), max.1.min(shape_max.1), max.2.min(shape_max.2)),
+        );
+        if min.0 > max.0 || min.1 > max.1 || min.2 > max.2 { return 0; }
+        match self {
+            Shape::Cuboid { .. } => return (max.0 - min.0 + 1) as usize * (max.1 - min.1 + 1) as usize * (max.2 - min.2 + 1) as usize,
+            Shape::Mask { blocks, .. } => return blocks.iter().filter(|b| in_box(**b, min, max)).count(),
+            _ => {}
+        }
+
+        let mut count = 0;
+        for x in min.0..=max.0 {
+            for z in min.2..=max.2 {
+                count += (min.1..=max.1).filter(|y| self.contains_xyz(x, *y, z)).count();
+            }
+        }
+        count
+    }
+}
+
+fn non_empty(bounds: BlockBounds) -> Option<BlockBounds> {
+    let (min, max) = bounds;
+    if min.0 > max.0 || min.1 > max.1 || min.2 > max.2 { None } else { Some(bounds) }
+}
+
+// Even-odd rule, points on an edge count as inside
+fn polygon_contains(points: &[(i32, i32)], x: i32, z: i32) -> bool {
+    let (x, z) = (x as i64, z as i64);
+    let mut inside = false;
+    for i in 0..points.len() {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        let (ax, az, bx, bz) = (a.0 as i64, a.1 as i64, b.0 as i64, b.1 as i64);
+
+        let on_line = (bx - ax) * (z - az) == (bz - az) * (x - ax);
+        if on_line && x >= ax.min(bx) && x <= ax.max(bx) && z >= az.min(bz) && z <= az.max(bz) { return true; }
+
+        if (az > z) != (bz > z) {
+            // x of the edge at z, compared without dividing
+            let cross_x = (bx - ax) * (z - az);
+            let relative = (x - ax) * (bz - az);
+            if (bz > az && relative < cross_x) || (bz < az && relative > cross_x) { inside = !inside; }
+        }
+    }
+    inside
+}
+
+fn in_box(block: (i32, i32, i32), min: (i32, i32, i32), max: (i32, i32, i32)) -> bool {
+    block.0 >= min.0 && block.1 >= min.1 && block.2 >= min.2 && block.0 <= max.0 && block.1 <= max.1 && block.2 <= max.2
+}
+
+const ZERO: Vector = (0, 0, 0);
+
+fn vector(p: (i32, i32, i32)) -> Vector { (p.0 as i128, p.1 as i128, p.2 as i128) }
+fn sub(a: Vector, b: Vector) -> Vector { (a.0 - b.0, a.1 - b.1, a.2 - b.2) }
+fn neg(a: Vector) -> Vector { (-a.0, -a.1, -a.2) }
+fn dot(a: Vector, b: Vector) -> i128 { a.0 * b.0 + a.1 * b.1 + a.2 * b.2 }
+fn cross(a: Vector, b: Vector) -> Vector { (a.1 * b.2 - a.2 * b.1, a.2 * b.0 - a.0 * b.2, a.0 * b.1 - a.1 * b.0) }
+
+fn gcd(a: i128, b: i128) -> i128 { if b == 0 { a.abs() } else { gcd(b, a % b) } }
+
+// The plane through point with the normal, scaled down so the same plane always looks the same
+fn plane(normal: Vector, point: Vector) -> Plane {
+    let divisor = gcd(gcd(normal.0, normal.1), normal.2).max(1);
+    let normal = (normal.0 / divisor, normal.1 / divisor, normal.2 / divisor);
+    (normal, dot(normal, point))
+}
+
+fn both_sides(normal: Vector, point: Vector) -> [Plane; 2] { [plane(normal, point), plane(neg(normal), point)] }
+
+// The faces of the hull as planes. One block needs none, the bounds already are that block
+fn hull_planes(points: &[Vector]) -> Vec<Plane> {
+    let Some(&a) = points.first() else { return Vec::new() };
+    let Some(&b) = points.iter().find(|p| **p != a) else { return Vec::new() };
+    let direction = sub(b, a);
+    let Some(&c) = points.iter().find(|p| cross(direction, sub(**p, a)) != ZERO) else {
+        // a segment: two planes through the line kept from both sides, the bounds cut off the ends.
+        // Crossing with the axis the line moves least along can't give 0
+        let smallest = [direction.0.abs(), direction.1.abs(), direction.2.abs()].into_iter().enumerate().min_by_key(|(_, d)| *d).unwrap().0;
+        let axis = [(1, 0, 0), (0, 1, 0), (0, 0, 1)][smallest];
+        let first = cross(direction, axis);
+        return [first, cross(direction, first)].into_iter().flat_map(|normal| both_sides(normal, a)).collect();
+    };
+    let normal = cross(direction, sub(c, a));
+    let Some(&d) = points.iter().find(|p| dot(normal, sub(**p, a)) != 0) else { return flat_hull_planes(points, normal, a) };
+
+    // Starts as the tetrahedron a b c d, every point that's outside replaces the faces it can see with faces to the
+    // edges around them. Faces wind so their normal points out
+    let outward = |p: Vector, q: Vector, r: Vector, inside: Vector| if dot(cross(sub(q, p), sub(r, p)), sub(inside, p)) > 0 { [p, r, q] } else { [p, q, r] };
+    let face_normal = |f: &[Vector; 3]| cross(sub(f[1], f[0]), sub(f[2], f[0]));
+    let mut faces = vec![outward(a, b, c, d), outward(a, b, d, c), outward(a, c, d, b), outward(b, c, d, a)];
+    for &p in points {
+        let (visible, hidden): (Vec<_>, Vec<_>) = faces.into_iter().partition(|f| dot(face_normal(f), sub(p, f[0])) > 0);
+        faces = hidden;
+        if visible.is_empty() { continue; }
+        let edges = visible.iter().flat_map(|f| [(f[0], f[1]), (f[1], f[2]), (f[2], f[0])]).collect::<HashSet<_>>();
+        faces.extend(edges.iter().filter(|(u, v)| !edges.contains(&(*v, *u))).map(|(u, v)| [*u, *v, p]));
+    }
+
+    let mut planes: Vec<Plane> = Vec::new();
+    for face in &faces {
+        let plane = plane(face_normal(face), face[0]);
+        if !planes.contains(&plane) { planes.push(plane); }
+    }
+    planes
+}
+
+// Points that are all on one plane (and not on one line): that plane from both sides, and the edges of their 2D hull
+// in it, found in the two coordinates the plane doesn't depend on the least
+fn flat_hull_planes(points: &[Vector], normal: Vector, on_plane: Vector) -> Vec<Plane> {
+    let dropped = [normal.0.abs(), normal.1.abs(), normal.2.abs()].into_iter().enumerate().max_by_key(|(_, n)| *n).unwrap().0;
+    let flat = |p: &Vector| match dropped { 0 => (p.1, p.2), 1 => (p.0, p.2), _ => (p.0, p.1) };
+    let mut sorted = points.to_vec();
+    sorted.sort_by_key(flat);
+
+    // monotone chain, collinear points are dropped
+    let turn = |o: &Vector, a: &Vector, b: &Vector| {
+        let (o, a, b) = (flat(o), flat(a), flat(b));
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    let mut hull: Vec<Vector> = Vec::new();
+    for pass in [sorted.clone(), sorted.into_iter().rev().collect()] {
+        let start = hull.len();
+        for p in pass {
+            while hull.len() >= start + 2 && turn(&hull[hull.len() - 2], &hull[hull.len() - 1], &p) <= 0 { hull.pop(); }
+            hull.push(p);
+        }
+        hull.pop(); // the last point starts the other half
+    }
+
+    let mut planes = both_sides(normal, on_plane).to_vec();
+    for i in 0..hull.len() {
+        let (p, q, other) = (hull[i], hull[(i + 1) % hull.len()], hull[(i + 2) % hull.len()]);
+        let edge = cross(sub(q, p), normal);
+        planes.push(plane(if dot(edge, sub(other, p)) > 0 { neg(edge) } else { edge }, p));
+    }
+    planes
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::versions::VersionManager;
+    use crate::models::other::tick::Tick;
+    use crate::models::world::block::PaletteBlock;
+    use crate::models::world::chunk::Chunk;
+    use crate::models::world::dimension::Dimension;
+    use crate::models::filter::filter::Filter;
+    use crate::models::world::selection::SelectionBuilder;
+    use crate::models::world::world::World;
+    use crate::traits::access::prelude::BlockReader;
+    use crate::types::WorldKind;
+
+    fn pos(x: i32, y: i32, z: i32) -> Position { Position::new("overworld".into(), x, y, z) }
+
+    #[test]
+    fn shape_membership() {
+        let sphere = Shape::sphere(&pos(0, 0, 0), 2.0);
+        assert!(sphere.contains(&pos(2, 0, 0)) && sphere.contains(&pos(1, 1, 1)));
+        assert!(!sphere.contains(&pos(2, 1, 0)) && !sphere.contains(&Position::new("the_nether".into(), 0, 0, 0)));
+        assert_eq!(sphere.bounds(), Some(((-2, -2, -2), (2, 2, 2))));
+
+        let cylinder = Shape::cylinder(&pos(0, 10, 0), 1.5, 3);
+        assert!(cylinder.contains_xyz(1, 12, 1) && !cylinder.contains_xyz(1, 13, 1) && !cylinder.contains_xyz(2, 10, 0));
+
+        // L shape
+        let polygon = Shape::polygon("overworld".into(), vec![(0, 0), (4, 0), (4, 2), (2, 2), (2, 4), (0, 4)], 0, 0);
+        assert!(polygon.contains_xyz(4, 0, 1) && polygon.contains_xyz(2, 0, 3) && polygon.contains_xyz(1, 0, 1));
+        assert!(!polygon.contains_xyz(3, 0, 3) && !polygon.contains_xyz(5, 0, 0));
+        assert_eq!(polygon.volume_in((-10, -10, -10), (10, 10, 10)), 21);
+
+        let corners = [pos(0, 0, 0), pos(4, 0, 0), pos(0, 4, 0), pos(0, 0, 4)];
+        let hull = Shape::convex_hull(&corners).unwrap();
+        assert!(hull.contains_xyz(1, 1, 1) && hull.contains_xyz(4, 0, 0) && hull.contains_xyz(2, 2, 0));
+        assert!(!hull.contains_xyz(2, 2, 1) && !hull.contains_xyz(-1, 0, 0));
+        assert!(Shape::convex_hull(&[pos(0, 0, 0), Position::new("the_end".into(), 1, 1, 1)]).is_none());
+
+        // a segment and a flat triangle are only that, not their bounding boxes
+        let segment = Shape::convex_hull(&[pos(0, 0, 0), pos(4, 2, 0)]).unwrap();
+        assert!(segment.contains_xyz(2, 1, 0) && segment.contains_xyz(4, 2, 0));
+        assert!(!segment.contains_xyz(1, 1, 0) && !segment.contains_xyz(2, 1, 1));
+        assert_eq!(segment.volume_in((-10, -10, -10), (10, 10, 10)), 3);
+        let triangle = Shape::convex_hull(&[pos(0, 3, 0), pos(4, 3, 0), pos(0, 3, 4), pos(1, 3, 1)]).unwrap();
+        assert!(triangle.contains_xyz(2, 3, 2) && triangle.contains_xyz(0, 3, 4));
+        assert!(!triangle.contains_xyz(3, 3, 2) && !triangle.contains_xyz(1, 2, 1));
+        assert_eq!(triangle.volume_in((-10, -10, -10), (10, 10, 10)), 15);
+        let tilted = Shape::convex_hull(&[pos(0, 0, 0), pos(2, 2, 0), pos(0, 2, 2)]).unwrap();
+        assert!(tilted.contains_xyz(1, 2, 1) && !tilted.contains_xyz(1, 1, 1));
+        assert!(Shape::convex_hull(&[pos(3, 3, 3), pos(3, 3, 3)]).unwrap().volume_in((-10, -10, -10), (10, 10, 10)) == 1);
+
+        // the blocks on a sphere's surface, the hull is the ball without its outermost bumps
+        let surface = (-12..=12).flat_map(|x| (-12..=12).flat_map(move |y| (-12..=12).map(move |z| (x, y, z))))
+            .filter(|(x, y, z)| ((x * x + y * y + z * z) as f64).sqrt().round() == 12.0).map(|(x, y, z)| pos(x, y, z)).collect::<Vec<_>>();
+        let ball = Shape::convex_hull(&surface).unwrap();
+        assert!(ball.contains_xyz(0, 0, 0) && ball.contains_xyz(12, 0, 0) && ball.contains_xyz(6, 6, 6));
+        assert!(!ball.contains_xyz(13, 0, 0) && !ball.contains_xyz(8, 8, 8));
+        assert!(surface.iter().all(|p| ball.contains(p)));
+
+        let mask = Shape::mask(&[pos(0, 0, 0), pos(5, 1, 17), pos(-3, 0, 2), pos(5, 1, 17)]).unwrap();
+        assert!(mask.contains_xyz(5, 1, 17) && !mask.contains_xyz(1, 0, 1));
+        assert_eq!(mask.bounds(), Some(((-3, 0, 0), (5, 1, 17))));
+        assert_eq!(mask.volume_in((-10, -10, -10), (10, 10, 10)), 2);
+        assert_eq!(mask.chunk_positions(16).into_iter().map(|c| c.position()).collect::<Vec<_>>(), vec![(-1, 0), (0, 0), (0, 1)]);
+        assert!(Shape::mask(&[]).is_none());
+
+        let cuboid = Shape::cuboid(&pos(15, 0, -1), &pos(16, 1, 0));
+        let chunks = cuboid.chunk_positions(16).into_iter().map(|c| c.position()).collect::<Vec<_>>();
+        assert_eq!(chunks, vec![(0, -1), (0, 0), (1, -1), (1, 0)]);
+        // the corner chunks of the bounding box are too far away
+        assert_eq!(Shape::sphere(&pos(0, 0, 0), 20.0).chunk_positions(16).len(), 12);
+    }
+
+    #[test]
+    fn selection_is_clipped_to_shape() {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new("".into(), version.clone());
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        for (x, z) in [(0, 0), (1, 0), (5, 5)] {
+            let mut chunk = Chunk::new(ChunkPosition::new(x, z, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version);
+            // a stone block at (1, 0, 1) of every chunk
+            chunk.set_block_at_index((64 * 16 + 1) * 16 + 1, PaletteBlock::from("minecraft:stone"));
+            dimension.set_chunk(chunk);
+        }
+        world.lock().unwrap().set_dimension("overworld".into(), dimension);
+
+        let mut locked = world.lock().unwrap();
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version).sphere(&pos(8, 0, 1), 8.0).build();
+        assert_eq!(selection.chunk_count(), 2);
+        // the chunks at z = -1 don't exist
+        assert_eq!(selection.block_count(), Shape::sphere(&pos(8, 0, 1), 8.0).volume_in((0, -64, 0), (31, 319, 15)));
+
+        let mut found = Vec::new();
+        selection.find_blocks(Filter::from_filter_string("id == minecraft:stone").unwrap(), |b| { found.push(b.position().clone()); true });
+        assert_eq!(found, vec![pos(1, 0, 1)]);
+        assert!(selection.block_at_position(pos(17, 0, 1)).is_none());
+        assert!(selection.block_at_position(pos(1, 0, 1)).is_some());
+    }
+
+    #[test]
+    fn selection_masks_blocks() {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new("".into(), version.clone());
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        let mut chunk = Chunk::new(ChunkPosition::new(0, 0, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version);
+        chunk.set_block_at_index((64 * 16 + 1) * 16 + 1, PaletteBlock::from("minecraft:stone"));
+        chunk.set_block_at_index((64 * 16 + 2) * 16 + 1, PaletteBlock::from("minecraft:stone"));
+        dimension.set_chunk(chunk);
+        world.lock().unwrap().set_dimension("overworld".into(), dimension);
+
+        let mut locked = world.lock().unwrap();
+        let mut selection = SelectionBuilder::new_owned(&mut locked, version).mask(&[pos(1, 0, 1), pos(3, 0, 3), pos(40, 0, 40)]).build();
+        assert_eq!(selection.chunk_count(), 1);
+        let mut found = Vec::new();
+        selection.find_blocks(Filter::from_filter_string("id == minecraft:stone").unwrap(), |b| { found.push(b.position().clone()); true });
+        assert_eq!(found, vec![pos(1, 0, 1)]);
+    }
+}
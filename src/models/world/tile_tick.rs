@@ -1,12 +1,12 @@
 use crate::models::positions::whole_position::Position;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TileTickType {
     FLUID,
     BLOCK
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TileTick {
     priority: i32,
     ticks_till_processed: i32,
@@ -0,0 +1,501 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use regex::Regex;
+use crate::constants::blocks::block_entity_id;
+use crate::models::block_entity::block_entity::BlockEntity;
+use crate::models::entity::entity::Entity;
+use crate::models::other::lasso_string::LassoString;
+use crate::models::positions::chunk_position::ChunkPosition;
+use crate::models::positions::whole_position::Position;
+use crate::models::world::block::PaletteBlock;
+use crate::models::world::fulls::block_state_types::is_valid_state;
+use crate::models::world::pattern::Pattern;
+use crate::models::world::world::{SaveOptions, World};
+use crate::traits::block_entity::BlockEntityTrait;
+use crate::utils::position_utils::{block_position_to_chunk_pos_and_block_index, world_position_to_chunk_position};
+
+type BlockKey = (LassoString, i32, i32, i32);
+
+fn key_of(position: &Position) -> BlockKey { (*position.dimension(), position.x(), position.y(), position.z()) }
+fn position_of(key: &BlockKey) -> Position { Position::new(key.0, key.1, key.2, key.3) }
+
+#[derive(Debug)]
+pub enum TransactionIssue {
+    UnknownDimension(LassoString),
+    ChunkNotLoaded(ChunkPosition),
+    OutOfHeightRange(Position),
+    InvalidBlockId { position: Position, id: String },
+    InvalidBlockState { position: Position, id: String, state: String },
+    OrphanBlockEntity { position: Position, block_entity: String, block: String },
+    MissingEntity { dimension: LassoString, uuid: u128 },
+}
+
+impl fmt::Display for TransactionIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransactionIssue::UnknownDimension(dimension) => write!(f, "dimension {} doesn't exist", dimension),
+            TransactionIssue::ChunkNotLoaded(chunk) => write!(f, "chunk {}, {} in {} isn't loaded", chunk.x(), chunk.z(), chunk.dimension()),
+            TransactionIssue::OutOfHeightRange(position) => write!(f, "{} is outside of the world's height range", position),
+            TransactionIssue::InvalidBlockId { position, id } => write!(f, "{} at {} isn't a valid block id", id, position),
+            TransactionIssue::InvalidBlockState { position, id, state } => write!(f, "{} at {} has an invalid {} state", id, position, state),
+            TransactionIssue::OrphanBlockEntity { position, block_entity, block } => write!(f, "{} block entity at {} would be on {}", block_entity, position, block),
+            TransactionIssue::MissingEntity { dimension, uuid } => write!(f, "entity {:032x} isn't in {}", uuid, dimension),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TransactionError {
+    Aborted(String),
+    Invalid(Vec<TransactionIssue>),
+    Save(io::Error), // the edits were applied but writing them failed, the region files are untouched
+}
+
+impl fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransactionError::Aborted(reason) => write!(f, "transaction aborted: {}", reason),
+            TransactionError::Invalid(issues) => {
+                write!(f, "transaction discarded, {} invalid edit(s)", issues.len())?;
+                for issue in issues { write!(f, "\n - {}", issue)?; }
+                Ok(())
+            },
+            TransactionError::Save(e) => write!(f, "failed to save the transaction: {}", e),
+        }
+    }
+}
+
+impl Error for TransactionError {}
+
+// Edits made in World::transaction. Nothing is written to the world while the closure runs, reads through the
+// transaction see the buffered edits on top of the world
+pub struct WorldTransaction<'w, 'a> {
+    world: &'w World<'a>,
+    blocks: HashMap<BlockKey, PaletteBlock>,
+    block_entities: HashMap<BlockKey, Option<BlockEntity>>, // None removes
+    added_entities: Vec<Entity>,
+    removed_entities: Vec<(LassoString, u128)>,
+    aborted: Option<String>,
+}
+
+impl<'w, 'a> WorldTransaction<'w, 'a> {
+    fn new(world: &'w World<'a>) -> Self {
+        Self { world, blocks: HashMap::new(), block_entities: HashMap::new(), added_entities: Vec::new(), removed_entities: Vec::new(), aborted: None }
+    }
+
+    pub fn world(&self) -> &World<'a> { self.world }
+    pub fn edit_count(&self) -> usize { self.blocks.len() + self.block_entities.len() + self.added_entities.len() + self.removed_entities.len() }
+    pub fn is_aborted(&self) -> bool { self.aborted.is_some() }
+
+    // Discards everything, the transaction returns TransactionError::Aborted
+    pub fn abort(&mut self, reason: &str) { self.aborted = Some(reason.to_string()); }
+
+    // Patterns give their block at the position
+    pub fn set_block(&mut self, position: &Position, block: impl Into<Pattern>) {
+        self.blocks.insert(key_of(position), block.into().block_at(position.x(), position.y(), position.z()));
+    }
+    pub fn fill(&mut self, corner1: &Position, corner2: &Position, pattern: impl Into<Pattern>) -> usize {
+        let pattern = pattern.into();
+        let (min, max) = ((corner1.x().min(corner2.x()), corner1.y().min(corner2.y()), corner1.z().min(corner2.z())), (corner1.x().max(corner2.x()), corner1.y().max(corner2.y()), corner1.z().max(corner2.z())));
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                for z in min.2..=max.2 { self.blocks.insert((*corner1.dimension(), x, y, z), pattern.block_at(x, y, z)); }
+            }
+        }
+        ((max.0 - min.0 + 1) * (max.1 - min.1 + 1) * (max.2 - min.2 + 1)) as usize
+    }
+    pub fn set_block_entity(&mut self, block_entity: BlockEntity) { self.block_entities.insert(key_of(block_entity.base().position()), Some(block_entity)); }
+    pub fn remove_block_entity(&mut self, position: &Position) { self.block_entities.insert(key_of(position), None); }
+    pub fn add_entity(&mut self, entity: Entity) { self.added_entities.push(entity); }
+    pub fn remove_entity(&mut self, dimension: LassoString, uuid: u128) { self.removed_entities.push((dimension, uuid)); }
+
+    pub fn block_at(&self, position: &Position) -> Option<PaletteBlock> {
+        if let Some(block) = self.blocks.get(&key_of(position)) { return Some(block.clone()); }
+        self.world_block_at(position)
+    }
+
+    pub fn block_entity_at(&self, position: &Position) -> Option<BlockEntity> {
+        let key = key_of(position);
+        if let Some(block_entity) = self.block_entities.get(&key) { return block_entity.clone(); }
+        // a block that becomes another block loses its block entity when it's applied
+        if let Some(block) = self.blocks.get(&key) && self.world_block_at(position).is_none_or(|old| old.full_name() != block.full_name()) { return None; }
+        self.world_block_entity_at(position)
+    }
+
+    fn world_block_at(&self, position: &Position) -> Option<PaletteBlock> {
+        let version = self.world.version();
+        let (chunk_pos, index) = block_position_to_chunk_pos_and_block_index(position, version.data.chunk_size, version.data.lowest_y);
+        let chunk = self.world.dimension(position.dimension())?.chunk(chunk_pos.position())?;
+        chunk.lock().unwrap().block_store().get_block_at_index(index)
+    }
+
+    fn world_block_entity_at(&self, position: &Position) -> Option<BlockEntity> {
+        let version = self.world.version();
+        let (chunk_pos, _) = block_position_to_chunk_pos_and_block_index(position, version.data.chunk_size, version.data.lowest_y);
+        let chunk = self.world.dimension(position.dimension())?.chunk(chunk_pos.position())?;
+        chunk.lock().unwrap().block_entities_store().get_at_world_position(position).map(|b| (*b).clone())
+    }
+
+    // Checks the world as it would be after the edits, only where they touch it
+    fn validate(&self) -> Vec<TransactionIssue> {
+        let version = self.world.version();
+        let (chunk_size, lowest_y, highest_y) = (version.data.chunk_size, version.data.lowest_y, version.data.highest_y);
+        let id_format = Regex::new(r"^[a-z0-9_.-]+:[a-z0-9_./-]+$").unwrap();
+        let mut issues = Vec::new();
+        let mut missing = HashSet::new();
+
+        let mut check_position = |position: &Position, issues: &mut Vec<TransactionIssue>| {
+            let Some(dimension) = self.world.dimension(position.dimension()) else {
+                if missing.insert(ChunkPosition::new(0, 0, *position.dimension())) { issues.push(TransactionIssue::UnknownDimension(*position.dimension())); }
+                return false;
+            };
+            if position.y() < lowest_y || position.y() >= highest_y {
+                issues.push(TransactionIssue::OutOfHeightRange(position.clone()));
+                return false;
+            }
+            let (chunk_pos, _) = block_position_to_chunk_pos_and_block_index(position, chunk_size, lowest_y);
+            if dimension.chunk(chunk_pos.position()).is_none() {
+                if missing.insert(chunk_pos.clone()) { issues.push(TransactionIssue::ChunkNotLoaded(chunk_pos)); }
+                return false;
+            }
+            true
+        };
+
+        let touched = self.blocks.keys().chain(self.block_entities.keys()).collect::<HashSet<_>>();
+        for key in touched {
+            let position = position_of(key);
+            if !check_position(&position, &mut issues) { continue; }
+
+            if let Some(block) = self.blocks.get(key) {
+                let id = block.full_name().to_string();
+                if block.is_null() || !id_format.is_match(&id) {
+                    issues.push(TransactionIssue::InvalidBlockId { position, id });
+                    continue;
+                }
+                let invalid_state = block.properties().raw().iter().find(|(k, v)| !is_valid_state(k, v)).map(|(k, _)| k.clone());
+                if let Some(state) = invalid_state {
+                    issues.push(TransactionIssue::InvalidBlockState { position, id, state });
+                    continue;
+                }
+            }
+
+            let Some(block_entity) = self.block_entity_at(&position) else { continue };
+            let block = self.block_at(&position).map(|b| b.full_name().to_string()).unwrap_or_else(|| "minecraft:air".to_string());
+            // blocks outside of the vanilla namespace aren't known, anything goes for them
+            if !block.starts_with("minecraft:") { continue; }
+            if block_entity_id(&block) != Some(block_entity.base().id()) {
+                issues.push(TransactionIssue::OrphanBlockEntity { position, block_entity: block_entity.base().id().to_string(), block });
+            }
+        }
+
+        for entity in &self.added_entities {
+            let position = entity.base().position();
+            let Some(dimension) = self.world.dimension(position.dimension()) else {
+                issues.push(TransactionIssue::UnknownDimension(*position.dimension()));
+                continue;
+            };
+            let chunk_pos = world_position_to_chunk_position(position.x(), position.z(), chunk_size);
+            if dimension.chunk(chunk_pos).is_none() && missing.insert(ChunkPosition::new(chunk_pos.0, chunk_pos.1, *position.dimension())) {
+                issues.push(TransactionIssue::ChunkNotLoaded(ChunkPosition::new(chunk_pos.0, chunk_pos.1, *position.dimension())));
+            }
+        }
+        for (dimension, uuid) in &self.removed_entities {
+            if self.world.dimension(dimension).and_then(|d| d.entity_key_by_uuid(*uuid)).is_none() {
+                issues.push(TransactionIssue::MissingEntity { dimension: *dimension, uuid: *uuid });
+            }
+        }
+        issues
+    }
+}
+
+// Edits that passed validation, taken out of the transaction so the world can be borrowed mutably again
+struct ValidatedEdits {
+    blocks: HashMap<BlockKey, PaletteBlock>,
+    block_entities: HashMap<BlockKey, Option<BlockEntity>>,
+    added_entities: Vec<Entity>,
+    removed_entities: Vec<(LassoString, u128)>,
+}
+
+impl<'a> World<'a> {
+    // Runs edit against a buffer, validates what it did and applies all of it, or nothing when the closure aborted or
+    // anything is invalid. Applied edits are one transaction in the edit history
+    pub fn transaction<F, T>(&mut self, edit: F) -> Result<T, TransactionError> where F: FnOnce(&mut WorldTransaction<'_, 'a>) -> T {
+        self.run_transaction(edit).map(|(result, _)| result)
+    }
+
+    // Same as transaction, then saves the chunks it touched (see World::save_chunks)
+    pub fn transaction_and_save<F, T>(&mut self, edit: F, options: &SaveOptions) -> Result<T, TransactionError> where F: FnOnce(&mut WorldTransaction<'_, 'a>) -> T {
+        let (result, touched) = self.run_transaction(edit)?;
+        self.save_chunks(&touched, options).map_err(TransactionError::Save)?;
+        Ok(result)
+    }
+
+    fn run_transaction<F, T>(&mut self, edit: F) -> Result<(T, Vec<ChunkPosition>), TransactionError> where F: FnOnce(&mut WorldTransaction<'_, 'a>) -> T {
+        let (result, edits) = {
+            let mut transaction = WorldTransaction::new(self);
+            let result = edit(&mut transaction);
+            if let Some(reason) = transaction.aborted.take() { return Err(TransactionError::Aborted(reason)); }
+            let issues = transaction.validate();
+            if !issues.is_empty() { return Err(TransactionError::Invalid(issues)); }
+            let WorldTransaction { blocks, block_entities, added_entities, removed_entities, .. } = transaction;
+            (result, ValidatedEdits { blocks, block_entities, added_entities, removed_entities })
+        };
+        Ok((result, self.apply_edits(edits)))
+    }
+
+    // Can't fail, everything it needs was checked by validate. Returns the chunks whose blocks changed
+    fn apply_edits(&mut self, edits: ValidatedEdits) -> Vec<ChunkPosition> {
+        let (chunk_size, lowest_y) = (self.version().data.chunk_size, self.version().data.lowest_y);
+        self.history_mut().begin("transaction");
+
+        let mut per_chunk: HashMap<ChunkPosition, (HashMap<usize, usize>, Vec<PaletteBlock>)> = HashMap::new();
+        for (key, block) in edits.blocks {
+            let (chunk_pos, index) = block_position_to_chunk_pos_and_block_index(&position_of(&key), chunk_size, lowest_y);
+            let (choices, blocks) = per_chunk.entry(chunk_pos).or_default();
+            choices.insert(index, blocks.len());
+            blocks.push(block);
+        }
+        let mut touched = per_chunk.keys().cloned().collect::<HashSet<_>>();
+        let mut replaced = HashSet::new(); // blocks that became a different block, their default block entity was recorded
+        for (chunk_pos, (choices, blocks)) in per_chunk {
+            let chunk = self.dimension(chunk_pos.dimension()).unwrap().chunk(chunk_pos.position()).unwrap();
+            let mut chunk = chunk.lock().unwrap();
+            let indices = choices.keys().copied().collect::<Vec<_>>();
+            let edit = chunk.edit_blocks_at_indices(&indices, &blocks, |index| choices[&index]);
+            replaced.extend(edit.replaced().iter().map(|index| key_of(&chunk.block_position(*index))));
+            self.history_mut().record_chunk_edit(&chunk, &edit);
+        }
+
+        for (key, block_entity) in edits.block_entities {
+            let position = position_of(&key);
+            let (chunk_pos, _) = block_position_to_chunk_pos_and_block_index(&position, chunk_size, lowest_y);
+            let chunk = self.dimension(chunk_pos.dimension()).unwrap().chunk(chunk_pos.position()).unwrap();
+            let mut chunk = chunk.lock().unwrap();
+            let before = match &block_entity {
+                Some(block_entity) => {
+                    let before = if replaced.contains(&key) { None }
+                        else { chunk.block_entities_store().get_at_world_position(&position).map(|b| (*b).clone()) };
+                    chunk.block_entities_store_mut().set_at_world_position(block_entity.clone());
+                    before
+                },
+                None => chunk.block_entities_store_mut().remove_at_world_position(&position).map(|b| (*b).clone()),
+            };
+            self.history_mut().record_block_entity(&position, before, block_entity);
+            touched.insert(chunk_pos);
+        }
+
+        for (dimension_id, uuid) in edits.removed_entities {
+            let dimension = self.dimension_mut(&dimension_id).unwrap();
+            let Some(removed) = dimension.entity_key_by_uuid(uuid).and_then(|key| dimension.remove_entity(key)) else { continue };
+            self.history_mut().record_entity(dimension_id, uuid, Some(removed), None);
+        }
+        for entity in edits.added_entities {
+            let (dimension_id, uuid) = (*entity.base().position().dimension(), entity.base().uuid());
+            self.dimension_mut(&dimension_id).unwrap().add_entity(entity.clone());
+            self.history_mut().record_entity(dimension_id, uuid, None, Some(entity));
+        }
+
+        self.history_mut().commit();
+        touched.into_iter().collect()
+    }
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, process};
+    use fastnbt::Value;
+    use crate::constants::versions::VersionManager;
+    use crate::models::block_entity::block_entity::GenericBlockEntity;
+    use crate::models::other::properties::Properties;
+    use crate::models::other::region::{Region, RegionType};
+    use crate::models::other::tick::Tick;
+    use crate::models::world::chunk::Chunk;
+    use crate::models::world::dimension::Dimension;
+    use crate::models::block_entity::types::storage_container::storage_container::StorageContainerBlockEntity;
+    use crate::models::block_entity::types::storage_container::types::standard_container::StandardStorageContainerBlockEntity;
+    use crate::models::entity::entity::{Entity, EntityType};
+    use crate::models::other::inventory::{Inventory, Item};
+    use crate::models::other::mojang_data::text_component::TextComponent;
+    use crate::models::positions::entity_position::EntityPosition;
+    use crate::traits::block_entity::{BlockEntityTrait, StorageContainerTrait};
+    use crate::types::WorldKind;
+
+    fn pos(x: i32, y: i32, z: i32) -> Position { Position::new("overworld".into(), x, y, z) }
+
+    fn block_name(world: &World, p: &Position) -> Option<String> {
+        WorldTransaction::new(world).block_at(p).map(|b| b.full_name().to_string())
+    }
+
+    fn chest(p: Position) -> BlockEntity {
+        BlockEntity::Other(GenericBlockEntity::new("minecraft:chest".to_string(), p, Properties::new(HashMap::new())))
+    }
+
+    #[test]
+    fn applies_all_or_nothing() {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new("".into(), version.clone());
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        dimension.set_chunk(Chunk::new(ChunkPosition::new(0, 0, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+        let mut locked = world.lock().unwrap();
+        locked.set_dimension("overworld".into(), dimension);
+
+        let stone = PaletteBlock::from("minecraft:stone");
+        let placed = locked.transaction(|tx| {
+            let placed = tx.fill(&pos(0, 0, 0), &pos(3, 0, 3), &stone);
+            tx.set_block(&pos(1, 1, 1), PaletteBlock::from("minecraft:chest"));
+            tx.set_block_entity(chest(pos(1, 1, 1)));
+            assert_eq!(tx.block_at(&pos(2, 0, 2)).unwrap().full_name(), "minecraft:stone");
+            placed
+        }).unwrap();
+        assert_eq!(placed, 16);
+
+        // patterns are resolved per block, the same way a selection fill would
+        let result = locked.transaction(|tx| {
+            let mix = Pattern::parse("granite, diorite").unwrap();
+            tx.fill(&pos(0, 2, 0), &pos(3, 2, 3), &mix);
+            assert!((0..4).all(|x| tx.block_at(&pos(x, 2, 1)).unwrap() == mix.block_at(x, 2, 1)));
+            tx.abort("only looking");
+        });
+        assert!(matches!(result, Err(TransactionError::Aborted(_))));
+        assert_eq!(block_name(&locked, &pos(3, 0, 3)).as_deref(), Some("minecraft:stone"));
+        assert_eq!(locked.history().undo_names(), vec!["transaction"]);
+
+        let invalid: Vec<Box<dyn Fn(&mut WorldTransaction)>> = vec![
+            Box::new(|tx| tx.set_block(&pos(0, 500, 0), PaletteBlock::from("minecraft:dirt"))),
+            Box::new(|tx| tx.set_block(&pos(40, 0, 0), PaletteBlock::from("minecraft:dirt"))),
+            Box::new(|tx| tx.set_block(&pos(0, 0, 0), PaletteBlock::new("minecraft:furnace", Some(HashMap::from([("facing".to_string(), Value::String("sideways".to_string()))]))))),
+            Box::new(|tx| tx.set_block(&pos(0, 0, 0), PaletteBlock::from("Not A Block"))),
+            Box::new(|tx| tx.set_block_entity(chest(pos(2, 0, 2)))),
+            // replacing the chest drops its block entity, giving it a new one that doesn't fit is still an orphan
+            Box::new(|tx| { tx.set_block(&pos(1, 1, 1), PaletteBlock::from("minecraft:stone")); tx.set_block_entity(chest(pos(1, 1, 1))); }),
+            Box::new(|tx| tx.remove_entity("overworld".into(), 42)),
+        ];
+        for edit in invalid {
+            let result = locked.transaction(|tx| {
+                tx.fill(&pos(0, 2, 0), &pos(1, 2, 1), &PaletteBlock::from("minecraft:glass"));
+                edit(tx);
+            });
+            assert!(matches!(result, Err(TransactionError::Invalid(ref issues)) if issues.len() == 1), "{:?}", result);
+            assert_eq!(block_name(&locked, &pos(0, 2, 0)), None);
+        }
+        assert_eq!(block_name(&locked, &pos(1, 1, 1)).as_deref(), Some("minecraft:chest"));
+
+        let result = locked.transaction(|tx| {
+            tx.set_block(&pos(0, 2, 0), PaletteBlock::from("minecraft:glass"));
+            tx.abort("changed my mind");
+        });
+        assert!(matches!(result, Err(TransactionError::Aborted(_))));
+        assert_eq!(block_name(&locked, &pos(0, 2, 0)), None);
+
+        // breaking the chest takes its block entity with it
+        locked.transaction(|tx| tx.set_block(&pos(1, 1, 1), PaletteBlock::from("minecraft:air"))).unwrap();
+        let chunk = locked.dimension(&"overworld".into()).unwrap().chunk((0, 0)).unwrap();
+        assert!(chunk.lock().unwrap().block_entities_store().get_at_world_position(&pos(1, 1, 1)).is_none());
+        assert_eq!(locked.undo().unwrap().as_deref(), Some("transaction"));
+        assert!(chunk.lock().unwrap().block_entities_store().get_at_world_position(&pos(1, 1, 1)).is_some());
+    }
+
+    #[test]
+    fn undoing_a_placed_block_entity_removes_it() {
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new("".into(), version.clone());
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        let chunk = dimension.set_chunk(Chunk::new(ChunkPosition::new(0, 0, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+        let mut locked = world.lock().unwrap();
+        locked.set_dimension("overworld".into(), dimension);
+
+        let named = BlockEntity::Other(GenericBlockEntity::new("minecraft:chest".to_string(), pos(1, 1, 1),
+            Properties::new(HashMap::from([("CustomName".to_string(), Value::String("loot".to_string()))]))));
+        locked.transaction(|tx| {
+            tx.set_block(&pos(1, 1, 1), PaletteBlock::from("minecraft:chest"));
+            tx.set_block_entity(named);
+        }).unwrap();
+        let block_entities = || chunk.lock().unwrap().block_entities_store().get_all().iter().map(|b| b.base().properties().get("CustomName").is_some()).collect::<Vec<_>>();
+        assert_eq!(block_entities(), vec![true]);
+
+        // the block was air before, so is its block entity: neither the default nor the given one stays behind
+        assert_eq!(locked.undo().unwrap().as_deref(), Some("transaction"));
+        assert_eq!(block_name(&locked, &pos(1, 1, 1)), None);
+        assert!(block_entities().is_empty());
+        assert_eq!(locked.redo().unwrap().as_deref(), Some("transaction"));
+        assert_eq!(block_name(&locked, &pos(1, 1, 1)).as_deref(), Some("minecraft:chest"));
+        assert_eq!(block_entities(), vec![true]);
+    }
+
+    #[test]
+    fn saves_atomically() {
+        let directory = std::env::temp_dir().join(format!("cubicle_transaction_test_{}", process::id()));
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new(directory.clone(), version.clone());
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        dimension.set_chunk(Chunk::new(ChunkPosition::new(1, 2, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version));
+        let mut locked = world.lock().unwrap();
+        locked.set_dimension("overworld".into(), dimension);
+
+        let slab = PaletteBlock::new("minecraft:oak_slab", Some(HashMap::from([("type".to_string(), Value::String("top".to_string()))])));
+        locked.transaction_and_save(|tx| tx.set_block(&pos(17, 70, 33), slab.clone()), &SaveOptions::new()).unwrap();
+        let path = directory.join("region").join("r.0.0.mca");
+        let files = || fs::read_dir(directory.join("region")).unwrap().map(|f| f.unwrap().file_name().into_string().unwrap()).collect::<HashSet<_>>();
+        assert_eq!(files(), HashSet::from(["r.0.0.mca".to_string()]));
+
+        locked.transaction_and_save(|tx| tx.set_block(&pos(18, 70, 33), PaletteBlock::from("minecraft:stone")), &SaveOptions::new().with_backup(true)).unwrap();
+        assert_eq!(files(), HashSet::from(["r.0.0.mca".to_string(), "r.0.0.mca.bak".to_string()]));
+
+        let region = Region { position: ChunkPosition::new(0, 0, "overworld".into()), path: path.clone(), region_type: RegionType::Block };
+        let (chunks, _) = locked.loader().block_loader().parse_region(&region);
+        assert_eq!(chunks.len(), 1);
+        let block_at = |x: usize, y: i32, z: usize| chunks[0].block_store().get_block_at_index((((y + 64) as usize * 16) + z) * 16 + x).unwrap();
+        let saved = block_at(1, 70, 1);
+        assert_eq!(saved.full_name(), "minecraft:oak_slab");
+        assert_eq!(saved.properties().raw().get("type"), Some(&Value::String("top".to_string())));
+        assert_eq!(block_at(2, 70, 1).full_name(), "minecraft:stone");
+        assert_eq!(block_at(0, 0, 0).full_name(), "minecraft:air");
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn save_writes_typed_block_entities_and_entities() {
+        let directory = std::env::temp_dir().join(format!("cubicle_save_entities_test_{}", process::id()));
+        let version = VersionManager::get("1.20.1", WorldKind::Singleplayer);
+        let world = World::new(directory.clone(), version.clone());
+        let mut chunk = Chunk::new(ChunkPosition::new(1, 2, "overworld".into()), 3465, Tick::new(0), Tick::new(0), "minecraft:full".to_string(), &version);
+        let index = ((70 + 64) * 16 + 1) * 16 + 1;
+        chunk.set_block_at_index(index, PaletteBlock::from("minecraft:chest"));
+        let mut items = Inventory::new(27);
+        items.set_item(3, Item::new("minecraft:diamond".to_string(), 5, Properties::new(HashMap::new())));
+        let generic = GenericBlockEntity::new("minecraft:chest".to_string(), pos(17, 70, 33), Properties::new(HashMap::from([("LootTableSeed".to_string(), Value::Long(7))])));
+        let typed = StandardStorageContainerBlockEntity::new(generic, None, Some(TextComponent::new("Loot")), items);
+        assert!(chunk.set_block_entity_at_index(index, BlockEntity::StorageContainer(StorageContainerBlockEntity::Normal(typed))));
+        let mut dimension = Dimension::new("overworld".into(), version.clone());
+        dimension.set_chunk(chunk);
+        dimension.add_entity(Entity::new(EntityType::Mob, Tick::new(300), 0.0, Tick::new(0), false, (0.0, 0.0, 0.0), true,
+            EntityPosition::new(17.5, 70.0, 33.5, 90.0, 0.0, "overworld".into()), 42, Properties::new(HashMap::new())));
+        let mut locked = world.lock().unwrap();
+        locked.set_dimension("overworld".into(), dimension);
+        assert_eq!(locked.save(&SaveOptions::new()).unwrap(), 1);
+
+        let region = |folder: &str, region_type| Region { position: ChunkPosition::new(0, 0, "overworld".into()), path: directory.join(folder).join("r.0.0.mca"), region_type };
+        let (chunks, _) = locked.loader().block_loader().parse_region(&region("region", RegionType::Block));
+        let saved = chunks[0].block_entities_store().get_at_world_position(&pos(17, 70, 33)).unwrap();
+        let storage = saved.as_storage().unwrap();
+        assert_eq!(storage.items().get_at(3).map(|i| (i.id(), i.count())), Some(("minecraft:diamond", 5)));
+        assert_eq!(storage.custom_name().map(|n| n.text().as_str()), Some("Loot"));
+        assert_eq!(saved.base().properties().raw().get("LootTableSeed"), Some(&Value::Long(7)));
+
+        let entities = locked.loader().entity_loader().parse_region(&region("entities", RegionType::Entity));
+        assert_eq!(entities[&(1, 2)].iter().map(|e| (e.base().uuid(), e.base().position().yaw())).collect::<Vec<_>>(), vec![(42, 90.0)]);
+
+        // chunks loaded without their entities can't have entity changes saved
+        let chunk = locked.dimension(&"overworld".into()).unwrap().chunk((1, 2)).unwrap();
+        chunk.lock().unwrap().set_entities_loaded(false);
+        chunk.lock().unwrap().mark_entities_dirty();
+        assert_eq!(locked.save(&SaveOptions::new()).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(chunk.lock().unwrap().entities_dirty());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
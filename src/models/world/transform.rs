@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use fastnbt::Value;
+use crate::models::world::block::PaletteBlock;
+use crate::models::world::fulls::block_state_types::{Axis, BlockType, Direction, Hinge, Orientation, Shape, WallConnection};
+
+// Same meaning as vanilla's Rotation / Mirror. Mirroring is applied before rotating
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rotation {
+    None,
+    Clockwise90,
+    Clockwise180,
+    CounterClockwise90,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mirror {
+    None,
+    LeftRight, // flips z, north <-> south
+    FrontBack, // flips x, east <-> west
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [Rotation::None, Rotation::Clockwise90, Rotation::Clockwise180, Rotation::CounterClockwise90];
+
+    pub fn quarter_turns(self) -> i32 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Clockwise90 => 1,
+            Rotation::Clockwise180 => 2,
+            Rotation::CounterClockwise90 => 3,
+        }
+    }
+}
+
+// Transforms a horizontal (x, z) offset, y never changes
+pub fn transform_xz(x: i32, z: i32, rotation: Rotation, mirror: Mirror) -> (i32, i32) {
+    let (x, z) = match mirror {
+        Mirror::None => (x, z),
+        Mirror::LeftRight => (x, -z),
+        Mirror::FrontBack => (-x, z),
+    };
+    match rotation {
+        Rotation::None => (x, z),
+        Rotation::Clockwise90 => (-z, x),
+        Rotation::Clockwise180 => (-x, -z),
+        Rotation::CounterClockwise90 => (z, -x),
+    }
+}
+
+// Same as transform_xz for continuous coordinates (entity positions, velocities), turning around (0, 0)
+pub fn transform_xz_f64(x: f64, z: f64, rotation: Rotation, mirror: Mirror) -> (f64, f64) {
+    let (x, z) = match mirror {
+        Mirror::None => (x, z),
+        Mirror::LeftRight => (x, -z),
+        Mirror::FrontBack => (-x, z),
+    };
+    match rotation {
+        Rotation::None => (x, z),
+        Rotation::Clockwise90 => (-z, x),
+        Rotation::Clockwise180 => (-x, -z),
+        Rotation::CounterClockwise90 => (z, -x),
+    }
+}
+
+impl Direction {
+    const HORIZONTAL: [Direction; 4] = [Direction::North, Direction::East, Direction::South, Direction::West];
+
+    pub fn from_name(name: &str) -> Option<Direction> { Direction::from_nbt(Some(&Value::String(name.to_string()))) }
+    pub fn name(&self) -> &'static str {
+        match self {
+            Direction::Down => "down",
+            Direction::East => "east",
+            Direction::North => "north",
+            Direction::South => "south",
+            Direction::Up => "up",
+            Direction::West => "west",
+        }
+    }
+
+    pub fn rotated(self, rotation: Rotation) -> Direction {
+        match Direction::HORIZONTAL.iter().position(|d| *d == self) {
+            Some(i) => Direction::HORIZONTAL[(i + rotation.quarter_turns() as usize) % 4],
+            None => self
+        }
+    }
+
+    pub fn mirrored(self, mirror: Mirror) -> Direction {
+        match (mirror, self) {
+            (Mirror::LeftRight, Direction::North) => Direction::South,
+            (Mirror::LeftRight, Direction::South) => Direction::North,
+            (Mirror::FrontBack, Direction::East) => Direction::West,
+            (Mirror::FrontBack, Direction::West) => Direction::East,
+            _ => self
+        }
+    }
+
+    pub fn transformed(self, rotation: Rotation, mirror: Mirror) -> Direction { self.mirrored(mirror).rotated(rotation) }
+}
+
+impl Axis {
+    pub fn rotated(self, rotation: Rotation) -> Axis {
+        if rotation.quarter_turns() % 2 == 0 { return self; }
+        match self {
+            Axis::X => Axis::Z,
+            Axis::Z => Axis::X,
+            Axis::Y => Axis::Y,
+        }
+    }
+}
+
+impl Hinge {
+    pub fn mirrored(self, mirror: Mirror) -> Hinge {
+        match (mirror, self) {
+            (Mirror::None, _) => self,
+            (_, Hinge::Left) => Hinge::Right,
+            (_, Hinge::Right) => Hinge::Left,
+        }
+    }
+}
+
+impl BlockType {
+    // only chest halves have a handedness
+    pub fn mirrored(self, mirror: Mirror) -> BlockType {
+        match (mirror, self) {
+            (Mirror::None, _) => self,
+            (_, BlockType::Left) => BlockType::Right,
+            (_, BlockType::Right) => BlockType::Left,
+            _ => self
+        }
+    }
+}
+
+impl Shape {
+    fn rail_directions(self) -> Option<(Direction, Direction)> {
+        match self {
+            Shape::NorthSouth => Some((Direction::North, Direction::South)),
+            Shape::EastWest => Some((Direction::East, Direction::West)),
+            Shape::NorthEast => Some((Direction::North, Direction::East)),
+            Shape::NorthWest => Some((Direction::North, Direction::West)),
+            Shape::SouthEast => Some((Direction::South, Direction::East)),
+            Shape::SouthWest => Some((Direction::South, Direction::West)),
+            _ => None
+        }
+    }
+
+    fn from_rail_directions(a: Direction, b: Direction) -> Option<Shape> {
+        [Shape::NorthSouth, Shape::EastWest, Shape::NorthEast, Shape::NorthWest, Shape::SouthEast, Shape::SouthWest].into_iter()
+            .find(|shape| shape.rail_directions().is_some_and(|(x, y)| (x, y) == (a, b) || (y, x) == (a, b)))
+    }
+
+    fn ascending(direction: Direction) -> Option<Shape> {
+        match direction {
+            Direction::North => Some(Shape::AscendingNorth),
+            Direction::East => Some(Shape::AscendingEast),
+            Direction::South => Some(Shape::AscendingSouth),
+            Direction::West => Some(Shape::AscendingWest),
+            _ => None
+        }
+    }
+
+    // rail shapes turn with the block, stair corners only change hands when mirrored
+    pub fn transformed(self, rotation: Rotation, mirror: Mirror) -> Shape {
+        let transform = |d: Direction| d.transformed(rotation, mirror);
+        match self {
+            Shape::AscendingNorth => Shape::ascending(transform(Direction::North)).unwrap_or(self),
+            Shape::AscendingEast => Shape::ascending(transform(Direction::East)).unwrap_or(self),
+            Shape::AscendingSouth => Shape::ascending(transform(Direction::South)).unwrap_or(self),
+            Shape::AscendingWest => Shape::ascending(transform(Direction::West)).unwrap_or(self),
+            Shape::InnerLeft | Shape::InnerRight | Shape::OuterLeft | Shape::OuterRight if mirror == Mirror::None => self,
+            Shape::InnerLeft => Shape::InnerRight,
+            Shape::InnerRight => Shape::InnerLeft,
+            Shape::OuterLeft => Shape::OuterRight,
+            Shape::OuterRight => Shape::OuterLeft,
+            Shape::Straight => self,
+            _ => self.rail_directions()
+                .and_then(|(a, b)| Shape::from_rail_directions(transform(a), transform(b)))
+                .unwrap_or(self)
+        }
+    }
+}
+
+impl Orientation {
+    const ALL: [Orientation; 12] = [
+        Orientation::DownEast, Orientation::DownNorth, Orientation::DownSouth, Orientation::DownWest,
+        Orientation::EastUp, Orientation::NorthUp, Orientation::SouthUp, Orientation::WestUp,
+        Orientation::UpEast, Orientation::UpNorth, Orientation::UpSouth, Orientation::UpWest,
+    ];
+
+    // (front, top), e.g. (Down, East) for down_east
+    pub fn directions(self) -> (Direction, Direction) {
+        let Value::String(name) = self.to_nbt() else { unreachable!() };
+        let (front, top) = name.split_once('_').unwrap();
+        (Direction::from_name(front).unwrap(), Direction::from_name(top).unwrap())
+    }
+
+    pub fn from_directions(front: Direction, top: Direction) -> Option<Orientation> {
+        Orientation::ALL.into_iter().find(|o| o.directions() == (front, top))
+    }
+
+    pub fn transformed(self, rotation: Rotation, mirror: Mirror) -> Orientation {
+        let (front, top) = self.directions();
+        Orientation::from_directions(front.transformed(rotation, mirror), top.transformed(rotation, mirror)).unwrap_or(self)
+    }
+}
+
+fn transform_states(states: &HashMap<String, Value>, rotation: Rotation, mirror: Mirror) -> HashMap<String, Value> {
+    let mut transformed = states.clone();
+    let text = |key: &str| match states.get(key) { Some(Value::String(s)) => Some(s.as_str()), _ => None };
+
+    if let Some(facing) = Direction::from_nbt(states.get("facing")) {
+        transformed.insert("facing".to_string(), facing.transformed(rotation, mirror).to_nbt());
+    }
+    if let Some(axis) = Axis::from_nbt(states.get("axis")) {
+        transformed.insert("axis".to_string(), axis.rotated(rotation).to_nbt());
+    }
+    // signs, banners, skulls: 16 steps, 0 is south
+    if let Some(value) = text("rotation").and_then(|r| r.parse::<i32>().ok()) {
+        let mirrored = match mirror {
+            Mirror::None => value,
+            Mirror::FrontBack => (16 - value) % 16,
+            Mirror::LeftRight => (8 - value + 16) % 16,
+        };
+        transformed.insert("rotation".to_string(), Value::String(((mirrored + rotation.quarter_turns() * 4) % 16).to_string()));
+    }
+    // fences, walls, redstone, glass panes, etc
+    let connections = Direction::HORIZONTAL.map(|d| (d, WallConnection::from_nbt(states.get(d.name()))));
+    for (direction, connection) in connections {
+        if connection.is_some() { transformed.remove(direction.name()); }
+    }
+    for (direction, connection) in connections {
+        if let Some(connection) = connection {
+            transformed.insert(direction.transformed(rotation, mirror).name().to_string(), connection.to_nbt());
+        }
+    }
+    // rails and stairs
+    if let Some(shape) = Shape::from_nbt(states.get("shape")) {
+        transformed.insert("shape".to_string(), shape.transformed(rotation, mirror).to_nbt());
+    }
+    // jigsaws and crafters
+    if let Some(orientation) = Orientation::from_nbt(states.get("orientation")) {
+        transformed.insert("orientation".to_string(), orientation.transformed(rotation, mirror).to_nbt());
+    }
+    // doors
+    if let Some(hinge) = Hinge::from_nbt(states.get("hinge")) {
+        transformed.insert("hinge".to_string(), hinge.mirrored(mirror).to_nbt());
+    }
+    // double chests, slabs and pistons also use "type" but those values never change
+    if let Some(block_type) = BlockType::from_nbt(states.get("type")) {
+        transformed.insert("type".to_string(), block_type.mirrored(mirror).to_nbt());
+    }
+    // "half" (stairs, trapdoors, doors, tall plants) is vertical and y is never flipped, so it's kept as is
+    transformed
+}
+
+pub fn transform_block(block: &PaletteBlock, rotation: Rotation, mirror: Mirror) -> PaletteBlock {
+    let states = block.properties().raw().clone();
+    if states.is_empty() || (rotation == Rotation::None && mirror == Mirror::None) {
+        return PaletteBlock::new(&block.full_name(), Some(states));
+    }
+    PaletteBlock::new(&block.full_name(), Some(transform_states(&states, rotation, mirror)))
+}
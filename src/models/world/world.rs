@@ -1,19 +1,35 @@
 use std::cmp::PartialEq;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use fastnbt::Value;
+use crate::constants::constants::{BLOCK_INDEX_FILE_NAME, LEVEL_FILE_NAME, REGION_CHUNK_LINE, VANILLA_DIMENSIONS, ZLIB_COMPRESSION_TYPE};
+use crate::constants::structure_sets::StructureSet;
 use crate::constants::versions::Version;
 use crate::loaders::loader::MainLoader;
-use crate::models::entity::entity::PlayerEntity;
+use crate::loaders::loader_utils::{compress_gzip, compress_zlib, handle_chunk_compression, read_region_chunk, read_region_chunks, replace_file, uncompress_gzip, RawRegionChunk, RegionWrites};
+use crate::models::entity::entity::{Entity, PlayerEntity};
+use crate::models::filter::filter::Filter;
 use crate::models::other::lasso_string::LassoString;
 use crate::models::other::region::{Region, RegionType};
-use crate::models::other::tick::Tick;
 use crate::models::positions::chunk_position::ChunkPosition;
+use crate::models::world::block_index::{count_blocks, count_in, BlockIndex};
 use crate::models::world::chunk::Chunk;
+use crate::models::world::chunk_cache::{CacheOptions, ChunkCache};
 use crate::models::world::dimension::Dimension;
 use crate::models::world::fulls::full_block::FullBlock;
+use crate::models::world::generator::{ChunkGenerator, FlatLayer};
+use crate::models::world::history::EditHistory;
 use crate::models::world::selection::{Selection, SelectionBuilder};
-use crate::types::{ChunkType, RegionPosition, WorldType};
+use crate::models::world_structures::generic_structure::GenericParentStructure;
+use crate::models::world_structures::prediction;
+use crate::models::world_structures::prediction::{PredictionStatus, StructurePrediction};
+use crate::types::{ChunkType, RegionPosition, WorldKind, WorldType};
+use crate::utils::parallel::parallel_map;
+use crate::utils::position_utils::chunk_position_to_region_position_and_index;
 // TODO: When loading a world have a WorldInfo struct with readonly flag
 
 pub struct World<'a> {
@@ -26,6 +42,13 @@ pub struct World<'a> {
     dimensions: HashMap<LassoString, Dimension>,
     unloaded_regions: Vec<Region>,
     players: Vec<PlayerEntity>,
+    block_index: Option<BlockIndex>,
+    history: EditHistory,
+    threads: usize, // workers for loading and par_ scans, 0 is one per core
+    chunk_cache: Option<ChunkCache>,
+    eviction_error: Option<io::Error>, // from an eviction that couldn't hand its error back, see take_eviction_error
+    generator: Option<ChunkGenerator>, // makes the chunks that aren't loaded or on disk, see World::load_chunk
+    generated_dimensions: HashSet<LassoString>, // the ones the generator made chunks in, see save_level_generator
 
     self_ref: Option<WorldType<'a>>
 }
@@ -41,6 +64,14 @@ impl<'a> World<'a> {
                     seed: 0,
                     dimensions: HashMap::new(),
                     unloaded_regions: Vec::new(),
+                    players: Vec::new(),
+                    block_index: None,
+                    history: EditHistory::new(),
+                    threads: 0,
+                    chunk_cache: None,
+                    eviction_error: None,
+                    generator: None,
+                    generated_dimensions: HashSet::new(),
                     loader: MainLoader::new(version.clone()),
                     version,
                     self_ref: None,
@@ -63,34 +94,72 @@ impl<'a> World<'a> {
     pub fn path(&self) -> &PathBuf { &self.path }
     pub fn loader(&self) -> &MainLoader<'a> { &self.loader }
     pub fn version(&self) -> Arc<Version> { self.version.clone() }
+    pub fn history(&self) -> &EditHistory { &self.history }
+    pub fn history_mut(&mut self) -> &mut EditHistory { &mut self.history }
+    pub fn threads(&self) -> usize { self.threads }
+    pub fn chunk_cache(&self) -> Option<&ChunkCache> { self.chunk_cache.as_ref() }
+    pub fn generator(&self) -> Option<&ChunkGenerator> { self.generator.as_ref() }
     pub fn get(&self) -> WorldType<'a> { self.self_ref.clone().unwrap() }
+    pub fn unloaded_regions(&self) -> &Vec<Region> { &self.unloaded_regions }
+    pub fn players(&self) -> &Vec<PlayerEntity> { &self.players }
+    pub fn players_mut(&mut self) -> &mut Vec<PlayerEntity> { &mut self.players }
+    pub fn player(&self, uuid: u128) -> Option<&PlayerEntity> { self.players.iter().find(|p| p.base().uuid() == uuid) }
+    pub fn player_by_name(&self, name: &str) -> Option<&PlayerEntity> { self.players.iter().find(|p| p.name().is_some_and(|n| n.eq_ignore_ascii_case(name))) }
     pub fn select<'r>(&'r mut self) -> Selection<'r, 'a> {
         SelectionBuilder::new_owned(self, self.version.clone()).all_chunks().build()
     }
 
     pub fn set_seed(&mut self, seed: u64) { self.seed = seed; }
+    pub fn set_threads(&mut self, threads: usize) { self.threads = threads; }
+    pub fn set_generator(&mut self, generator: Option<ChunkGenerator>) { self.generator = generator; }
+    // Players aren't in the dimensions' entity stores, so Selection::find_entities doesn't see them
+    pub fn find_players(&self, filter: &Filter) -> Vec<&PlayerEntity> {
+        self.players.iter().filter(|p| filter.matches_player(p)).collect()
+    }
+
     pub fn set_dimension(&mut self, name: LassoString, dimension: Dimension) { self.dimensions.insert(name, dimension); }
     pub fn set_unloaded_regions(&mut self, unloaded_regions: Vec<Region>) { self.unloaded_regions = unloaded_regions }
 
+    // Replaces the chunk with a flat one, a single layer of block at the bottom of the world (air makes an empty chunk).
+    // None when the dimension isn't loaded. The chunk that was loaded there is dropped with its entities, whatever
+    // wasn't saved of it is lost. replace_chunk does the same with any ChunkGenerator
     pub fn set_chunk(&mut self, position: ChunkPosition, block: FullBlock) -> Option<ChunkType> {
-        let chunk = Chunk::new(
-            position,
-            self.version.data.version_data,
-            Tick::new(0),
-            Tick::new(0),
-            "minecraft:full".to_string(),
-            &self.version
-        );
+        self.dimension(position.dimension())?;
+        Some(self.replace_chunk(position, &ChunkGenerator::flat(vec![FlatLayer::new(block.palette_block(), 1)])))
+    }
 
-        match self.dimension_mut(chunk.position().dimension()) {
-            Some(dim) => {
-                Some(dim.set_chunk(chunk))
-            },
-            None => { None }
-        }
+    // Replaces the chunk with a freshly generated one, the dimension is created if needed. The chunk that was loaded
+    // there is dropped with its entities, whatever wasn't saved of it is lost
+    pub fn replace_chunk(&mut self, position: ChunkPosition, generator: &ChunkGenerator) -> ChunkType {
+        self.delete_chunk(position.clone());
+        let chunk = generator.generate(position.clone(), &self.version);
+        let version = self.version.clone();
+        let added = self.dimensions.entry(*position.dimension())
+            .or_insert_with(|| Dimension::new(*position.dimension(), version))
+            .set_chunk(chunk);
+        let tracked = self.track_chunks(&[position]);
+        self.keep_eviction_error(tracked);
+        added
+    }
+
+    // The chunk made by the world's generator, None without one. Loaded chunks are kept, this doesn't look on disk
+    pub fn generate_chunk(&mut self, position: &ChunkPosition) -> Option<ChunkType> {
+        if let Some(chunk) = self.dimension(position.dimension()).and_then(|d| d.chunk(position.position())) { return Some(chunk); }
+        if !self.generates_in(position.dimension()) { return None; }
+        let generator = self.generator.clone()?;
+        self.generated_dimensions.insert(*position.dimension());
+        Some(self.replace_chunk(position.clone(), &generator))
+    }
+
+    // The generator only makes chunks of the world's dimensions and the vanilla ones, not of any name a position has
+    fn generates_in(&self, dimension: &LassoString) -> bool {
+        self.generator.is_some() && (self.dimensions.contains_key(dimension) || VANILLA_DIMENSIONS.contains(&dimension.get()))
     }
 
+    // Drops the chunk and its entities without saving them
     pub fn delete_chunk(&mut self, position: ChunkPosition) -> Option<ChunkType> {
+        if let Some(cache) = self.chunk_cache.as_mut() { cache.forget(&position); }
+        if let Some(index) = self.block_index.as_mut() { index.detach_chunk(&position); }
         let dim = self.dimension_mut(position.dimension());
         dim?.delete_chunk(position.position())
     }
@@ -103,11 +172,10 @@ impl<'a> World<'a> {
     pub fn load(&mut self) {
         // TODO: here will dry load all regions etc.
 
+        // player files that can't be read are left out
         let pl = self.loader.player_loader();
         let paths = pl.get_player_files(self.path.clone());
-        for path in paths {
-            self.players.push(pl.parse_player(&path));
-        }
+        self.players.extend(paths.iter().filter_map(|path| pl.parse_player(path)));
     }
 
     pub fn register_regions(&mut self) -> usize {
@@ -123,31 +191,537 @@ impl<'a> World<'a> {
         self.unloaded_regions.len()
     }
 
-    pub fn load_region(&mut self, position: RegionPosition) {
+    // The chunks of the region are parsed in parallel. Loading never replaces loaded chunks, so edits aren't lost,
+    // unload them first to read them from disk again
+    // The error is from evicting other chunks to make room, the region is loaded anyway
+    pub fn load_region(&mut self, position: RegionPosition) -> io::Result<()> {
         let regions: Vec<_> = self.unloaded_regions.iter()
             .filter(|region| position == region.position)
             .cloned()
             .collect();
 
-        for region in regions {
-            match region.region_type {
-                RegionType::Block => {
-                    // println!("Loading block region");
-                    let (chunks, new_structures) = self.loader.block_loader().parse_region(&region);
-                    let dim = self.dimensions.get_mut(region.position.dimension()).unwrap();
-                    dim.set_chunks(chunks);
-                    dim.structure_store_mut().add_structures(new_structures);
-                }
-                RegionType::Entity => {
-                    // println!("Loading entity region");
-                    let chunks_entities = self.loader().entity_loader().parse_region(&region);
-                    let dim = self.dimensions.get_mut(region.position.dimension()).unwrap();
-                    for (chunk_pos, chunk_entities) in chunks_entities {
-                        let entity_keys = dim.entity_store_mut().add_entities(chunk_entities);
-                        dim.chunk_mut(chunk_pos).unwrap().lock().unwrap().set_entities(entity_keys);
-                    }
-                }
+        let mut added = Vec::new();
+        for region in regions.iter().filter(|r| matches!(r.region_type, RegionType::Block)) {
+            let (chunks, new_structures) = self.loader.block_loader().parse_region_parallel(region, self.threads);
+            added.extend(self.add_block_region(region, chunks, new_structures));
+        }
+        for region in regions.iter().filter(|r| matches!(r.region_type, RegionType::Entity)) {
+            let chunks_entities = self.loader().entity_loader().parse_region(region);
+            self.add_entity_region(region, chunks_entities, &added);
+        }
+        self.track_chunks(&added)
+    }
+
+    // One region per worker, better than load_region for many regions. Block regions go first since entities are
+    // added to their chunks
+    pub fn load_regions(&mut self, positions: &[RegionPosition]) -> io::Result<()> {
+        let added = self.load_region_files(positions, self.threads, true);
+        self.track_chunks(&added)
+    }
+
+    // Returns the chunks that weren't loaded before, those are the only ones added
+    pub(crate) fn load_region_files(&mut self, positions: &[RegionPosition], threads: usize, entities: bool) -> Vec<ChunkPosition> {
+        let (block_regions, entity_regions): (Vec<_>, Vec<_>) = self.unloaded_regions.iter()
+            .filter(|region| positions.contains(&region.position))
+            .filter(|region| entities || matches!(region.region_type, RegionType::Block))
+            .cloned()
+            .partition(|region| matches!(region.region_type, RegionType::Block));
+
+        let loader = &self.loader;
+        let parsed = parallel_map(block_regions, threads, |region| {
+            let (chunks, new_structures) = loader.block_loader().parse_region(&region);
+            (region, chunks, new_structures)
+        });
+        let mut added = Vec::new();
+        for (region, chunks, new_structures) in parsed {
+            added.extend(self.add_block_region(&region, chunks, new_structures));
+        }
+
+        let loader = &self.loader;
+        let parsed = parallel_map(entity_regions, threads, |region| {
+            let chunks_entities = loader.entity_loader().parse_region(&region);
+            (region, chunks_entities)
+        });
+        for (region, chunks_entities) in parsed {
+            self.add_entity_region(&region, chunks_entities, &added);
+        }
+        if !entities {
+            for position in &added {
+                let chunk = self.dimension(position.dimension()).and_then(|d| d.chunk(position.position()));
+                if let Some(chunk) = chunk { chunk.lock().unwrap().set_entities_loaded(false); }
             }
         }
+        added
+    }
+
+    // Loaded, or in a registered region file so load_chunk can try to read it
+    pub fn chunk_available(&self, position: &ChunkPosition) -> bool {
+        if self.generates_in(position.dimension()) { return true; }
+        let Some(dimension) = self.dimension(position.dimension()) else { return false };
+        if dimension.chunk(position.position()).is_some() { return true; }
+        let (region_position, _) = chunk_position_to_region_position_and_index(position);
+        self.unloaded_regions.iter().any(|r| r.position == region_position && matches!(r.region_type, RegionType::Block))
+    }
+
+    // The chunk, read from its region file (with its entities) when it isn't loaded yet. When it isn't on disk either
+    // the world's generator makes it, None without one. Selections load their chunks through this, so with a chunk
+    // cache loading can evict other chunks. A region file that can't be read (or a chunk that doesn't parse) is an error
+    pub fn load_chunk(&mut self, position: &ChunkPosition) -> io::Result<Option<ChunkType>> {
+        if let Some(chunk) = self.dimension(position.dimension()).and_then(|d| d.chunk(position.position())) {
+            self.touch_chunk(position, &chunk);
+            return Ok(Some(chunk));
+        }
+        let (region_position, index) = chunk_position_to_region_position_and_index(position);
+        let region_file = |blocks: bool| self.unloaded_regions.iter()
+            .find(|r| r.position == region_position && matches!(r.region_type, RegionType::Block) == blocks)
+            .cloned();
+        let Some(block_region) = region_file(true) else { return Ok(self.generate_chunk(position)) };
+        let entity_region = region_file(false);
+
+        let Some(raw) = read_region_chunk(&block_region.path, index)? else { return Ok(self.generate_chunk(position)) };
+        let invalid = |kind: &str, path: &PathBuf| io::Error::new(io::ErrorKind::InvalidData, format!("failed to parse {} of chunk {:?} in {}", kind, position.position(), path.display()));
+        let (chunk, structures) = self.loader.block_loader().parse_chunk(raw.data, raw.compression_type, position.dimension())
+            .ok_or_else(|| invalid("blocks", &block_region.path))?;
+        let entities = match entity_region {
+            Some(region) => match read_region_chunk(&region.path, index)? {
+                Some(raw) => Some(self.loader.entity_loader().parse_entity_chunk(raw.data, raw.compression_type, position.dimension())
+                    .ok_or_else(|| invalid("entities", &region.path))?),
+                None => None,
+            },
+            None => None,
+        };
+
+        let added = self.add_block_region(&block_region, vec![chunk], HashMap::from([(position.reference(), structures)]));
+        if let Some(entities) = entities { self.add_entity_region(&block_region, HashMap::from([(position.position(), entities)]), &added); }
+        let tracked = self.track_chunks(&added);
+        self.keep_eviction_error(tracked);
+        Ok(self.dimension(position.dimension()).and_then(|d| d.chunk(position.position())))
+    }
+
+    fn add_block_region(&mut self, region: &Region, chunks: Vec<Chunk>, new_structures: HashMap<i64, Vec<GenericParentStructure>>) -> Vec<ChunkPosition> {
+        let dim = self.dimensions.get_mut(region.position.dimension()).unwrap();
+        let chunks = chunks.into_iter().filter(|c| dim.chunk(c.position().position()).is_none()).collect::<Vec<_>>();
+        let added = chunks.iter().map(|c| c.position().clone()).collect();
+        if let Some(index) = self.block_index.as_mut() { index.update_region(region, &chunks); }
+        let dim = self.dimensions.get_mut(region.position.dimension()).unwrap();
+        dim.set_chunks(chunks);
+        dim.structure_store_mut().add_structures(new_structures);
+        added
+    }
+
+    // Only the chunks that were just added get entities, the loaded ones already have theirs
+    fn add_entity_region(&mut self, region: &Region, chunks_entities: HashMap<(i32, i32), Vec<Entity>>, added: &[ChunkPosition]) {
+        let added = added.iter().map(|p| p.position()).collect::<HashSet<_>>();
+        let dim = self.dimensions.get_mut(region.position.dimension()).unwrap();
+        for (chunk_pos, chunk_entities) in chunks_entities {
+            if !added.contains(&chunk_pos) { continue; }
+            let Some(chunk) = dim.chunk_mut(chunk_pos) else { continue };
+            let entity_keys = dim.entity_store_mut().add_entities(chunk_entities);
+            chunk.lock().unwrap().set_entities(entity_keys);
+        }
+    }
+}
+
+// Unloading and the chunk cache
+// Unloaded chunks are dropped with their entities. With a cache the chunks loaded through World (load_chunk,
+// load_region(s), set_chunk) are tracked, and the least recently used ones are evicted when they don't fit its budget,
+// after saving them if their blocks or entities changed. A chunk whose entities changed but weren't loaded can't be
+// saved, so it isn't evicted either. Chunks loaded by scan_regions are the scan's to unload
+impl<'a> World<'a> {
+    // Tracks the chunks that are loaded already and evicts right away if they don't fit
+    pub fn enable_chunk_cache(&mut self, options: CacheOptions) -> io::Result<usize> {
+        let mut cache = ChunkCache::new(options);
+        for dimension in self.dimensions.values() {
+            for chunk in dimension.chunks() {
+                let chunk = chunk.lock().unwrap();
+                cache.touch(chunk.position(), Some(chunk.estimated_memory()));
+            }
+        }
+        self.chunk_cache = Some(cache);
+        self.evict_chunks()
+    }
+    // The chunks stay loaded
+    pub fn disable_chunk_cache(&mut self) -> Option<ChunkCache> { self.chunk_cache.take() }
+
+    // Evicts until the cache fits its budget, returns how many chunks were evicted
+    pub fn evict_chunks(&mut self) -> io::Result<usize> {
+        let Some(cache) = &self.chunk_cache else { return Ok(0) };
+        let candidates = cache.eviction_candidates();
+        if candidates.is_empty() { return Ok(0); }
+        let save = cache.options().save().clone();
+        self.unload_chunks(&candidates, Some(&save))?;
+        let cache = self.chunk_cache.as_mut().unwrap();
+        for position in &candidates { cache.record_eviction(position); }
+        Ok(candidates.len())
+    }
+
+    // With save the chunks that changed are saved first, and nothing is unloaded when that fails. Without it their
+    // changes are lost. Returns how many chunks were unloaded
+    pub fn unload_chunks(&mut self, positions: &[ChunkPosition], save: Option<&SaveOptions>) -> io::Result<usize> {
+        if let Some(options) = save {
+            let dirty = positions.iter()
+                .filter(|p| self.dimension(p.dimension()).and_then(|d| d.chunk(p.position())).is_some_and(|c| c.lock().unwrap().has_unsaved_changes()))
+                .cloned()
+                .collect::<Vec<_>>();
+            if !dirty.is_empty() { self.save_chunks(&dirty, options)?; }
+        }
+
+        let mut per_dimension: HashMap<LassoString, Vec<(i32, i32)>> = HashMap::new();
+        for position in positions {
+            if let Some(cache) = self.chunk_cache.as_mut() { cache.forget(position); }
+            if let Some(index) = self.block_index.as_mut() { index.detach_chunk(position); }
+            per_dimension.entry(*position.dimension()).or_default().push(position.position());
+        }
+        let mut unloaded = 0;
+        for (dimension, chunks) in per_dimension {
+            if let Some(dimension) = self.dimension_mut(&dimension) { unloaded += dimension.unload_chunks(&chunks).len(); }
+        }
+        Ok(unloaded)
+    }
+    pub fn unload_chunk(&mut self, position: &ChunkPosition, save: Option<&SaveOptions>) -> io::Result<bool> {
+        Ok(self.unload_chunks(std::slice::from_ref(position), save)? == 1)
+    }
+    // The loaded chunks of the region
+    pub fn unload_region(&mut self, position: &RegionPosition, save: Option<&SaveOptions>) -> io::Result<usize> {
+        let line = REGION_CHUNK_LINE as i32;
+        let Some(dimension) = self.dimension(position.dimension()) else { return Ok(0) };
+        let chunks = dimension.chunk_positions().into_iter()
+            .filter(|(x, z)| x.div_euclid(line) == position.x() && z.div_euclid(line) == position.z())
+            .map(|(x, z)| ChunkPosition::new(*x, *z, *position.dimension()))
+            .collect::<Vec<_>>();
+        self.unload_chunks(&chunks, save)
+    }
+
+    pub(crate) fn pause_eviction(&mut self) {
+        if let Some(cache) = self.chunk_cache.as_mut() { cache.pause(); }
+    }
+    pub(crate) fn resume_eviction(&mut self) -> io::Result<()> {
+        let Some(cache) = self.chunk_cache.as_mut() else { return Ok(()) };
+        cache.resume();
+        self.evict_chunks().map(|_| ())
+    }
+
+    // The error of the last eviction that failed where it couldn't be returned (load_chunk, set_chunk, generate_chunk
+    // and the par_ scans of Selection). The chunks that couldn't be saved stay loaded
+    pub fn take_eviction_error(&mut self) -> Option<io::Error> { self.eviction_error.take() }
+    pub(crate) fn keep_eviction_error(&mut self, result: io::Result<()>) {
+        if let Err(e) = result { self.eviction_error = Some(e); }
+    }
+
+    // A chunk that's locked keeps its last measured size
+    fn touch_chunk(&mut self, position: &ChunkPosition, chunk: &ChunkType) {
+        let Some(cache) = self.chunk_cache.as_mut() else { return };
+        if !cache.contains(position) { return; }
+        cache.touch(position, chunk.try_lock().ok().map(|c| c.estimated_memory()));
+    }
+
+    fn track_chunks(&mut self, positions: &[ChunkPosition]) -> io::Result<()> {
+        let Some(cache) = self.chunk_cache.as_mut() else { return Ok(()) };
+        for position in positions {
+            let Some(chunk) = self.dimensions.get(position.dimension()).and_then(|d| d.chunk(position.position())) else { continue };
+            cache.touch(position, chunk.try_lock().ok().map(|c| c.estimated_memory()));
+        }
+        self.evict_chunks().map(|_| ())
+    }
+}
+
+// Block index related
+impl<'a> World<'a> {
+    pub fn block_index(&self) -> Option<&BlockIndex> { self.block_index.as_ref() }
+    pub fn block_index_path(&self) -> PathBuf { self.path.join(BLOCK_INDEX_FILE_NAME) }
+
+    // Uses the saved index if there is one (call after register_regions so changed regions are dropped),
+    // from here on every loaded region is indexed
+    pub fn enable_block_index(&mut self, with_states: bool) {
+        let block_regions = self.unloaded_regions.iter().filter(|r| matches!(r.region_type, RegionType::Block)).cloned().collect::<Vec<_>>();
+        let index = match BlockIndex::load(&self.block_index_path(), &block_regions) {
+            Ok(index) if index.with_states() == with_states => index,
+            _ => BlockIndex::new(with_states)
+        };
+        self.block_index = Some(index);
+    }
+
+    pub fn disable_block_index(&mut self) { self.block_index = None; }
+
+    pub fn save_block_index(&self) -> io::Result<()> {
+        match &self.block_index {
+            Some(index) => index.save(&self.block_index_path()),
+            None => Ok(())
+        }
+    }
+
+    // Indexes every block region that isn't indexed yet without keeping its chunks loaded
+    pub fn build_block_index(&mut self) -> usize {
+        let Some(index) = self.block_index.as_mut() else { return 0 };
+        let mut indexed = 0;
+        for region in self.unloaded_regions.iter().filter(|r| matches!(r.region_type, RegionType::Block)) {
+            if index.is_region_current(region) { continue; }
+            let (chunks, _) = self.loader.block_loader().parse_region(region);
+            index.update_region(region, &chunks);
+            indexed += 1;
+        }
+        indexed
+    }
+
+    // Every chunk containing the block and how many there are. Loaded chunks are counted directly if the index
+    // has nothing (current) for them, other chunks only show up if they're in the index
+    pub fn locate(&self, id: &str) -> Vec<(ChunkPosition, u32)> {
+        let with_states = self.block_index.as_ref().is_some_and(|i| i.with_states()) || id.contains('[');
+        let mut found: HashMap<ChunkPosition, u32> = HashMap::new();
+        if let Some(index) = &self.block_index { found.extend(index.locate(id)); }
+
+        for dimension in self.dimensions.values() {
+            for chunk in dimension.chunks() {
+                let chunk = chunk.lock().unwrap();
+                let revision = Some(chunk.block_store().revision());
+                if self.block_index.as_ref().is_some_and(|i| i.chunk_counts(chunk.position(), revision).is_some()) { continue; }
+
+                let count = count_in(&count_blocks(chunk.block_store(), with_states), id);
+                if count > 0 { found.insert(chunk.position().clone(), count); }
+                else { found.remove(chunk.position()); }
+            }
+        }
+
+        let mut found = found.into_iter().collect::<Vec<_>>();
+        found.sort_by_key(|(pos, _)| (pos.dimension().to_string(), pos.x(), pos.z()));
+        found
+    }
+}
+
+// Save related
+// Block regions are written with the POI records of replaced blocks, entity regions only for chunks whose entities
+// changed. All regions of a save are replaced together, so a save that fails leaves the world as it was
+#[derive(Debug, Clone, Default)]
+pub struct SaveOptions {
+    backup: bool, // copy each region to <region>.mca.bak before replacing it
+}
+
+impl SaveOptions {
+    pub fn new() -> Self { Self::default() }
+    pub fn backup(&self) -> bool { self.backup }
+    pub fn with_backup(mut self, backup: bool) -> Self { self.backup = backup; self }
+}
+
+impl<'a> World<'a> {
+    pub fn region_path(&self, position: &RegionPosition) -> PathBuf {
+        self.unloaded_regions.iter()
+            .find(|r| matches!(r.region_type, RegionType::Block) && &r.position == position)
+            .map(|r| r.path.clone())
+            .unwrap_or_else(|| self.loader.block_loader().region_path(&self.path, position))
+    }
+
+    pub fn save(&mut self, options: &SaveOptions) -> io::Result<usize> {
+        let positions = self.dimensions.values()
+            .flat_map(|d| d.chunks().map(|c| c.lock().unwrap().position().clone()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        self.save_chunks(&positions, options)
+    }
+
+    // Writes the loaded chunks into their region files, chunks that aren't loaded are skipped. Returns how many were
+    // written. The block, POI and entity regions of all the chunks are replaced together (see RegionWrites), when it
+    // fails nothing on disk changed and the chunks stay dirty
+    pub fn save_chunks(&mut self, positions: &[ChunkPosition], options: &SaveOptions) -> io::Result<usize> {
+        let mut per_region: HashMap<RegionPosition, Vec<(usize, ChunkType)>> = HashMap::new();
+        for position in positions {
+            let Some(chunk) = self.dimension(position.dimension()).and_then(|d| d.chunk(position.position())) else { continue };
+            let (region, index) = chunk_position_to_region_position_and_index(position);
+            per_region.entry(region).or_default().push((index, chunk));
+        }
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as u32).unwrap_or(0);
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        let mut writes = RegionWrites::new();
+        let mut written = Vec::new(); // region, whether the index was current for it, its chunks
+        let mut new_regions = Vec::new();
+        let mut saved = 0;
+        for (region_position, chunks) in per_region {
+            let path = self.region_path(&region_position);
+            let existed = path.exists();
+            let region = Region { position: region_position.clone(), path: path.clone(), region_type: RegionType::Block };
+            let indexed = !existed || self.block_index.as_ref().is_some_and(|i| i.is_region_current(&region));
+            let mut raw = if existed { read_region_chunks(&path)? } else { Vec::new() };
+            for (index, chunk) in &chunks {
+                // a chunk that's on disk but can't be read is an error, writing it from scratch would lose what isn't loaded
+                let original = match raw.iter().position(|r| r.index == *index) {
+                    Some(i) => {
+                        let old = raw.swap_remove(i);
+                        Some(handle_chunk_compression(old.compression_type, old.data).ok_or_else(|| invalid("failed to decompress chunk"))?)
+                    },
+                    None => None
+                };
+                let nbt = self.loader.block_loader().write_chunk(&chunk.lock().unwrap(), original).ok_or_else(|| invalid("failed to write chunk"))?;
+                raw.push(RawRegionChunk { index: *index, timestamp, compression_type: ZLIB_COMPRESSION_TYPE, data: compress_zlib(&nbt)? });
+                saved += 1;
+            }
+            raw.sort_by_key(|r| r.index);
+            writes.stage(&path, &raw)?;
+            self.stage_poi_records(&region_position, &chunks, &mut writes)?;
+            if let Some(entity_region) = self.stage_entity_chunks(&region_position, &chunks, timestamp, &mut writes)? { new_regions.push(entity_region); }
+
+            if !existed { new_regions.push(region.clone()); }
+            written.push((region, indexed, chunks));
+        }
+        // the chunks the game generates next to the saved ones have to match them
+        if self.generator.is_some() && !writes.is_empty() { self.save_level_generator()?; }
+        writes.commit(options.backup())?;
+
+        for (region, indexed, chunks) in written {
+            let locked = chunks.iter().map(|(_, chunk)| chunk.lock().unwrap()).collect::<Vec<_>>();
+            if let Some(index) = self.block_index.as_mut() { index.update_saved_region(&region, indexed, &locked.iter().map(|c| &**c).collect::<Vec<_>>()); }
+            for mut chunk in locked { chunk.mark_clean(); }
+        }
+        self.unloaded_regions.extend(new_regions);
+        Ok(saved)
+    }
+
+    // POI records of POI blocks that became a different block are removed, the game would keep looking for a bed or a
+    // workstation that isn't there anymore. Staged with the block region, so the two are replaced together
+    fn stage_poi_records(&self, region: &RegionPosition, chunks: &[(usize, ChunkType)], writes: &mut RegionWrites) -> io::Result<()> {
+        let replaced = chunks.iter().filter_map(|(index, chunk)| {
+            let chunk = chunk.lock().unwrap();
+            let positions = chunk.replaced_blocks().iter().map(|i| chunk.block_position(*i)).collect::<Vec<_>>();
+            (!positions.is_empty()).then_some((*index, positions))
+        }).collect::<Vec<_>>();
+        if replaced.is_empty() { return Ok(()); }
+        let path = self.loader.block_loader().poi_region_path(&self.path, region);
+        if !path.exists() { return Ok(()); }
+
+        let mut raw = read_region_chunks(&path)?;
+        let mut changed = false;
+        for (index, positions) in replaced {
+            let Some(poi) = raw.iter_mut().find(|r| r.index == index) else { continue };
+            let Some(nbt) = handle_chunk_compression(poi.compression_type, poi.data.clone())
+                .and_then(|data| self.loader.block_loader().remove_poi_records(data, &positions)) else { continue };
+            poi.compression_type = ZLIB_COMPRESSION_TYPE;
+            poi.data = compress_zlib(&nbt)?;
+            changed = true;
+        }
+        if changed { writes.stage(&path, &raw)?; }
+        Ok(())
+    }
+
+    // The entities of the chunks whose entities changed, returns the entity region when it's a new file. A chunk loaded
+    // without its entities can't be saved like that, the ones on disk would be lost
+    fn stage_entity_chunks(&self, region: &RegionPosition, chunks: &[(usize, ChunkType)], timestamp: u32, writes: &mut RegionWrites) -> io::Result<Option<Region>> {
+        let chunks = chunks.iter().filter(|(_, chunk)| chunk.lock().unwrap().entities_dirty()).collect::<Vec<_>>();
+        if chunks.is_empty() { return Ok(None); }
+        let Some(dimension) = self.dimension(region.dimension()) else { return Ok(None) };
+        let path = self.loader.entity_loader().region_path(&self.path, region);
+        let existed = path.exists();
+        let mut raw = if existed { read_region_chunks(&path)? } else { Vec::new() };
+        for (index, chunk) in chunks {
+            let chunk = chunk.lock().unwrap();
+            if !chunk.entities_loaded() {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("the entities of chunk {:?} weren't loaded", chunk.position().position())));
+            }
+            let entities = chunk.entity_keys().iter().map(|k| dimension.entity_store().get(*k)).collect::<Vec<_>>();
+            let nbt = self.loader.entity_loader().write_chunk(chunk.position(), &entities)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "failed to write entity chunk"))?;
+            raw.retain(|r| r.index != *index);
+            raw.push(RawRegionChunk { index: *index, timestamp, compression_type: ZLIB_COMPRESSION_TYPE, data: compress_zlib(&nbt)? });
+        }
+        raw.sort_by_key(|r| r.index);
+        writes.stage(&path, &raw)?;
+        Ok((!existed).then(|| Region { position: region.clone(), path, region_type: RegionType::Entity }))
+    }
+}
+
+// Level related
+impl<'a> World<'a> {
+    pub fn level_path(&self) -> PathBuf {
+        let prefix = if self.version.world_type() == &WorldKind::Multiplayer { "world" } else { "" };
+        self.path.join(prefix).join(LEVEL_FILE_NAME)
+    }
+
+    // Puts the generator's settings into level.dat for the dimensions it made chunks in, the rest of the file is kept.
+    // A dimension that already generates something else than flat chunks keeps its generator, the world isn't turned
+    // into a flat one. A world without level.dat gets a minimal one. save_chunks calls this when the world has a generator
+    pub fn save_level_generator(&self) -> io::Result<()> {
+        let Some(generator) = &self.generator else { return Ok(()) };
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let path = self.level_path();
+        let mut level = match fs::read(&path) {
+            Ok(bytes) => {
+                let nbt = uncompress_gzip(bytes).ok_or_else(|| invalid(format!("{} isn't gzipped", path.display())))?;
+                fastnbt::from_bytes::<HashMap<String, Value>>(&nbt).map_err(|e| invalid(e.to_string()))?
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e),
+        };
+
+        let data = compound_entry(&mut level, "Data")?;
+        data.entry("DataVersion".to_string()).or_insert(Value::Int(self.version.data.version_data));
+        let name = self.path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        data.entry("LevelName".to_string()).or_insert(Value::String(name));
+        let settings = compound_entry(data, "WorldGenSettings")?;
+        settings.entry("seed".to_string()).or_insert(Value::Long(self.seed as i64));
+        settings.entry("generate_features".to_string()).or_insert(Value::Byte(0));
+        settings.entry("bonus_chest".to_string()).or_insert(Value::Byte(0));
+        let dimensions = compound_entry(settings, "dimensions")?;
+        for name in &self.generated_dimensions {
+            let name = name.get();
+            let key = if name.contains(':') { name.to_string() } else { format!("minecraft:{}", name) };
+            let dimension = compound_entry(dimensions, &key)?;
+            let kind = match dimension.get("generator") {
+                Some(Value::Compound(existing)) => existing.get("type").cloned(),
+                _ => None,
+            };
+            if kind.as_ref().is_some_and(|k| *k != Value::String("minecraft:flat".to_string())) {
+                log::warn!("Not replacing the {:?} generator of {} in level.dat", kind.unwrap(), key);
+                continue;
+            }
+            dimension.entry("type".to_string()).or_insert_with(|| Value::String(key.clone()));
+            dimension.insert("generator".to_string(), generator.level_settings());
+        }
+
+        let nbt = fastnbt::to_bytes(&level).map_err(|e| invalid(e.to_string()))?;
+        replace_file(&path, &compress_gzip(&nbt)?)
+    }
+}
+
+// The compound at key, added when it's missing
+fn compound_entry<'v>(parent: &'v mut HashMap<String, Value>, key: &str) -> io::Result<&'v mut HashMap<String, Value>> {
+    match parent.entry(key.to_string()).or_insert_with(|| Value::Compound(HashMap::new())) {
+        Value::Compound(compound) => Ok(compound),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} in level.dat isn't a compound", key))),
+    }
+}
+
+// Seed related
+// Everything here is computed from the seed alone and then checked against the loaded overworld
+impl<'a> World<'a> {
+    pub fn is_slime_chunk(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        prediction::is_slime_chunk(self.seed as i64, chunk_x, chunk_z)
+    }
+
+    pub fn slime_chunks_in_area(&self, from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+        let mut chunks = Vec::new();
+        for x in from.0.min(to.0)..=from.0.max(to.0) {
+            for z in from.1.min(to.1)..=from.1.max(to.1) {
+                if self.is_slime_chunk(x, z) { chunks.push((x, z)); }
+            }
+        }
+        chunks
+    }
+
+    // from and to are chunk coordinates
+    pub fn predict_structures(&self, set: &StructureSet, from: (i32, i32), to: (i32, i32)) -> Vec<StructurePrediction> {
+        let overworld = self.dimension(&"overworld".into());
+        prediction::structure_chunks_in_area(self.seed as i64, set, from, to).into_iter()
+            .map(|(x, z)| match overworld {
+                Some(dim) => prediction::verify_structure_chunk(dim, set, x, z),
+                None => StructurePrediction { chunk: ChunkPosition::new(x, z, "overworld".into()), structure_id: None, status: PredictionStatus::Unknown }
+            })
+            .collect()
+    }
+
+    pub fn predict_strongholds(&self) -> Vec<StructurePrediction> {
+        let overworld = self.dimension(&"overworld".into());
+        prediction::stronghold_ring_chunks(self.seed as i64).into_iter()
+            .map(|(x, z)| match overworld {
+                Some(dim) => prediction::verify_stronghold_chunk(dim, x, z),
+                None => StructurePrediction { chunk: ChunkPosition::new(x, z, "overworld".into()), structure_id: None, status: PredictionStatus::Unknown }
+            })
+            .collect()
     }
 }
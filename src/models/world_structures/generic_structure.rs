@@ -3,12 +3,15 @@ use crate::models::other::lasso_string::LassoString;
 use crate::models::other::properties::Properties;
 use crate::models::positions::chunk_position::ChunkPosition;
 use crate::models::positions::whole_position::Position;
+use crate::utils::position_utils::is_position_within_bounding_box;
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct BoundingBox {
     min_pos: Position,
     max_pos: Position,
 }
 
+#[derive(Debug)]
 pub struct GenericParentStructure {
     chunk_position: ChunkPosition,
     id: String,
@@ -16,6 +19,7 @@ pub struct GenericParentStructure {
     extra: Properties
 }
 
+#[derive(Debug)]
 pub struct GenericChildStructure {
     pub(crate) bounding_box: BoundingBox,
     pub(crate) id: String,
@@ -36,6 +40,25 @@ impl GenericParentStructure {
     pub fn id(&self) -> &String { &self.id }
     pub fn children(&self) -> &Vec<GenericChildStructure> { &self.children }
     pub fn properties(&self) -> &Properties { &self.extra }
+
+    // The whole structure box is the box around all of its pieces, None if the structure has no pieces
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        let mut children = self.children.iter();
+        let first = children.next()?.bounding_box().clone();
+        Some(children.fold(first, |acc, child| acc.union(child.bounding_box())))
+    }
+
+    pub fn contains(&self, position: &Position) -> bool {
+        self.children.iter().any(|child| child.contains(position))
+    }
+
+    pub fn intersects(&self, bounding_box: &BoundingBox) -> bool {
+        self.children.iter().any(|child| child.bounding_box().intersects(bounding_box))
+    }
+
+    pub fn piece_at(&self, position: &Position) -> Option<&GenericChildStructure> {
+        self.children.iter().find(|child| child.contains(position))
+    }
 }
 
 impl GenericChildStructure {
@@ -47,11 +70,22 @@ impl GenericChildStructure {
         }
     }
 
+    pub fn id(&self) -> &String { &self.id }
     pub fn bounding_box(&self) -> &BoundingBox { &self.bounding_box }
     pub fn properties(&self) -> &Properties { &self.extra }
+
+    pub fn contains(&self, position: &Position) -> bool { self.bounding_box.contains(position) }
 }
 
 impl BoundingBox {
+    pub fn new(corner1: &Position, corner2: &Position) -> BoundingBox {
+        let dimension = *corner1.dimension();
+        BoundingBox {
+            min_pos: Position::new(dimension, corner1.x().min(corner2.x()), corner1.y().min(corner2.y()), corner1.z().min(corner2.z())),
+            max_pos: Position::new(dimension, corner1.x().max(corner2.x()), corner1.y().max(corner2.y()), corner1.z().max(corner2.z())),
+        }
+    }
+
     pub fn from_BB(bb_list: Value, dimension: LassoString) -> BoundingBox {
         if let Value::IntArray(bb_list) = bb_list {
             let bb_list: &[i32] = &*bb_list;
@@ -63,4 +97,100 @@ impl BoundingBox {
             panic!("BoundingBox::from_bB called on non-array");
         }
     }
-}
\ No newline at end of file
+
+    pub fn min(&self) -> &Position { &self.min_pos }
+    pub fn max(&self) -> &Position { &self.max_pos }
+    pub fn dimension(&self) -> &LassoString { self.min_pos.dimension() }
+
+    // Bounding boxes are inclusive on both ends, like in the save format
+    pub fn size(&self) -> (i32, i32, i32) {
+        (self.max_pos.x() - self.min_pos.x() + 1, self.max_pos.y() - self.min_pos.y() + 1, self.max_pos.z() - self.min_pos.z() + 1)
+    }
+
+    pub fn volume(&self) -> i64 {
+        let (x, y, z) = self.size();
+        x as i64 * y as i64 * z as i64
+    }
+
+    pub fn center(&self) -> Position {
+        Position::new(
+            *self.dimension(),
+            (self.min_pos.x() + self.max_pos.x()).div_euclid(2),
+            (self.min_pos.y() + self.max_pos.y()).div_euclid(2),
+            (self.min_pos.z() + self.max_pos.z()).div_euclid(2)
+        )
+    }
+
+    pub fn contains(&self, position: &Position) -> bool {
+        position.dimension() == self.dimension() && is_position_within_bounding_box(position, &self.min_pos, &self.max_pos)
+    }
+
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.dimension() == other.dimension() &&
+        self.min_pos.x() <= other.max_pos.x() && self.max_pos.x() >= other.min_pos.x() &&
+        self.min_pos.y() <= other.max_pos.y() && self.max_pos.y() >= other.min_pos.y() &&
+        self.min_pos.z() <= other.max_pos.z() && self.max_pos.z() >= other.min_pos.z()
+    }
+
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        let dimension = *self.dimension();
+        BoundingBox {
+            min_pos: Position::new(dimension, self.min_pos.x().min(other.min_pos.x()), self.min_pos.y().min(other.min_pos.y()), self.min_pos.z().min(other.min_pos.z())),
+            max_pos: Position::new(dimension, self.max_pos.x().max(other.max_pos.x()), self.max_pos.y().max(other.max_pos.y()), self.max_pos.z().max(other.max_pos.z())),
+        }
+    }
+
+    // Squared distance from the position to the closest point of the box, 0 if inside. None if in another dimension
+    pub fn distance_squared(&self, position: &Position) -> Option<i64> {
+        if position.dimension() != self.dimension() { return None; }
+        let axis = |value: i32, min: i32, max: i32| (min - value).max(0).max(value - max) as i64;
+        let dx = axis(position.x(), self.min_pos.x(), self.max_pos.x());
+        let dy = axis(position.y(), self.min_pos.y(), self.max_pos.y());
+        let dz = axis(position.z(), self.min_pos.z(), self.max_pos.z());
+        Some(dx * dx + dy * dy + dz * dz)
+    }
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn pos(x: i32, y: i32, z: i32) -> Position { Position::new("overworld".into(), x, y, z) }
+    fn piece(corner1: Position, corner2: Position) -> GenericChildStructure {
+        GenericChildStructure::new("piece", BoundingBox::new(&corner1, &corner2), Properties::new(HashMap::new()))
+    }
+
+    #[test]
+    fn bounding_boxes_and_pieces() {
+        // corners in any order, inclusive on both ends
+        let bb = BoundingBox::new(&pos(-1, 10, -20), &pos(-8, -5, -17));
+        assert_eq!((bb.min().x(), bb.min().y(), bb.min().z()), (-8, -5, -20));
+        assert_eq!(bb.size(), (8, 16, 4));
+        assert_eq!(bb.volume(), 512);
+        assert_eq!(bb.center(), pos(-5, 2, -19));
+        assert!(bb.contains(&pos(-1, 10, -17)) && bb.contains(&pos(-8, -5, -20)));
+        assert!(!bb.contains(&pos(0, 0, -18)) && !bb.contains(&pos(-4, 0, -21)) && !bb.contains(&pos(-4, 11, -18)));
+        assert!(!bb.contains(&Position::new("the_nether".into(), -4, 0, -18)));
+
+        // touching boxes overlap, boxes a block apart don't
+        assert!(bb.intersects(&BoundingBox::new(&pos(-1, 10, -17), &pos(5, 20, 5))));
+        assert!(!bb.intersects(&BoundingBox::new(&pos(0, 0, -20), &pos(5, 5, -17))));
+        assert!(!bb.intersects(&BoundingBox::new(&pos(-8, -7, -20), &pos(-1, -6, -17))));
+        assert_eq!(bb.distance_squared(&pos(2, 0, -18)), Some(9));
+        assert_eq!(bb.distance_squared(&pos(-4, 0, -18)), Some(0));
+        assert_eq!(bb.distance_squared(&Position::new("the_end".into(), 0, 0, 0)), None);
+
+        // an L shaped structure only contains what its pieces contain, not its whole box
+        let structure = GenericParentStructure::new(ChunkPosition::new(-1, -2, "overworld".into()), "minecraft:fortress",
+            vec![piece(pos(-16, 0, -32), pos(-1, 5, -17)), piece(pos(-16, 0, -16), pos(-13, 5, -1))], Properties::new(HashMap::new()));
+        assert_eq!(structure.bounding_box(), Some(BoundingBox::new(&pos(-16, 0, -32), &pos(-1, 5, -1))));
+        assert!(structure.contains(&pos(-1, 0, -17)) && structure.contains(&pos(-13, 5, -1)));
+        assert!(!structure.contains(&pos(-1, 0, -1)));
+        assert!(structure.piece_at(&pos(-12, 0, -16)).is_none());
+        assert!(structure.intersects(&BoundingBox::new(&pos(-14, 3, -3), &pos(-10, 3, -2))));
+        assert!(!structure.intersects(&BoundingBox::new(&pos(-10, 0, -10), &pos(0, 10, 0))));
+    }
+}
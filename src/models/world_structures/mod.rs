@@ -1 +1,2 @@
-pub mod generic_structure;
\ No newline at end of file
+pub mod generic_structure;
+pub mod prediction;
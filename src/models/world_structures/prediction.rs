@@ -0,0 +1,239 @@
+use crate::constants::structure_sets::*;
+use crate::models::positions::chunk_position::ChunkPosition;
+use crate::models::world::dimension::Dimension;
+use crate::utils::java_random::JavaRandom;
+
+/*
+Offline versions of the vanilla placement checks. These only use the seed, so they give candidates:
+the structure can still fail to generate because of the biome or terrain at that chunk.
+Use the verify functions to check candidates against what is actually in the world.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PredictionStatus {
+    Generated,
+    NotGenerated,
+    Unknown, // chunk not loaded / not generated yet
+}
+
+#[derive(Debug, Clone)]
+pub struct StructurePrediction {
+    pub chunk: ChunkPosition,
+    pub structure_id: Option<String>,
+    pub status: PredictionStatus,
+}
+
+pub fn is_slime_chunk(seed: i64, chunk_x: i32, chunk_z: i32) -> bool {
+    let (x, z) = (chunk_x, chunk_z);
+    let slime_seed = seed
+        .wrapping_add(x.wrapping_mul(x).wrapping_mul(4987142) as i64)
+        .wrapping_add(x.wrapping_mul(5947611) as i64)
+        .wrapping_add((z.wrapping_mul(z) as i64).wrapping_mul(4392871))
+        .wrapping_add(z.wrapping_mul(389711) as i64)
+        ^ SLIME_CHUNK_SALT;
+    JavaRandom::new(slime_seed).next_int_bounded(10) == 0
+}
+
+// The one chunk in the spacing x spacing cell around the chunk that can hold a start
+pub fn potential_structure_chunk(seed: i64, set: &StructureSet, chunk_x: i32, chunk_z: i32) -> (i32, i32) {
+    let cell_x = chunk_x.div_euclid(set.spacing);
+    let cell_z = chunk_z.div_euclid(set.spacing);
+    let mut random = JavaRandom::with_large_feature_salt(seed, cell_x, cell_z, set.salt);
+
+    let range = set.spacing - set.separation;
+    let mut offset = || match set.spread_type {
+        SpreadType::Linear => random.next_int_bounded(range),
+        SpreadType::Triangular => (random.next_int_bounded(range) + random.next_int_bounded(range)) / 2,
+    };
+    let offset_x = offset();
+    let offset_z = offset();
+    (cell_x * set.spacing + offset_x, cell_z * set.spacing + offset_z)
+}
+
+fn passes_frequency(seed: i64, set: &StructureSet, chunk_x: i32, chunk_z: i32) -> bool {
+    if set.frequency >= 1.0 { return true; }
+    match set.frequency_reduction {
+        FrequencyReduction::Default => {
+            // vanilla passes the salt and chunk coords in this order
+            JavaRandom::with_large_feature_salt(seed, set.salt, chunk_x, chunk_z).next_float() < set.frequency
+        }
+        FrequencyReduction::LegacyType1 => {
+            let (region_x, region_z) = (chunk_x >> 4, chunk_z >> 4);
+            let mut random = JavaRandom::new((region_x ^ (region_z << 4)) as i64 ^ seed);
+            random.next_int();
+            random.next_int_bounded((1.0 / set.frequency) as i32) == 0
+        }
+    }
+}
+
+fn has_structure_chunk_in_range(seed: i64, set: &StructureSet, chunk_x: i32, chunk_z: i32, range: i32) -> bool {
+    for cell_x in (chunk_x - range).div_euclid(set.spacing)..=(chunk_x + range).div_euclid(set.spacing) {
+        for cell_z in (chunk_z - range).div_euclid(set.spacing)..=(chunk_z + range).div_euclid(set.spacing) {
+            let (x, z) = potential_structure_chunk(seed, set, cell_x * set.spacing, cell_z * set.spacing);
+            if (x - chunk_x).abs() <= range && (z - chunk_z).abs() <= range && is_structure_chunk(seed, set, x, z) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+pub fn is_structure_chunk(seed: i64, set: &StructureSet, chunk_x: i32, chunk_z: i32) -> bool {
+    if potential_structure_chunk(seed, set, chunk_x, chunk_z) != (chunk_x, chunk_z) { return false; }
+    if !passes_frequency(seed, set, chunk_x, chunk_z) { return false; }
+    match set.exclusion_zone {
+        Some(zone) => !has_structure_chunk_in_range(seed, zone.other_set, chunk_x, chunk_z, zone.chunk_count),
+        None => true
+    }
+}
+
+// All start candidates with from <= chunk <= to (chunk coordinates)
+pub fn structure_chunks_in_area(seed: i64, set: &StructureSet, from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+    let (min_x, max_x) = (from.0.min(to.0), from.0.max(to.0));
+    let (min_z, max_z) = (from.1.min(to.1), from.1.max(to.1));
+    let mut chunks = Vec::new();
+
+    for cell_x in min_x.div_euclid(set.spacing)..=max_x.div_euclid(set.spacing) {
+        for cell_z in min_z.div_euclid(set.spacing)..=max_z.div_euclid(set.spacing) {
+            let (x, z) = potential_structure_chunk(seed, set, cell_x * set.spacing, cell_z * set.spacing);
+            if x < min_x || x > max_x || z < min_z || z > max_z { continue; }
+            if is_structure_chunk(seed, set, x, z) { chunks.push((x, z)); }
+        }
+    }
+    chunks
+}
+
+// Ring positions before vanilla moves them to a matching biome (up to STRONGHOLD_BIOME_SEARCH_RADIUS blocks away),
+// we dont have the biome source so these are only accurate to that radius
+pub fn stronghold_ring_chunks(seed: i64) -> Vec<(i32, i32)> {
+    let mut random = JavaRandom::new(seed);
+    let mut positions = Vec::with_capacity(STRONGHOLD_COUNT as usize);
+
+    let mut angle = random.next_double() * std::f64::consts::PI * 2.0;
+    let mut spread = STRONGHOLD_RING_SPREAD;
+    let mut in_ring = 0;
+    let mut ring = 0;
+    for n in 0..STRONGHOLD_COUNT {
+        let distance = (4 * STRONGHOLD_RING_DISTANCE + STRONGHOLD_RING_DISTANCE * ring * 6) as f64
+            + (random.next_double() - 0.5) * (STRONGHOLD_RING_DISTANCE as f64 * 2.5);
+        // Math.round
+        let x = (angle.cos() * distance + 0.5).floor() as i32;
+        let z = (angle.sin() * distance + 0.5).floor() as i32;
+        random.fork(); // the biome search random, still has to be taken to keep the sequence right
+        positions.push((x, z));
+
+        angle += std::f64::consts::PI * 2.0 / spread as f64;
+        in_ring += 1;
+        if in_ring == spread {
+            ring += 1;
+            in_ring = 0;
+            spread += 2 * spread / (ring + 1);
+            spread = spread.min(STRONGHOLD_COUNT - n);
+            angle += random.next_double() * std::f64::consts::PI * 2.0;
+        }
+    }
+    positions
+}
+
+// Starts are saved in the chunk they start in, so a loaded candidate chunk without one didn't generate
+pub fn verify_structure_chunk(dimension: &Dimension, set: &StructureSet, chunk_x: i32, chunk_z: i32) -> StructurePrediction {
+    let chunk = ChunkPosition::new(chunk_x, chunk_z, *dimension.id());
+    let generated = dimension.structure_store().get_structures_by_chunk_reference(chunk.reference()).iter()
+        .find(|structure| set.structures.contains(&structure.id().as_str()))
+        .map(|structure| structure.id().clone());
+
+    let status = match (&generated, dimension.chunk((chunk_x, chunk_z))) {
+        (Some(_), _) => PredictionStatus::Generated,
+        (None, Some(_)) => PredictionStatus::NotGenerated,
+        (None, None) => PredictionStatus::Unknown,
+    };
+    StructurePrediction { chunk, structure_id: generated, status }
+}
+
+// Strongholds always generate, if one is found near the ring position the prediction is moved to it
+pub fn verify_stronghold_chunk(dimension: &Dimension, chunk_x: i32, chunk_z: i32) -> StructurePrediction {
+    let chunk_size = dimension.version().data.chunk_size;
+    let search_radius = STRONGHOLD_BIOME_SEARCH_RADIUS.div_euclid(chunk_size) + 1;
+
+    let found = dimension.structure_store().get_structures_by_id(STRONGHOLD_ID).into_iter()
+        .map(|structure| structure.chunk_position().clone())
+        .filter(|position| (position.x() - chunk_x).abs() <= search_radius && (position.z() - chunk_z).abs() <= search_radius)
+        .min_by_key(|position| (position.x() - chunk_x).pow(2) + (position.z() - chunk_z).pow(2));
+
+    match found {
+        Some(chunk) => StructurePrediction { chunk, structure_id: Some(STRONGHOLD_ID.to_string()), status: PredictionStatus::Generated },
+        None => StructurePrediction {
+            chunk: ChunkPosition::new(chunk_x, chunk_z, *dimension.id()),
+            structure_id: None,
+            status: PredictionStatus::Unknown
+        }
+    }
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::versions::VersionManager;
+    use crate::models::world::world::World;
+    use crate::types::WorldKind;
+
+    // Reference values from the vanilla slime chunk and concentric ring placement code run on java.util.Random
+    const SEED: i64 = -4172144997902289642;
+
+    #[test]
+    fn slime_chunks_match_vanilla() {
+        let world = World::new("".into(), VersionManager::get("1.20.1", WorldKind::Singleplayer));
+        let mut world = world.lock().unwrap();
+        world.set_seed(SEED as u64);
+        assert_eq!(world.slime_chunks_in_area((4, 4), (-4, -4)), vec![(-4, -4), (-3, -4), (-2, 3), (1, 0), (1, 1), (3, 0), (4, -4), (4, -2), (4, 3)]);
+
+        world.set_seed(12345);
+        assert_eq!(world.slime_chunks_in_area((-4, -4), (4, 4)), vec![(-4, 0), (-2, -4), (-2, 1), (-2, 4), (-1, 2), (0, -2), (3, 0), (4, -3), (4, 1)]);
+    }
+
+    fn sorted(mut chunks: Vec<(i32, i32)>) -> Vec<(i32, i32)> { chunks.sort(); chunks }
+
+    // Reference values from the 1.20.1 RandomSpreadStructurePlacement and its frequency reducers and exclusion zone
+    #[test]
+    fn structure_chunks_match_vanilla() {
+        assert_eq!(potential_structure_chunk(SEED, &VILLAGES, 0, 0), (8, 22));
+        assert_eq!(potential_structure_chunk(SEED, &VILLAGES, -1, -1), (-32, -23));
+        assert_eq!(potential_structure_chunk(SEED, &OCEAN_MONUMENTS, 100, -100), (101, -112));
+        assert_eq!(potential_structure_chunk(12345, &VILLAGES, 0, 0), (21, 5));
+        assert_eq!(potential_structure_chunk(12345, &OCEAN_MONUMENTS, 100, -100), (120, -119));
+
+        assert_eq!(sorted(structure_chunks_in_area(SEED, &VILLAGES, (40, 40), (-40, -40))), vec![(-32, -23), (-20, 7), (8, 22), (13, -15), (36, 3)]);
+        assert_eq!(sorted(structure_chunks_in_area(SEED, &PILLAGER_OUTPOSTS, (-100, -100), (100, 100))), vec![(-83, -61), (-78, -18), (-16, -77), (97, 20)]);
+        assert_eq!(sorted(structure_chunks_in_area(12345, &PILLAGER_OUTPOSTS, (-100, -100), (100, 100))), vec![(-59, 100), (5, 2), (17, -60), (67, -41)]);
+
+        // candidates the legacy reducer drops, and ones it keeps that are too close to a village
+        for (seed, chunk) in [(SEED, (-12, -23)), (SEED, (19, 16)), (12345, (-17, -17)), (SEED, (-19, 11)), (SEED, (22, -17)), (12345, (9, 69))] {
+            assert_eq!(potential_structure_chunk(seed, &PILLAGER_OUTPOSTS, chunk.0, chunk.1), chunk);
+            assert!(!is_structure_chunk(seed, &PILLAGER_OUTPOSTS, chunk.0, chunk.1));
+        }
+        assert!(is_structure_chunk(SEED, &VILLAGES, -20, 7));
+        assert!(is_structure_chunk(SEED, &PILLAGER_OUTPOSTS, 97, 20));
+
+        // no 1.20.1 set uses the default reducer with a frequency below 1
+        let halved = StructureSet { spacing: 20, separation: 4, salt: 12345678, frequency: 0.5, ..VILLAGES };
+        assert_eq!(sorted(structure_chunks_in_area(SEED, &halved, (-40, -40), (40, 40))),
+            vec![(-37, -5), (-30, 21), (-29, 10), (-20, 13), (-12, -34), (-12, -6), (-10, 25), (2, 12), (22, -29), (23, 24)]);
+        assert!(!is_structure_chunk(SEED, &halved, 8, -15));
+        assert_eq!(sorted(structure_chunks_in_area(12345, &halved, (-40, -40), (40, 40))),
+            vec![(-32, 25), (-29, -10), (-15, 23), (7, 32), (8, -35), (22, 6), (26, 21), (32, -25), (32, -17)]);
+    }
+
+    #[test]
+    fn stronghold_rings_match_vanilla() {
+        let rings = stronghold_ring_chunks(SEED);
+        assert_eq!(rings.len(), STRONGHOLD_COUNT as usize);
+        assert_eq!(rings[..3], [(-42, 87), (-81, -118), (132, -11)]);
+        assert_eq!((rings[9], rings[127]), ((204, -451), (1452, -168)));
+
+        let rings = stronghold_ring_chunks(12345);
+        assert_eq!(rings[..3], [(-105, 124), (-39, -107), (114, 21)]);
+        assert_eq!((rings[9], rings[127]), ((437, 329), (-1163, 868)));
+    }
+}
@@ -15,6 +15,7 @@ pub enum WorldKind {
     Multiplayer,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum HeightmapKind {
     MotionBlocking,
     MotionBlockingNoLeaves,
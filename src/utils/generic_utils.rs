@@ -11,4 +11,43 @@ pub fn bit_length(num: i32) -> u32 {
 pub fn div_rem_nonzero(a: i32, b: i32) -> (i32, i32) {
     assert!(b > 0);
     (a / b, a % b)
-}
\ No newline at end of file
+}
+
+// '*' matches any run of characters, '?' a single one
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+// The usual 8-4-4-4-12 hex form, e.g. "069a79f4-44e9-4726-a5be-fca90e38aaf5"
+pub fn uuid_to_string(uuid: u128) -> String {
+    let hex = format!("{:032x}", uuid);
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+// A random (version 4) uuid, for entities that are copied into the world. 122 random bits from the OS
+pub fn random_uuid() -> u128 {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).expect("the OS random number generator failed");
+    let uuid = u128::from_be_bytes(bytes);
+    (uuid & !(0xF << 76) | (0x4 << 76)) & !(0x3 << 62) | (0x2 << 62)
+}
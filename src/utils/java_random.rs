@@ -0,0 +1,80 @@
+// java.util.Random (what vanilla calls LegacyRandomSource), needed to reproduce worldgen decisions from the seed
+
+const MULTIPLIER: i64 = 0x5DEECE66D;
+const ADDEND: i64 = 0xB;
+const MASK: i64 = (1 << 48) - 1;
+const DOUBLE_UNIT: f64 = 1.0 / (1u64 << 53) as f64;
+
+#[derive(Debug, Clone)]
+pub struct JavaRandom {
+    seed: i64,
+}
+
+impl JavaRandom {
+    pub fn new(seed: i64) -> JavaRandom {
+        JavaRandom { seed: (seed ^ MULTIPLIER) & MASK }
+    }
+
+    pub fn set_seed(&mut self, seed: i64) { self.seed = (seed ^ MULTIPLIER) & MASK; }
+
+    // WorldgenRandom::setLargeFeatureWithSalt
+    pub fn with_large_feature_salt(seed: i64, x: i32, z: i32, salt: i32) -> JavaRandom {
+        JavaRandom::new(
+            (x as i64).wrapping_mul(341873128712)
+                .wrapping_add((z as i64).wrapping_mul(132897987541))
+                .wrapping_add(seed)
+                .wrapping_add(salt as i64)
+        )
+    }
+
+    fn next(&mut self, bits: u32) -> i32 {
+        self.seed = (self.seed.wrapping_mul(MULTIPLIER).wrapping_add(ADDEND)) & MASK;
+        (self.seed >> (48 - bits)) as i32
+    }
+
+    pub fn next_int(&mut self) -> i32 { self.next(32) }
+
+    pub fn next_int_bounded(&mut self, bound: i32) -> i32 {
+        assert!(bound > 0, "bound must be positive");
+        if bound & -bound == bound {
+            return ((bound as i64 * self.next(31) as i64) >> 31) as i32;
+        }
+
+        loop {
+            let bits = self.next(31);
+            let value = bits % bound;
+            if bits.wrapping_sub(value).wrapping_add(bound - 1) >= 0 { return value; }
+        }
+    }
+
+    pub fn next_long(&mut self) -> i64 {
+        ((self.next(32) as i64) << 32).wrapping_add(self.next(32) as i64)
+    }
+
+    pub fn next_float(&mut self) -> f32 {
+        self.next(24) as f32 / (1 << 24) as f32
+    }
+
+    pub fn next_double(&mut self) -> f64 {
+        (((self.next(26) as i64) << 27) + self.next(27) as i64) as f64 * DOUBLE_UNIT
+    }
+
+    // RandomSource::fork, consumes a long from this random
+    pub fn fork(&mut self) -> JavaRandom { JavaRandom::new(self.next_long()) }
+}
+
+/// --------- TESTS ---------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // values from java.util.Random
+    #[test]
+    fn matches_java_random() {
+        assert_eq!(JavaRandom::new(0).next_int(), -1155484576);
+        assert_eq!(JavaRandom::new(0).next_long(), -4962768465676381896);
+        assert_eq!(JavaRandom::new(0).next_double(), 0.730967787376657);
+        assert_eq!(JavaRandom::new(42).next_int_bounded(10), 0);
+    }
+}
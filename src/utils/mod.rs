@@ -2,3 +2,5 @@ pub mod generic_utils;
 pub mod position_utils;
 pub mod lock_utils;
 pub mod mojang_api;
+pub mod java_random;
+pub mod parallel;
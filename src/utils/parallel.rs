@@ -0,0 +1,56 @@
+use std::sync::Mutex;
+use std::thread;
+
+// 0 means one worker per core
+pub fn worker_count(threads: usize) -> usize {
+    if threads > 0 { return threads; }
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+// Runs work on every item with up to threads workers. Workers take the next item when they're done with one, so a
+// few slow items don't hold the rest up. Results are in the order of items
+pub fn parallel_map<T, R, F>(items: Vec<T>, threads: usize, work: F) -> Vec<R> where T: Send, R: Send, F: Fn(T) -> R + Sync {
+    let workers = worker_count(threads).min(items.len());
+    if workers <= 1 { return items.into_iter().map(work).collect(); }
+
+    let count = items.len();
+    let queue = Mutex::new(items.into_iter().enumerate());
+    let mut results = thread::scope(|scope| {
+        let handles = (0..workers).map(|_| scope.spawn(|| {
+            let mut done = Vec::new();
+            loop {
+                let next = queue.lock().unwrap().next();
+                let Some((index, item)) = next else { break };
+                done.push((index, work(item)));
+            }
+            done
+        })).collect::<Vec<_>>();
+        handles.into_iter().flat_map(|h| h.join().unwrap()).collect::<Vec<_>>()
+    });
+    debug_assert_eq!(results.len(), count);
+    results.sort_unstable_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+// Every worker folds the items it takes into its own accumulator, starting from init(). The accumulators are then
+// combined with reduce, in no particular order
+pub fn parallel_fold<T, A, I, F, R>(items: Vec<T>, threads: usize, init: I, fold: F, reduce: R) -> A
+where T: Send, A: Send, I: Fn() -> A + Sync, F: Fn(A, T) -> A + Sync, R: Fn(A, A) -> A {
+    let workers = worker_count(threads).min(items.len());
+    if workers <= 1 { return items.into_iter().fold(init(), fold); }
+
+    let queue = Mutex::new(items.into_iter());
+    let accumulators = thread::scope(|scope| {
+        let handles = (0..workers).map(|_| scope.spawn(|| {
+            let mut accumulator = init();
+            loop {
+                let next = queue.lock().unwrap().next();
+                let Some(item) = next else { break };
+                accumulator = fold(accumulator, item);
+            }
+            accumulator
+        })).collect::<Vec<_>>();
+        handles.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>()
+    });
+    accumulators.into_iter().reduce(reduce).unwrap_or_else(init)
+}
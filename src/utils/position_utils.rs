@@ -46,6 +46,14 @@ pub fn is_position_within_bounding_box(position: &Position, corner1: &Position,
     position.z() >= min_z && position.z() <= max_z
 }
 
+// The region a chunk is in and its slot in the region's location table
+pub fn chunk_position_to_region_position_and_index(chunk_pos: &ChunkPosition) -> (ChunkPosition, usize) {
+    let line = REGION_CHUNK_LINE as i32;
+    let region = ChunkPosition::new(chunk_pos.x().div_euclid(line), chunk_pos.z().div_euclid(line), *chunk_pos.dimension());
+    let index = chunk_pos.z().rem_euclid(line) as usize * REGION_CHUNK_LINE + chunk_pos.x().rem_euclid(line) as usize;
+    (region, index)
+}
+
 pub fn chunk_offset_to_position(offset: usize, region: &Region) -> (i32, i32) {
     let index = offset / 4;
     let local_x = (index % REGION_CHUNK_LINE) as i32;